@@ -0,0 +1,39 @@
+//! Prints a scenario preset as TOML that can be passed to the simulator with `--config`.
+//!
+//! cargo run --example scenario_presets -- <top5-hosting-providers|nation-state COUNTRY|tor-adversary>
+use simulator::Scenario;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let scenario = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["top5-hosting-providers"] => Ok(Scenario::top5_hosting_providers()),
+        ["nation-state", country] => Scenario::nation_state(country),
+        ["tor-adversary"] => Ok(Scenario::tor_adversary()),
+        _ => Err(format!(
+            "Usage: scenario_presets <top5-hosting-providers|nation-state COUNTRY|tor-adversary>. \
+            Known countries: {}",
+            Scenario::countries().join(", ")
+        )),
+    };
+    match scenario {
+        Ok(scenario) => {
+            println!("# {}", scenario.description);
+            println!(
+                "{}",
+                scenario
+                    .config
+                    .to_toml_string()
+                    .expect("Error serialising scenario.")
+            );
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(-1)
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Topology computations the binaries are built on. The signatures are kept stable so other tools
+//! can depend on them instead of copying the logic
+
+use crate::{AsIpMap, AsSelectionStrategy};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
+use simlib::{graph::Graph, ID};
+use std::collections::{BTreeMap, HashMap};
+
+/// The channels of an AS's nodes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AsChannels {
+    /// Channels to nodes in the same AS
+    pub intra: u32,
+    /// Channels to nodes in other ASs
+    pub inter: u32,
+    /// Nodes without channels
+    pub zero_degree: usize,
+}
+
+/// The nodes and channels located in a country
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountryStats {
+    pub num_nodes: usize,
+    /// Channels of the country's nodes
+    pub num_channels: usize,
+    /// Channels to nodes in the same country
+    pub num_intra_channels: usize,
+    pub capacity_msat: usize,
+}
+
+/// Returns the ASN the node is mapped to
+pub fn asn_of_node(as_ip_map: &AsIpMap, node: &ID) -> Option<u32> {
+    as_ip_map.asn_of(node)
+}
+
+/// Returns the number of intra- and inter-AS channels and zero-degree nodes per AS
+pub fn as_channels(as_ip_map: &AsIpMap, graph: &Graph) -> HashMap<u32, AsChannels> {
+    let zero_degree = as_ip_map.get_zero_degree_nodes(graph);
+    as_ip_map
+        .get_sum_of_as_channels(graph)
+        .into_iter()
+        .map(|(asn, (intra, inter))| {
+            let zero_degree = zero_degree.get(&asn).copied().unwrap_or_default();
+            (
+                asn,
+                AsChannels {
+                    intra,
+                    inter,
+                    zero_degree,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Returns the share of each node's channels that stay within its AS, truncated to two decimals.
+/// Zero-degree nodes have no ratio
+pub fn intra_as_channel_ratios(
+    as_ip_map: &AsIpMap,
+    graph: &Graph,
+) -> HashMap<u32, HashMap<ID, f32>> {
+    as_ip_map.get_intra_as_channels_ratio(graph)
+}
+
+/// Returns the number of channels of each node per AS
+pub fn node_degrees(as_ip_map: &AsIpMap, graph: &Graph) -> HashMap<u32, Vec<usize>> {
+    as_ip_map
+        .as_to_nodes()
+        .iter()
+        .map(|(asn, nodes)| {
+            let degrees = nodes
+                .iter()
+                .map(|n| graph.get_edges_for_node(n).unwrap_or_default().len())
+                .collect();
+            (*asn, degrees)
+        })
+        .collect()
+}
+
+/// Returns the number of nodes, channels and the capacity per country. Nodes without a country are
+/// not counted
+pub fn country_stats(
+    node_countries: &HashMap<ID, String>,
+    graph: &Graph,
+) -> BTreeMap<String, CountryStats> {
+    let mut stats: BTreeMap<String, CountryStats> = BTreeMap::new();
+    for (node, country) in node_countries.iter() {
+        let entry = stats.entry(country.clone()).or_default();
+        entry.num_nodes += 1;
+        for edge in graph.get_edges_for_node(node).unwrap_or_default() {
+            entry.num_channels += 1;
+            entry.capacity_msat += edge.capacity;
+            if node_countries.get(&edge.destination) == Some(country) {
+                entry.num_intra_channels += 1;
+            }
+        }
+    }
+    stats
+}
+
+/// Returns the `n` top ASs w.r.t. the strategy and their nodes, as the simulator selects the
+/// adversaries. The seed is used to approximate the betweenness
+pub fn top_n_asns(
+    as_ip_map: &AsIpMap,
+    graph: &Graph,
+    n: usize,
+    strategy: AsSelectionStrategy,
+    seed: u64,
+) -> Vec<(u32, Vec<ID>)> {
+    match strategy {
+        AsSelectionStrategy::MaxNodes => as_ip_map.top_n_asns_nodes(n, graph),
+        AsSelectionStrategy::MaxChannels => as_ip_map.top_n_asns_channels(n, graph),
+        AsSelectionStrategy::MaxBetweenness => {
+            let mut rng = StdRng::seed_from_u64(seed);
+            as_ip_map.top_n_asns_betweenness(n, graph, &mut rng)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+
+    #[test]
+    fn topology_analysis() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        assert_eq!(asn_of_node(&as_ip_map, &String::from("bob")), Some(24940));
+        assert_eq!(asn_of_node(&as_ip_map, &String::from("unknown")), None);
+        let channels = as_channels(&as_ip_map, &graph);
+        let (intra, inter) = as_ip_map.get_sum_of_as_channels(&graph)[&797];
+        assert_eq!(
+            channels[&797],
+            AsChannels {
+                intra,
+                inter,
+                zero_degree: 0,
+            }
+        );
+        let degrees = node_degrees(&as_ip_map, &graph);
+        assert_eq!(degrees.len(), 2);
+        assert_eq!(degrees[&24940].len(), 2);
+        assert_eq!(
+            degrees[&797].iter().sum::<usize>() as u32,
+            channels[&797].intra + channels[&797].inter
+        );
+        assert_eq!(
+            intra_as_channel_ratios(&as_ip_map, &graph),
+            as_ip_map.get_intra_as_channels_ratio(&graph)
+        );
+        for strategy in [
+            AsSelectionStrategy::MaxNodes,
+            AsSelectionStrategy::MaxChannels,
+            AsSelectionStrategy::MaxBetweenness,
+        ] {
+            let top = top_n_asns(&as_ip_map, &graph, 1, strategy, 19);
+            assert_eq!(top.len(), 1);
+            assert_eq!(top[0].1.len(), 2);
+        }
+        // dina is not located
+        let countries = HashMap::from([
+            (String::from("alice"), String::from("DE")),
+            (String::from("bob"), String::from("DE")),
+            (String::from("chan"), String::from("US")),
+        ]);
+        let stats = country_stats(&countries, &graph);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["DE"].num_nodes, 2);
+        let bob = graph.get_edges_for_node(&String::from("bob")).unwrap();
+        let alice = graph.get_edges_for_node(&String::from("alice")).unwrap();
+        assert_eq!(stats["DE"].num_channels, alice.len() + bob.len());
+        // alice-bob in both directions
+        assert_eq!(stats["DE"].num_intra_channels, 2);
+        assert_eq!(
+            stats["DE"].capacity_msat,
+            alice
+                .iter()
+                .chain(bob.iter())
+                .map(|e| e.capacity)
+                .sum::<usize>()
+        );
+        assert_eq!(stats["US"].num_intra_channels, 0);
+    }
+}
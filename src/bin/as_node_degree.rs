@@ -1,8 +1,7 @@
 use clap::Parser;
 use csv::Writer;
 use log::{error, info, LevelFilter};
-use simlib::graph::Graph;
-use simulator::AsIpMap;
+use simulator::{analysis, AsIpMap, TorTreatment};
 use std::{collections::HashMap, error::Error, path::PathBuf};
 
 #[derive(clap::Parser)]
@@ -20,6 +19,10 @@ struct Cli {
     /// Overwrite the existing file, if it exists
     #[arg(short = 'u', long = "overwrite")]
     overwrite: bool,
+    /// Treatment of nodes with only an onion address: exclude, include (in a common Tor AS) or both.
+    /// Both adds an include_tor column with the results of each treatment
+    #[arg(long = "include-tor", default_value = "include")]
+    include_tor: TorTreatment,
     verbose: bool,
 }
 
@@ -45,15 +48,23 @@ fn main() {
         PathBuf::from("ln-topology-analysis.csv")
     };
     info!("Topology analysis will be written to {:#?}.", output_path);
-    let as_ip_map = AsIpMap::new(&graph, true);
-    write_to_csv_file(&as_ip_map.as_to_nodes, &output_path, args.overwrite, &graph).unwrap();
+    let degrees: Vec<(bool, HashMap<u32, Vec<usize>>)> = args
+        .include_tor
+        .include_tor()
+        .into_iter()
+        .map(|include_tor| {
+            let as_ip_map = AsIpMap::new(&graph, include_tor);
+            (include_tor, analysis::node_degrees(&as_ip_map, &graph))
+        })
+        .collect();
+    write_to_csv_file(&degrees, &output_path, args.overwrite).unwrap();
 }
 
+/// Prepends an include_tor column if there are results for more than one treatment
 fn write_to_csv_file(
-    data: &HashMap<u32, Vec<String>>,
+    data: &[(bool, HashMap<u32, Vec<usize>>)],
     output_path: &PathBuf,
     overwrite_allowed: bool,
-    graph: &Graph,
 ) -> Result<(), Box<dyn Error>> {
     if !overwrite_allowed && output_path.exists() {
         Err(Box::new(std::io::Error::new(
@@ -62,13 +73,20 @@ fn write_to_csv_file(
         )))
     } else {
         let mut writer = Writer::from_path(output_path)?;
-        writer.serialize(("asn", "degree"))?;
-        for asn in data.keys() {
-            for node in data[asn].iter() {
-                let degree = graph.get_edges_for_node(node).unwrap_or_default().len();
-                writer.serialize((asn, degree))?;
+        let compare = data.len() > 1;
+        let header = ["include_tor", "asn", "degree"];
+        writer.write_record(if compare { &header[..] } else { &header[1..] })?;
+        for (include_tor, degrees_per_asn) in data.iter() {
+            for (asn, degrees) in degrees_per_asn.iter() {
+                for degree in degrees.iter() {
+                    if compare {
+                        writer.serialize((include_tor, asn, degree))?;
+                    } else {
+                        writer.serialize((asn, degree))?;
+                    }
+                }
+                writer.flush()?;
             }
-            writer.flush()?;
         }
         Ok(())
     }
@@ -79,26 +97,14 @@ mod tests {
 
     use super::*;
     use csv::{Reader, StringRecord};
-    use std::path::Path;
     use tempfile::NamedTempFile;
 
     #[test]
     fn persist() {
-        let graph = Graph::to_sim_graph(
-            &network_parser::Graph::from_json_file(
-                &Path::new("test_data/trivial_connected_lnd.json"),
-                network_parser::GraphSource::Lnd,
-            )
-            .unwrap(),
-            network_parser::GraphSource::Lnd,
-        );
-        let data = HashMap::from([
-            (24290, vec![String::from("034"), String::from("025")]),
-            (797, vec![String::from("036")]),
-        ]);
+        let data = [(true, HashMap::from([(24290, vec![2, 2]), (797, vec![2])]))];
         let file = NamedTempFile::new().expect("Error opening tempfile");
         let overwrite = true;
-        assert!(write_to_csv_file(&data, &PathBuf::from(file.path()), overwrite, &graph).is_ok());
+        assert!(write_to_csv_file(&data, &PathBuf::from(file.path()), overwrite).is_ok());
         let mut reader = Reader::from_path(file.path()).unwrap();
         assert_eq!(
             *reader.headers().unwrap(),
@@ -112,5 +118,22 @@ mod tests {
         for record in reader.records() {
             assert!(expected.contains(&record.unwrap()));
         }
+        let data = [
+            (false, HashMap::from([(797, vec![2])])),
+            (true, HashMap::from([(0, vec![1])])),
+        ];
+        assert!(write_to_csv_file(&data, &PathBuf::from(file.path()), overwrite).is_ok());
+        let mut reader = Reader::from_path(file.path()).unwrap();
+        assert_eq!(
+            *reader.headers().unwrap(),
+            StringRecord::from(vec!["include_tor", "asn", "degree"])
+        );
+        assert_eq!(
+            reader.records().map(|r| r.unwrap()).collect::<Vec<_>>(),
+            vec![
+                StringRecord::from(vec!["false", "797", "2"]),
+                StringRecord::from(vec!["true", "0", "1"]),
+            ]
+        );
     }
 }
@@ -0,0 +1,200 @@
+use clap::Parser;
+use csv::Writer;
+use log::{error, info, LevelFilter};
+use simulator::{AsDelta, Report};
+use std::{collections::BTreeMap, error::Error, fmt::Write, path::PathBuf};
+
+#[derive(clap::Parser)]
+#[command(name = "compare-runs", version, about)]
+struct Cli {
+    /// Path to the first report, e.g. simulation-run19.json of the older snapshot
+    first: PathBuf,
+    /// Path to the second report the first one is compared to
+    second: PathBuf,
+    #[arg(long = "log", short = 'l', default_value = "info")]
+    log_level: LevelFilter,
+    /// Path to CSV file where the deltas should be written to
+    #[arg(long = "out", short = 'o', default_value = "compare-runs.csv")]
+    output_path: PathBuf,
+    /// Number of ASs with the largest change of their censorship rate listed in the summary
+    #[arg(long = "top", default_value_t = 10)]
+    top: usize,
+    /// Overwrite the existing file, if it exists
+    #[arg(short = 'u', long = "overwrite")]
+    overwrite: bool,
+}
+
+fn main() {
+    let args = Cli::parse();
+    env_logger::builder().filter_level(args.log_level).init();
+    let read = |path: &PathBuf| match Report::read_from_file(path) {
+        Ok(report) => report,
+        Err(e) => {
+            error!("Error in report {} {}. Exiting.", path.display(), e);
+            std::process::exit(-1)
+        }
+    };
+    let first = read(&args.first);
+    let second = read(&args.second);
+    let deltas = first.as_deltas(&second);
+    if let Err(e) = write_to_csv_file(&deltas, &args.output_path, args.overwrite) {
+        error!("Error writing deltas {}. Exiting.", e);
+        std::process::exit(-1)
+    }
+    info!("Deltas written to {:#?}.", args.output_path);
+    print!("{}", summary(&deltas, args.top));
+}
+
+fn write_to_csv_file(
+    deltas: &[AsDelta],
+    output_path: &PathBuf,
+    overwrite_allowed: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !overwrite_allowed && output_path.exists() {
+        return Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "Output file exists, refusing to overwrite.",
+        )));
+    }
+    let mut writer = Writer::from_path(output_path)?;
+    writer.write_record([
+        "amt_sat",
+        "strategy",
+        "asn",
+        "censorship_rate_first",
+        "censorship_rate_second",
+        "censorship_rate_delta",
+        "num_censored_first",
+        "num_censored_second",
+        "num_censored_delta",
+        "f1_first",
+        "f1_second",
+        "f1_delta",
+    ])?;
+    for d in deltas {
+        let f1 = |c: &Option<simulator::AsCensorship>| {
+            c.as_ref().and_then(|c| c.accuracy.as_ref()).map(|a| a.f1)
+        };
+        writer.serialize((
+            d.amt_sat,
+            &d.strategy,
+            &d.asn,
+            d.this.as_ref().map(|c| c.censorship_rate),
+            d.other.as_ref().map(|c| c.censorship_rate),
+            d.censorship_rate_delta(),
+            d.this.as_ref().map(|c| c.num_censored),
+            d.other.as_ref().map(|c| c.num_censored),
+            d.num_censored_delta(),
+            f1(&d.this),
+            f1(&d.other),
+            d.f1_delta(),
+        ))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The mean change of the censorship rate per amount and strategy, the ASs only simulated in one
+/// of the reports and the ASs whose rate changed the most
+fn summary(deltas: &[AsDelta], top: usize) -> String {
+    let mut summary = String::new();
+    let mut per_strategy: BTreeMap<(usize, &String), Vec<f32>> = BTreeMap::new();
+    for d in deltas {
+        let rates = per_strategy.entry((d.amt_sat, &d.strategy)).or_default();
+        if let Some(delta) = d.censorship_rate_delta() {
+            rates.push(delta);
+        }
+    }
+    let _ = writeln!(summary, "Mean change of the censorship rate:");
+    for ((amt_sat, strategy), rates) in per_strategy {
+        let mean = if rates.is_empty() {
+            0.0
+        } else {
+            rates.iter().sum::<f32>() / rates.len() as f32
+        };
+        let _ = writeln!(
+            summary,
+            "  {} sat {}: {:+.2} percentage points across {} ASs",
+            amt_sat,
+            strategy,
+            mean * 100.0,
+            rates.len()
+        );
+    }
+    let only_first = deltas.iter().filter(|d| d.other.is_none()).count();
+    let only_second = deltas.iter().filter(|d| d.this.is_none()).count();
+    let _ = writeln!(
+        summary,
+        "{} ASs only in the first report, {} only in the second.",
+        only_first, only_second
+    );
+    let mut changed: Vec<(&AsDelta, f32)> = deltas
+        .iter()
+        .filter_map(|d| d.censorship_rate_delta().map(|delta| (d, delta)))
+        .collect();
+    changed.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+    let _ = writeln!(summary, "Largest changes of the censorship rate:");
+    for (d, delta) in changed.into_iter().take(top) {
+        let _ = writeln!(
+            summary,
+            "  AS {} ({} sat {}): {:+.2} percentage points",
+            d.asn,
+            d.amt_sat,
+            d.strategy,
+            delta * 100.0
+        );
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use csv::Reader;
+    use simulator::AsCensorship;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn compare() {
+        let censorship = |num_censored, censorship_rate| {
+            Some(AsCensorship {
+                num_censored,
+                censorship_rate,
+                accuracy: None,
+            })
+        };
+        let deltas = vec![
+            AsDelta {
+                amt_sat: 1000,
+                strategy: String::from("All"),
+                asn: String::from("797"),
+                this: censorship(2, 0.2),
+                other: censorship(5, 0.5),
+            },
+            AsDelta {
+                amt_sat: 1000,
+                strategy: String::from("All"),
+                asn: String::from("24940"),
+                this: censorship(1, 0.1),
+                other: None,
+            },
+        ];
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        let path = PathBuf::from(file.path());
+        assert!(write_to_csv_file(&deltas, &path, false).is_err());
+        assert!(write_to_csv_file(&deltas, &path, true).is_ok());
+        let rows: Vec<csv::StringRecord> = Reader::from_path(&path)
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][2], "797");
+        assert_eq!(&rows[0][8], "3");
+        assert_eq!(&rows[1][4], "");
+        let summary = summary(&deltas, 10);
+        assert!(summary.contains("1000 sat All: +30.00 percentage points across 1 ASs"));
+        assert!(summary.contains("1 ASs only in the first report, 0 only in the second."));
+        assert!(summary.contains("AS 797 (1000 sat All): +30.00"));
+    }
+}
@@ -0,0 +1,141 @@
+use clap::Parser;
+use csv::Writer;
+use log::{error, info, LevelFilter};
+use simulator::{analysis, CountryReader, GeoIpEdition};
+use std::{collections::BTreeMap, error::Error, path::PathBuf};
+
+#[derive(clap::Parser)]
+#[command(name = "country-stats", version, about)]
+struct Cli {
+    /// Path to JSON file describing topology
+    graph_file: PathBuf,
+    #[arg(long = "log", short = 'l', default_value = "info")]
+    log_level: LevelFilter,
+    /// Path to CSV file where the results should be written to
+    #[arg(long = "out", short = 'o')]
+    output_path: Option<PathBuf>,
+    #[arg(long = "graph-source", short = 'g', default_value = "lnd")]
+    graph_type: network_parser::GraphSource,
+    /// Path to the GeoLite2-Country database. Defaults to the one downloaded with `simulator geoip`
+    #[arg(long = "country-db")]
+    country_db: Option<PathBuf>,
+    /// Overwrite the existing file, if it exists
+    #[arg(short = 'u', long = "overwrite")]
+    overwrite: bool,
+    /// Group nodes with only an onion address under a common tor country
+    #[arg(long = "include-tor")]
+    include_tor: bool,
+    verbose: bool,
+}
+
+fn main() {
+    let args = Cli::parse();
+    let log_level = args.log_level;
+    env_logger::builder().filter_level(log_level).init();
+    let graph_source = args.graph_type;
+    let g = network_parser::Graph::from_json_file(
+        std::path::Path::new(&args.graph_file),
+        graph_source.clone(),
+    );
+    let graph = match g {
+        Ok(graph) => simlib::core_types::graph::Graph::to_sim_graph(&graph, graph_source),
+        Err(e) => {
+            error!("Error in graph file {}. Exiting.", e);
+            std::process::exit(-1)
+        }
+    };
+    let country_db = args
+        .country_db
+        .unwrap_or_else(|| GeoIpEdition::Country.default_path());
+    let reader = match CountryReader::try_new(&country_db) {
+        Ok(reader) => reader,
+        Err(e) => {
+            error!("Error in country database {}. Exiting.", e);
+            std::process::exit(-1)
+        }
+    };
+    let output_path = if let Some(output_path) = args.output_path {
+        output_path
+    } else {
+        PathBuf::from("ln-country-stats.csv")
+    };
+    info!("Country statistics will be written to {:#?}.", output_path);
+    let countries = reader.node_countries(&graph, args.include_tor);
+    info!(
+        "Located {} of {} nodes.",
+        countries.len(),
+        graph.node_count()
+    );
+    let stats = analysis::country_stats(&countries, &graph);
+    write_to_csv_file(&stats, &output_path, args.overwrite).unwrap();
+    info!("CSV successfully written to {:#?}.", output_path);
+}
+
+fn write_to_csv_file(
+    stats: &BTreeMap<String, analysis::CountryStats>,
+    output_path: &PathBuf,
+    overwrite_allowed: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !overwrite_allowed && output_path.exists() {
+        Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "Output file exists, refusing to overwrite.",
+        )))
+    } else {
+        let mut writer = Writer::from_path(output_path)?;
+        writer.write_record([
+            "country",
+            "num_nodes",
+            "num_channels",
+            "num_intra_channels",
+            "capacity_sat",
+        ])?;
+        for (country, s) in stats.iter() {
+            writer.serialize((
+                country,
+                s.num_nodes,
+                s.num_channels,
+                s.num_intra_channels,
+                s.capacity_msat / 1000,
+            ))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use analysis::CountryStats;
+    use csv::{Reader, StringRecord};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn persist() {
+        let stats = BTreeMap::from([
+            (
+                String::from("DE"),
+                CountryStats {
+                    num_nodes: 2,
+                    num_channels: 3,
+                    num_intra_channels: 2,
+                    capacity_msat: 5_000_000,
+                },
+            ),
+            (String::from("tor"), CountryStats::default()),
+        ]);
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        let path = PathBuf::from(file.path());
+        assert!(write_to_csv_file(&stats, &path, false).is_err());
+        assert!(write_to_csv_file(&stats, &path, true).is_ok());
+        let mut reader = Reader::from_path(file.path()).unwrap();
+        assert_eq!(
+            reader.records().map(|r| r.unwrap()).collect::<Vec<_>>(),
+            vec![
+                StringRecord::from(vec!["DE", "2", "3", "2", "5000"]),
+                StringRecord::from(vec!["tor", "0", "0", "0", "0"]),
+            ]
+        );
+    }
+}
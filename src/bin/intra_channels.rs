@@ -1,9 +1,13 @@
 use clap::Parser;
 use csv::Writer;
 use log::{error, info, LevelFilter};
-use simulator::AsIpMap;
+use simlib::ID;
+use simulator::{analysis, AsIpMap, TorTreatment};
 use std::{collections::HashMap, error::Error, path::PathBuf};
 
+/// Per treatment of onion-only nodes, the share of intra-AS channels and the degree of each node
+type NodeRatios = Vec<(bool, HashMap<u32, HashMap<ID, (f32, usize)>>)>;
+
 #[derive(clap::Parser)]
 #[command(name = "intra-channels", version, about)]
 struct Cli {
@@ -19,6 +23,13 @@ struct Cli {
     /// Overwrite the existing file, if it exists
     #[arg(short = 'u', long = "overwrite")]
     overwrite: bool,
+    /// Treatment of nodes with only an onion address: exclude, include (in a common Tor AS) or both.
+    /// Both adds an include_tor column with the results of each treatment
+    #[arg(long = "include-tor", default_value = "include")]
+    include_tor: TorTreatment,
+    /// Path to a CSV file to write each node's share of intra-AS channels and degree to
+    #[arg(long = "node-ratios")]
+    node_ratios_path: Option<PathBuf>,
     verbose: bool,
 }
 
@@ -44,13 +55,90 @@ fn main() {
         PathBuf::from("ln-intra-inter-channels.csv")
     };
     info!("Topology analysis will be written to {:#?}.", output_path);
-    let sums = AsIpMap::new(&graph, true).get_sum_of_as_channels(&graph);
+    let sums: Vec<(bool, HashMap<u32, (u32, u32, usize)>)> = args
+        .include_tor
+        .include_tor()
+        .into_iter()
+        .map(|include_tor| {
+            let as_ip_map = AsIpMap::new(&graph, include_tor);
+            let sums = analysis::as_channels(&as_ip_map, &graph)
+                .into_iter()
+                .map(|(asn, c)| (asn, (c.intra, c.inter, c.zero_degree)))
+                .collect();
+            (include_tor, sums)
+        })
+        .collect();
     write_to_csv_file(&sums, &output_path, args.overwrite).unwrap();
     info!("CSV successfully written to {:#?}.", output_path);
+    if let Some(path) = args.node_ratios_path {
+        let ratios: NodeRatios = args
+            .include_tor
+            .include_tor()
+            .into_iter()
+            .map(|include_tor| {
+                let as_ip_map = AsIpMap::new(&graph, include_tor);
+                let ratios = analysis::intra_as_channel_ratios(&as_ip_map, &graph)
+                    .into_iter()
+                    .map(|(asn, ratios)| {
+                        let ratios = ratios
+                            .into_iter()
+                            .map(|(node, ratio)| {
+                                let degree =
+                                    graph.get_edges_for_node(&node).unwrap_or_default().len();
+                                (node, (ratio, degree))
+                            })
+                            .collect();
+                        (asn, ratios)
+                    })
+                    .collect();
+                (include_tor, ratios)
+            })
+            .collect();
+        match write_node_ratios(&ratios, &path, args.overwrite) {
+            Ok(()) => info!("Node ratios written to {:#?}.", path),
+            Err(e) => error!("Error writing node ratios {}.", e),
+        }
+    }
+}
+
+/// One row per node with a ratio, ordered by ASN and node. Prepends an include_tor column if there
+/// are results for more than one treatment
+fn write_node_ratios(
+    data: &NodeRatios,
+    output_path: &PathBuf,
+    overwrite_allowed: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !overwrite_allowed && output_path.exists() {
+        return Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "Output file exists, refusing to overwrite.",
+        )));
+    }
+    let mut writer = Writer::from_path(output_path)?;
+    let compare = data.len() > 1;
+    let header = ["include_tor", "asn", "node_id", "intra_ratio", "degree"];
+    writer.write_record(if compare { &header[..] } else { &header[1..] })?;
+    for (include_tor, ratios) in data.iter() {
+        let mut rows: Vec<(&u32, &ID, &(f32, usize))> = ratios
+            .iter()
+            .flat_map(|(asn, nodes)| nodes.iter().map(move |(node, r)| (asn, node, r)))
+            .collect();
+        rows.sort_by(|a, b| a.0.cmp(b.0).then(a.1.cmp(b.1)));
+        for (asn, node, (ratio, degree)) in rows {
+            if compare {
+                writer.serialize((include_tor, asn, node, ratio, degree))?;
+            } else {
+                writer.serialize((asn, node, ratio, degree))?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
 }
 
+/// Prepends an include_tor column if there are results for more than one treatment
 fn write_to_csv_file(
-    data: &HashMap<u32, (u32, u32)>,
+    data: &[(bool, HashMap<u32, (u32, u32, usize)>)],
     output_path: &PathBuf,
     overwrite_allowed: bool,
 ) -> Result<(), Box<dyn Error>> {
@@ -61,10 +149,18 @@ fn write_to_csv_file(
         )))
     } else {
         let mut writer = Writer::from_path(output_path)?;
-        writer.serialize(("asn", "intra", "inter"))?;
-        for (asn, (num_intra, num_inter)) in data.iter() {
-            writer.serialize((asn, num_intra, num_inter))?;
-            writer.flush()?;
+        let compare = data.len() > 1;
+        let header = ["include_tor", "asn", "intra", "inter", "zero_degree"];
+        writer.write_record(if compare { &header[..] } else { &header[1..] })?;
+        for (include_tor, sums) in data.iter() {
+            for (asn, (num_intra, num_inter, num_zero_degree)) in sums.iter() {
+                if compare {
+                    writer.serialize((include_tor, asn, num_intra, num_inter, num_zero_degree))?;
+                } else {
+                    writer.serialize((asn, num_intra, num_inter, num_zero_degree))?;
+                }
+                writer.flush()?;
+            }
         }
         Ok(())
     }
@@ -79,7 +175,7 @@ mod tests {
 
     #[test]
     fn persist() {
-        let sums = HashMap::from([(0, (1, 2))]);
+        let sums = [(true, HashMap::from([(0, (1, 2, 3))]))];
         let file = NamedTempFile::new().expect("Error opening tempfile");
         let overwrite = false;
         assert!(write_to_csv_file(&sums, &PathBuf::from(file.path()), overwrite).is_err());
@@ -88,10 +184,64 @@ mod tests {
         let mut reader = Reader::from_path(file.path()).unwrap();
         assert_eq!(
             *reader.headers().unwrap(),
-            StringRecord::from(vec!["asn", "intra", "inter"])
+            StringRecord::from(vec!["asn", "intra", "inter", "zero_degree"])
         );
         for record in reader.records() {
-            assert_eq!(record.unwrap(), StringRecord::from(vec!["0", "1", "2"]));
+            assert_eq!(
+                record.unwrap(),
+                StringRecord::from(vec!["0", "1", "2", "3"])
+            );
         }
+        let sums = [
+            (false, HashMap::from([(797, (1, 2, 0))])),
+            (true, HashMap::from([(0, (1, 2, 3))])),
+        ];
+        assert!(write_to_csv_file(&sums, &PathBuf::from(file.path()), overwrite).is_ok());
+        let mut reader = Reader::from_path(file.path()).unwrap();
+        assert_eq!(
+            *reader.headers().unwrap(),
+            StringRecord::from(vec!["include_tor", "asn", "intra", "inter", "zero_degree"])
+        );
+        assert_eq!(
+            reader.records().map(|r| r.unwrap()).collect::<Vec<_>>(),
+            vec![
+                StringRecord::from(vec!["false", "797", "1", "2", "0"]),
+                StringRecord::from(vec!["true", "0", "1", "2", "3"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn persist_node_ratios() {
+        let ratios = vec![(
+            false,
+            HashMap::from([
+                (797, HashMap::from([(String::from("chan"), (0.5, 2))])),
+                (
+                    24940,
+                    HashMap::from([
+                        (String::from("bob"), (0.5, 2)),
+                        (String::from("alice"), (1.0, 1)),
+                    ]),
+                ),
+            ]),
+        )];
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        let path = PathBuf::from(file.path());
+        assert!(write_node_ratios(&ratios, &path, false).is_err());
+        assert!(write_node_ratios(&ratios, &path, true).is_ok());
+        let mut reader = Reader::from_path(file.path()).unwrap();
+        assert_eq!(
+            *reader.headers().unwrap(),
+            StringRecord::from(vec!["asn", "node_id", "intra_ratio", "degree"])
+        );
+        assert_eq!(
+            reader.records().map(|r| r.unwrap()).collect::<Vec<_>>(),
+            vec![
+                StringRecord::from(vec!["797", "chan", "0.5", "2"]),
+                StringRecord::from(vec!["24940", "alice", "1.0", "1"]),
+                StringRecord::from(vec!["24940", "bob", "0.5", "2"]),
+            ]
+        );
     }
 }
@@ -1,22 +1,59 @@
-use clap::Parser;
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches};
 use log::{error, info, warn, LevelFilter};
 use rayon::prelude::*;
+use simlib::{PaymentParts, RoutingMetric, ID};
 use std::{
-    collections::HashMap,
-    path::PathBuf,
-    sync::{Arc, Mutex},
+    fs::File,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 
 use simulator::{
-    AsIpMap, AsSelectionStrategy, PacketDropStrategy, PerStrategyResults, Report, SimBuilder,
-    SimOutput, SimResult,
+    append_series_csv, cln_to_lnd, convert_graph_json, draw_uniform_pairs, parse_payment_parts,
+    parse_routing_metric, processed_snapshots, read_cln_graph, read_node_list, snapshot_files,
+    write_exposure_csv, write_fee_revenue_csv, AdversaryGranularity, AmountModel, Anonymizer,
+    AsDiverseRouting, AsGrowth, AsIpMap, AsMetadataMap, AsRelationships, AsSelectionStrategy,
+    AsnCandidates, AsnDatabase, AsnOverrides, AsnResolver, BlocklistScenario, CapacityModel,
+    Checkpoint, CostModel, Decisions, EvasionConfig, ExperimentConfig, GraphFilter, GraphMetadata,
+    GuardConsensus, LiquidityFreeze, NodeAliases, NodeBlacklist, NumAsSweep, OverlapPolicy,
+    PacketDropStrategy, PairDraw, PairSampling, PaymentLog, ProviderMap, Replay, Report,
+    ReportFormat, ReportWriter, RetryPolicy, Scenario, ScenarioConfig, SimBuilder, SimOutput,
+    SimProgress, SimulationRunner, SimulatorError, StageTelemetry, TorModel, TorTreatment,
+    TransitMap, ValueRanking, DEFAULT_COVERAGE_LEVELS, DEFAULT_DROP_BUDGETS,
+    DEFAULT_DROP_STRATEGIES, DEFAULT_GROWTH_STEPS, DEFAULT_MIN_SAMPLE_SIZE, IP_CACHE_SIZE,
+    OPERATOR_ASN,
 };
 
+/// Payment volumes in sat that are simulated if no amount is given
+static DEFAULT_AMOUNTS: [usize; 6] = [100, 1000, 10000, 100000, 1000000, 10000000];
+/// Number of src/dest pairs that are simulated if neither the command line nor the scenario
+/// gives one
+static DEFAULT_NUM_PAIRS: usize = 1000;
+
 #[derive(clap::Parser)]
-#[command(name = "simulator", version, about)]
+#[command(
+    name = "simulator",
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Path to JSON file describing topology
-    graph_file: PathBuf,
+    #[cfg_attr(not(feature = "lnd-grpc"), arg(required = true))]
+    #[cfg_attr(feature = "lnd-grpc", arg(required_unless_present = "from_lnd"))]
+    graph_file: Option<PathBuf>,
+    /// Path to TOML file describing the scenario. Options given on the command line take precedence
+    #[arg(long = "config", short = 'c')]
+    config_file: Option<PathBuf>,
+    /// Preset experiment: exposure-survey, strategy-comparison, defence-evaluation,
+    /// top5-hosting-providers, tor-adversary or nation-state-<COUNTRY>. The scenario file and
+    /// options given on the command line take precedence
+    #[arg(long = "preset")]
+    preset: Option<Scenario>,
     #[arg(long = "log", short = 'l', default_value = "info")]
     log_level: LevelFilter,
     /// Path to directory where the results will be stored
@@ -25,172 +62,1470 @@ struct Cli {
     /// The payment volume (in sat) we are trying to route
     #[arg(long = "amount", short = 'a')]
     amount: Option<usize>,
+    /// Amounts of the payments: fixed=<SAT>,<SAT>,... simulates each amount on its own while
+    /// log-uniform=<MIN_SAT>:<MAX_SAT> and empirical=<FILE> with a CSV file of an amount_sat
+    /// column draw the amount of each payment
+    #[arg(long = "amount-model", conflicts_with = "amount")]
+    amount_model: Option<AmountModel>,
     /// Set the seed for the simulation
     #[arg(long, short, default_value_t = 19)]
     run: u64,
     #[arg(long = "graph-source", short = 'g', default_value = "lnd")]
     graph_type: network_parser::GraphSource,
+    /// Path to Core Lightning's listnodes output. The graph file is read as CLN's listchannels
+    /// output and the graph source is ignored
+    #[arg(long = "cln-nodes")]
+    cln_nodes: Option<PathBuf>,
+    /// Address (host:port) of an LND node whose current graph is fetched over gRPC and simulated
+    /// instead of the graph file
+    #[cfg(feature = "lnd-grpc")]
+    #[arg(
+        long = "from-lnd",
+        requires_all = ["macaroon", "tls_cert"],
+        conflicts_with_all = ["graph_file", "cln_nodes"]
+    )]
+    from_lnd: Option<String>,
+    /// Path to the macaroon used to authenticate with the LND node, e.g. readonly.macaroon
+    #[cfg(feature = "lnd-grpc")]
+    #[arg(long = "macaroon")]
+    macaroon: Option<PathBuf>,
+    /// Path to the TLS certificate of the LND node
+    #[cfg(feature = "lnd-grpc")]
+    #[arg(long = "tls-cert")]
+    tls_cert: Option<PathBuf>,
     /// Number of src/dest pairs to use in the simulation
     #[arg(long = "payments", short = 'p', default_value_t = 1000)]
     num_pairs: usize,
+    /// How destinations are drawn: uniform, capacity or merchants=<FILE> with a CSV file of node
+    /// and weight columns. The weights are stored in the report
+    #[arg(long = "sampling", default_value = "uniform")]
+    sampling: PairSampling,
+    /// Draw fresh pairs for each amount, seeded by the run and the amount, instead of simulating
+    /// the same pairs for all amounts
+    #[arg(long = "independent-pairs", conflicts_with_all = ["record_replay", "replay_file"])]
+    independent_pairs: bool,
     /// The number of adversarial ASs to simulate (top-n)
     #[arg(long = "num-as", short = 'n', default_value_t = 5)]
     num_adv_as: usize,
-    /// AS selection strategy. 0 for number of nodes and 1 for number of channels
+    /// AS selection strategy. 0 for number of nodes, 1 for number of channels and 2 for betweenness
     #[arg(long = "as-strategy", short = 's', default_value_t = 1)]
     as_sel_strategy: usize,
+    /// Comma-separated list of adversarial ASNs to simulate instead of the top-n
+    #[arg(long = "asns", value_delimiter = ',')]
+    asns: Vec<u32>,
+    /// Path to a file with one node ID or public key per line. The nodes are simulated as a single
+    /// adversary (e.g. an LSP) instead of the ASs
+    #[arg(long = "adv-nodes", conflicts_with_all = ["asns", "exposure_threshold"])]
+    adv_nodes_file: Option<PathBuf>,
+    /// What a single adversary controls: as or provider, which merges the ASs of each hosting
+    /// provider into one adversary identified by its lowest ASN
+    #[arg(long = "adversary-granularity", default_value = "as")]
+    adversary_granularity: AdversaryGranularity,
+    /// Path to a CSV file with the columns asn and provider to group the ASs by instead of the
+    /// bundled list of large hosting providers
+    #[arg(long = "providers")]
+    providers_file: Option<PathBuf>,
+    /// AS database the addresses are resolved with: geolite2, ip2location=<FILE> with an
+    /// IP2Location LITE ASN CSV file or ipinfo=<FILE> with an ipinfo.io ASN MMDB file
+    #[arg(long = "asn-db", default_value = "geolite2")]
+    asn_db: AsnDatabase,
+    /// Path to a CSV file with the columns node_or_ip and asn. The ASNs take precedence over the
+    /// database lookup
+    #[arg(long = "asn-overrides")]
+    asn_overrides: Option<PathBuf>,
+    /// Path to a CSV file with the columns node_id and asn to map the nodes with instead of looking
+    /// up their addresses, e.g. written by --save-asn-map
+    #[arg(long = "asn-map", conflicts_with_all = ["asn_overrides", "include_tor", "multi_asn"])]
+    asn_map: Option<PathBuf>,
+    /// Write the nodes' ASNs to a CSV file that can be passed to --asn-map later
+    #[arg(long = "save-asn-map")]
+    save_asn_map: Option<PathBuf>,
+    /// Treatment of nodes with only an onion address: exclude them from the ASs, include them in a
+    /// common Tor AS or both, i.e., exclude them and report the results with them included side by
+    /// side. Overrides the scenario file
+    #[arg(long = "include-tor")]
+    include_tor: Option<TorTreatment>,
+    /// Model onion-only nodes as hidden from the ASs and report the payments that escape
+    /// censorship thanks to them
+    #[arg(long = "tor-aware")]
+    tor_aware: bool,
+    /// Path to a Tor network-status consensus. Onion-only nodes become censorable by the AS of
+    /// their guard relay. Implies --tor-aware
+    #[arg(long = "tor-consensus")]
+    tor_consensus: Option<PathBuf>,
+    /// Compute the exposure of every AS in the graph and write it to a CSV file for each amount
+    #[arg(long = "all-ases")]
+    all_ases: bool,
+    /// Only simulate the ASs whose exposure (0 to 1) is at least the threshold. Implies --all-ases
+    #[arg(long = "exposure-threshold")]
+    exposure_threshold: Option<f32>,
+    /// Select the top-n ASs for each amount by the value of the payments they could censor in that
+    /// amount's baseline and report how the ranking shifts across amounts
+    #[arg(
+        long = "rerank-by-value",
+        conflicts_with_all = ["asns", "adv_nodes_file", "exposure_threshold"]
+    )]
+    rerank_by_value: bool,
+    /// Attribute nodes shared by several adversarial ASs only to the highest-ranked AS
+    #[arg(long = "dedup-overlap")]
+    dedup_overlap: bool,
+    /// How senders pick their paths, minfee or maxprob (maximum success probability)
+    #[arg(long = "routing-metric", value_parser = parse_routing_metric, default_value = "minfee")]
+    routing_metric: RoutingMetric,
+    /// Whether senders send each payment in a single part or split it, single or split
+    #[arg(long = "payment-parts", value_parser = parse_payment_parts, default_value = "split")]
+    payment_parts: PaymentParts,
+    /// Write each strategy's results to a newline-delimited JSON file as soon as they complete
+    #[arg(long = "stream")]
+    stream: bool,
+    /// Store each distinct path once per amount and refer to it by index from the payments
+    #[arg(long = "dedup-paths")]
+    dedup_paths: bool,
+    /// Write the routing fees earned per AS in the baseline to a CSV file for each amount
+    #[arg(long = "fee-revenue")]
+    fee_revenue: bool,
+    /// Store the baseline once per amount in the report
+    #[arg(long = "include-baseline")]
+    include_baseline: bool,
+    /// Compare the payments censored by two strategies (e.g. all intra-as) for each adversary
+    #[arg(long = "diff-strategies", num_args = 2, value_names = ["FIRST", "SECOND"])]
+    diff_strategies: Vec<PacketDropStrategy>,
+    /// Path to JSON file describing a blocklist of destinations enforced by a coalition of ASs
+    #[arg(long = "blocklist")]
+    blocklist_file: Option<PathBuf>,
+    /// Also simulate ASs holding HTLCs for the given number of seconds instead of dropping them
+    #[arg(long = "delay")]
+    hold_time_secs: Option<u32>,
+    /// Delay in seconds after which a held payment counts as timed out
+    #[arg(long = "delay-timeout", default_value_t = simulator::DEFAULT_DELAY_TIMEOUT_SECS)]
+    delay_timeout_secs: u32,
+    /// Also simulate ASs only dropping payment parts of more than the given amount in sat
+    #[arg(long = "amount-above")]
+    amount_above_sat: Option<usize>,
+    /// Also simulate ASs only dropping payment parts of less than the given amount in sat
+    #[arg(long = "amount-below")]
+    amount_below_sat: Option<usize>,
+    /// Also simulate ASs dropping the payments they forward to one of the nodes listed in the file,
+    /// one node ID per line, and report the censored and collaterally dropped payments
+    #[arg(long = "destination-blacklist")]
+    blacklist_file: Option<PathBuf>,
+    /// Apply the strategies of all adversaries in parallel to chunks of the given number of
+    /// payments. Only the counters are reported so memory does not grow with the number of ASs
+    #[arg(long = "chunk-size")]
+    chunk_size: Option<usize>,
+    /// Also write one JSON line per payment, strategy and adversary with the ASNs on its paths,
+    /// whether it was censored and whether dropping it was right to the given file
+    #[arg(long = "payment-log", conflicts_with = "chunk_size")]
+    payment_log: Option<PathBuf>,
+    /// Directory to store the baseline of each amount and the results of each strategy and
+    /// adversary in as they complete. Rerunning with the same arguments resumes from there
+    #[arg(long = "checkpoint")]
+    checkpoint_dir: Option<PathBuf>,
+    /// Add the aliases the sender, recipient and hops announce in the graph file to each payment
+    /// in the report
+    #[arg(
+        long = "aliases",
+        requires = "graph_file",
+        conflicts_with = "cln_nodes"
+    )]
+    aliases: bool,
+    /// Also simulate the given number of rounds in which the senders of censored payments open a
+    /// channel around each adversary and report how the success rate recovers
+    #[arg(long = "evasion-rounds")]
+    evasion_rounds: Option<usize>,
+    /// Maximum number of channels the victims open over all rounds
+    #[arg(long = "evasion-budget", default_value = "100")]
+    evasion_budget: usize,
+    /// Capacity in sat of the channels the victims open
+    #[arg(long = "evasion-capacity", default_value = "1000000")]
+    evasion_capacity_sat: usize,
+    /// The drop strategy the adversaries apply in each round
+    #[arg(long = "evasion-strategy", default_value = "all")]
+    evasion_strategy: PacketDropStrategy,
+    /// Also simulate senders routing defensively by adding the given base fee in msat to every hop
+    /// within an AS and report how much each adversary's censorship rate drops
+    #[arg(long = "as-diverse-routing")]
+    as_diverse_penalty_msat: Option<usize>,
+    /// Flag the rates of each adversary that are computed from fewer payments, e.g. the precision
+    /// of an AS that dropped only a handful of payments
+    #[arg(long = "min-sample-size", default_value_t = DEFAULT_MIN_SAMPLE_SIZE)]
+    min_sample_size: usize,
+    /// Bootstrap 95% confidence intervals of each adversary's success rate and accuracy over its
+    /// payments with the given number of resamples
+    #[arg(long = "bootstrap", num_args = 0..=1, default_missing_value = "1000")]
+    bootstrap_resamples: Option<usize>,
+    /// Benefit per correctly and cost per falsely dropped payment of the intra-probability
+    /// strategy as <BENEFIT>:<COST>. Reports each adversary's net utility
+    #[arg(long = "cost-model")]
+    cost_model: Option<CostModel>,
+    /// Record the wall-clock time, peak memory and number of payments of the baseline and of each
+    /// strategy per adversary in the report
+    #[arg(long = "telemetry")]
+    telemetry: bool,
+    /// Also simulate each adversary freezing the given share (0 to 1) of its nodes' channel
+    /// liquidity instead of dropping payments and report the impact on the success rate
+    #[arg(long = "freeze-liquidity")]
+    frozen_share: Option<f32>,
+    /// Let senders retry each censored payment up to the given number of times on paths that avoid
+    /// the hops that failed before and report first-attempt, eventual and hard failures
+    #[arg(long = "max-retries")]
+    max_retries: Option<usize>,
+    /// Summarise the adversaries whose strategy dropped or delayed fewer than the given number of
+    /// payments in a single entry per strategy instead of reporting their full results
+    #[arg(long = "min-affected")]
+    min_affected: Option<usize>,
+    /// Also search the smallest set of ASs whose nodes together are traversed by the given share
+    /// (0 to 1) of the successful payments, i.e., that could censor them by colluding
+    #[arg(long = "placement-target")]
+    placement_target: Option<f32>,
+    /// Also report the censorship rate of the top-1, top-2, ... ASs colluding, for every number of
+    /// ASs in the range, e.g. 1..50
+    #[arg(long = "num-as-sweep")]
+    num_as_sweep: Option<NumAsSweep>,
+    /// Also evaluate every pair of the top-k ASs of the ranking as a colluding coalition and report
+    /// the pair traversed by the most successful payments along with the pairwise coverage matrix
+    #[arg(long = "pair-coalitions")]
+    pair_coalitions: Option<usize>,
+    /// Track every AS the addresses of a node map to and report each adversary's successful
+    /// payments in the best and worst case of attributing the ambiguous nodes
+    #[arg(long = "multi-asn")]
+    multi_asn: bool,
+    /// Path to a CAIDA AS-relationship file. Infers the AS paths between the peers of each channel
+    /// and reports the payments the selected or top-n transit ASs censor by carrying them
+    #[arg(long = "as-relationships")]
+    as_relationships: Option<PathBuf>,
+    /// Path to a CAIDA as2org file or AS Rank dump (JSON lines). Adds the name, organisation,
+    /// country and customer cone size of each adversarial AS to its results. May be repeated to
+    /// combine both
+    #[arg(long = "as-metadata")]
+    as_metadata: Vec<PathBuf>,
+    /// Also simulate the AS acquiring the best-connected nodes of other ASs before it attacks and
+    /// report its censorship rate against its market share
+    #[arg(long = "grow-asn")]
+    grow_asn: Option<u32>,
+    /// Comma-separated shares (0 to 1) of all mapped nodes the AS acquires, each simulated on its
+    /// own
+    #[arg(long = "growth-steps", value_delimiter = ',', requires = "grow_asn")]
+    growth_steps: Vec<f32>,
+    /// Also report the censorship of each adversary that may drop at most the comma-separated
+    /// shares (0 to 1) of the payments it forwards, those most likely from or to it first.
+    /// Defaults to 0.01,0.05,0.1,0.25,0.5 if no shares are given
+    #[arg(long = "drop-budgets", value_delimiter = ',', num_args = 0..)]
+    drop_budgets: Option<Vec<f32>>,
+    /// Share (0 to 1) of its nodes each adversary controls, drawn from the seed, e.g. as an AS
+    /// cannot interfere with every customer VM
+    #[arg(long = "as-coverage")]
+    as_coverage: Option<f32>,
+    /// Also report the censorship rate of each adversary controlling each of the comma-separated
+    /// shares of its nodes. Defaults to 0.1,0.25,0.5,0.75,1 if no shares are given
+    #[arg(long = "coverage-sweep", value_delimiter = ',', num_args = 0..)]
+    coverage_sweep: Option<Vec<f32>>,
+    /// Also reroute the payments each adversary censors around its nodes and report the fees it
+    /// loses and how the forwarding shares of the other nodes shift
+    #[arg(long = "network-impact")]
+    network_impact: bool,
+    /// Also count the payments that already failed in the baseline which each adversary's strategy
+    /// would have censored had they succeeded
+    #[arg(long = "classify-failed")]
+    classify_failed: bool,
+    /// Maximum number of threads simulating amounts and adversaries in parallel. Defaults to the
+    /// number of CPUs
+    #[arg(long = "threads")]
+    threads: Option<usize>,
+    /// Record every stochastic decision to replay-run<RUN>.json in the output directory
+    #[arg(long = "record-replay")]
+    record_replay: bool,
+    /// Re-run the simulation recorded in the replay file. Overrides the seed and payment pairs
+    #[arg(long = "replay", conflicts_with = "record_replay")]
+    replay_file: Option<PathBuf>,
+    /// Do not show progress bars
+    #[arg(long = "no-progress")]
+    no_progress: bool,
+    /// Also render the report as simulation-run<RUN>.html with tables and charts of the censorship
+    /// rates: json or html
+    #[arg(long = "report", default_value = "json", conflicts_with = "stream")]
+    report_format: ReportFormat,
+    /// Also write one row per payment to simulation-run<RUN>.parquet
+    #[cfg(feature = "parquet")]
+    #[arg(long = "parquet", conflicts_with = "stream")]
+    parquet: bool,
+    /// Also append the results to normalized tables (runs, amounts, strategies, asns, results and
+    /// payments) in the SQLite database
+    #[cfg(feature = "sqlite")]
+    #[arg(long = "sqlite", conflicts_with = "stream")]
+    sqlite_file: Option<PathBuf>,
+    /// Compress the JSON or streamed report with zstd, i.e., write simulation-run<RUN>.json.zst
+    #[cfg(feature = "zstd")]
+    #[arg(long = "compress")]
+    compress: bool,
+    /// Impute the capacity of channels without one: degree-median, global-median or a constant
+    /// capacity in sat. The number of imputed channels is written to graph-metadata.json
+    #[arg(long = "capacity-model")]
+    capacity_model: Option<CapacityModel>,
+    /// Comma-separated list of filters applied in order to prune the graph: with-address,
+    /// min-capacity=<SAT>, largest-component or top-degree=<N>
+    #[arg(long = "filter", value_delimiter = ',')]
+    filters: Vec<GraphFilter>,
     verbose: bool,
 }
 
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Check an experiment configuration and print a JSON validation report without simulating
+    Validate(ValidateArgs),
+    /// Simulate every new graph snapshot that appears in a directory and append the results to a
+    /// CSV file
+    Watch(WatchArgs),
+    /// Convert a graph file to another format
+    GraphConvert(GraphConvertArgs),
+    /// Write the graph with hashed node and channel IDs and the nodes' ASNs instead of their
+    /// addresses, along with the ASN mapping of the hashed nodes
+    ExportGraph(ExportGraphArgs),
+    /// Download or update the GeoLite2 databases, which are then used instead of the bundled ones
+    #[cfg(feature = "geoip-download")]
+    Geoip(GeoipArgs),
+}
+
+#[derive(clap::Args)]
+struct ValidateArgs {
+    /// Path to JSON file describing topology
+    graph_file: PathBuf,
+    #[arg(long = "graph-source", short = 'g', default_value = "lnd")]
+    graph_type: network_parser::GraphSource,
+    /// Path to directory where the results will be stored
+    #[arg(long = "out", short = 'o', default_value = "sim-results")]
+    output_dir: PathBuf,
+    /// The payment volume (in sat) we are trying to route
+    #[arg(long = "amount", short = 'a')]
+    amount: Option<usize>,
+    /// Number of src/dest pairs to use in the simulation
+    #[arg(long = "payments", short = 'p', default_value_t = 1000)]
+    num_pairs: usize,
+    /// The number of adversarial ASs to simulate (top-n)
+    #[arg(long = "num-as", short = 'n', default_value_t = 5)]
+    num_adv_as: usize,
+    /// AS selection strategy. 0 for number of nodes, 1 for number of channels and 2 for betweenness
+    #[arg(long = "as-strategy", short = 's', default_value_t = 1)]
+    as_sel_strategy: usize,
+    /// Minimum share of nodes (0 to 1) that must be mapped to an ASN
+    #[arg(long = "min-coverage", default_value_t = 0.5)]
+    min_asn_coverage: f32,
+    /// Maximum age of the AS database in days
+    #[arg(long = "max-db-age", default_value_t = 90)]
+    max_db_age_days: u64,
+}
+
+#[derive(clap::Args)]
+struct WatchArgs {
+    /// Path to a directory where JSON graph snapshots named after their date appear
+    snapshot_dir: PathBuf,
+    #[arg(long = "graph-source", short = 'g', default_value = "lnd")]
+    graph_type: network_parser::GraphSource,
+    /// Path to the CSV file the results are appended to. Snapshots with results are not simulated
+    /// again
+    #[arg(long = "store", default_value = "sim-results/exposure-monitor.csv")]
+    store: PathBuf,
+    /// Path to TOML file describing the experiment run on each snapshot
+    #[arg(long = "config", short = 'c')]
+    config_file: Option<PathBuf>,
+    /// Preset experiment run on each snapshot. The scenario file takes precedence
+    #[arg(long = "preset")]
+    preset: Option<Scenario>,
+    /// Set the seed for the simulation
+    #[arg(long, short, default_value_t = 19)]
+    run: u64,
+    /// Number of src/dest pairs to use in the simulation of each snapshot. Overrides the scenario
+    /// file [default: 1000]
+    #[arg(long = "payments", short = 'p')]
+    num_pairs: Option<usize>,
+    /// Seconds to wait before checking the directory for new snapshots again
+    #[arg(long = "interval", default_value_t = 3600)]
+    interval_secs: u64,
+    /// Simulate the snapshots that are present and exit instead of waiting for new ones
+    #[arg(long = "once")]
+    once: bool,
+    /// Treatment of nodes with only an onion address: exclude them from the ASs or include them
+    /// in a common Tor AS. Overrides the scenario file
+    #[arg(long = "include-tor", value_parser = TorTreatment::from_str)]
+    include_tor: Option<TorTreatment>,
+    /// AS database the addresses are resolved with: geolite2, ip2location=<FILE> with an
+    /// IP2Location LITE ASN CSV file or ipinfo=<FILE> with an ipinfo.io ASN MMDB file
+    #[arg(long = "asn-db", default_value = "geolite2")]
+    asn_db: AsnDatabase,
+    /// Path to a CSV file with the columns node_or_ip and asn. The ASNs take precedence over the
+    /// database lookup
+    #[arg(long = "asn-overrides")]
+    asn_overrides: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+struct GraphConvertArgs {
+    /// Path to JSON file describing topology
+    graph_file: PathBuf,
+    /// Format of the input graph
+    #[arg(long = "graph-source", short = 'g', default_value = "lnd")]
+    graph_type: network_parser::GraphSource,
+    /// Path to Core Lightning's listnodes output. The graph file is read as CLN's listchannels
+    /// output and the graph source is ignored
+    #[arg(long = "cln-nodes")]
+    cln_nodes: Option<PathBuf>,
+    /// Format of the converted graph
+    #[arg(long = "to", short = 't', default_value = "lnr")]
+    target_type: network_parser::GraphSource,
+    /// Path to the converted graph file
+    #[arg(long = "out", short = 'o', default_value = "converted-graph.json")]
+    output_path: PathBuf,
+    /// Overwrite the existing file, if it exists
+    #[arg(short = 'u', long = "overwrite")]
+    overwrite: bool,
+}
+
+#[derive(clap::Args)]
+struct ExportGraphArgs {
+    /// Path to JSON file describing topology
+    graph_file: PathBuf,
+    #[arg(long = "graph-source", short = 'g', default_value = "lnd")]
+    graph_type: network_parser::GraphSource,
+    /// Secret the IDs are hashed with. A random one is drawn if none is given, so the IDs of two
+    /// exports cannot be linked
+    #[arg(long = "salt")]
+    salt: Option<String>,
+    /// Map nodes with only an onion address to a common Tor AS
+    #[arg(long = "include-tor")]
+    include_tor: bool,
+    /// Path to the anonymized graph file
+    #[arg(long = "out", short = 'o', default_value = "anonymized-graph.json")]
+    output_path: PathBuf,
+    /// Path to the CSV file with the ASN of each hashed node, which can be passed to --asn-map
+    #[arg(long = "asn-map-out", default_value = "anonymized-asn-map.csv")]
+    asn_map_path: PathBuf,
+    /// Overwrite the existing files, if they exist
+    #[arg(short = 'u', long = "overwrite")]
+    overwrite: bool,
+}
+
+#[cfg(feature = "geoip-download")]
+#[derive(clap::Args)]
+struct GeoipArgs {
+    /// MaxMind license key. Defaults to the MAXMIND_LICENSE_KEY environment variable
+    #[arg(long = "license-key")]
+    license_key: Option<String>,
+    /// Comma-separated list of databases to download: asn or country
+    #[arg(
+        long = "editions",
+        value_delimiter = ',',
+        default_value = "asn,country"
+    )]
+    editions: Vec<simulator::GeoIpEdition>,
+    /// Directory the databases are stored in. Defaults to the directory they are discovered from
+    #[arg(long = "dir")]
+    dir: Option<PathBuf>,
+}
+
 fn main() {
-    let args = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut args = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
     let log_level = args.log_level;
     env_logger::builder().filter_level(log_level).init();
-    let graph_source = args.graph_type;
-    let g = network_parser::Graph::from_json_file(
-        std::path::Path::new(&args.graph_file),
-        graph_source.clone(),
-    );
-    let graph = match g {
-        Ok(graph) => simlib::core_types::graph::Graph::to_sim_graph(&graph, graph_source),
-        Err(e) => {
-            error!("Error in graph file {}. Exiting.", e);
-            std::process::exit(-1)
-        }
-    };
-    let output_dir = if let Some(output_dir) = args.output_dir {
-        output_dir
-    } else {
-        PathBuf::from("sim-results")
+    let result = match args.command.take() {
+        Some(Command::Validate(validate_args)) => validate(validate_args),
+        Some(Command::Watch(watch_args)) => watch(watch_args),
+        Some(Command::GraphConvert(convert_args)) => graph_convert(convert_args),
+        Some(Command::ExportGraph(export_args)) => export_graph(export_args),
+        #[cfg(feature = "geoip-download")]
+        Some(Command::Geoip(geoip_args)) => geoip(geoip_args),
+        None => simulate(args, &matches),
     };
+    if let Err(e) = result {
+        error!("{}. Exiting.", e);
+        std::process::exit(-1)
+    }
+}
+
+/// Prefixes an error with the step that failed, e.g. `"Error in scenario file"`
+fn failed<E: std::fmt::Display>(step: &'static str) -> impl Fn(E) -> SimulatorError {
+    move |e| SimulatorError::Cli(format!("{} {}", step, e))
+}
+
+/// Simulates the attacks for each run and amount and writes the reports
+fn simulate(mut args: Cli, matches: &ArgMatches) -> Result<(), SimulatorError> {
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(failed("Error in thread pool"))?;
+    }
+    for (name, share) in [
+        ("Frozen share", args.frozen_share),
+        ("Placement target", args.placement_target),
+        ("AS coverage", args.as_coverage),
+    ] {
+        if let Some(share) = share {
+            if !(0.0..=1.0).contains(&share) {
+                return Err(SimulatorError::Cli(format!(
+                    "{} {} is not between 0 and 1",
+                    name, share
+                )));
+            }
+        }
+    }
+    let scenario = read_scenario(args.config_file.as_ref(), args.preset.take())?;
+    apply_scenario(&mut args, matches, &scenario)?;
+    let (graph, graph_source) = read_graph(&args).map_err(failed("Error in graph file"))?;
+    let mut graph = simlib::core_types::graph::Graph::to_sim_graph(&graph, graph_source);
+    let output_dir = args
+        .output_dir
+        .take()
+        .unwrap_or_else(|| PathBuf::from("sim-results"));
     info!(
         "Simulation results will be written to {:#?}/ directory.",
         output_dir
     );
+    if let Some(capacity_model) = args.capacity_model {
+        let mut metadata = GraphMetadata::new(&graph);
+        metadata.num_imputed_capacities = capacity_model.impute(&mut graph);
+        metadata.capacity_model = Some(capacity_model);
+        info!(
+            "Imputed the capacity of {} of {} channels.",
+            metadata.num_imputed_capacities, metadata.num_channels
+        );
+        if let Err(e) = metadata.write_to_file(output_dir.clone()) {
+            error!("Error writing graph metadata {}.", e);
+        }
+    }
+    for filter in args.filters.iter() {
+        let (num_nodes, num_channels) = filter.apply(&mut graph);
+        info!(
+            "Filter {:?} removed {} nodes and {} channels.",
+            filter, num_nodes, num_channels
+        );
+    }
     let amounts = if let Some(amount) = args.amount {
         vec![amount]
+    } else if let Some(AmountModel::Fixed(amounts)) = &args.amount_model {
+        amounts.clone()
+    } else if let Some(amounts) = scenario.amounts.clone() {
+        amounts
     } else {
-        vec![100, 1000, 10000, 100000, 1000000, 10000000]
-    };
-    let as_selection_strategy = match args.as_sel_strategy {
-        0 => AsSelectionStrategy::MaxNodes,
-        1 => AsSelectionStrategy::MaxChannels,
-        _ => {
-            warn!(
-                "Invalid AsSelectionStrategy. Defaulting to {:?}",
-                AsSelectionStrategy::MaxNodes
-            );
-            AsSelectionStrategy::MaxNodes
-        }
-    };
-    let results = Arc::new(Mutex::new(Vec::with_capacity(amounts.len())));
-    let pairs = simlib::Simulation::draw_n_pairs_for_simulation(&graph, args.num_pairs);
-    amounts.par_iter().for_each(|amount| {
-        info!("Starting simulation for {amount} sat.");
-        let msat = simlib::to_millisatoshi(*amount);
-        let mut builder = SimBuilder::new(
-            args.run,
-            &graph,
-            msat,
-            args.num_adv_as,
-            as_selection_strategy,
-        );
-        let baseline = builder.simulate(pairs.clone());
-        let per_strategy_results = asn_simulation(&builder, baseline);
-        let sim_output = SimOutput {
-            amt_sat: *amount,
-            total_num_payments: args.num_pairs,
-            per_strategy_results,
-        };
-        results.lock().unwrap().push(sim_output);
-        info!("Completed simulation for {amount} sat.");
+        DEFAULT_AMOUNTS.to_vec()
+    };
+    let replay = args
+        .replay_file
+        .as_ref()
+        .map(|path| Replay::from_json_file(path).map_err(failed("Error in replay file")))
+        .transpose()?;
+    let runs = match (&replay, &scenario.seeds) {
+        (Some(replay), _) => {
+            args.num_pairs = replay.pairs.len();
+            vec![replay.run]
+        }
+        (None, Some(seeds)) if !is_set_on_command_line(matches, "run") => seeds.clone(),
+        _ => vec![args.run],
+    };
+    let as_selection_strategy = as_selection_strategy(args.as_sel_strategy)?;
+    let overlap_policy = if args.dedup_overlap {
+        OverlapPolicy::FirstWins
+    } else {
+        OverlapPolicy::Keep
+    };
+    let diff_strategies = match args.diff_strategies.as_slice() {
+        [first, second] => Some((first.clone(), second.clone())),
+        _ => None,
+    };
+    let mut blocklist_scenario = args
+        .blocklist_file
+        .as_ref()
+        .map(|path| {
+            BlocklistScenario::from_json_file(path).map_err(failed("Error in blocklist file"))
+        })
+        .transpose()?;
+    let drop_strategies = drop_strategies(&args, &scenario)?;
+    let resolver = asn_resolver(&args.asn_db, args.asn_overrides.as_ref())?;
+    let tor_aware = args.tor_aware || args.tor_consensus.is_some();
+    let mut tor_treatment = args.include_tor.unwrap_or_else(|| {
+        TorTreatment::from_include_tor(scenario.include_tor.unwrap_or_default())
     });
-    let sim_report = if let Ok(s) = results.lock() {
-        Report(args.run, s.clone())
+    if tor_aware && tor_treatment != TorTreatment::Exclude {
+        warn!("Onion-only nodes are modelled by the Tor model instead of a Tor ASN.");
+        tor_treatment = TorTreatment::Exclude;
+    }
+    if args.adv_nodes_file.is_some() && tor_treatment == TorTreatment::Both {
+        warn!("Onion-only nodes cannot be compared for adversarial nodes. Excluding them.");
+        tor_treatment = TorTreatment::Exclude;
+    }
+    if args.asn_map.is_some() && tor_treatment == TorTreatment::Both {
+        warn!("Onion-only nodes cannot be compared for a mapping file. Using the mapping as is.");
+        tor_treatment = TorTreatment::Exclude;
+    }
+    let (mut as_ip_map, mut tor_as_ip_map) =
+        as_ip_maps(args.asn_map.as_ref(), &graph, &resolver, tor_treatment)?;
+    if let Some(path) = &args.save_asn_map {
+        match as_ip_map.write_to_file(path) {
+            Ok(()) => info!("ASN mapping written to {}.", path.display()),
+            Err(e) => error!("Error writing ASN mapping {}.", e),
+        }
+    }
+    let mut provider_groups = None;
+    if args.adversary_granularity == AdversaryGranularity::Provider {
+        let providers = match &args.providers_file {
+            Some(path) => ProviderMap::from_file(path).map_err(failed("Error in provider file"))?,
+            None => ProviderMap::bundled(),
+        };
+        let (grouped, groups) = as_ip_map.group_by_provider(&providers);
+        as_ip_map = grouped;
+        tor_as_ip_map = tor_as_ip_map.map(|m| m.group_by_provider(&providers).0);
+        provider_groups = Some(groups);
+    }
+    // the given ASNs are simulated as part of their provider's adversary
+    let mut target_asns: Vec<u32> = match &provider_groups {
+        Some(groups) => args.asns.iter().map(|a| groups.adversary_asn(*a)).collect(),
+        None => args.asns.clone(),
+    };
+    let mut seen = std::collections::HashSet::new();
+    target_asns.retain(|asn| seen.insert(*asn));
+    let asn_candidates = args
+        .multi_asn
+        .then(|| AsnCandidates::new(&graph, &resolver));
+    let transit_map = args
+        .as_relationships
+        .as_ref()
+        .map(|path| {
+            AsRelationships::from_file(path)
+                .map(|relationships| TransitMap::new(&graph, &as_ip_map, &relationships))
+                .map_err(failed("Error in AS relationships"))
+        })
+        .transpose()?;
+    let as_metadata = read_as_metadata(&args.as_metadata)?;
+    if let Some(scenario) = blocklist_scenario.as_mut() {
+        scenario
+            .resolve_coalition(as_metadata.as_ref())
+            .map_err(failed("Error in blocklist file"))?;
+    }
+    let concentration = as_ip_map.concentration(&graph);
+    info!(
+        "Channel Gini coefficient across {} ASs: {:.3}.",
+        concentration.num_asns, concentration.channels.gini
+    );
+    let tor_model = if tor_aware {
+        let tor_model = TorModel::new(&graph);
+        info!("Modelling {} onion-only nodes.", tor_model.onion_only.len());
+        match &args.tor_consensus {
+            Some(path) => {
+                let guards = GuardConsensus::from_consensus_file(path, &resolver)
+                    .map_err(failed("Error in Tor consensus"))?;
+                Some(tor_model.with_guards(guards))
+            }
+            None => Some(tor_model),
+        }
     } else {
-        Report(args.run, vec![])
-    };
-
-    sim_report
-        .write_to_file(output_dir)
-        .expect("Failed to write report to file.");
-}
-
-/// Returns the simulation results for each packet drop strategy
-fn asn_simulation(
-    sim_builder: &SimBuilder,
-    baseline_result: simlib::SimResult,
-) -> Vec<PerStrategyResults> {
-    let mut per_strategy_results = vec![];
-    let as_ip_map = AsIpMap::new(&sim_builder.graph, false);
-    let attack_asns = sim_builder.get_adverserial_asns(&as_ip_map);
-    let drop_strategies = vec![
-        PacketDropStrategy::All,
-        PacketDropStrategy::IntraAs,
-        PacketDropStrategy::InterAs,
-    ];
-    for strategy in drop_strategies {
-        let mut attack_results = vec![];
-        let intra_as_channel_ratios = if strategy == PacketDropStrategy::IntraProbability {
-            as_ip_map.get_intra_as_channels_ratio(&sim_builder.graph)
+        None
+    };
+    if let Some(path) = &args.adv_nodes_file {
+        as_ip_map = read_node_list(path)
+            .map_err(|e| e.to_string())
+            .and_then(|nodes| as_ip_map.with_operator_nodes(&nodes, &graph))
+            .map_err(failed("Error in adversarial nodes file"))?;
+        info!(
+            "Simulating {} nodes as a single adversary with ASN {}.",
+            as_ip_map.as_to_nodes()[&OPERATOR_ASN].len(),
+            OPERATOR_ASN
+        );
+    }
+    let sampling_weights = args
+        .sampling
+        .weights(&graph)
+        .map_err(failed("Error in sampling weights"))?;
+    let mut builder = SimBuilder::new(args.run, &graph, 0, args.num_adv_as, as_selection_strategy);
+    if args.adv_nodes_file.is_some() {
+        builder = builder.with_target_asns(vec![OPERATOR_ASN]);
+    } else if !target_asns.is_empty() {
+        builder = builder.with_target_asns(target_asns.clone());
+    }
+    builder
+        .validate(args.num_pairs, &as_ip_map, &drop_strategies)
+        .map_err(failed("Error in configuration"))?;
+    let node_aliases = match (&args.graph_file, args.aliases) {
+        (Some(graph_file), true) => {
+            let aliases = NodeAliases::from_file(graph_file, &args.graph_type)
+                .map_err(failed("Error reading aliases"))?;
+            info!("Read the aliases of {} nodes.", aliases.len());
+            Some(aliases)
+        }
+        _ => None,
+    };
+    let payment_log = args
+        .payment_log
+        .as_ref()
+        .map(|path| PaymentLog::new(path.clone()).map_err(failed("Error creating payment log")))
+        .transpose()?;
+    let checkpoint = args
+        .checkpoint_dir
+        .as_ref()
+        .map(|dir| Checkpoint::new(dir.clone()).map_err(failed("Error creating checkpoint")))
+        .transpose()?;
+    for run in runs {
+        let report_writer = if args.stream {
+            #[cfg(feature = "zstd")]
+            let writer = if args.compress {
+                ReportWriter::new_compressed(output_dir.clone(), run)
+            } else {
+                ReportWriter::new(output_dir.clone(), run)
+            };
+            #[cfg(not(feature = "zstd"))]
+            let writer = ReportWriter::new(output_dir.clone(), run);
+            let writer = writer
+                .and_then(|writer| {
+                    writer.write_header(&concentration)?;
+                    Ok(writer)
+                })
+                .map_err(failed("Error creating report file"))?;
+            Some(writer)
         } else {
-            HashMap::default()
+            None
         };
-        for (asn, nodes) in attack_asns.iter() {
-            let mut attack_sim = SimBuilder::per_asn_simulation(
-                baseline_result.clone(),
-                *asn,
-                nodes,
-                strategy,
-                intra_as_channel_ratios.get(asn),
-                &as_ip_map,
-            );
-            // add the baseline results
-            attack_sim.sim_results.insert(
-                0,
-                SimResult::from_simlib_results(baseline_result.clone(), 0),
-            );
-            attack_results.push(attack_sim);
+        let progress = if args.no_progress {
+            SimProgress::hidden()
+        } else {
+            SimProgress::new()
+        };
+        let drawn_amounts = args
+            .amount_model
+            .as_ref()
+            .filter(|model| model.draws_per_payment());
+        let amounts = match drawn_amounts {
+            Some(model) => model.amounts_sat(args.num_pairs, run),
+            None => amounts.clone(),
+        };
+        let (decisions, pairs): (Decisions, Vec<(ID, ID)>) = if let Some(replay) = &replay {
+            (Decisions::replaying(replay), replay.pairs.clone())
+        } else {
+            let pairs = if let Some(weights) = &sampling_weights {
+                weights.draw_pairs(&graph, args.num_pairs, run)
+            } else {
+                simlib::Simulation::draw_n_pairs_for_simulation(&graph, args.num_pairs).collect()
+            };
+            if args.record_replay {
+                (Decisions::recording(run), pairs)
+            } else {
+                (Decisions::new(run), pairs)
+            }
+        };
+        let results = amounts
+            .par_iter()
+            .map(|amount| -> Result<SimOutput, SimulatorError> {
+                info!("Starting simulation for {amount} sat.");
+                let msat = simlib::to_millisatoshi(*amount);
+                let amount_progress = progress.amount_bar(*amount);
+                let mut builder =
+                    SimBuilder::new(run, &graph, msat, args.num_adv_as, as_selection_strategy)
+                        .with_overlap_policy(overlap_policy)
+                        .with_routing_metric(args.routing_metric)
+                        .with_payment_parts(args.payment_parts)
+                        .with_progress(amount_progress.clone());
+                if let Some(model) = drawn_amounts {
+                    builder = builder.with_amount_model(model.clone());
+                }
+                if args.adv_nodes_file.is_some() {
+                    builder = builder.with_target_asns(vec![OPERATOR_ASN]);
+                } else if !target_asns.is_empty() {
+                    builder = builder.with_target_asns(target_asns.clone());
+                }
+                let (pair_draw, amount_pairs) = if args.independent_pairs {
+                    let seed = PairDraw::seed_for(run, *amount);
+                    let pairs = if let Some(weights) = &sampling_weights {
+                        weights.draw_pairs(&graph, args.num_pairs, seed)
+                    } else {
+                        draw_uniform_pairs(&graph, args.num_pairs, seed)
+                    };
+                    (PairDraw::Independent { seed }, pairs)
+                } else {
+                    (PairDraw::Shared, pairs.clone())
+                };
+                let (baseline, baseline_stage) = StageTelemetry::measure(
+                    None,
+                    None,
+                    || match &checkpoint {
+                        Some(checkpoint) => {
+                            builder.simulate_or_resume(amount_pairs.into_iter(), checkpoint)
+                        }
+                        None => Ok(builder.simulate(amount_pairs.into_iter())),
+                    },
+                    |b| b.as_ref().map(|b| b.total_num).unwrap_or_default(),
+                );
+                let baseline = baseline.map_err(failed("Error in checkpoint"))?;
+                if args.all_ases || args.exposure_threshold.is_some() {
+                    let exposure = SimBuilder::exposure_scores(&baseline, &as_ip_map);
+                    let mut path = output_dir.clone();
+                    path.push(format!("as-exposure-{}sat.csv", amount));
+                    if let Err(e) = write_exposure_csv(&exposure, &path) {
+                        error!("Error writing AS exposure to {}: {}", path.display(), e);
+                    }
+                    if let Some(threshold) = args.exposure_threshold {
+                        let exposed_asns: Vec<u32> = exposure
+                            .iter()
+                            .filter(|e| e.exposure >= threshold)
+                            .map(|e| e.asn)
+                            .collect();
+                        info!(
+                            "{} ASs with an exposure of at least {threshold}.",
+                            exposed_asns.len()
+                        );
+                        builder = builder.with_target_asns(exposed_asns);
+                    }
+                }
+                let value_ranking = if args.rerank_by_value {
+                    let ranking = ValueRanking::new(
+                        SimBuilder::value_exposure_scores(&baseline, &as_ip_map),
+                        args.num_adv_as,
+                    );
+                    info!("Simulating ASs {:?} for {amount} sat.", ranking.asns());
+                    builder = builder.with_target_asns(ranking.asns());
+                    Some(ranking)
+                } else {
+                    None
+                };
+                if args.fee_revenue {
+                    let fees_per_asn =
+                        SimBuilder::fees_per_asn(&SimBuilder::fees_per_node(&baseline), &as_ip_map);
+                    let mut path = output_dir.clone();
+                    path.push(format!("fee-revenue-{}sat.csv", amount));
+                    if let Err(e) = write_fee_revenue_csv(&fees_per_asn, &path) {
+                        error!("Error writing fee revenue to {}: {}", path.display(), e);
+                    }
+                }
+                let mut runner = SimulationRunner::new(builder, &as_ip_map)
+                    .with_drop_strategies(drop_strategies.clone())
+                    .with_baseline(args.include_baseline)
+                    .with_decisions(&decisions)
+                    .with_path_dedup(args.dedup_paths)
+                    .with_min_sample_size(args.min_sample_size);
+                if let Some(num_resamples) = args.bootstrap_resamples {
+                    runner = runner.with_bootstrap(num_resamples);
+                }
+                if let Some(cost_model) = args.cost_model {
+                    runner = runner.with_cost_model(cost_model);
+                }
+                if args.telemetry {
+                    runner = runner.with_telemetry(vec![baseline_stage]);
+                }
+                if let Some(penalty_msat) = args.as_diverse_penalty_msat {
+                    runner = runner.with_defensive_routing(AsDiverseRouting { penalty_msat });
+                }
+                if let Some(frozen_share) = args.frozen_share {
+                    runner = runner.with_liquidity_freeze(LiquidityFreeze { frozen_share });
+                }
+                if let Some(max_retries) = args.max_retries {
+                    runner = runner.with_retry_policy(RetryPolicy { max_retries });
+                }
+                if let Some(min_affected) = args.min_affected {
+                    runner = runner.with_min_affected(min_affected);
+                }
+                if let Some(target_share) = args.placement_target {
+                    runner = runner.with_placement_target(target_share);
+                }
+                if let Some(sweep) = args.num_as_sweep {
+                    runner = runner.with_num_as_sweep(sweep);
+                }
+                if let Some(k) = args.pair_coalitions {
+                    runner = runner.with_pair_coalitions(k);
+                }
+                if let Some(candidates) = &asn_candidates {
+                    runner = runner.with_asn_candidates(candidates);
+                }
+                if let Some(transit_map) = &transit_map {
+                    runner = runner.with_transit_map(transit_map);
+                }
+                if let Some(as_metadata) = &as_metadata {
+                    runner = runner.with_as_metadata(as_metadata);
+                }
+                if let Some(groups) = &provider_groups {
+                    runner = runner.with_providers(groups);
+                }
+                if let Some(asn) = args.grow_asn {
+                    let steps = if args.growth_steps.is_empty() {
+                        DEFAULT_GROWTH_STEPS.to_vec()
+                    } else {
+                        args.growth_steps.clone()
+                    };
+                    runner = runner.with_growth(AsGrowth { asn, steps });
+                }
+                if let Some(budgets) = &args.drop_budgets {
+                    let budgets = if budgets.is_empty() {
+                        DEFAULT_DROP_BUDGETS.to_vec()
+                    } else {
+                        budgets.clone()
+                    };
+                    runner = runner.with_drop_budgets(budgets);
+                }
+                if let Some(coverage) = args.as_coverage {
+                    runner = runner.with_coverage(coverage);
+                }
+                if let Some(levels) = &args.coverage_sweep {
+                    let levels = if levels.is_empty() {
+                        DEFAULT_COVERAGE_LEVELS.to_vec()
+                    } else {
+                        levels.clone()
+                    };
+                    runner = runner.with_coverage_levels(levels);
+                }
+                runner = runner.with_network_impact(args.network_impact);
+                runner = runner.with_classify_failed(args.classify_failed);
+                if let Some(rounds) = args.evasion_rounds {
+                    runner = runner.with_evasion(EvasionConfig {
+                        rounds,
+                        budget: args.evasion_budget,
+                        channel_capacity_msat: simlib::to_millisatoshi(args.evasion_capacity_sat),
+                        strategy: args.evasion_strategy.clone(),
+                    });
+                }
+                if let Some((first, second)) = &diff_strategies {
+                    runner = runner.with_diff_strategies(first.clone(), second.clone());
+                }
+                if let Some(chunk_size) = args.chunk_size {
+                    runner = runner.with_chunk_size(chunk_size);
+                }
+                if let Some(payment_log) = &payment_log {
+                    runner = runner.with_payment_log(payment_log);
+                }
+                if let Some(checkpoint) = &checkpoint {
+                    runner = runner.with_checkpoint(checkpoint);
+                }
+                if let Some(node_aliases) = &node_aliases {
+                    runner = runner.with_node_aliases(node_aliases);
+                }
+                if let Some(scenario) = &blocklist_scenario {
+                    runner = runner.with_blocklist(scenario);
+                }
+                if let Some(weights) = &sampling_weights {
+                    runner = runner.with_sampling_weights(weights.clone());
+                }
+                if let Some(tor_model) = &tor_model {
+                    runner = runner.with_tor_model(tor_model);
+                }
+                if let Some(tor_as_ip_map) = &tor_as_ip_map {
+                    runner = runner.with_tor_comparison(tor_as_ip_map, true);
+                }
+                let mut per_strategy_results = vec![];
+                // the remaining strategies are not written once a line failed
+                let mut streamed = Ok(());
+                let attacks = runner.run_attacks(baseline, |r| match &report_writer {
+                    Some(writer) if streamed.is_ok() => {
+                        streamed = writer
+                            .write_strategy_results(*amount, args.num_pairs, &r)
+                            .map_err(failed("Error writing strategy results"));
+                    }
+                    Some(_) => {}
+                    None => per_strategy_results.push(r),
+                });
+                let mut sim_output = attacks?;
+                streamed?;
+                if let (Some(writer), Some(b)) = (&report_writer, sim_output.baseline.take()) {
+                    writer
+                        .write_baseline(*amount, args.num_pairs, &b)
+                        .map_err(failed("Error writing baseline"))?;
+                }
+                if let (Some(writer), Some(paths)) = (&report_writer, sim_output.paths.take()) {
+                    writer
+                        .write_paths(*amount, &paths)
+                        .map_err(failed("Error writing paths"))?;
+                }
+                sim_output.per_strategy_results = per_strategy_results;
+                sim_output.value_ranking = value_ranking;
+                sim_output.pair_draw = pair_draw;
+                sim_output.amount_model = drawn_amounts.map(|model| model.to_string());
+                if let Some(writer) = &report_writer {
+                    writer
+                        .write_summary(&sim_output)
+                        .map_err(failed("Error writing summary"))?;
+                }
+                amount_progress.finish();
+                info!("Completed simulation for {amount} sat.");
+                Ok(sim_output)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        progress.finish();
+        if args.record_replay {
+            match decisions
+                .into_replay(pairs)
+                .write_to_file(output_dir.clone())
+            {
+                Ok(path) => info!("Decisions recorded to {}.", path.display()),
+                Err(e) => error!("Error recording decisions: {}", e),
+            }
         }
-        per_strategy_results.push(PerStrategyResults {
-            strategy,
-            attack_results,
-        })
+        let mut sim_report = Report(run, results, Some(concentration.clone()));
+        if args.rerank_by_value {
+            ValueRanking::compare_across_amounts(&mut sim_report.1);
+        }
+        write_report(&sim_report, &args, &output_dir, report_writer.is_some())?;
     }
-    per_strategy_results
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use network_parser::GraphSource::*;
-    use simlib::graph::Graph;
-    use std::path::Path;
-
-    #[test]
-    fn baseline_to_as_results() {
-        let graph = Graph::to_sim_graph(
-            &network_parser::Graph::from_json_file(
-                &Path::new("test_data/trivial_connected_lnd.json"),
-                Lnd,
-            )
-            .unwrap(),
-            Lnd,
-        );
-        let amt_msat = 1000;
-        let num_adv_as = 1;
-        let run = 0;
-        let num_pairs = 3;
-        let mut sim_builder = SimBuilder::new(
-            run,
-            &graph,
-            amt_msat,
-            num_adv_as,
-            AsSelectionStrategy::MaxNodes,
-        );
-        let pairs = simlib::Simulation::draw_n_pairs_for_simulation(&graph, num_pairs);
-        let baseline_result = sim_builder.simulate(pairs);
-        let actual = asn_simulation(&sim_builder, baseline_result);
-        assert_eq!(actual.len(), 3);
+    Ok(())
+}
+
+/// Reads the scenario file, if given, and fills in the options it does not set from the preset
+fn read_scenario(
+    config_file: Option<&PathBuf>,
+    preset: Option<Scenario>,
+) -> Result<ScenarioConfig, SimulatorError> {
+    let scenario = match config_file {
+        Some(path) => {
+            ScenarioConfig::from_toml_file(path).map_err(failed("Error in scenario file"))?
+        }
+        None => ScenarioConfig::default(),
+    };
+    Ok(match preset {
+        Some(preset) => {
+            info!("Using preset {}: {}.", preset.name, preset.description);
+            scenario.or(preset.config)
+        }
+        None => scenario,
+    })
+}
+
+/// The drop strategies of the scenario, or the default ones, followed by the strategies given on
+/// the command line
+fn drop_strategies(
+    args: &Cli,
+    scenario: &ScenarioConfig,
+) -> Result<Vec<PacketDropStrategy>, SimulatorError> {
+    let mut drop_strategies = scenario
+        .drop_strategies()
+        .map_err(failed("Error in scenario file"))?
+        .unwrap_or_else(|| DEFAULT_DROP_STRATEGIES.to_vec());
+    if let Some(hold_time_secs) = args.hold_time_secs {
+        drop_strategies.push(PacketDropStrategy::Delay {
+            hold_time_secs,
+            timeout_secs: args.delay_timeout_secs,
+        });
+    }
+    for (threshold_sat, above) in [
+        (args.amount_above_sat, true),
+        (args.amount_below_sat, false),
+    ] {
+        if let Some(threshold_sat) = threshold_sat {
+            drop_strategies.push(PacketDropStrategy::AmountThreshold {
+                threshold_msat: simlib::to_millisatoshi(threshold_sat),
+                above,
+            });
+        }
+    }
+    if let Some(path) = &args.blacklist_file {
+        let blacklist =
+            NodeBlacklist::from_file(path).map_err(failed("Error in destination blacklist"))?;
+        drop_strategies.push(PacketDropStrategy::DestinationBlacklist { blacklist });
+    }
+    Ok(drop_strategies)
+}
+
+/// The AS map read from the mapping file or resolved for the graph and, if both Tor treatments
+/// are compared, the resolved map with onion-only nodes included
+fn as_ip_maps(
+    asn_map: Option<&PathBuf>,
+    graph: &simlib::core_types::graph::Graph,
+    resolver: &AsnResolver,
+    tor_treatment: TorTreatment,
+) -> Result<(AsIpMap, Option<AsIpMap>), SimulatorError> {
+    let as_ip_map = |include_tor| match asn_map {
+        Some(path) => AsIpMap::from_file(path).map_err(failed("Error in ASN mapping file")),
+        None => Ok(AsIpMap::with_resolver(graph, include_tor, resolver)),
+    };
+    let primary = as_ip_map(tor_treatment == TorTreatment::Include)?;
+    let with_tor = match tor_treatment {
+        TorTreatment::Both => Some(as_ip_map(true)?),
+        _ => None,
+    };
+    Ok((primary, with_tor))
+}
+
+/// Merges the metadata files, later files overriding earlier ones. None if no file is given
+fn read_as_metadata(paths: &[PathBuf]) -> Result<Option<AsMetadataMap>, SimulatorError> {
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    let mut as_metadata = AsMetadataMap::default();
+    for path in paths.iter() {
+        as_metadata.merge(AsMetadataMap::from_file(path).map_err(failed("Error in AS metadata"))?);
+    }
+    Ok(Some(as_metadata))
+}
+
+/// Writes the report unless it was streamed and the other formats that were requested. Only
+/// failing to write the report itself is an error
+fn write_report(
+    sim_report: &Report,
+    args: &Cli,
+    output_dir: &Path,
+    streamed: bool,
+) -> Result<(), SimulatorError> {
+    if !streamed {
+        #[cfg(feature = "zstd")]
+        let written = if args.compress {
+            sim_report.write_to_zstd_file(output_dir.to_path_buf())
+        } else {
+            sim_report.write_to_file(output_dir.to_path_buf())
+        };
+        #[cfg(not(feature = "zstd"))]
+        let written = sim_report.write_to_file(output_dir.to_path_buf());
+        written.map_err(failed("Error writing report"))?;
+    }
+    if args.report_format == ReportFormat::Html {
+        if let Err(e) = sim_report.write_html(output_dir.to_path_buf()) {
+            error!("Error writing HTML report {}.", e);
+        }
+    }
+    #[cfg(feature = "parquet")]
+    if args.parquet {
+        if let Err(e) = sim_report.write_to_parquet(output_dir.to_path_buf()) {
+            error!("Error writing Parquet file {}.", e);
+        }
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(sqlite_file) = &args.sqlite_file {
+        if let Err(e) = sim_report.write_to_sqlite(sqlite_file) {
+            error!("Error writing to SQLite database {}.", e);
+        }
+    }
+    Ok(())
+}
+
+/// Opens the AS database and overrides the ASNs of the nodes and addresses in the overrides file
+fn asn_resolver(
+    asn_db: &AsnDatabase,
+    asn_overrides: Option<&PathBuf>,
+) -> Result<AsnResolver, SimulatorError> {
+    let db = asn_db.open().map_err(failed("Error opening AS database"))?;
+    let resolver = AsnResolver::with_lookup(db, IP_CACHE_SIZE);
+    let Some(path) = asn_overrides else {
+        return Ok(resolver);
+    };
+    let overrides =
+        AsnOverrides::from_csv_file(path).map_err(failed("Error in ASN overrides file"))?;
+    info!(
+        "Overriding the ASN of {} nodes and addresses.",
+        overrides.len()
+    );
+    Ok(resolver.with_overrides(overrides))
+}
+
+fn as_selection_strategy(as_sel_strategy: usize) -> Result<AsSelectionStrategy, SimulatorError> {
+    AsSelectionStrategy::try_from(as_sel_strategy).map_err(failed("Error in configuration"))
+}
+
+/// True if the argument was given on the command line rather than taken from its default
+fn is_set_on_command_line(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// Fails if the output file exists and may not be overwritten
+fn check_overwrite(path: &Path, overwrite: bool) -> Result<(), SimulatorError> {
+    if !overwrite && path.exists() {
+        return Err(SimulatorError::Cli(format!(
+            "Output file {:#?} exists, refusing to overwrite",
+            path
+        )));
+    }
+    Ok(())
+}
+
+/// Fills in the options of the scenario file that were not given on the command line. Amounts,
+/// seeds and drop strategies are read from the scenario directly
+fn apply_scenario(
+    args: &mut Cli,
+    matches: &ArgMatches,
+    scenario: &ScenarioConfig,
+) -> Result<(), SimulatorError> {
+    fn resolve<T: Clone>(matches: &ArgMatches, id: &str, arg: &mut T, value: &Option<T>) {
+        if let Some(value) = value {
+            if !is_set_on_command_line(matches, id) {
+                *arg = value.clone();
+            }
+        }
+    }
+    resolve(
+        matches,
+        "num_pairs",
+        &mut args.num_pairs,
+        &scenario.num_pairs,
+    );
+    resolve(
+        matches,
+        "num_adv_as",
+        &mut args.num_adv_as,
+        &scenario.num_adv_as,
+    );
+    resolve(
+        matches,
+        "as_sel_strategy",
+        &mut args.as_sel_strategy,
+        &scenario.as_strategy,
+    );
+    resolve(matches, "asns", &mut args.asns, &scenario.asns);
+    resolve(
+        matches,
+        "dedup_overlap",
+        &mut args.dedup_overlap,
+        &scenario.dedup_overlap,
+    );
+    resolve(matches, "stream", &mut args.stream, &scenario.stream);
+    resolve(
+        matches,
+        "include_baseline",
+        &mut args.include_baseline,
+        &scenario.include_baseline,
+    );
+    resolve(
+        matches,
+        "fee_revenue",
+        &mut args.fee_revenue,
+        &scenario.fee_revenue,
+    );
+    resolve(matches, "all_ases", &mut args.all_ases, &scenario.all_ases);
+    let diff_strategies = scenario
+        .diff_strategies()
+        .map_err(failed("Error in scenario file"))?;
+    resolve(
+        matches,
+        "diff_strategies",
+        &mut args.diff_strategies,
+        &diff_strategies,
+    );
+    if args.blocklist_file.is_none() {
+        args.blocklist_file = scenario.blocklist.clone();
+    }
+    if args.output_dir.is_none() {
+        args.output_dir = scenario.output_dir.clone();
+    }
+    Ok(())
+}
+
+/// Reads the graph file in its format or fetches the graph from the LND node
+fn read_graph(
+    args: &Cli,
+) -> Result<(network_parser::Graph, network_parser::GraphSource), Box<dyn std::error::Error>> {
+    #[cfg(feature = "lnd-grpc")]
+    if let (Some(address), Some(macaroon), Some(tls_cert)) =
+        (&args.from_lnd, &args.macaroon, &args.tls_cert)
+    {
+        info!("Fetching the graph from LND node {}.", address);
+        let graph = simulator::fetch_lnd_graph(address, macaroon, tls_cert)?;
+        return Ok((graph, network_parser::GraphSource::Lnd));
+    }
+    let graph_file = args.graph_file.as_ref().ok_or("Graph file is required.")?;
+    Ok(match &args.cln_nodes {
+        Some(listnodes) => (
+            read_cln_graph(graph_file, listnodes)?,
+            network_parser::GraphSource::Lnd,
+        ),
+        None => (
+            network_parser::Graph::from_json_file(graph_file, args.graph_type.clone())?,
+            args.graph_type.clone(),
+        ),
+    })
+}
+
+/// Prints the validation report as JSON and exits with code 1 if the configuration is invalid
+fn validate(args: ValidateArgs) -> Result<(), SimulatorError> {
+    let config = ExperimentConfig {
+        graph_file: args.graph_file,
+        graph_source: args.graph_type,
+        output_dir: args.output_dir,
+        amounts: args
+            .amount
+            .map(|a| vec![a])
+            .unwrap_or_else(|| DEFAULT_AMOUNTS.to_vec()),
+        num_pairs: args.num_pairs,
+        num_adv_as: args.num_adv_as,
+        as_sel_strategy: args.as_sel_strategy,
+        min_asn_coverage: args.min_asn_coverage,
+        max_db_age_days: args.max_db_age_days,
+    };
+    let report = config.validate();
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.valid {
+        error!("Validation failed.");
+        std::process::exit(1)
+    }
+    Ok(())
+}
+
+/// Writes the graph in the target format
+fn graph_convert(args: GraphConvertArgs) -> Result<(), SimulatorError> {
+    check_overwrite(&args.output_path, args.overwrite)?;
+    let read_json = |path: &PathBuf| -> Result<serde_json::Value, String> {
+        File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|f| serde_json::from_reader(f).map_err(|e| e.to_string()))
+    };
+    let graph = read_json(&args.graph_file).map_err(failed("Error in graph file"))?;
+    let (graph, graph_type) = match &args.cln_nodes {
+        Some(listnodes) => (
+            read_json(listnodes)
+                .and_then(|nodes| cln_to_lnd(&graph, &nodes))
+                .map_err(failed("Error in CLN graph"))?,
+            network_parser::GraphSource::Lnd,
+        ),
+        None => (graph, args.graph_type),
+    };
+    let converted = convert_graph_json(&graph, &graph_type, &args.target_type)
+        .map_err(failed("Error converting graph"))?;
+    File::create(&args.output_path)
+        .map_err(|e| e.to_string())
+        .and_then(|f| serde_json::to_writer_pretty(f, &converted).map_err(|e| e.to_string()))
+        .map_err(failed("Error writing converted graph"))?;
+    info!("Converted graph written to {:#?}.", args.output_path);
+    Ok(())
+}
+
+fn export_graph(args: ExportGraphArgs) -> Result<(), SimulatorError> {
+    for path in [&args.output_path, &args.asn_map_path] {
+        check_overwrite(path, args.overwrite)?;
+    }
+    let graph = network_parser::Graph::from_json_file(&args.graph_file, args.graph_type.clone())
+        .map_err(failed("Error in graph file"))?;
+    let graph = simlib::core_types::graph::Graph::to_sim_graph(&graph, args.graph_type.clone());
+    let as_ip_map = AsIpMap::new(&graph, args.include_tor);
+    let mut json: serde_json::Value = File::open(&args.graph_file)
+        .map_err(|e| e.to_string())
+        .and_then(|f| serde_json::from_reader(f).map_err(|e| e.to_string()))
+        .map_err(failed("Error in graph file"))?;
+    let salt = args.salt.unwrap_or_else(|| {
+        info!("No salt given, drawing a random one.");
+        format!("{:032x}", rand::random::<u128>())
+    });
+    let anonymizer = Anonymizer::new(salt);
+    let hashed_map = anonymizer.anonymize_graph_json(&mut json, &args.graph_type, &as_ip_map);
+    File::create(&args.output_path)
+        .map_err(|e| e.to_string())
+        .and_then(|f| serde_json::to_writer_pretty(f, &json).map_err(|e| e.to_string()))
+        .map_err(failed("Error writing anonymized graph"))?;
+    hashed_map
+        .write_to_file(&args.asn_map_path)
+        .map_err(failed("Error writing ASN mapping"))?;
+    info!(
+        "Anonymized graph written to {:#?} and its ASN mapping to {:#?}.",
+        args.output_path, args.asn_map_path
+    );
+    Ok(())
+}
+
+#[cfg(feature = "geoip-download")]
+fn geoip(args: GeoipArgs) -> Result<(), SimulatorError> {
+    let license_key = args
+        .license_key
+        .or_else(|| std::env::var("MAXMIND_LICENSE_KEY").ok())
+        .ok_or_else(|| SimulatorError::Cli(String::from("No MaxMind license key given")))?;
+    let dir = args.dir.unwrap_or_else(simulator::geoip_data_dir);
+    for edition in args.editions {
+        simulator::download_database(&license_key, edition, &dir).map_err(|e| {
+            SimulatorError::Cli(format!("Error downloading {} {}", edition.edition_id(), e))
+        })?;
+    }
+    Ok(())
+}
+
+/// Runs the experiment of the scenario on each snapshot without results in the store, appends the
+/// results and waits for new snapshots. Snapshots that cannot be parsed, e.g. because they are
+/// still being written, are retried in the next round
+fn watch(args: WatchArgs) -> Result<(), SimulatorError> {
+    let scenario = read_scenario(args.config_file.as_ref(), args.preset)?;
+    let drop_strategies = scenario
+        .drop_strategies()
+        .map_err(failed("Error in scenario file"))?
+        .unwrap_or_else(|| {
+            vec![
+                PacketDropStrategy::All,
+                PacketDropStrategy::IntraAs,
+                PacketDropStrategy::InterAs,
+            ]
+        });
+    let amounts = scenario
+        .amounts
+        .clone()
+        .unwrap_or_else(|| DEFAULT_AMOUNTS.to_vec());
+    let as_selection_strategy = as_selection_strategy(scenario.as_strategy.unwrap_or(1))?;
+    let num_adv_as = scenario.num_adv_as.unwrap_or(5);
+    let num_pairs = args
+        .num_pairs
+        .or(scenario.num_pairs)
+        .unwrap_or(DEFAULT_NUM_PAIRS);
+    let include_tor = match args
+        .include_tor
+        .or(scenario.include_tor.map(TorTreatment::from_include_tor))
+        .unwrap_or_default()
+    {
+        TorTreatment::Exclude => false,
+        TorTreatment::Include => true,
+        TorTreatment::Both => {
+            return Err(SimulatorError::Cli(String::from(
+                "watch cannot compare Tor treatments, use exclude or include",
+            )))
+        }
+    };
+    let mut processed =
+        processed_snapshots(&args.store).map_err(failed("Error reading results store"))?;
+    info!(
+        "Watching {:#?} for new snapshots, {} already in {:#?}.",
+        args.snapshot_dir,
+        processed.len(),
+        args.store
+    );
+    // most addresses do not change between snapshots
+    let resolver = asn_resolver(&args.asn_db, args.asn_overrides.as_ref())?;
+    loop {
+        let files = snapshot_files(&args.snapshot_dir)
+            .map_err(failed("Error reading snapshot directory"))?;
+        for file in files.iter() {
+            let snapshot = file
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if processed.contains(&snapshot) {
+                continue;
+            }
+            let graph = match network_parser::Graph::from_json_file(file, args.graph_type.clone()) {
+                Ok(graph) => {
+                    simlib::core_types::graph::Graph::to_sim_graph(&graph, args.graph_type.clone())
+                }
+                Err(e) => {
+                    warn!("Skipping snapshot {} for now: {}", snapshot, e);
+                    continue;
+                }
+            };
+            let as_ip_map = AsIpMap::with_resolver(&graph, include_tor, &resolver);
+            let mut results = vec![];
+            for amount in amounts.iter() {
+                info!("Simulating {amount} sat in snapshot {snapshot}.");
+                let mut builder = SimBuilder::new(
+                    args.run,
+                    &graph,
+                    simlib::to_millisatoshi(*amount),
+                    num_adv_as,
+                    as_selection_strategy,
+                );
+                if let Some(asns) = &scenario.asns {
+                    builder = builder.with_target_asns(asns.clone());
+                }
+                builder
+                    .validate(num_pairs, &as_ip_map, &drop_strategies)
+                    .map_err(failed("Error in configuration"))?;
+                results.extend(builder.simulate_snapshot(
+                    &snapshot,
+                    num_pairs,
+                    &as_ip_map,
+                    &drop_strategies,
+                ));
+            }
+            append_series_csv(&results, &args.store)
+                .map_err(failed("Error writing results store"))?;
+            info!(
+                "Results of snapshot {} appended to {:#?}.",
+                snapshot, args.store
+            );
+            processed.insert(snapshot);
+        }
+        if args.once {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_secs(args.interval_secs));
     }
 }
@@ -0,0 +1,113 @@
+use clap::Parser;
+use log::{error, info, LevelFilter};
+use rand::{rngs::StdRng, SeedableRng};
+use simulator::{relabel_graph_json, AsIpMap, RelabelRule};
+use std::{error::Error, fs::File, path::PathBuf};
+
+#[derive(clap::Parser)]
+#[command(name = "relabel-graph", version, about)]
+struct Cli {
+    /// Path to JSON file describing topology
+    graph_file: PathBuf,
+    /// Path to JSON file with the list of rules describing which ASs' nodes move where
+    #[arg(long = "rules")]
+    rules_file: PathBuf,
+    #[arg(long = "log", short = 'l', default_value = "info")]
+    log_level: LevelFilter,
+    /// Path to the modified graph file
+    #[arg(long = "out", short = 'o')]
+    output_path: Option<PathBuf>,
+    #[arg(long = "graph-source", short = 'g', default_value = "lnd")]
+    graph_type: network_parser::GraphSource,
+    /// Set the seed used to sample the new addresses
+    #[arg(long, short, default_value_t = 19)]
+    run: u64,
+    /// Overwrite the existing file, if it exists
+    #[arg(short = 'u', long = "overwrite")]
+    overwrite: bool,
+    /// Map nodes with only an onion address to a common Tor AS
+    #[arg(long = "include-tor")]
+    include_tor: bool,
+    verbose: bool,
+}
+
+fn main() {
+    let args = Cli::parse();
+    let log_level = args.log_level;
+    env_logger::builder().filter_level(log_level).init();
+    let graph_source = args.graph_type;
+    let g = network_parser::Graph::from_json_file(
+        std::path::Path::new(&args.graph_file),
+        graph_source.clone(),
+    );
+    let graph = match g {
+        Ok(graph) => simlib::core_types::graph::Graph::to_sim_graph(&graph, graph_source.clone()),
+        Err(e) => {
+            error!("Error in graph file {}. Exiting.", e);
+            std::process::exit(-1)
+        }
+    };
+    let rules = match RelabelRule::from_json_file(&args.rules_file) {
+        Ok(rules) => rules,
+        Err(e) => {
+            error!("Error in rules file {}. Exiting.", e);
+            std::process::exit(-1)
+        }
+    };
+    let output_path = if let Some(output_path) = args.output_path {
+        output_path
+    } else {
+        PathBuf::from("relabelled-graph.json")
+    };
+    let as_ip_map = AsIpMap::new(&graph, args.include_tor);
+    let mut rng = StdRng::seed_from_u64(args.run);
+    let addresses = match as_ip_map.relabel_addresses(&graph, &rules, &mut rng) {
+        Ok(addresses) => addresses,
+        Err(e) => {
+            error!("{} Exiting.", e);
+            std::process::exit(-1)
+        }
+    };
+    info!("Moving {} nodes to a different AS.", addresses.len());
+    let mut json: serde_json::Value =
+        serde_json::from_reader(File::open(&args.graph_file).expect("Error opening graph file."))
+            .expect("Error reading graph file.");
+    relabel_graph_json(&mut json, &addresses, &graph_source);
+    write_to_json_file(&json, &output_path, args.overwrite).unwrap();
+    info!("Modified graph written to {:#?}.", output_path);
+}
+
+fn write_to_json_file(
+    graph: &serde_json::Value,
+    output_path: &PathBuf,
+    overwrite_allowed: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !overwrite_allowed && output_path.exists() {
+        Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "Output file exists, refusing to overwrite.",
+        )))
+    } else {
+        let file = File::create(output_path)?;
+        serde_json::to_writer_pretty(file, graph)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn persist() {
+        let graph = serde_json::json!({"nodes": [], "edges": []});
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        let path = PathBuf::from(file.path());
+        assert!(write_to_json_file(&graph, &path, false).is_err());
+        assert!(write_to_json_file(&graph, &path, true).is_ok());
+        let actual: serde_json::Value =
+            serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        assert_eq!(actual, graph);
+    }
+}
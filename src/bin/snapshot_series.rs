@@ -0,0 +1,133 @@
+use clap::Parser;
+use log::{error, info, warn, LevelFilter};
+use simulator::{
+    snapshot_files, write_series_csv, AsIpMap, AsSelectionStrategy, AsnResolver,
+    PacketDropStrategy, SimBuilder,
+};
+use std::path::PathBuf;
+
+#[derive(clap::Parser)]
+#[command(name = "snapshot-series", version, about)]
+struct Cli {
+    /// Path to a directory of JSON graph snapshots named after their date, e.g. 2024-01-12.json
+    snapshot_dir: PathBuf,
+    #[arg(long = "log", short = 'l', default_value = "info")]
+    log_level: LevelFilter,
+    /// Path to CSV file where the time series should be written to
+    #[arg(long = "out", short = 'o')]
+    output_path: Option<PathBuf>,
+    #[arg(long = "graph-source", short = 'g', default_value = "lnd")]
+    graph_type: network_parser::GraphSource,
+    /// Comma-separated list of payment volumes (in sat)
+    #[arg(
+        long = "amounts",
+        short = 'a',
+        value_delimiter = ',',
+        default_value = "1000"
+    )]
+    amounts: Vec<usize>,
+    /// Set the seed for the simulation
+    #[arg(long, short, default_value_t = 19)]
+    run: u64,
+    /// Number of src/dest pairs to use in the simulation of each snapshot
+    #[arg(long = "payments", short = 'p', default_value_t = 1000)]
+    num_pairs: usize,
+    /// The number of adversarial ASs to simulate (top-n) in each snapshot
+    #[arg(long = "num-as", short = 'n', default_value_t = 5)]
+    num_adv_as: usize,
+    /// AS selection strategy. 0 for number of nodes, 1 for number of channels and 2 for betweenness
+    #[arg(long = "as-strategy", short = 's', default_value_t = 1)]
+    as_sel_strategy: usize,
+    /// Comma-separated list of adversarial ASNs to follow over time instead of the top-n
+    #[arg(long = "asns", value_delimiter = ',')]
+    asns: Vec<u32>,
+    /// Comma-separated list of drop strategies
+    #[arg(
+        long = "strategies",
+        value_delimiter = ',',
+        default_value = "all,intra-as,inter-as"
+    )]
+    drop_strategies: Vec<PacketDropStrategy>,
+    /// Overwrite the existing file, if it exists
+    #[arg(short = 'u', long = "overwrite")]
+    overwrite: bool,
+    /// Map nodes with only an onion address to a common Tor AS
+    #[arg(long = "include-tor")]
+    include_tor: bool,
+    verbose: bool,
+}
+
+fn main() {
+    let args = Cli::parse();
+    env_logger::builder().filter_level(args.log_level).init();
+    let output_path = if let Some(output_path) = args.output_path {
+        output_path
+    } else {
+        PathBuf::from("snapshot-series.csv")
+    };
+    if !args.overwrite && output_path.exists() {
+        error!("Output file exists, refusing to overwrite. Exiting.");
+        std::process::exit(-1)
+    }
+    let files = match snapshot_files(&args.snapshot_dir) {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Error reading snapshot directory {}. Exiting.", e);
+            std::process::exit(-1)
+        }
+    };
+    let as_selection_strategy = match AsSelectionStrategy::try_from(args.as_sel_strategy) {
+        Ok(strategy) => strategy,
+        Err(e) => {
+            error!("Error in configuration {}. Exiting.", e);
+            std::process::exit(-1)
+        }
+    };
+    info!("Simulating {} snapshots.", files.len());
+    let mut results = vec![];
+    // most addresses do not change between snapshots
+    let resolver = AsnResolver::new();
+    for file in files.iter() {
+        let snapshot = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let graph = match network_parser::Graph::from_json_file(file, args.graph_type.clone()) {
+            Ok(graph) => {
+                simlib::core_types::graph::Graph::to_sim_graph(&graph, args.graph_type.clone())
+            }
+            Err(e) => {
+                warn!("Skipping snapshot {}: {}", snapshot, e);
+                continue;
+            }
+        };
+        let as_ip_map = AsIpMap::with_resolver(&graph, args.include_tor, &resolver);
+        for amount in args.amounts.iter() {
+            info!("Simulating {amount} sat in snapshot {snapshot}.");
+            let mut builder = SimBuilder::new(
+                args.run,
+                &graph,
+                simlib::to_millisatoshi(*amount),
+                args.num_adv_as,
+                as_selection_strategy,
+            );
+            if !args.asns.is_empty() {
+                builder = builder.with_target_asns(args.asns.clone());
+            }
+            if let Err(e) = builder.validate(args.num_pairs, &as_ip_map, &args.drop_strategies) {
+                error!("Error in configuration {}. Exiting.", e);
+                std::process::exit(-1)
+            }
+            results.extend(builder.simulate_snapshot(
+                &snapshot,
+                args.num_pairs,
+                &as_ip_map,
+                &args.drop_strategies,
+            ));
+        }
+    }
+    match write_series_csv(&results, &output_path) {
+        Ok(()) => info!("Time series written to {:#?}.", output_path),
+        Err(e) => error!("Error writing time series {}.", e),
+    }
+}
@@ -0,0 +1,25 @@
+use crate::{net::Asn, ConfigError};
+#[cfg(feature = "mmdb")]
+use maxminddb::MaxMindDBError;
+
+/// Failures the library surfaces to the caller instead of aborting
+#[derive(Debug, thiserror::Error)]
+pub enum SimulatorError {
+    #[cfg(feature = "mmdb")]
+    #[error("Error opening AS database: {0}")]
+    Database(#[from] MaxMindDBError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error serialising report: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// The adversaries could not be selected from the AS map
+    #[error("{0}")]
+    Selection(String),
+    #[error("AS {0} is not in the AS map.")]
+    UnknownAsn(Asn),
+    /// A step of the command line tools failed, the message names the step and its cause
+    #[error("{0}")]
+    Cli(String),
+}
@@ -1,20 +1,31 @@
+pub mod analysis;
+mod error;
 mod net;
+mod scenario;
+mod scenarios;
 mod sim;
+mod validate;
 
+pub use error::*;
 pub use net::*;
-use serde::Serialize;
+pub use scenario::*;
+pub use scenarios::*;
+use serde::{Deserialize, Serialize};
 pub use sim::*;
-use std::collections::HashMap;
+use std::{path::Path, str::FromStr};
+pub use validate::*;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AsSelectionStrategy {
     MaxNodes = 0,
     MaxChannels = 1,
+    /// Sum of the (approximate) betweenness centrality of the AS's nodes
+    MaxBetweenness = 2,
 }
 
 /// An AS with either drop all packets or drop a packet based on the probabilty that it remains
 /// within the AS
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum PacketDropStrategy {
     #[default]
     All,
@@ -26,19 +37,264 @@ pub enum PacketDropStrategy {
     /// Drop payments from/to nodes outside our AS, i.e., the simulator will fail all payments if the
     /// src or dst do not belong to the attacking AS.
     InterAs,
+    /// Drop only the parts of a split payment that traverse the AS's nodes. The payment still
+    /// succeeds if the remaining parts carry the full amount
+    SplitAware,
+    /// Drop payments routed over a channel whose peers both belong to the AS, i.e., the AS only
+    /// sees the traffic between its own nodes. Weaker than dropping at every node of the AS
+    IntraAsChannel,
+    /// Do not fail any payments but hold the HTLCs at each of the AS's nodes for some time. Payments
+    /// delayed by more than the timeout are reported
+    Delay {
+        hold_time_secs: u32,
+        timeout_secs: u32,
+    },
+    /// Drop payments with a part traversing the AS whose amount is above (or below) the threshold.
+    /// The AS only sees the amount of the HTLC it forwards, i.e., of the part
+    AmountThreshold {
+        threshold_msat: usize,
+        above: bool,
+    },
+    /// Drop only payments that do not look like probes, i.e., that carry more than
+    /// `probe_max_msat`. Senders disguise a real payment as a probe with a probability of
+    /// `evasion_permille` and the AS detects a disguised payment, e.g. by its hash reuse, with a
+    /// probability of `detection_permille`
+    ProbeFilter {
+        probe_max_msat: usize,
+        evasion_permille: u16,
+        detection_permille: u16,
+    },
+    /// Drop payments the AS forwards to one of the blacklisted nodes, e.g. a sanctioned merchant.
+    /// The AS only sees the next hop, so payments routed through a blacklisted node to another
+    /// destination are dropped as well
+    DestinationBlacklist {
+        blacklist: NodeBlacklist,
+    },
+    /// Drop the payments traversing the AS until `max_drops` were dropped in the current window of
+    /// `window_secs`, i.e., throttle rather than block. Payments arrive every `interval_secs` in
+    /// the order of their IDs
+    RateLimit {
+        max_drops: u32,
+        window_secs: u32,
+        interval_secs: u32,
+    },
 }
 
-pub(crate) static TOR_ASN: u32 = 0;
+/// Hold time used if the delay strategy is given without parameters
+pub static DEFAULT_HOLD_TIME_SECS: u32 = 30;
+/// Timeout used if the delay strategy is given without parameters
+pub static DEFAULT_DELAY_TIMEOUT_SECS: u32 = 60;
+/// Largest amount in sat that looks like a probe if the probe filter is given without parameters
+pub static DEFAULT_PROBE_MAX_SAT: usize = 1;
+/// Window of the rate limit if it is given without one
+pub static DEFAULT_RATE_LIMIT_WINDOW_SECS: u32 = 60;
+/// Time between the arrivals of the payments if the rate limit is given without one
+pub static DEFAULT_PAYMENT_INTERVAL_SECS: u32 = 1;
+
+impl FromStr for PacketDropStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the path keeps its case
+        if let Some(path) = s.strip_prefix("destination-blacklist=") {
+            return NodeBlacklist::from_file(Path::new(path))
+                .map(|blacklist| Self::DestinationBlacklist { blacklist });
+        }
+        match s.to_lowercase().as_str() {
+            "all" => Ok(Self::All),
+            "intra-probability" => Ok(Self::IntraProbability),
+            "intra-as" => Ok(Self::IntraAs),
+            "inter-as" => Ok(Self::InterAs),
+            "split-aware" => Ok(Self::SplitAware),
+            "intra-as-channel" => Ok(Self::IntraAsChannel),
+            "delay" => Ok(Self::Delay {
+                hold_time_secs: DEFAULT_HOLD_TIME_SECS,
+                timeout_secs: DEFAULT_DELAY_TIMEOUT_SECS,
+            }),
+            "probe-filter" => Ok(Self::ProbeFilter {
+                probe_max_msat: simlib::to_millisatoshi(DEFAULT_PROBE_MAX_SAT),
+                evasion_permille: 0,
+                detection_permille: 0,
+            }),
+            s => match s.split_once('=') {
+                Some(("probe-filter", params)) => Self::parse_probe_filter(params),
+                Some(("rate-limit", params)) => Self::parse_rate_limit(params),
+                Some((side @ ("amount-above" | "amount-below"), threshold)) => threshold
+                    .parse::<usize>()
+                    .map(|sat| Self::AmountThreshold {
+                        threshold_msat: simlib::to_millisatoshi(sat),
+                        above: side == "amount-above",
+                    })
+                    .map_err(|e| format!("Invalid amount threshold {}: {}", threshold, e)),
+                _ => Err(format!("Unknown packet drop strategy {}", s)),
+            },
+        }
+    }
+}
+
+impl PacketDropStrategy {
+    /// `<MAX_SAT>[:<EVASION>:<DETECTION>]` with the probabilities in per mille
+    fn parse_probe_filter(params: &str) -> Result<Self, String> {
+        let err = |e: std::num::ParseIntError| format!("Invalid probe filter {}: {}", params, e);
+        let mut params = params.split(':');
+        let probe_max_sat: usize = params.next().unwrap_or_default().parse().map_err(err)?;
+        let mut permille = || -> Result<u16, String> {
+            let permille: u16 = params.next().unwrap_or("0").parse().map_err(err)?;
+            if permille > 1000 {
+                return Err(format!("Probability {} exceeds 1000 per mille", permille));
+            }
+            Ok(permille)
+        };
+        Ok(Self::ProbeFilter {
+            probe_max_msat: simlib::to_millisatoshi(probe_max_sat),
+            evasion_permille: permille()?,
+            detection_permille: permille()?,
+        })
+    }
 
-pub(crate) fn find_key_for_value(map: &HashMap<u32, Vec<String>>, value: &String) -> Option<u32> {
-    map.iter().find_map(|(key, val)| {
-        if val.contains(value) {
-            Some(*key)
+    /// `<MAX_DROPS>[:<WINDOW_SECS>[:<INTERVAL_SECS>]]`
+    fn parse_rate_limit(params: &str) -> Result<Self, String> {
+        let err = |e: std::num::ParseIntError| format!("Invalid rate limit {}: {}", params, e);
+        let mut params = params.split(':');
+        let max_drops: u32 = params.next().unwrap_or_default().parse().map_err(err)?;
+        let mut secs = |default: u32| -> Result<u32, String> {
+            let secs = params
+                .next()
+                .map_or(Ok(default), |s| s.parse().map_err(err))?;
+            if secs == 0 {
+                return Err(String::from(
+                    "Rate limit windows and intervals must not be 0",
+                ));
+            }
+            Ok(secs)
+        };
+        Ok(Self::RateLimit {
+            max_drops,
+            window_secs: secs(DEFAULT_RATE_LIMIT_WINDOW_SECS)?,
+            interval_secs: secs(DEFAULT_PAYMENT_INTERVAL_SECS)?,
+        })
+    }
+}
+
+/// How nodes that are attributed to more than one adversarial AS are treated
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum OverlapPolicy {
+    /// Keep the node in every AS it belongs to, i.e., it is counted once per AS
+    #[default]
+    Keep,
+    /// Only keep the node in the highest-ranked AS it belongs to
+    FirstWins,
+}
+
+/// How nodes with only an onion address are mapped to ASs
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum TorTreatment {
+    /// The nodes are not mapped to any AS
+    #[default]
+    Exclude,
+    /// The nodes are mapped to a common Tor ASN
+    Include,
+    /// The nodes are excluded and the results are compared to those with the nodes included
+    Both,
+}
+
+impl FromStr for TorTreatment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "exclude" | "false" => Ok(Self::Exclude),
+            "include" | "true" => Ok(Self::Include),
+            "both" => Ok(Self::Both),
+            _ => Err(format!("Unknown Tor treatment {}", s)),
+        }
+    }
+}
+
+impl TorTreatment {
+    pub fn from_include_tor(include_tor: bool) -> Self {
+        if include_tor {
+            Self::Include
         } else {
-            None
+            Self::Exclude
+        }
+    }
+
+    /// The `include_tor` values the AS maps are built with, the primary one first
+    pub fn include_tor(&self) -> Vec<bool> {
+        match self {
+            Self::Exclude => vec![false],
+            Self::Include => vec![true],
+            Self::Both => vec![false, true],
         }
-    })
+    }
 }
 
+pub(crate) static TOR_ASN: u32 = 0;
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_drop_strategy() {
+        assert_eq!(
+            PacketDropStrategy::from_str("intra-as"),
+            Ok(PacketDropStrategy::IntraAs)
+        );
+        assert_eq!(
+            PacketDropStrategy::from_str("Split-Aware"),
+            Ok(PacketDropStrategy::SplitAware)
+        );
+        assert_eq!(
+            PacketDropStrategy::from_str("intra-as-channel"),
+            Ok(PacketDropStrategy::IntraAsChannel)
+        );
+        assert_eq!(
+            PacketDropStrategy::from_str("amount-below=1000"),
+            Ok(PacketDropStrategy::AmountThreshold {
+                threshold_msat: 1000000,
+                above: false
+            })
+        );
+        assert!(PacketDropStrategy::from_str("amount-above=x").is_err());
+        assert_eq!(
+            PacketDropStrategy::from_str("probe-filter=10:250:500"),
+            Ok(PacketDropStrategy::ProbeFilter {
+                probe_max_msat: 10000,
+                evasion_permille: 250,
+                detection_permille: 500
+            })
+        );
+        assert!(PacketDropStrategy::from_str("probe-filter=10:1001").is_err());
+        assert_eq!(
+            PacketDropStrategy::from_str("rate-limit=5:30"),
+            Ok(PacketDropStrategy::RateLimit {
+                max_drops: 5,
+                window_secs: 30,
+                interval_secs: 1
+            })
+        );
+        assert!(PacketDropStrategy::from_str("rate-limit=5:0").is_err());
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "# sanctioned\nchan\n").unwrap();
+        let strategy = format!("destination-blacklist={}", file.path().display());
+        match PacketDropStrategy::from_str(&strategy) {
+            Ok(PacketDropStrategy::DestinationBlacklist { blacklist }) => {
+                assert_eq!(blacklist.nodes.len(), 1);
+                assert!(blacklist.contains(&String::from("chan")));
+            }
+            other => panic!("Unexpected strategy {:?}", other),
+        }
+        assert!(PacketDropStrategy::from_str("destination-blacklist=missing.txt").is_err());
+        assert!(PacketDropStrategy::from_str("none").is_err());
+    }
+
+    #[test]
+    fn parse_tor_treatment() {
+        assert_eq!(TorTreatment::from_str("true"), Ok(TorTreatment::Include));
+        assert_eq!(TorTreatment::from_str("Exclude"), Ok(TorTreatment::Exclude));
+        assert_eq!(TorTreatment::Both.include_tor(), vec![false, true]);
+        assert!(TorTreatment::from_str("onion").is_err());
+    }
+}
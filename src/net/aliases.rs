@@ -0,0 +1,73 @@
+use network_parser::GraphSource;
+use serde_json::Value;
+use simlib::ID;
+use std::{collections::HashMap, error::Error, fs::File, path::Path};
+
+/// The aliases the nodes announce, keyed by their public keys, i.e., their IDs in the simulation
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NodeAliases {
+    aliases: HashMap<ID, String>,
+}
+
+impl NodeAliases {
+    pub fn from_file(path: &Path, graph_source: &GraphSource) -> Result<Self, Box<dyn Error>> {
+        let graph: Value = serde_json::from_reader(File::open(path)?)?;
+        Ok(Self::from_graph_json(&graph, graph_source))
+    }
+
+    /// Nodes without an alias or with an empty one are left out
+    pub fn from_graph_json(graph: &Value, graph_source: &GraphSource) -> Self {
+        let id_key = match graph_source {
+            GraphSource::Lnd => "pub_key",
+            GraphSource::Lnresearch => "id",
+        };
+        let aliases = graph
+            .get("nodes")
+            .and_then(|n| n.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|node| {
+                let id = node.get(id_key)?.as_str()?;
+                let alias = node.get("alias")?.as_str().filter(|a| !a.is_empty())?;
+                Some((id.to_owned(), alias.to_owned()))
+            })
+            .collect();
+        Self { aliases }
+    }
+
+    pub fn get(&self, node: &ID) -> Option<&String> {
+        self.aliases.get(node)
+    }
+
+    pub fn len(&self) -> usize {
+        self.aliases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn read_aliases() {
+        let graph = json!({"nodes": [
+            {"pub_key": "034", "alias": "ACINQ", "addresses": []},
+            {"pub_key": "025", "alias": "", "addresses": []},
+            {"pub_key": "036", "addresses": []},
+        ]});
+        let aliases = NodeAliases::from_graph_json(&graph, &GraphSource::Lnd);
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(
+            aliases.get(&String::from("034")),
+            Some(&String::from("ACINQ"))
+        );
+        assert_eq!(aliases.get(&String::from("025")), None);
+        let aliases = NodeAliases::from_graph_json(&graph, &GraphSource::Lnresearch);
+        assert!(aliases.is_empty());
+    }
+}
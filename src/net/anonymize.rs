@@ -0,0 +1,160 @@
+use super::{AsIpMap, Asn};
+use network_parser::GraphSource;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use simlib::ID;
+use std::collections::HashMap;
+
+/// Replaces node and channel IDs with hashes salted with `salt`, e.g. a random secret, so that
+/// the IDs cannot be matched against the public graph
+pub struct Anonymizer {
+    salt: String,
+}
+
+impl Anonymizer {
+    pub fn new(salt: impl Into<String>) -> Self {
+        Self { salt: salt.into() }
+    }
+
+    pub fn hash(&self, id: &str) -> ID {
+        let digest = Sha256::new()
+            .chain_update(self.salt.as_bytes())
+            .chain_update(id.as_bytes())
+            .finalize();
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Replaces the IDs in a graph file with their hashes and the nodes' addresses with their
+    /// ASNs. Aliases and colours are blanked, all other fields are left as they are. Returns the
+    /// AS map of the hashed nodes
+    pub fn anonymize_graph_json(
+        &self,
+        graph: &mut Value,
+        graph_source: &GraphSource,
+        as_ip_map: &AsIpMap,
+    ) -> AsIpMap {
+        let id_key = match graph_source {
+            GraphSource::Lnd => "pub_key",
+            GraphSource::Lnresearch => "id",
+        };
+        if let Some(nodes) = graph.get_mut("nodes").and_then(|n| n.as_array_mut()) {
+            for node in nodes.iter_mut().filter_map(|n| n.as_object_mut()) {
+                let Some(id) = node
+                    .get(id_key)
+                    .and_then(|id| id.as_str())
+                    .map(String::from)
+                else {
+                    continue;
+                };
+                node.insert(id_key.to_owned(), json!(self.hash(&id)));
+                node.insert(
+                    "addresses".to_owned(),
+                    match graph_source {
+                        GraphSource::Lnd => json!([]),
+                        GraphSource::Lnresearch => json!(""),
+                    },
+                );
+                if let Some(asn) = as_ip_map.asn_of(&id) {
+                    node.insert("asn".to_owned(), json!(asn));
+                }
+                for key in ["alias", "color"] {
+                    if node.contains_key(key) {
+                        node.insert(key.to_owned(), json!(""));
+                    }
+                }
+                node.retain(|key, _| !key.starts_with('_'));
+            }
+        }
+        match graph_source {
+            GraphSource::Lnd => {
+                for edge in graph
+                    .get_mut("edges")
+                    .and_then(|e| e.as_array_mut())
+                    .into_iter()
+                    .flatten()
+                {
+                    self.hash_fields(edge, &["channel_id", "node1_pub", "node2_pub"]);
+                    if edge.get("chan_point").is_some() {
+                        edge["chan_point"] = json!("");
+                    }
+                }
+            }
+            GraphSource::Lnresearch => {
+                for edge in graph
+                    .get_mut("adjacency")
+                    .and_then(|a| a.as_array_mut())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|e| e.as_array_mut())
+                    .flatten()
+                {
+                    self.hash_fields(edge, &["source", "destination", "id"]);
+                    // the direction suffix of the scid is kept
+                    if let Some(scid) = edge.get("scid").and_then(|s| s.as_str()) {
+                        let hashed = match scid.split_once('/') {
+                            Some((channel_id, direction)) => {
+                                format!("{}/{}", self.hash(channel_id), direction)
+                            }
+                            None => self.hash(scid),
+                        };
+                        edge["scid"] = json!(hashed);
+                    }
+                }
+            }
+        }
+        let as_to_nodes: HashMap<Asn, Vec<ID>> = as_ip_map
+            .as_to_nodes()
+            .iter()
+            .map(|(asn, nodes)| (*asn, nodes.iter().map(|n| self.hash(n)).collect()))
+            .collect();
+        AsIpMap::from(as_to_nodes)
+    }
+
+    fn hash_fields(&self, value: &mut Value, keys: &[&str]) {
+        for key in keys {
+            if let Some(hashed) = value
+                .get(*key)
+                .and_then(|v| v.as_str())
+                .map(|id| self.hash(id))
+            {
+                value[*key] = json!(hashed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use std::{fs::File, path::Path};
+
+    #[test]
+    fn anonymize_graph() {
+        let path = Path::new("test_data/lnbook_example_lnr.json");
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(path, Lnresearch).unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let mut json: Value = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+        let anonymizer = Anonymizer::new("secret");
+        let hashed_map = anonymizer.anonymize_graph_json(&mut json, &Lnresearch, &as_ip_map);
+        let alice = anonymizer.hash("alice");
+        assert_eq!(alice.len(), 64);
+        assert_ne!(alice, Anonymizer::new("other").hash("alice"));
+        assert_eq!(hashed_map.asn_of(&alice), Some(24940));
+        let node = &json["nodes"][0];
+        assert_eq!(node["id"], json!(alice));
+        assert_eq!(node["addresses"], json!(""));
+        assert_eq!(node["asn"], json!(24940));
+        assert!(node.get("_isp").is_none());
+        assert_eq!(json["adjacency"][0][0]["source"], json!(alice));
+        assert_eq!(
+            json["adjacency"][0][0]["scid"],
+            json!(anonymizer.hash("alice1"))
+        );
+        assert!(!json.to_string().contains("5.9.0.1"));
+    }
+}
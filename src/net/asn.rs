@@ -1,31 +1,164 @@
+#[cfg(feature = "mmdb")]
 use crate::TOR_ASN;
 
-use super::{Asn, DbReader};
+use super::Asn;
+#[cfg(feature = "mmdb")]
+use super::AsnResolver;
 
-use simlib::{graph::Graph, Node, ID};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "mmdb")]
+use simlib::Node;
+use simlib::{graph::Graph, ID};
+#[cfg(feature = "mmdb")]
+use std::str::FromStr;
 use std::{
     cmp::Reverse,
     collections::{BinaryHeap, HashMap},
-    str::FromStr,
+    error::Error,
+    path::Path,
 };
 
+#[cfg(all(not(test), feature = "mmdb"))]
+use log::trace;
 #[cfg(not(test))]
-use log::{info, trace, warn};
+use log::{info, warn};
 #[cfg(test)]
 use std::{println as info, println as warn, println as trace};
 
+/// The nodes of each AS, indexed by node when the map is created. The map cannot be changed, so
+/// the index does not go stale; build a new map with `AsIpMap::from` instead
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct AsIpMap {
-    pub as_to_nodes: HashMap<Asn, Vec<ID>>,
+    as_to_nodes: HashMap<Asn, Vec<ID>>,
+    node_to_asn: HashMap<ID, Asn>,
+}
+
+impl From<HashMap<Asn, Vec<ID>>> for AsIpMap {
+    /// A node listed in several ASs is attributed to the lowest ASN
+    fn from(as_to_nodes: HashMap<Asn, Vec<ID>>) -> Self {
+        let mut node_to_asn: HashMap<ID, Asn> = HashMap::new();
+        for (asn, nodes) in as_to_nodes.iter() {
+            for node in nodes {
+                node_to_asn
+                    .entry(node.clone())
+                    .and_modify(|a| *a = (*a).min(*asn))
+                    .or_insert(*asn);
+            }
+        }
+        Self {
+            as_to_nodes,
+            node_to_asn,
+        }
+    }
+}
+
+/// The share of each node's channels that stay within its AS, i.e., how likely the node infers a
+/// payment it forwards to be intra-AS
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AsNodeRatios {
+    pub ratios: HashMap<ID, f32>,
+    /// Mean ratio of the AS's nodes, None if none of them has a channel
+    pub mean: Option<f32>,
+}
+
+impl AsNodeRatios {
+    pub fn new(ratios: HashMap<ID, f32>) -> Self {
+        let mean = (!ratios.is_empty()).then(|| ratios.values().sum::<f32>() / ratios.len() as f32);
+        Self { ratios, mean }
+    }
+
+    /// The node's ratio or the AS's mean if the node has none
+    pub fn ratio_of(&self, node: &ID) -> Option<f32> {
+        self.ratios.get(node).copied().or(self.mean)
+    }
+
+    /// Probability that at least one of the nodes drops a payment if each drops it with its ratio.
+    /// None if there are no nodes or no ratios
+    pub fn drop_probability<'a>(&self, nodes: impl Iterator<Item = &'a ID>) -> Option<f32> {
+        let mut nodes = nodes.peekable();
+        nodes.peek()?;
+        let kept = nodes.try_fold(1.0, |kept, node| {
+            self.ratio_of(node).map(|ratio| kept * (1.0 - ratio))
+        })?;
+        Some(1.0 - kept)
+    }
+}
+
+/// A row of a node-to-ASN mapping file
+#[derive(Deserialize, Serialize)]
+struct MappingRecord {
+    node_id: ID,
+    asn: Asn,
 }
 
 impl AsIpMap {
+    /// Reads a CSV file with the columns `node_id` and `asn`, e.g. written by `write_to_file`,
+    /// instead of looking up the nodes' addresses
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut as_to_nodes: HashMap<Asn, Vec<ID>> = HashMap::default();
+        for record in csv::Reader::from_path(path)?.deserialize() {
+            let record: MappingRecord = record?;
+            as_to_nodes
+                .entry(record.asn)
+                .or_default()
+                .push(record.node_id);
+        }
+        info!(
+            "Read {} ASNs from the mapping in {:#?}.",
+            as_to_nodes.len(),
+            path
+        );
+        Ok(Self::from(as_to_nodes))
+    }
+
+    /// Returns the nodes of each AS
+    pub fn as_to_nodes(&self) -> &HashMap<Asn, Vec<ID>> {
+        &self.as_to_nodes
+    }
+
+    /// Returns the ASN the node is mapped to
+    pub fn asn_of(&self, node: &ID) -> Option<Asn> {
+        self.node_to_asn.get(node).copied()
+    }
+
+    /// Writes one row per mapped node, ordered by ASN and node, so that a run can be repeated
+    /// without the AS database
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut records: Vec<MappingRecord> = self
+            .as_to_nodes
+            .iter()
+            .flat_map(|(asn, nodes)| {
+                nodes.iter().map(|node_id| MappingRecord {
+                    node_id: node_id.clone(),
+                    asn: *asn,
+                })
+            })
+            .collect();
+        records.sort_by(|a, b| a.asn.cmp(&b.asn).then(a.node_id.cmp(&b.node_id)));
+        let mut writer = csv::Writer::from_path(path)?;
+        for record in records {
+            writer.serialize(record)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    #[cfg(feature = "mmdb")]
     pub fn new(graph: &Graph, include_tor: bool) -> Self {
-        let db_reader = DbReader::new();
+        Self::with_resolver(graph, include_tor, &AsnResolver::new())
+    }
+
+    /// Maps the nodes using a resolver that caches the lookups of previous maps
+    #[cfg(feature = "mmdb")]
+    pub fn with_resolver(graph: &Graph, include_tor: bool, resolver: &AsnResolver) -> Self {
         let mut as_to_nodes = HashMap::default();
         let nodes = graph.get_nodes();
         let mut num_public_addr = 0;
         for node in &nodes {
-            if let Some(asn) = Self::lookup_asn_for_node(&db_reader, node, include_tor) {
+            let asn = resolver.asn_for_node(node, include_tor, |resolver| {
+                Self::lookup_asn_for_node(resolver, node, include_tor)
+            });
+            if let Some(asn) = asn {
                 if asn != TOR_ASN {
                     num_public_addr += 1;
                 }
@@ -43,7 +176,7 @@ impl AsIpMap {
             "{}% of nodes have a public address",
             num_public_addr as f32 / nodes.len() as f32 * 100.0
         );
-        Self { as_to_nodes }
+        Self::from(as_to_nodes)
     }
 
     /// Returns an ordered list of the n most-represented ASNs w.r.t the number of nodes.
@@ -98,11 +231,37 @@ impl AsIpMap {
             .collect()
     }
 
-    fn lookup_asn_for_node(db_reader: &DbReader, node: &Node, include_tor: bool) -> Option<Asn> {
+    /// Returns the nodes of each given ASN in descending order of number of channels or an error
+    /// naming the first ASN that has no nodes in the graph
+    pub(crate) fn selected_asns(
+        &self,
+        asns: &[Asn],
+        graph: &Graph,
+    ) -> Result<Vec<(Asn, Vec<ID>)>, String> {
+        let mut selected = Vec::with_capacity(asns.len());
+        for asn in asns {
+            let mut nodes = match self.as_to_nodes.get(asn) {
+                Some(nodes) if !nodes.is_empty() => nodes.clone(),
+                _ => return Err(format!("AS {} has no nodes in the graph.", asn)),
+            };
+            nodes.sort_by(|a, b| {
+                graph
+                    .get_edges_for_node(b)
+                    .unwrap_or_default()
+                    .len()
+                    .cmp(&graph.get_edges_for_node(a).unwrap_or_default().len())
+            });
+            selected.push((*asn, nodes));
+        }
+        Ok(selected)
+    }
+
+    #[cfg(feature = "mmdb")]
+    fn lookup_asn_for_node(resolver: &AsnResolver, node: &Node, include_tor: bool) -> Option<Asn> {
         for addr in &node.addresses {
             if !addr.addr.contains("onion") {
                 if let Ok(ip) = FromStr::from_str(&addr.addr) {
-                    if let Some(asn) = db_reader.lookup_asn(ip) {
+                    if let Some(asn) = resolver.lookup_asn(ip) {
                         return Some(asn);
                     } else {
                         warn!("No ASN entry found for {} in database.", ip);
@@ -121,36 +280,79 @@ impl AsIpMap {
         None
     }
 
-    pub fn get_intra_as_channels_ratio(&self, graph: &Graph) -> HashMap<u32, Vec<f32>> {
+    /// Returns the share of each node's channels that stay within its AS, truncated to two
+    /// decimals. Zero-degree nodes have no ratio
+    pub fn get_intra_as_channels_ratio(&self, graph: &Graph) -> HashMap<Asn, HashMap<ID, f32>> {
+        self.node_ratios(graph)
+            .into_iter()
+            .map(|(asn, ratios)| (asn, ratios.into_iter().collect()))
+            .collect()
+    }
+
+    /// The ratios of each AS's nodes, from which PacketDropStrategy::IntraProbability takes the
+    /// drop probability of the nodes forwarding a payment
+    pub(crate) fn get_intra_as_node_ratios(&self, graph: &Graph) -> HashMap<Asn, AsNodeRatios> {
+        self.get_intra_as_channels_ratio(graph)
+            .into_iter()
+            .map(|(asn, ratios)| (asn, AsNodeRatios::new(ratios)))
+            .collect()
+    }
+
+    fn node_ratios(&self, graph: &Graph) -> HashMap<Asn, Vec<(ID, f32)>> {
         let mut per_node_ratio = HashMap::new();
 
         for (asn, nodes) in self.as_to_nodes.iter() {
             per_node_ratio.insert(*asn, vec![]);
+            let mut num_zero_degree = 0;
             for node in nodes {
                 if let Some(edges) = graph.get_edges_for_node(node) {
                     let total = edges.len();
                     if total.eq(&0) {
-                        // shouldnt happen
-                        break;
+                        // no ratio for isolated nodes
+                        num_zero_degree += 1;
+                        continue;
                     }
                     let mut same_asn = 0;
                     for e in edges.iter() {
-                        if let Some(dst_asn) =
-                            crate::find_key_for_value(&self.as_to_nodes, &e.destination)
-                        {
+                        if let Some(dst_asn) = self.asn_of(&e.destination) {
                             if dst_asn == *asn {
                                 same_asn += 1;
                             }
                         }
                     }
                     let ratio = f32::trunc((same_asn as f32 / total as f32) * 100.0) / 100.0;
-                    per_node_ratio.entry(*asn).and_modify(|r| r.push(ratio));
+                    per_node_ratio
+                        .entry(*asn)
+                        .and_modify(|r: &mut Vec<(ID, f32)>| r.push((node.clone(), ratio)));
+                } else {
+                    num_zero_degree += 1;
                 }
             }
+            if num_zero_degree > 0 {
+                warn!(
+                    "Skipping {} zero-degree nodes of AS {}.",
+                    num_zero_degree, asn
+                );
+            }
         }
         per_node_ratio
     }
 
+    /// Returns the number of nodes without channels per AS. These nodes are skipped when
+    /// computing channel ratios and sums
+    pub fn get_zero_degree_nodes(&self, graph: &Graph) -> HashMap<u32, usize> {
+        self.as_to_nodes
+            .iter()
+            .map(|(asn, nodes)| {
+                let num_zero_degree = nodes
+                    .iter()
+                    .filter(|n| graph.get_edges_for_node(n).unwrap_or_default().is_empty())
+                    .count();
+                (*asn, num_zero_degree)
+            })
+            .collect()
+    }
+
     /// Returns the total number of (intra, inter)-AS channels per AS
     pub fn get_sum_of_as_channels(&self, graph: &Graph) -> HashMap<u32, (u32, u32)> {
         let mut as_channels = HashMap::with_capacity(self.as_to_nodes.len());
@@ -160,15 +362,8 @@ impl AsIpMap {
             let mut intra = 0;
             for node in nodes {
                 if let Some(edges) = graph.get_edges_for_node(node) {
-                    let total = edges.len();
-                    if total.eq(&0) {
-                        // shouldnt happen
-                        break;
-                    }
                     for e in edges.iter() {
-                        if let Some(dst_asn) =
-                            crate::find_key_for_value(&self.as_to_nodes, &e.destination)
-                        {
+                        if let Some(dst_asn) = self.asn_of(&e.destination) {
                             if dst_asn == *asn {
                                 intra += 1;
                             } else {
@@ -216,12 +411,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn shared_resolver() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let resolver = AsnResolver::new();
+        let first = AsIpMap::with_resolver(&graph, false, &resolver);
+        let num_db_lookups = resolver.num_db_lookups();
+        let second = AsIpMap::with_resolver(&graph, false, &resolver.clone());
+        assert_eq!(resolver.num_db_lookups(), num_db_lookups);
+        assert_eq!(first.as_to_nodes, second.as_to_nodes);
+    }
+
     #[test]
     fn asn_lookup() {
-        let db_reader = DbReader::new();
+        let resolver = AsnResolver::new();
         let node = Node::default();
         let include_tor = false;
-        let actual = AsIpMap::lookup_asn_for_node(&db_reader, &node, include_tor);
+        let actual = AsIpMap::lookup_asn_for_node(&resolver, &node, include_tor);
         let expected = None;
         assert_eq!(expected, actual);
         let node = Node {
@@ -238,7 +451,7 @@ mod tests {
             ],
             ..Default::default()
         };
-        let actual = AsIpMap::lookup_asn_for_node(&db_reader, &node, include_tor);
+        let actual = AsIpMap::lookup_asn_for_node(&resolver, &node, include_tor);
         let expected = Some(15169);
         assert_eq!(expected, actual);
     }
@@ -318,6 +531,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn selected_asns() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let actual = as_ip_map.selected_asns(&[797, 24940], &graph);
+        let expected = vec![
+            (797, vec!["chan".to_owned(), "dina".to_owned()]),
+            (24940, vec!["bob".to_owned(), "alice".to_owned()]),
+        ];
+        assert_eq!(actual, Ok(expected));
+        let actual = as_ip_map.selected_asns(&[797, 16509], &graph);
+        assert!(actual.is_err());
+    }
+
     #[test]
     fn intra_channels_rate() {
         let graph = Graph::to_sim_graph(
@@ -331,15 +565,36 @@ mod tests {
         let include_tor = true;
         let as_ip_map = AsIpMap::new(&graph, include_tor);
         let actual = as_ip_map.get_intra_as_channels_ratio(&graph);
-        let expected = HashMap::from([(24940, vec![0.5, 1.0]), (797, vec![0.5, 1.0])]);
-        assert_eq!(actual.len(), expected.len());
-        for a in actual {
-            let e = expected.get(&a.0).unwrap();
-            assert_eq!(a.1.len(), e.len());
-            for expected_ratio in e {
-                assert!(a.1.contains(expected_ratio));
-            }
-        }
+        // bob and chan each have one of their two channels in their AS
+        let expected = HashMap::from([
+            (
+                24940,
+                HashMap::from([(String::from("alice"), 1.0), (String::from("bob"), 0.5)]),
+            ),
+            (
+                797,
+                HashMap::from([(String::from("chan"), 0.5), (String::from("dina"), 1.0)]),
+            ),
+        ]);
+        assert_eq!(actual, expected);
+        let node_ratios = as_ip_map.get_intra_as_node_ratios(&graph);
+        assert_eq!(node_ratios[&24940].ratios, expected[&24940]);
+        assert_eq!(node_ratios[&24940].mean, Some(0.75));
+        assert_eq!(node_ratios[&797].ratios, expected[&797]);
+        assert_eq!(node_ratios[&797].mean, Some(0.75));
+        let chan = String::from("chan");
+        let dina = String::from("dina");
+        assert_eq!(node_ratios[&797].ratio_of(&chan), Some(0.5));
+        // chan drops with 0.5, dina with 1.0
+        assert_eq!(
+            node_ratios[&797].drop_probability([&chan, &dina].into_iter()),
+            Some(1.0)
+        );
+        assert_eq!(
+            node_ratios[&797].ratio_of(&String::from("unknown")),
+            Some(0.75)
+        );
+        assert_eq!(node_ratios[&797].drop_probability(std::iter::empty()), None);
         let graph = Graph::to_sim_graph(
             &network_parser::Graph::from_json_file(
                 &Path::new("test_data/trivial_connected_lnd.json"),
@@ -350,17 +605,51 @@ mod tests {
         );
         let as_ip_map = AsIpMap::new(&graph, include_tor);
         let actual = as_ip_map.get_intra_as_channels_ratio(&graph);
-        let expected = HashMap::from([(24940, vec![0.5, 0.5]), (797, vec![0.])]);
-        assert_eq!(actual.len(), expected.len());
-        for a in actual {
-            let e = expected.get(&a.0).unwrap();
-            assert_eq!(a.1.len(), e.len());
-            for expected_ratio in e {
-                assert!(a.1.contains(expected_ratio));
-            }
+        // 034 and 025 share a channel, 036 has no channel within its AS
+        let expected = HashMap::from([
+            (
+                24940,
+                HashMap::from([(String::from("034"), 0.5), (String::from("025"), 0.5)]),
+            ),
+            (797, HashMap::from([(String::from("036"), 0.)])),
+        ]);
+        assert_eq!(actual, expected);
+        let node_ratios = as_ip_map.get_intra_as_node_ratios(&graph);
+        let expected_means = HashMap::from([(24940, Some(0.5)), (797, Some(0.))]);
+        assert_eq!(node_ratios.len(), expected.len());
+        for (asn, node_ratios) in node_ratios {
+            assert_eq!(node_ratios.ratios, expected[&asn]);
+            assert_eq!(node_ratios.mean, expected_means[&asn]);
         }
     }
 
+    #[test]
+    fn zero_degree_nodes() {
+        let mut graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, true);
+        assert_eq!(
+            as_ip_map.get_zero_degree_nodes(&graph),
+            HashMap::from([(24940, 0), (797, 0)])
+        );
+        graph.edges.insert(String::from("alice"), vec![]);
+        assert_eq!(
+            as_ip_map.get_zero_degree_nodes(&graph),
+            HashMap::from([(24940, 1), (797, 0)])
+        );
+        // only the isolated node is skipped, not the rest of its AS
+        let actual = as_ip_map.get_intra_as_channels_ratio(&graph);
+        assert!(!actual[&24940].contains_key("alice"));
+        assert_eq!(actual[&24940].len(), 1);
+        assert_eq!(actual[&797].len(), 2);
+    }
+
     #[test]
     fn num_as_channels() {
         let graph = Graph::to_sim_graph(
@@ -397,4 +686,44 @@ mod tests {
             assert_eq!(a.1, *e);
         }
     }
+
+    #[test]
+    fn indexed_lookup() {
+        let as_ip_map = AsIpMap::from(HashMap::from([
+            (797, vec![String::from("chan"), String::from("dina")]),
+            (24940, vec![String::from("bob"), String::from("dina")]),
+        ]));
+        assert_eq!(as_ip_map.asn_of(&String::from("bob")), Some(24940));
+        // the lowest ASN wins for nodes in several ASs
+        assert_eq!(as_ip_map.asn_of(&String::from("dina")), Some(797));
+        assert_eq!(as_ip_map.asn_of(&String::from("alice")), None);
+    }
+
+    #[test]
+    fn mapping_file() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        as_ip_map.write_to_file(file.path()).unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            "node_id,asn\nchan,797\ndina,797\nalice,24940\nbob,24940\n"
+        );
+        let mut actual = AsIpMap::from_file(file.path()).unwrap().as_to_nodes;
+        let mut expected = as_ip_map.as_to_nodes;
+        for nodes in actual.values_mut().chain(expected.values_mut()) {
+            nodes.sort();
+        }
+        assert_eq!(actual, expected);
+        std::fs::write(file.path(), "node_id,asn\nalice,x\n").unwrap();
+        assert!(AsIpMap::from_file(file.path()).is_err());
+    }
 }
@@ -0,0 +1,216 @@
+use super::Asn;
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use std::println as info;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+/// What is registered about an AS, so that reports can be read without looking the ASNs up
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AsMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    /// ISO 3166 code of the country the organisation is registered in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// Number of ASs in the AS's customer cone, including itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_cone_size: Option<usize>,
+}
+
+/// Metadata of the ASs from CAIDA's AS-to-organisation or AS Rank datasets
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AsMetadataMap {
+    asns: HashMap<Asn, AsMetadata>,
+}
+
+/// A line of the AS Rank API's JSON lines dump
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsRankRecord {
+    asn: String,
+    asn_name: Option<String>,
+    organization: Option<AsRankOrganization>,
+    country: Option<AsRankCountry>,
+    cone: Option<AsRankCone>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsRankOrganization {
+    org_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AsRankCountry {
+    iso: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsRankCone {
+    number_asns: Option<usize>,
+}
+
+impl AsMetadataMap {
+    /// Reads a CAIDA as2org file or an AS Rank dump with one JSON object per line
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let is_json = reader.fill_buf()?.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+        let metadata = if is_json {
+            Self::from_as_rank(reader)?
+        } else {
+            Self::from_as2org(reader)?
+        };
+        info!(
+            "Read the metadata of {} ASs from {:#?}.",
+            metadata.asns.len(),
+            path
+        );
+        Ok(metadata)
+    }
+
+    /// Reads the `aut|changed|aut_name|org_id|opaque_id|source` and
+    /// `org_id|changed|org_name|country|source` sections of an as2org file
+    pub fn from_as2org(reader: impl BufRead) -> Result<Self, Box<dyn Error>> {
+        let mut as_orgs: Vec<(Asn, String, String)> = vec![];
+        let mut orgs: HashMap<String, (String, String)> = HashMap::new();
+        let mut in_org_section = false;
+        for line in reader.lines() {
+            let line = line?;
+            if let Some(format) = line.strip_prefix("# format:") {
+                in_org_section = format.starts_with("org_id");
+                continue;
+            }
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('|').collect();
+            if in_org_section {
+                if fields.len() < 4 {
+                    return Err(format!("Invalid organisation {}", line).into());
+                }
+                orgs.insert(
+                    fields[0].to_owned(),
+                    (fields[2].to_owned(), fields[3].to_owned()),
+                );
+            } else {
+                if fields.len() < 4 {
+                    return Err(format!("Invalid AS {}", line).into());
+                }
+                as_orgs.push((
+                    fields[0].parse()?,
+                    fields[2].to_owned(),
+                    fields[3].to_owned(),
+                ));
+            }
+        }
+        let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_owned());
+        let asns = as_orgs
+            .into_iter()
+            .map(|(asn, name, org_id)| {
+                let org = orgs.get(&org_id);
+                let metadata = AsMetadata {
+                    name: non_empty(&name),
+                    organization: org.and_then(|(org_name, _)| non_empty(org_name)),
+                    country: org.and_then(|(_, country)| non_empty(country)),
+                    customer_cone_size: None,
+                };
+                (asn, metadata)
+            })
+            .collect();
+        Ok(Self { asns })
+    }
+
+    pub fn from_as_rank(reader: impl BufRead) -> Result<Self, Box<dyn Error>> {
+        let mut asns = HashMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: AsRankRecord = serde_json::from_str(&line)?;
+            let metadata = AsMetadata {
+                name: record.asn_name,
+                organization: record.organization.and_then(|o| o.org_name),
+                country: record.country.and_then(|c| c.iso),
+                customer_cone_size: record.cone.and_then(|c| c.number_asns),
+            };
+            asns.insert(record.asn.parse()?, metadata);
+        }
+        Ok(Self { asns })
+    }
+
+    /// Fills the fields missing here from the other dataset, e.g. the cone sizes of AS Rank
+    pub fn merge(&mut self, other: Self) {
+        for (asn, metadata) in other.asns {
+            let entry = self.asns.entry(asn).or_default();
+            entry.name = entry.name.take().or(metadata.name);
+            entry.organization = entry.organization.take().or(metadata.organization);
+            entry.country = entry.country.take().or(metadata.country);
+            entry.customer_cone_size = entry.customer_cone_size.or(metadata.customer_cone_size);
+        }
+    }
+
+    pub fn get(&self, asn: Asn) -> Option<&AsMetadata> {
+        self.asns.get(&asn)
+    }
+
+    /// The ASs whose metadata matches, e.g. those registered in a country, sorted by ASN
+    pub fn asns_where(&self, predicate: impl Fn(&AsMetadata) -> bool) -> Vec<Asn> {
+        let mut asns: Vec<Asn> = self
+            .asns
+            .iter()
+            .filter(|(_, metadata)| predicate(metadata))
+            .map(|(asn, _)| *asn)
+            .collect();
+        asns.sort_unstable();
+        asns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn as2org_and_as_rank() {
+        let as2org = "# name: AS Org\n\
+                      # format:aut|changed|aut_name|org_id|opaque_id|source\n\
+                      3356|20120224|LEVEL3|LPL-141-ARIN||ARIN\n\
+                      24940|20170126|HETZNER-AS||ORG-HOA1-RIPE|RIPE\n\
+                      # format:org_id|changed|org_name|country|source\n\
+                      LPL-141-ARIN|20230321|Level 3 Parent, LLC|US|ARIN\n";
+        let mut metadata = AsMetadataMap::from_as2org(as2org.as_bytes()).unwrap();
+        let expected = AsMetadata {
+            name: Some(String::from("LEVEL3")),
+            organization: Some(String::from("Level 3 Parent, LLC")),
+            country: Some(String::from("US")),
+            customer_cone_size: None,
+        };
+        assert_eq!(metadata.get(3356), Some(&expected));
+        // the organisation is missing
+        assert_eq!(metadata.get(24940).unwrap().country, None);
+
+        let as_rank = r#"{"asn":"3356","asnName":"LEVEL3","rank":1,"cone":{"numberAsns":50000}}
+{"asn":"797","asnName":"AS797","country":{"iso":"US"},"cone":{"numberAsns":1}}"#;
+        metadata.merge(AsMetadataMap::from_as_rank(as_rank.as_bytes()).unwrap());
+        let expected = AsMetadata {
+            customer_cone_size: Some(50000),
+            ..expected
+        };
+        assert_eq!(metadata.get(3356), Some(&expected));
+        assert_eq!(metadata.get(797).unwrap().customer_cone_size, Some(1));
+        assert_eq!(metadata.get(1), None);
+    }
+}
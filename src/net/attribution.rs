@@ -0,0 +1,138 @@
+use super::Asn;
+#[cfg(feature = "mmdb")]
+use super::AsnResolver;
+#[cfg(all(not(test), feature = "mmdb"))]
+use log::info;
+use simlib::ID;
+#[cfg(feature = "mmdb")]
+use simlib::{graph::Graph, Node};
+use std::collections::HashMap;
+#[cfg(test)]
+use std::println as info;
+#[cfg(feature = "mmdb")]
+use std::{collections::BTreeMap, net::IpAddr, str::FromStr};
+
+/// Every AS a node's clearnet addresses map to, weighted by the share of its addresses in the AS.
+/// Only nodes with addresses in more than one AS are kept, all others are attributed unambiguously
+/// by the AS map
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AsnCandidates {
+    pub candidates: HashMap<ID, Vec<(Asn, f32)>>,
+}
+
+impl AsnCandidates {
+    #[cfg(feature = "mmdb")]
+    pub fn new(graph: &Graph, resolver: &AsnResolver) -> Self {
+        Self::from_nodes(&graph.get_nodes(), resolver)
+    }
+
+    #[cfg(feature = "mmdb")]
+    pub fn from_nodes(nodes: &[Node], resolver: &AsnResolver) -> Self {
+        let mut candidates = HashMap::new();
+        for node in nodes {
+            let asns: Vec<Asn> = node
+                .addresses
+                .iter()
+                .filter(|a| !a.addr.contains("onion"))
+                .filter_map(|a| IpAddr::from_str(&a.addr).ok())
+                .filter_map(|ip| resolver.lookup_asn(ip))
+                .collect();
+            let mut counts: BTreeMap<Asn, usize> = BTreeMap::new();
+            for asn in asns.iter() {
+                *counts.entry(*asn).or_default() += 1;
+            }
+            if counts.len() > 1 {
+                let weights = counts
+                    .into_iter()
+                    .map(|(asn, count)| (asn, count as f32 / asns.len() as f32))
+                    .collect();
+                candidates.insert(node.id.clone(), weights);
+            }
+        }
+        info!(
+            "{} nodes have addresses in more than one AS.",
+            candidates.len()
+        );
+        Self { candidates }
+    }
+
+    pub fn num_ambiguous(&self) -> usize {
+        self.candidates.len()
+    }
+
+    /// The adversary's weight of the node, 1 for nodes attributed unambiguously
+    pub fn weight(&self, node: &ID, asn: Asn) -> f32 {
+        match self.candidates.get(node) {
+            Some(weights) => weights
+                .iter()
+                .find(|(a, _)| *a == asn)
+                .map(|(_, w)| *w)
+                .unwrap_or_default(),
+            None => 1.0,
+        }
+    }
+
+    /// The adversary's nodes without those that also have addresses in other ASs
+    pub fn best_case_nodes(&self, nodes: &[ID]) -> Vec<ID> {
+        nodes
+            .iter()
+            .filter(|n| !self.candidates.contains_key(*n))
+            .cloned()
+            .collect()
+    }
+
+    /// The adversary's nodes and those attributed elsewhere with an address in the adversary's AS
+    pub fn worst_case_nodes(&self, asn: Asn, nodes: &[ID]) -> Vec<ID> {
+        let mut worst_case = nodes.to_vec();
+        let mut candidates: Vec<&ID> = self
+            .candidates
+            .iter()
+            .filter(|(n, weights)| !nodes.contains(n) && weights.iter().any(|(a, _)| *a == asn))
+            .map(|(n, _)| n)
+            .collect();
+        candidates.sort();
+        worst_case.extend(candidates.into_iter().cloned());
+        worst_case
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::Address;
+
+    #[test]
+    fn candidate_asns() {
+        let node = |id: &str, addrs: Vec<&str>| Node {
+            id: id.to_string(),
+            addresses: addrs
+                .into_iter()
+                .map(|addr| Address {
+                    network: "tcp".to_string(),
+                    addr: addr.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let nodes = vec![
+            node("alice", vec!["8.8.8.8", "93.184.216.34", "8.8.4.4"]),
+            node("bob", vec!["8.8.8.8", "8.8.4.4"]),
+        ];
+        let candidates = AsnCandidates::from_nodes(&nodes, &AsnResolver::new());
+        assert_eq!(candidates.num_ambiguous(), 1);
+        let alice = String::from("alice");
+        assert_eq!(candidates.candidates[&alice].len(), 2);
+        assert_eq!(candidates.weight(&alice, 15169), 2.0 / 3.0);
+        assert_eq!(candidates.weight(&alice, 797), 0.0);
+        assert_eq!(candidates.weight(&String::from("bob"), 797), 1.0);
+        let nodes = vec![alice.clone(), String::from("bob")];
+        assert_eq!(
+            candidates.best_case_nodes(&nodes),
+            vec![String::from("bob")]
+        );
+        assert_eq!(
+            candidates.worst_case_nodes(15133, &[String::from("chan")]),
+            vec![String::from("chan"), alice]
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use super::{AsIpMap, Asn};
+#[cfg(not(test))]
+use log::info;
+use rayon::prelude::*;
+use simlib::{graph::Graph, ID};
+#[cfg(test)]
+use std::println as info;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+    error::Error,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+/// Provider-customer and peering links between ASs, e.g. from CAIDA's AS-relationship dataset
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AsRelationships {
+    providers: HashMap<Asn, Vec<Asn>>,
+    customers: HashMap<Asn, Vec<Asn>>,
+    peers: HashMap<Asn, Vec<Asn>>,
+}
+
+/// The preference of a route in the Gao-Rexford model, customer routes are preferred the most
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum RouteKind {
+    Customer,
+    Peer,
+    Provider,
+}
+
+/// The route an AS selected towards a destination
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Route {
+    kind: RouteKind,
+    len: usize,
+    next_hop: Asn,
+}
+
+impl Route {
+    /// Customer over peer over provider routes, then shorter routes and the lower next hop
+    fn is_better_than(&self, other: &Route) -> bool {
+        (self.kind, self.len, self.next_hop) < (other.kind, other.len, other.next_hop)
+    }
+}
+
+impl AsRelationships {
+    /// Reads a CAIDA serial-1 or serial-2 file with lines `<AS1>|<AS2>|<relationship>`, where -1
+    /// means AS1 is a provider of AS2 and 0 that they peer. Comments start with `#`
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let relationships = Self::from_reader(BufReader::new(File::open(path)?))?;
+        info!(
+            "Read the relationships of {} ASs from {:#?}.",
+            relationships.num_asns(),
+            path
+        );
+        Ok(relationships)
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, Box<dyn Error>> {
+        let mut relationships = Self::default();
+        for line in reader.lines() {
+            let line = line?;
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let mut fields = line.split('|');
+            let mut field = || {
+                fields
+                    .next()
+                    .ok_or(format!("Invalid relationship {}", line))
+            };
+            let (first, second): (Asn, Asn) = (field()?.parse()?, field()?.parse()?);
+            match field()? {
+                "-1" => relationships.add_customer(first, second),
+                "0" => relationships.add_peers(first, second),
+                rel => return Err(format!("Unknown relationship {} in {}", rel, line).into()),
+            }
+        }
+        Ok(relationships)
+    }
+
+    pub fn add_customer(&mut self, provider: Asn, customer: Asn) {
+        self.customers.entry(provider).or_default().push(customer);
+        self.providers.entry(customer).or_default().push(provider);
+    }
+
+    pub fn add_peers(&mut self, first: Asn, second: Asn) {
+        self.peers.entry(first).or_default().push(second);
+        self.peers.entry(second).or_default().push(first);
+    }
+
+    pub fn num_asns(&self) -> usize {
+        self.providers
+            .keys()
+            .chain(self.customers.keys())
+            .chain(self.peers.keys())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    fn neighbours<'a>(links: &'a HashMap<Asn, Vec<Asn>>, asn: &Asn) -> &'a [Asn] {
+        links.get(asn).map(|l| l.as_slice()).unwrap_or_default()
+    }
+
+    /// The route every AS selects towards `dest` if all ASs follow the Gao-Rexford model, i.e.,
+    /// prefer routes via customers over peers over providers and only export routes that are
+    /// valley-free
+    fn routes_to(&self, dest: Asn) -> HashMap<Asn, Route> {
+        let mut routes = HashMap::from([(
+            dest,
+            Route {
+                kind: RouteKind::Customer,
+                len: 0,
+                next_hop: dest,
+            },
+        )]);
+        // customer routes climb the providers, level by level so that routes are shortest
+        let mut frontier = vec![dest];
+        while !frontier.is_empty() {
+            let mut next: BTreeMap<Asn, Asn> = BTreeMap::new();
+            for asn in frontier.iter() {
+                for provider in Self::neighbours(&self.providers, asn) {
+                    if !routes.contains_key(provider) {
+                        let next_hop = next.entry(*provider).or_insert(*asn);
+                        *next_hop = (*next_hop).min(*asn);
+                    }
+                }
+            }
+            let len = routes[&frontier[0]].len + 1;
+            for (asn, next_hop) in next.iter() {
+                routes.insert(
+                    *asn,
+                    Route {
+                        kind: RouteKind::Customer,
+                        len,
+                        next_hop: *next_hop,
+                    },
+                );
+            }
+            frontier = next.into_keys().collect();
+        }
+        // customer routes are exported to peers, once
+        let mut peer_routes: HashMap<Asn, Route> = HashMap::new();
+        for (asn, route) in routes.iter() {
+            for peer in Self::neighbours(&self.peers, asn) {
+                if routes.contains_key(peer) {
+                    continue;
+                }
+                let candidate = Route {
+                    kind: RouteKind::Peer,
+                    len: route.len + 1,
+                    next_hop: *asn,
+                };
+                match peer_routes.get(peer) {
+                    Some(existing) if !candidate.is_better_than(existing) => {}
+                    _ => {
+                        peer_routes.insert(*peer, candidate);
+                    }
+                }
+            }
+        }
+        routes.extend(peer_routes);
+        // every route is exported to customers, the shortest ones first
+        let mut heap: BinaryHeap<Reverse<(usize, Asn)>> = routes
+            .iter()
+            .map(|(asn, r)| Reverse((r.len, *asn)))
+            .collect();
+        while let Some(Reverse((len, asn))) = heap.pop() {
+            if routes[&asn].len < len {
+                continue;
+            }
+            for customer in Self::neighbours(&self.customers, &asn) {
+                let candidate = Route {
+                    kind: RouteKind::Provider,
+                    len: len + 1,
+                    next_hop: asn,
+                };
+                match routes.get(customer) {
+                    Some(existing) if !candidate.is_better_than(existing) => {}
+                    _ => {
+                        routes.insert(*customer, candidate);
+                        heap.push(Reverse((len + 1, *customer)));
+                    }
+                }
+            }
+        }
+        routes
+    }
+
+    /// The AS-level path from `src` to `dest` including both, None if `src` has no route
+    pub fn path(&self, src: Asn, dest: Asn) -> Option<Vec<Asn>> {
+        Self::follow(&self.routes_to(dest), src, dest)
+    }
+
+    fn follow(routes: &HashMap<Asn, Route>, src: Asn, dest: Asn) -> Option<Vec<Asn>> {
+        let mut path = vec![src];
+        let mut current = src;
+        while current != dest {
+            current = routes.get(&current)?.next_hop;
+            path.push(current);
+        }
+        Some(path)
+    }
+}
+
+/// The transit ASs that carry the IP traffic of the channels between nodes in different ASs,
+/// without the ASs of the two peers
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransitMap {
+    /// Keyed by the peers of a channel in ascending order
+    pub channels: HashMap<(ID, ID), Vec<Asn>>,
+}
+
+impl TransitMap {
+    /// Infers the AS paths in both directions of every channel. Channels without a route are left
+    /// out
+    pub fn new(graph: &Graph, as_ip_map: &AsIpMap, relationships: &AsRelationships) -> Self {
+        let node_asns: HashMap<&ID, Asn> = as_ip_map
+            .as_to_nodes()
+            .iter()
+            .flat_map(|(asn, nodes)| nodes.iter().map(move |n| (n, *asn)))
+            .collect();
+        let mut peers: HashSet<(ID, ID)> = HashSet::new();
+        for (src, edges) in graph.edges.iter() {
+            for edge in edges.iter() {
+                let (first, second) = if *src < edge.destination {
+                    (src, &edge.destination)
+                } else {
+                    (&edge.destination, src)
+                };
+                match (node_asns.get(first), node_asns.get(second)) {
+                    (Some(a), Some(b)) if a != b => {
+                        peers.insert((first.clone(), second.clone()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let dests: HashSet<Asn> = peers
+            .iter()
+            .flat_map(|(a, b)| [node_asns[a], node_asns[b]])
+            .collect();
+        let routes: HashMap<Asn, HashMap<Asn, Route>> = dests
+            .into_par_iter()
+            .map(|dest| (dest, relationships.routes_to(dest)))
+            .collect();
+        let mut channels = HashMap::new();
+        for (first, second) in peers {
+            let (a, b) = (node_asns[&first], node_asns[&second]);
+            let mut transit: Vec<Asn> = [(a, b), (b, a)]
+                .into_iter()
+                .filter_map(|(src, dest)| AsRelationships::follow(&routes[&dest], src, dest))
+                .flat_map(|path| path[1..path.len() - 1].to_vec())
+                .collect();
+            transit.sort_unstable();
+            transit.dedup();
+            channels.insert((first, second), transit);
+        }
+        info!(
+            "Inferred the AS paths of {} inter-AS channels.",
+            channels.len()
+        );
+        Self { channels }
+    }
+
+    /// The transit ASs of the channel between the two nodes
+    pub fn transit_asns(&self, a: &ID, b: &ID) -> &[Asn] {
+        let key = if a < b {
+            (a.clone(), b.clone())
+        } else {
+            (b.clone(), a.clone())
+        };
+        self.channels
+            .get(&key)
+            .map(|t| t.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The ASs carrying the most channels as transit with the number of channels, in descending
+    /// order
+    pub fn top_n_transit_asns(&self, n: usize) -> Vec<(Asn, usize)> {
+        let mut counts: HashMap<Asn, usize> = HashMap::new();
+        for asn in self.channels.values().flatten() {
+            *counts.entry(*asn).or_default() += 1;
+        }
+        let mut counts: Vec<(Asn, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+
+    #[test]
+    fn valley_free_paths() {
+        // 1 and 2 are peering tier-1s, 3 is a customer of 1 and 4 a customer of 2 and 3. 5 is a
+        // customer of 3 and peers with 4
+        let input = "# source:topology|BGP\n1|2|0\n1|3|-1\n2|4|-1\n3|4|-1\n3|5|-1\n4|5|0|bgp\n";
+        let relationships = AsRelationships::from_reader(input.as_bytes()).unwrap();
+        assert_eq!(relationships.num_asns(), 5);
+        // the customer route via 3 is preferred over the peer route via 2
+        assert_eq!(relationships.path(1, 4), Some(vec![1, 3, 4]));
+        // the peer route is preferred over the provider route via 3
+        assert_eq!(relationships.path(5, 4), Some(vec![5, 4]));
+        // 4 does not export the routes of its provider 3 to its other provider 2
+        assert_eq!(relationships.path(2, 3), Some(vec![2, 1, 3]));
+        // nor the routes of its peer 5
+        assert_eq!(relationships.path(2, 5), Some(vec![2, 1, 3, 5]));
+        assert_eq!(relationships.path(1, 6), None);
+        assert!(AsRelationships::from_reader("1|2|1\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn transit_channels() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let mut relationships = AsRelationships::default();
+        relationships.add_customer(3356, 24940);
+        relationships.add_customer(3356, 797);
+        let transit = TransitMap::new(&graph, &as_ip_map, &relationships);
+        // bob-chan is the only channel between the ASs
+        assert_eq!(transit.channels.len(), 1);
+        let (bob, chan) = (String::from("bob"), String::from("chan"));
+        assert_eq!(transit.transit_asns(&chan, &bob), &[3356]);
+        assert!(transit
+            .transit_asns(&String::from("alice"), &bob)
+            .is_empty());
+        assert_eq!(transit.top_n_transit_asns(5), vec![(3356, 1)]);
+    }
+}
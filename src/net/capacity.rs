@@ -0,0 +1,174 @@
+#[cfg(not(test))]
+use log::warn;
+use serde::Serialize;
+use simlib::{graph::Graph, ID};
+#[cfg(test)]
+use std::println as warn;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{self, File},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// How the capacity of channels without capacity data (capacity of 0) is imputed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CapacityModel {
+    /// Median capacity of the channels whose source has the same degree. Falls back to the global
+    /// median if no such channel has a known capacity
+    DegreeMedian,
+    /// Median capacity of all channels with a known capacity
+    GlobalMedian,
+    /// The same capacity (in msat) for every channel
+    Constant(usize),
+}
+
+/// Information about the graph that is not part of the simulation results
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphMetadata {
+    pub num_nodes: usize,
+    pub num_channels: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity_model: Option<CapacityModel>,
+    pub num_imputed_capacities: usize,
+}
+
+impl FromStr for CapacityModel {
+    type Err = String;
+
+    /// Either "degree-median", "global-median" or a constant capacity in sat
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "degree-median" => Ok(Self::DegreeMedian),
+            "global-median" => Ok(Self::GlobalMedian),
+            other => other
+                .parse::<usize>()
+                .map(|sat| Self::Constant(simlib::to_millisatoshi(sat)))
+                .map_err(|_| format!("Unknown capacity model {}", s)),
+        }
+    }
+}
+
+impl CapacityModel {
+    /// Sets the capacity of every channel without one according to the model and returns the
+    /// number of channels that were changed
+    pub fn impute(&self, graph: &mut Graph) -> usize {
+        let degrees: HashMap<ID, usize> = graph
+            .edges
+            .iter()
+            .map(|(id, edges)| (id.clone(), edges.len()))
+            .collect();
+        let mut known: Vec<usize> = vec![];
+        let mut known_per_degree: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (id, edges) in graph.edges.iter() {
+            for edge in edges.iter().filter(|e| e.capacity > 0) {
+                known.push(edge.capacity);
+                known_per_degree
+                    .entry(degrees[id])
+                    .or_default()
+                    .push(edge.capacity);
+            }
+        }
+        let global_median = median(&mut known);
+        let degree_medians: HashMap<usize, usize> = known_per_degree
+            .into_iter()
+            .filter_map(|(degree, mut capacities)| Some((degree, median(&mut capacities)?)))
+            .collect();
+        let mut num_imputed = 0;
+        for (id, edges) in graph.edges.iter_mut() {
+            let capacity = match self {
+                Self::Constant(capacity) => Some(*capacity),
+                Self::GlobalMedian => global_median,
+                Self::DegreeMedian => degree_medians.get(&degrees[id]).copied().or(global_median),
+            };
+            let Some(capacity) = capacity else {
+                continue;
+            };
+            for edge in edges.iter_mut().filter(|e| e.capacity == 0) {
+                edge.capacity = capacity;
+                num_imputed += 1;
+            }
+        }
+        if global_median.is_none() && !matches!(self, Self::Constant(_)) {
+            warn!("No channel has a known capacity, capacities cannot be imputed.");
+        }
+        num_imputed
+    }
+}
+
+impl GraphMetadata {
+    pub fn new(graph: &Graph) -> Self {
+        Self {
+            num_nodes: graph.node_count(),
+            num_channels: graph.edges.values().map(|edges| edges.len()).sum(),
+            ..Default::default()
+        }
+    }
+
+    /// Writes `graph-metadata.json` to the directory and returns its path
+    pub fn write_to_file(&self, output_dir: PathBuf) -> Result<PathBuf, Box<dyn Error>> {
+        fs::create_dir_all(&output_dir)?;
+        let mut path = output_dir;
+        path.push("graph-metadata.json");
+        serde_json::to_writer_pretty(File::create(&path)?, self)?;
+        Ok(path)
+    }
+}
+
+/// The lower median so that the result is always the capacity of an existing channel
+fn median(values: &mut [usize]) -> Option<usize> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[(values.len() - 1) / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+
+    #[test]
+    fn impute_capacities() {
+        assert_eq!(
+            CapacityModel::from_str("degree-median"),
+            Ok(CapacityModel::DegreeMedian)
+        );
+        assert_eq!(
+            CapacityModel::from_str("1000"),
+            Ok(CapacityModel::Constant(1000000))
+        );
+        assert!(CapacityModel::from_str("mean").is_err());
+        let mut graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let metadata = GraphMetadata::new(&graph);
+        for edges in graph.edges.values_mut() {
+            for edge in edges.iter_mut() {
+                edge.capacity = 0;
+            }
+        }
+        // nothing to base the median on
+        assert_eq!(CapacityModel::GlobalMedian.impute(&mut graph), 0);
+        let alice_edges = graph.edges.get_mut("alice").unwrap();
+        alice_edges[0].capacity = 500;
+        let num_imputed = CapacityModel::DegreeMedian.impute(&mut graph);
+        assert_eq!(num_imputed, metadata.num_channels - 1);
+        assert!(graph
+            .edges
+            .values()
+            .flatten()
+            .all(|edge| edge.capacity == 500));
+        assert_eq!(CapacityModel::Constant(1).impute(&mut graph), 0);
+    }
+}
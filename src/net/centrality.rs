@@ -0,0 +1,137 @@
+use super::{AsIpMap, Asn};
+use rand::{rngs::StdRng, seq::index::sample};
+use simlib::{graph::Graph, ID};
+use std::collections::{HashMap, VecDeque};
+
+/// Number of source nodes sampled to approximate the betweenness centrality
+pub static BETWEENNESS_SAMPLES: usize = 500;
+
+/// Approximates the betweenness centrality of each node by counting the shortest paths (in number
+/// of hops) from `num_samples` randomly drawn sources that pass through it (Brandes' algorithm).
+/// The scores are scaled to the total number of nodes
+pub fn approx_betweenness(graph: &Graph, num_samples: usize, rng: &mut StdRng) -> HashMap<ID, f64> {
+    let mut ids: Vec<ID> = graph.get_nodes().into_iter().map(|n| n.id).collect();
+    // sorted so that the sampled sources only depend on the seed
+    ids.sort();
+    let index: HashMap<&ID, usize> = ids.iter().enumerate().map(|(i, id)| (id, i)).collect();
+    let adjacency: Vec<Vec<usize>> = ids
+        .iter()
+        .map(|id| {
+            graph
+                .get_edges_for_node(id)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|e| index.get(&e.destination).copied())
+                .collect()
+        })
+        .collect();
+    let num_nodes = ids.len();
+    let num_samples = std::cmp::min(num_samples, num_nodes);
+    let mut betweenness = vec![0.0; num_nodes];
+    for source in sample(rng, num_nodes, num_samples).into_iter() {
+        let mut stack = Vec::with_capacity(num_nodes);
+        let mut predecessors: Vec<Vec<usize>> = vec![vec![]; num_nodes];
+        let mut num_paths = vec![0.0; num_nodes];
+        let mut distance: Vec<Option<usize>> = vec![None; num_nodes];
+        num_paths[source] = 1.0;
+        distance[source] = Some(0);
+        let mut queue = VecDeque::from([source]);
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            let dist_v = distance[v].unwrap_or_default();
+            for &w in adjacency[v].iter() {
+                if distance[w].is_none() {
+                    distance[w] = Some(dist_v + 1);
+                    queue.push_back(w);
+                }
+                if distance[w] == Some(dist_v + 1) {
+                    num_paths[w] += num_paths[v];
+                    predecessors[w].push(v);
+                }
+            }
+        }
+        let mut dependency = vec![0.0; num_nodes];
+        while let Some(w) = stack.pop() {
+            for &v in predecessors[w].iter() {
+                dependency[v] += num_paths[v] / num_paths[w] * (1.0 + dependency[w]);
+            }
+            if w != source {
+                betweenness[w] += dependency[w];
+            }
+        }
+    }
+    let scale = if num_samples > 0 {
+        num_nodes as f64 / num_samples as f64
+    } else {
+        0.0
+    };
+    ids.into_iter()
+        .zip(betweenness)
+        .map(|(id, b)| (id, b * scale))
+        .collect()
+}
+
+impl AsIpMap {
+    /// Returns an ordered list of the n ASNs whose nodes have the highest sum of (approximate)
+    /// betweenness centrality. The list of nodes is sorted in descending order of betweenness
+    pub(crate) fn top_n_asns_betweenness(
+        &self,
+        n: usize,
+        graph: &Graph,
+        rng: &mut StdRng,
+    ) -> Vec<(Asn, Vec<ID>)> {
+        let betweenness = approx_betweenness(graph, BETWEENNESS_SAMPLES, rng);
+        let score = |node: &ID| betweenness.get(node).copied().unwrap_or_default();
+        let mut ranked: Vec<(f64, Asn, Vec<ID>)> = self
+            .as_to_nodes()
+            .iter()
+            .map(|(asn, nodes)| {
+                let mut nodes = nodes.clone();
+                nodes.sort_by(|a, b| score(b).total_cmp(&score(a)));
+                (nodes.iter().map(score).sum(), *asn, nodes)
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+        ranked
+            .into_iter()
+            .take(n)
+            .map(|(_, asn, nodes)| (asn, nodes))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use rand::SeedableRng;
+    use std::path::Path;
+
+    #[test]
+    fn betweenness() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let mut rng = StdRng::seed_from_u64(0);
+        // every node is a source so the result is exact
+        let exact = approx_betweenness(&graph, usize::MAX, &mut rng);
+        assert_eq!(exact.len(), graph.node_count());
+        assert!(exact.values().all(|b| *b >= 0.0));
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let total =
+            |asn: &Asn| -> f64 { as_ip_map.as_to_nodes()[asn].iter().map(|n| exact[n]).sum() };
+        let actual = as_ip_map.top_n_asns_betweenness(1, &graph, &mut rng);
+        assert_eq!(actual.len(), 1);
+        let (asn, nodes) = &actual[0];
+        assert_eq!(nodes.len(), as_ip_map.as_to_nodes()[asn].len());
+        assert!(as_ip_map
+            .as_to_nodes()
+            .keys()
+            .all(|other| total(other) <= total(asn)));
+    }
+}
@@ -0,0 +1,159 @@
+use super::{AsIpMap, Asn};
+use crate::TOR_ASN;
+use serde::{Deserialize, Serialize};
+use simlib::graph::Graph;
+
+/// Shares of the network for which the minimum number of ASs controlling them is reported
+pub static CONTROL_SHARES: [f32; 3] = [0.33, 0.5, 0.66];
+
+/// How the nodes, channels and capacity of the network are distributed across ASs. Nodes with only
+/// an onion address are not part of any AS
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Concentration {
+    pub num_asns: usize,
+    pub nodes: ConcentrationMetrics,
+    /// Channels of the ASs' nodes
+    pub channels: ConcentrationMetrics,
+    pub capacity: ConcentrationMetrics,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConcentrationMetrics {
+    /// 0 if every AS has the same share, close to 1 if a single AS has everything
+    pub gini: f64,
+    /// Herfindahl-Hirschman index, the sum of the squared shares of the ASs between 0 and 1
+    pub hhi: f64,
+    /// The minimum number of ASs that together hold at least each of the `CONTROL_SHARES`
+    pub min_asns_to_control: Vec<ControlThreshold>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlThreshold {
+    pub share: f32,
+    pub num_asns: usize,
+}
+
+impl ConcentrationMetrics {
+    pub fn new(values: &[usize]) -> Self {
+        let mut sorted: Vec<f64> = values.iter().map(|v| *v as f64).collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let n = sorted.len() as f64;
+        let total: f64 = sorted.iter().sum();
+        if total == 0.0 {
+            return Self {
+                min_asns_to_control: CONTROL_SHARES
+                    .iter()
+                    .map(|share| ControlThreshold {
+                        share: *share,
+                        num_asns: 0,
+                    })
+                    .collect(),
+                ..Default::default()
+            };
+        }
+        let weighted: f64 = sorted
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i + 1) as f64 * v)
+            .sum();
+        let gini = 2.0 * weighted / (n * total) - (n + 1.0) / n;
+        let hhi = sorted.iter().map(|v| (v / total).powi(2)).sum();
+        let min_asns_to_control = CONTROL_SHARES
+            .iter()
+            .map(|share| {
+                let mut held = 0.0;
+                let num_asns = sorted
+                    .iter()
+                    .rev()
+                    .take_while(|v| {
+                        let below = held < *share as f64 * total;
+                        held += **v;
+                        below
+                    })
+                    .count();
+                ControlThreshold {
+                    share: *share,
+                    num_asns,
+                }
+            })
+            .collect();
+        Self {
+            gini,
+            hhi,
+            min_asns_to_control,
+        }
+    }
+}
+
+impl AsIpMap {
+    /// The concentration of the graph's nodes, channels and capacity across the mapped ASs
+    pub fn concentration(&self, graph: &Graph) -> Concentration {
+        let asns: Vec<&Asn> = self
+            .as_to_nodes()
+            .keys()
+            .filter(|asn| **asn != TOR_ASN)
+            .collect();
+        let (mut nodes, mut channels, mut capacity) = (vec![], vec![], vec![]);
+        for asn in asns.iter() {
+            let as_nodes = &self.as_to_nodes()[*asn];
+            let (num_channels, capacity_msat) = as_nodes
+                .iter()
+                .flat_map(|n| graph.get_edges_for_node(n).unwrap_or_default())
+                .fold((0, 0), |(n, c), e| (n + 1, c + e.capacity));
+            nodes.push(as_nodes.len());
+            channels.push(num_channels);
+            capacity.push(capacity_msat);
+        }
+        Concentration {
+            num_asns: asns.len(),
+            nodes: ConcentrationMetrics::new(&nodes),
+            channels: ConcentrationMetrics::new(&channels),
+            capacity: ConcentrationMetrics::new(&capacity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+
+    #[test]
+    fn concentration_metrics() {
+        let equal = ConcentrationMetrics::new(&[5, 5, 5, 5]);
+        assert!(equal.gini.abs() < 1e-9);
+        assert!((equal.hhi - 0.25).abs() < 1e-9);
+        let num_asns: Vec<usize> = equal
+            .min_asns_to_control
+            .iter()
+            .map(|c| c.num_asns)
+            .collect();
+        assert_eq!(num_asns, vec![2, 2, 3]);
+        let monopoly = ConcentrationMetrics::new(&[0, 0, 0, 10]);
+        assert!((monopoly.gini - 0.75).abs() < 1e-9);
+        assert!((monopoly.hhi - 1.0).abs() < 1e-9);
+        assert!(monopoly.min_asns_to_control.iter().all(|c| c.num_asns == 1));
+        let empty = ConcentrationMetrics::new(&[]);
+        assert_eq!(empty.hhi, 0.0);
+        assert_eq!(empty.min_asns_to_control.len(), CONTROL_SHARES.len());
+
+        let graph = simlib::graph::Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let concentration = as_ip_map.concentration(&graph);
+        assert_eq!(concentration.num_asns, 2);
+        // both ASs host two nodes
+        assert!(concentration.nodes.gini.abs() < 1e-9);
+        assert!((concentration.nodes.hhi - 0.5).abs() < 1e-9);
+    }
+}
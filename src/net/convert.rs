@@ -0,0 +1,427 @@
+use network_parser::GraphSource;
+use serde_json::{json, Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fs::File,
+    net::SocketAddr,
+    path::Path,
+    str::FromStr,
+};
+
+/// Converts a graph file between the supported formats. Node addresses, channel capacities and
+/// policies are preserved, all other fields are dropped as are channels of nodes that are not in
+/// the graph. Converting a graph to its own format returns it unchanged
+pub fn convert_graph_json(
+    graph: &Value,
+    from: &GraphSource,
+    to: &GraphSource,
+) -> Result<Value, String> {
+    match (from, to) {
+        (GraphSource::Lnd, GraphSource::Lnresearch) => lnd_to_lnresearch(graph),
+        (GraphSource::Lnresearch, GraphSource::Lnd) => lnresearch_to_lnd(graph),
+        (GraphSource::Lnd, GraphSource::Lnd)
+        | (GraphSource::Lnresearch, GraphSource::Lnresearch) => Ok(graph.clone()),
+    }
+}
+
+fn array<'a>(graph: &'a Value, key: &str) -> Result<&'a Vec<Value>, String> {
+    graph
+        .get(key)
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| format!("Graph has no {} array.", key))
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> Result<&'a str, String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| format!("Missing {} in {}.", key, value))
+}
+
+/// LND encodes most numbers of a policy as strings
+fn u64_field(value: &Value, key: &str) -> Option<u64> {
+    value.get(key).and_then(|v| match v {
+        Value::String(s) => u64::from_str(s).ok(),
+        _ => v.as_u64(),
+    })
+}
+
+/// `5.9.0.1:9735` to `ipv4://5.9.0.1:9735`
+fn lnresearch_address(addr: &str) -> String {
+    let scheme = match SocketAddr::from_str(addr) {
+        Ok(SocketAddr::V4(_)) => "ipv4",
+        Ok(SocketAddr::V6(_)) => "ipv6",
+        Err(_) => match addr.split_once(".onion") {
+            Some((host, _)) if host.len() == 16 => "torv2",
+            Some(_) => "torv3",
+            None => "dns",
+        },
+    };
+    format!("{}://{}", scheme, addr)
+}
+
+fn lnd_to_lnresearch(graph: &Value) -> Result<Value, String> {
+    let mut index = HashMap::new();
+    let mut nodes = vec![];
+    for node in array(graph, "nodes")? {
+        let id = str_field(node, "pub_key")?;
+        let addresses: Vec<String> = node
+            .get("addresses")
+            .and_then(|a| a.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|a| a.get("addr").and_then(|a| a.as_str()))
+            .map(lnresearch_address)
+            .collect();
+        index.insert(id.to_owned(), nodes.len());
+        nodes.push(json!({"id": id, "addresses": addresses.join(",")}));
+    }
+    let mut adjacency: Vec<Vec<Value>> = vec![vec![]; nodes.len()];
+    for edge in array(graph, "edges")? {
+        let channel_id = str_field(edge, "channel_id")?;
+        let capacity = u64_field(edge, "capacity").unwrap_or_default();
+        let ends = [str_field(edge, "node1_pub")?, str_field(edge, "node2_pub")?];
+        if !ends.iter().all(|n| index.contains_key(*n)) {
+            continue;
+        }
+        for (direction, policy_key) in ["node1_policy", "node2_policy"].iter().enumerate() {
+            let Some(policy) = edge.get(policy_key).filter(|p| !p.is_null()) else {
+                continue;
+            };
+            let (source, destination) = (ends[direction], ends[1 - direction]);
+            adjacency[index[source]].push(json!({
+                "scid": format!("{}/{}", channel_id, direction),
+                "source": source,
+                "destination": destination,
+                "capacity": capacity,
+                "fee_base_msat": u64_field(policy, "fee_base_msat").unwrap_or_default(),
+                "fee_proportional_millionths":
+                    u64_field(policy, "fee_rate_milli_msat").unwrap_or_default(),
+                "htlc_minimim_msat": u64_field(policy, "min_htlc").unwrap_or_default(),
+                "htlc_maximum_msat": u64_field(policy, "max_htlc_msat").unwrap_or_default(),
+                "cltv_expiry_delta": u64_field(policy, "time_lock_delta").unwrap_or_default(),
+                "id": destination,
+            }));
+        }
+    }
+    for (node, edges) in nodes.iter_mut().zip(adjacency.iter()) {
+        node["out_degree"] = json!(edges.len());
+    }
+    Ok(json!({
+        "directed": true,
+        "multigraph": false,
+        "graph": [],
+        "nodes": nodes,
+        "adjacency": adjacency,
+    }))
+}
+
+fn lnresearch_to_lnd(graph: &Value) -> Result<Value, String> {
+    let mut nodes = vec![];
+    let mut ids = HashSet::new();
+    for node in array(graph, "nodes")? {
+        let id = str_field(node, "id")?;
+        ids.insert(id);
+        let addresses: Vec<Value> = node
+            .get("addresses")
+            .and_then(|a| a.as_str())
+            .unwrap_or_default()
+            .split(',')
+            .filter(|a| !a.is_empty())
+            .map(|a| {
+                let addr = a.split_once("://").map(|(_, addr)| addr).unwrap_or(a);
+                json!({"network": "tcp", "addr": addr})
+            })
+            .collect();
+        nodes.push(json!({"pub_key": id, "addresses": addresses}));
+    }
+    // both directions of a channel share the scid up to the direction suffix
+    let mut channels: Vec<Map<String, Value>> = vec![];
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for edge in array(graph, "adjacency")?
+        .iter()
+        .filter_map(|e| e.as_array())
+        .flatten()
+    {
+        let scid = str_field(edge, "scid")?;
+        let (channel_id, direction) = scid.split_once('/').unwrap_or((scid, "0"));
+        let (source, destination) = (str_field(edge, "source")?, str_field(edge, "destination")?);
+        if !ids.contains(source) || !ids.contains(destination) {
+            continue;
+        }
+        let (node1, node2, policy_key) = if direction == "1" {
+            (destination, source, "node2_policy")
+        } else {
+            (source, destination, "node1_policy")
+        };
+        let i = *index.entry(channel_id.to_owned()).or_insert_with(|| {
+            let mut channel = Map::new();
+            channel.insert("channel_id".to_owned(), json!(channel_id));
+            channel.insert("node1_pub".to_owned(), json!(node1));
+            channel.insert("node2_pub".to_owned(), json!(node2));
+            channel.insert("capacity".to_owned(), json!(0));
+            channel.insert("node1_policy".to_owned(), Value::Null);
+            channel.insert("node2_policy".to_owned(), Value::Null);
+            channels.push(channel);
+            channels.len() - 1
+        });
+        let max_htlc_msat = u64_field(edge, "htlc_maximum_msat").unwrap_or_default();
+        // without a capacity, the channel is at least as large as its largest HTLC
+        let capacity = u64_field(edge, "capacity").unwrap_or(max_htlc_msat / 1000);
+        let channel = &mut channels[i];
+        if capacity > channel["capacity"].as_u64().unwrap_or_default() {
+            channel.insert("capacity".to_owned(), json!(capacity));
+        }
+        channel.insert(
+            policy_key.to_owned(),
+            json!({
+                "time_lock_delta": u64_field(edge, "cltv_expiry_delta").unwrap_or_default(),
+                "min_htlc": u64_field(edge, "htlc_minimim_msat").unwrap_or_default().to_string(),
+                "fee_base_msat": u64_field(edge, "fee_base_msat").unwrap_or_default().to_string(),
+                "fee_rate_milli_msat": u64_field(edge, "fee_proportional_millionths")
+                    .unwrap_or_default()
+                    .to_string(),
+                "max_htlc_msat": max_htlc_msat.to_string(),
+            }),
+        );
+    }
+    let edges: Vec<Value> = channels.into_iter().map(Value::Object).collect();
+    Ok(json!({"nodes": nodes, "edges": edges}))
+}
+
+/// Reads Core Lightning's `listchannels` and `listnodes` output as an LND graph
+pub fn read_cln_graph(
+    listchannels: &Path,
+    listnodes: &Path,
+) -> Result<network_parser::Graph, Box<dyn Error>> {
+    let channels: Value = serde_json::from_reader(File::open(listchannels)?)?;
+    let nodes: Value = serde_json::from_reader(File::open(listnodes)?)?;
+    let graph = cln_to_lnd(&channels, &nodes)?;
+    Ok(network_parser::Graph::from_json_str(
+        &graph.to_string(),
+        GraphSource::Lnd,
+    )?)
+}
+
+/// Converts Core Lightning's `listchannels` and `listnodes` output to the LND format. Each
+/// direction of a channel is listed separately by CLN and becomes the policy of its source. Nodes
+/// without an announcement are added without addresses
+pub fn cln_to_lnd(listchannels: &Value, listnodes: &Value) -> Result<Value, String> {
+    let mut nodes = BTreeMap::new();
+    for node in array(listnodes, "nodes")? {
+        let id = str_field(node, "nodeid")?;
+        let addresses: Vec<Value> = node
+            .get("addresses")
+            .and_then(|a| a.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(cln_address)
+            .map(|addr| json!({"network": "tcp", "addr": addr}))
+            .collect();
+        nodes.insert(id.to_owned(), addresses);
+    }
+    let mut channels: BTreeMap<&str, Map<String, Value>> = BTreeMap::new();
+    for half in array(listchannels, "channels")? {
+        let scid = str_field(half, "short_channel_id")?;
+        let (source, destination) = (str_field(half, "source")?, str_field(half, "destination")?);
+        let direction = half.get("direction").and_then(|d| d.as_u64()).unwrap_or(0);
+        let (node1, node2, policy_key) = if direction == 1 {
+            (destination, source, "node2_policy")
+        } else {
+            (source, destination, "node1_policy")
+        };
+        let capacity = msat_field(half, "amount_msat")
+            .map(|msat| msat / 1000)
+            .or_else(|| u64_field(half, "satoshis"))
+            .unwrap_or_default();
+        let channel = channels.entry(scid).or_insert_with(|| {
+            let mut channel = Map::new();
+            channel.insert("channel_id".to_owned(), json!(scid));
+            channel.insert("node1_pub".to_owned(), json!(node1));
+            channel.insert("node2_pub".to_owned(), json!(node2));
+            channel.insert("capacity".to_owned(), json!(capacity));
+            channel.insert("node1_policy".to_owned(), Value::Null);
+            channel.insert("node2_policy".to_owned(), Value::Null);
+            channel
+        });
+        channel.insert(
+            policy_key.to_owned(),
+            json!({
+                "time_lock_delta": u64_field(half, "delay").unwrap_or_default(),
+                "min_htlc": msat_field(half, "htlc_minimum_msat").unwrap_or_default().to_string(),
+                "fee_base_msat": u64_field(half, "base_fee_millisatoshi")
+                    .unwrap_or_default()
+                    .to_string(),
+                "fee_rate_milli_msat": u64_field(half, "fee_per_millionth")
+                    .unwrap_or_default()
+                    .to_string(),
+                "max_htlc_msat": msat_field(half, "htlc_maximum_msat")
+                    .unwrap_or_default()
+                    .to_string(),
+            }),
+        );
+        for id in [source, destination] {
+            nodes.entry(id.to_owned()).or_default();
+        }
+    }
+    let nodes: Vec<Value> = nodes
+        .into_iter()
+        .map(|(id, addresses)| json!({"pub_key": id, "addresses": addresses}))
+        .collect();
+    let edges: Vec<Value> = channels.into_values().map(Value::Object).collect();
+    Ok(json!({"nodes": nodes, "edges": edges}))
+}
+
+/// Older CLN versions encode amounts as strings such as `1000msat`
+fn msat_field(value: &Value, key: &str) -> Option<u64> {
+    value.get(key).and_then(|v| match v {
+        Value::String(s) => u64::from_str(s.trim_end_matches("msat")).ok(),
+        _ => v.as_u64(),
+    })
+}
+
+/// `{"type": "ipv6", "address": "2a01:4f8::1", "port": 9735}` to `[2a01:4f8::1]:9735`
+fn cln_address(addr: &Value) -> Option<String> {
+    let address = addr.get("address").and_then(|a| a.as_str())?;
+    let port = addr.get("port").and_then(|p| p.as_u64()).unwrap_or(9735);
+    match addr.get("type").and_then(|t| t.as_str()) {
+        Some("ipv6") => Some(format!("[{}]:{}", address, port)),
+        _ => Some(format!("{}:{}", address, port)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use std::{fs::File, path::Path};
+
+    fn read(path: &str) -> Value {
+        serde_json::from_reader(File::open(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip_lnd() {
+        let lnd = read("test_data/trivial_connected_lnd.json");
+        let lnr = convert_graph_json(&lnd, &Lnd, &Lnresearch).unwrap();
+        assert_eq!(
+            lnr["nodes"][0]["addresses"],
+            "ipv4://5.9.0.1:9735,ipv6://[2a01:4f8:0:1::7:1]:9735"
+        );
+        let actual = convert_graph_json(&lnr, &Lnresearch, &Lnd).unwrap();
+        let sorted = |graph: &Value| {
+            let mut edges = graph["edges"].as_array().unwrap().clone();
+            edges.sort_by_key(|e| e["channel_id"].as_str().unwrap().to_owned());
+            edges
+        };
+        assert_eq!(sorted(&actual), sorted(&lnd));
+        for (actual, expected) in actual["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .zip(lnd["nodes"].as_array().unwrap())
+        {
+            assert_eq!(actual["pub_key"], expected["pub_key"]);
+            let addrs = |n: &Value| -> Vec<Value> {
+                n["addresses"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|a| a["addr"].clone())
+                    .collect()
+            };
+            assert_eq!(addrs(actual), addrs(expected));
+        }
+        assert_eq!(convert_graph_json(&lnd, &Lnd, &Lnd).unwrap(), lnd);
+    }
+
+    #[test]
+    fn convert_lnresearch() {
+        let lnr = read("test_data/trivial_connected_lnr.json");
+        let lnd = convert_graph_json(&lnr, &Lnresearch, &Lnd).unwrap();
+        assert_eq!(
+            lnd["nodes"][2]["addresses"][1]["addr"],
+            "wu5mkpokybtbf6dwdaepnujbzxpm6mqqqm2hwob6ndt5k74iujd2pdyd.onion:9735"
+        );
+        // the channel to 024 is dropped as the node is not in the graph
+        assert_eq!(lnd["edges"].as_array().unwrap().len(), 6);
+        assert_eq!(
+            convert_graph_json(&lnd, &Lnd, &Lnresearch).unwrap()["nodes"][2]["addresses"],
+            lnr["nodes"][2]["addresses"]
+        );
+        let file = tempfile::NamedTempFile::new().unwrap();
+        serde_json::to_writer(File::create(file.path()).unwrap(), &lnd).unwrap();
+        let expected = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/trivial_connected_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let actual = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(file.path(), Lnd).unwrap(),
+            Lnd,
+        );
+        assert_eq!(actual.node_count(), expected.node_count());
+        for node in expected.get_nodes() {
+            let addrs = |g: &Graph| -> Vec<String> {
+                let mut addrs: Vec<String> = g
+                    .get_nodes()
+                    .into_iter()
+                    .find(|n| n.id == node.id)
+                    .unwrap()
+                    .addresses
+                    .into_iter()
+                    .map(|a| a.addr)
+                    .collect();
+                addrs.sort();
+                addrs
+            };
+            assert_eq!(addrs(&actual), addrs(&expected));
+        }
+    }
+
+    #[test]
+    fn convert_cln() {
+        let half = |source: &str, destination: &str, direction: u64| {
+            json!({
+                "source": source,
+                "destination": destination,
+                "short_channel_id": "821x1x0",
+                "direction": direction,
+                "amount_msat": "1000000000msat",
+                "base_fee_millisatoshi": 1000,
+                "fee_per_millionth": 10,
+                "delay": 40,
+                "htlc_minimum_msat": 1,
+                "htlc_maximum_msat": 990000000,
+            })
+        };
+        let listchannels = json!({"channels": [half("02a", "03b", 0), half("03b", "02a", 1)]});
+        let listnodes = json!({"nodes": [{
+            "nodeid": "02a",
+            "addresses": [
+                {"type": "ipv4", "address": "5.9.0.1", "port": 9735},
+                {"type": "ipv6", "address": "2a01:4f8:0:1::7:1", "port": 9736},
+            ],
+        }]});
+        let lnd = cln_to_lnd(&listchannels, &listnodes).unwrap();
+        assert_eq!(lnd["nodes"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            lnd["nodes"][0]["addresses"][1]["addr"],
+            "[2a01:4f8:0:1::7:1]:9736"
+        );
+        assert_eq!(lnd["nodes"][1]["addresses"], json!([]));
+        let edges = lnd["edges"].as_array().unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0]["node1_pub"], "02a");
+        assert_eq!(edges[0]["capacity"], 1000000);
+        assert_eq!(edges[0]["node2_policy"]["max_htlc_msat"], "990000000");
+        let lnr = convert_graph_json(&lnd, &Lnd, &Lnresearch).unwrap();
+        assert_eq!(lnr["adjacency"][1][0]["destination"], "02a");
+        assert!(cln_to_lnd(&listchannels, &json!({})).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use log::{debug, warn};
+use maxminddb::{geoip2, MaxMindDBError};
+use simlib::{graph::Graph, ID};
+use std::{collections::HashMap, net::IpAddr, path::Path, str::FromStr};
+
+/// Location of the GeoLite2-Country database if it was not downloaded with `simulator geoip`. The
+/// database is not shipped with the code
+pub static COUNTRY_DB_PATH: &str = "./src/net/geolite2/GeoLite2-Country/GeoLite2-Country.mmdb";
+
+/// Country of nodes with only an onion address
+pub static TOR_COUNTRY: &str = "tor";
+
+pub struct CountryReader {
+    reader: maxminddb::Reader<Vec<u8>>,
+}
+
+impl CountryReader {
+    pub fn try_new(path: &Path) -> Result<Self, MaxMindDBError> {
+        let reader = maxminddb::Reader::open_readfile(path)?;
+        debug!("Succesfully opened country database.");
+        Ok(Self { reader })
+    }
+
+    /// The ISO code of the country the address is located in
+    pub fn lookup_country(&self, ip: IpAddr) -> Option<String> {
+        let country: Result<geoip2::Country, MaxMindDBError> = self.reader.lookup(ip);
+        match country {
+            Ok(country) => country
+                .country
+                .and_then(|c| c.iso_code)
+                .map(|c| c.to_owned()),
+            Err(err) => {
+                warn!("Country lookup for {} failed: {}", ip, err);
+                None
+            }
+        }
+    }
+
+    /// Maps each node to the country of its first address that can be looked up. Nodes with only an
+    /// onion address are mapped to `TOR_COUNTRY` if `include_tor` is set
+    pub fn node_countries(&self, graph: &Graph, include_tor: bool) -> HashMap<ID, String> {
+        let mut countries = HashMap::new();
+        for node in graph.get_nodes() {
+            let country = node
+                .addresses
+                .iter()
+                .filter(|a| !a.addr.contains("onion"))
+                .filter_map(|a| IpAddr::from_str(&a.addr).ok())
+                .find_map(|ip| self.lookup_country(ip));
+            let onion_only = !node.addresses.is_empty()
+                && node.addresses.iter().all(|a| a.addr.contains("onion"));
+            if let Some(country) = country {
+                countries.insert(node.id, country);
+            } else if include_tor && onion_only {
+                countries.insert(node.id, TOR_COUNTRY.to_owned());
+            }
+        }
+        countries
+    }
+}
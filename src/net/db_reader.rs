@@ -1,20 +1,30 @@
-use super::Asn;
+use super::{Asn, GeoIpEdition};
+use crate::SimulatorError;
 use log::{debug, warn};
 use maxminddb::{geoip2, MaxMindDBError};
 use std::net::IpAddr;
 
-static AS_ISP_DB_PATH: &str = "./src/net/geolite2/GeoLite2-ASN_20240116/GeoLite2-ASN.mmdb";
+/// The database shipped with the code, used unless a newer one was downloaded
+pub(crate) static AS_ISP_DB_PATH: &str =
+    "./src/net/geolite2/GeoLite2-ASN_20240116/GeoLite2-ASN.mmdb";
 
 pub struct DbReader {
     reader: maxminddb::Reader<Vec<u8>>,
 }
 
 impl DbReader {
-    pub fn new() -> Self {
-        let reader =
-            maxminddb::Reader::open_readfile(AS_ISP_DB_PATH).expect("Error opening database");
-        debug!("Succesfully opened AS database.");
-        DbReader { reader }
+    /// Opens the AS database. A database downloaded with `simulator geoip` takes precedence over
+    /// the bundled one
+    pub fn new() -> Result<Self, SimulatorError> {
+        let path = GeoIpEdition::Asn.default_path();
+        let reader = maxminddb::Reader::open_readfile(&path)?;
+        debug!("Succesfully opened AS database {:#?}.", path);
+        Ok(DbReader { reader })
+    }
+
+    /// Seconds since the UNIX epoch at which the database was built
+    pub fn build_epoch(&self) -> u64 {
+        self.reader.metadata.build_epoch
     }
 
     pub fn lookup_asn(&self, ip: IpAddr) -> Option<Asn> {
@@ -29,12 +39,6 @@ impl DbReader {
     }
 }
 
-impl Default for DbReader {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -42,7 +46,7 @@ mod tests {
 
     #[test]
     fn valid_ip_asn_lookup() {
-        let db_reader = DbReader::new();
+        let db_reader = DbReader::new().unwrap();
         let example: IpAddr = FromStr::from_str("93.184.216.34").unwrap();
         let actual = db_reader.lookup_asn(example);
         let expected = Some(15133);
@@ -51,15 +55,22 @@ mod tests {
 
     #[test]
     fn invalid_ip_asn_lookup() {
-        let db_reader = DbReader::new();
+        let db_reader = DbReader::new().unwrap();
         let zero_addr: IpAddr = FromStr::from_str("0.0.0.0").unwrap();
         let actual = db_reader.lookup_asn(zero_addr);
         assert!(actual.is_none());
     }
 
+    #[test]
+    fn database_build_date() {
+        let db_reader = DbReader::new().unwrap();
+        // 2024-01-01T00:00:00Z
+        assert!(db_reader.build_epoch() > 1704067200);
+    }
+
     #[test]
     fn valid_ipv6_lookup() {
-        let db_reader = DbReader::new();
+        let db_reader = DbReader::new().unwrap();
         let google: IpAddr = FromStr::from_str("2a00:1450:4005:80b::200e").unwrap();
         let actual = db_reader.lookup_asn(google);
         let expected = Some(15169);
@@ -0,0 +1,178 @@
+use simlib::{graph::Graph, ID};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    str::FromStr,
+};
+
+/// A step that prunes the graph before it is simulated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFilter {
+    /// Remove nodes without a network address
+    WithAddress,
+    /// Remove channels with a capacity (in msat) below the threshold
+    MinCapacity(usize),
+    /// Keep only the largest connected component, ignoring the direction of channels
+    LargestComponent,
+    /// Keep only the n nodes with the most channels
+    TopDegree(usize),
+}
+
+impl FromStr for GraphFilter {
+    type Err = String;
+
+    /// One of "with-address", "min-capacity=<sat>", "largest-component" or "top-degree=<n>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        let (name, value) = match s.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (s.as_str(), None),
+        };
+        let value = || -> Result<usize, String> {
+            value
+                .and_then(|v| v.parse().ok())
+                .ok_or(format!("Filter {} requires a numeric value", name))
+        };
+        match name {
+            "with-address" => Ok(Self::WithAddress),
+            "min-capacity" => Ok(Self::MinCapacity(simlib::to_millisatoshi(value()?))),
+            "largest-component" => Ok(Self::LargestComponent),
+            "top-degree" => Ok(Self::TopDegree(value()?)),
+            _ => Err(format!("Unknown graph filter {}", s)),
+        }
+    }
+}
+
+impl GraphFilter {
+    /// Prunes the graph and returns the number of removed nodes and channels
+    pub fn apply(&self, graph: &mut Graph) -> (usize, usize) {
+        let (num_nodes, num_channels) = Self::size(graph);
+        match self {
+            Self::WithAddress => {
+                let keep = graph
+                    .get_nodes()
+                    .into_iter()
+                    .filter(|n| !n.addresses.is_empty())
+                    .map(|n| n.id)
+                    .collect();
+                Self::retain_nodes(graph, &keep);
+            }
+            Self::MinCapacity(min_capacity) => {
+                for edges in graph.edges.values_mut() {
+                    edges.retain(|e| e.capacity >= *min_capacity);
+                }
+            }
+            Self::LargestComponent => {
+                let keep = Self::largest_component(graph);
+                Self::retain_nodes(graph, &keep);
+            }
+            Self::TopDegree(n) => {
+                let mut nodes: Vec<(usize, ID)> = graph
+                    .get_nodes()
+                    .into_iter()
+                    .map(|node| {
+                        let degree = graph.get_edges_for_node(&node.id).unwrap_or_default().len();
+                        (degree, node.id)
+                    })
+                    .collect();
+                nodes.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+                let keep = nodes.into_iter().take(*n).map(|(_, id)| id).collect();
+                Self::retain_nodes(graph, &keep);
+            }
+        }
+        let (remaining_nodes, remaining_channels) = Self::size(graph);
+        (
+            num_nodes - remaining_nodes,
+            num_channels - remaining_channels,
+        )
+    }
+
+    /// Removes all other nodes and their channels
+    fn retain_nodes(graph: &mut Graph, keep: &HashSet<ID>) {
+        graph.nodes.retain(|n| keep.contains(&n.id));
+        graph.edges.retain(|id, _| keep.contains(id));
+        for edges in graph.edges.values_mut() {
+            edges.retain(|e| keep.contains(&e.destination));
+        }
+    }
+
+    fn largest_component(graph: &Graph) -> HashSet<ID> {
+        let mut neighbours: HashMap<&ID, Vec<&ID>> = HashMap::new();
+        for (id, edges) in graph.edges.iter() {
+            for edge in edges.iter() {
+                neighbours.entry(id).or_default().push(&edge.destination);
+                neighbours.entry(&edge.destination).or_default().push(id);
+            }
+        }
+        let ids: Vec<ID> = graph.get_nodes().into_iter().map(|n| n.id).collect();
+        let mut seen: HashSet<&ID> = HashSet::new();
+        let mut largest = HashSet::new();
+        for id in ids.iter() {
+            if seen.contains(id) {
+                continue;
+            }
+            let mut component = HashSet::from([id.clone()]);
+            seen.insert(id);
+            let mut queue = VecDeque::from([id]);
+            while let Some(node) = queue.pop_front() {
+                for next in neighbours.get(node).into_iter().flatten() {
+                    if seen.insert(next) {
+                        component.insert((*next).clone());
+                        queue.push_back(next);
+                    }
+                }
+            }
+            if component.len() > largest.len() {
+                largest = component;
+            }
+        }
+        largest
+    }
+
+    fn size(graph: &Graph) -> (usize, usize) {
+        (
+            graph.node_count(),
+            graph.edges.values().map(|edges| edges.len()).sum(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+
+    #[test]
+    fn filter_graph() {
+        assert_eq!(
+            GraphFilter::from_str("top-degree=2"),
+            Ok(GraphFilter::TopDegree(2))
+        );
+        assert_eq!(
+            GraphFilter::from_str("min-capacity=1"),
+            Ok(GraphFilter::MinCapacity(1000))
+        );
+        assert!(GraphFilter::from_str("top-degree").is_err());
+        assert!(GraphFilter::from_str("unknown").is_err());
+        let mut graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let num_nodes = graph.node_count();
+        assert_eq!(GraphFilter::WithAddress.apply(&mut graph).0, 0);
+        assert_eq!(GraphFilter::LargestComponent.apply(&mut graph), (0, 0));
+        let (removed_nodes, removed_channels) = GraphFilter::TopDegree(2).apply(&mut graph);
+        assert_eq!(removed_nodes, num_nodes - 2);
+        assert!(removed_channels > 0);
+        assert_eq!(graph.node_count(), 2);
+        assert!(graph
+            .edges
+            .values()
+            .flatten()
+            .all(|e| graph.get_nodes().iter().any(|n| n.id == e.destination)));
+    }
+}
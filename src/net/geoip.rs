@@ -0,0 +1,203 @@
+#[cfg(feature = "geoip-download")]
+use log::info;
+#[cfg(feature = "geoip-download")]
+use std::path::Path;
+use std::{env, path::PathBuf, str::FromStr};
+
+/// Overrides the directory the downloaded databases are stored in and discovered from
+pub static DATA_DIR_VAR: &str = "SIMULATOR_DATA_DIR";
+
+/// The GeoLite2 databases the simulator reads
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GeoIpEdition {
+    Asn,
+    Country,
+}
+
+impl FromStr for GeoIpEdition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "asn" => Ok(Self::Asn),
+            "country" => Ok(Self::Country),
+            _ => Err(format!("Unknown GeoLite2 edition {}", s)),
+        }
+    }
+}
+
+impl GeoIpEdition {
+    /// Edition ID in MaxMind's download API
+    pub fn edition_id(&self) -> &'static str {
+        match self {
+            Self::Asn => "GeoLite2-ASN",
+            Self::Country => "GeoLite2-Country",
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{}.mmdb", self.edition_id())
+    }
+
+    /// The database shipped with or expected next to the code
+    fn bundled_path(&self) -> &'static str {
+        match self {
+            Self::Asn => super::AS_ISP_DB_PATH,
+            Self::Country => super::COUNTRY_DB_PATH,
+        }
+    }
+
+    /// The downloaded database if there is one, otherwise the bundled one
+    pub fn default_path(&self) -> PathBuf {
+        let downloaded = geoip_data_dir().join(self.file_name());
+        if downloaded.exists() {
+            downloaded
+        } else {
+            PathBuf::from(self.bundled_path())
+        }
+    }
+}
+
+/// `$SIMULATOR_DATA_DIR`, `$XDG_DATA_HOME/lightning-censorship-simulator/geoip` or
+/// `~/.local/share/lightning-censorship-simulator/geoip` in that order
+pub fn geoip_data_dir() -> PathBuf {
+    data_dir_from(
+        env::var_os(DATA_DIR_VAR).map(PathBuf::from),
+        env::var_os("XDG_DATA_HOME").map(PathBuf::from),
+        env::var_os("HOME").map(PathBuf::from),
+    )
+}
+
+fn data_dir_from(
+    data_dir: Option<PathBuf>,
+    xdg_data_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+) -> PathBuf {
+    if let Some(data_dir) = data_dir {
+        return data_dir;
+    }
+    let data_home = xdg_data_home
+        .filter(|d| d.is_absolute())
+        .or_else(|| home.map(|h| h.join(".local").join("share")))
+        .unwrap_or_else(|| PathBuf::from("."));
+    data_home
+        .join("lightning-censorship-simulator")
+        .join("geoip")
+}
+
+/// Downloads the latest database of the edition and replaces the one in `dir`. The database is
+/// only moved into place once it has been opened successfully
+#[cfg(feature = "geoip-download")]
+pub fn download_database(
+    license_key: &str,
+    edition: GeoIpEdition,
+    dir: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let url = format!(
+        "https://download.maxmind.com/app/geoip_download?edition_id={}&license_key={}&suffix=tar.gz",
+        edition.edition_id(),
+        license_key
+    );
+    info!("Downloading {}.", edition.edition_id());
+    // the errors of ureq contain the URL and thus the license key
+    let response = ureq::get(&url).call().map_err(|e| match e {
+        ureq::Error::Status(status, _) => format!("Download failed with status {}.", status),
+        ureq::Error::Transport(t) => format!("Download failed: {}.", t.kind()),
+    })?;
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(edition.file_name());
+    let tmp_path = path.with_extension("mmdb.part");
+    extract_mmdb(response.into_reader(), &tmp_path)?;
+    let reader = match maxminddb::Reader::open_readfile(&tmp_path) {
+        Ok(reader) => reader,
+        Err(e) => {
+            std::fs::remove_file(&tmp_path)?;
+            return Err(e.into());
+        }
+    };
+    info!(
+        "{} built at {} stored in {:#?}.",
+        edition.edition_id(),
+        reader.metadata.build_epoch,
+        path
+    );
+    std::fs::rename(&tmp_path, &path)?;
+    Ok(path)
+}
+
+/// Writes the .mmdb file of a gzipped tar archive as it is distributed by MaxMind to `path`
+#[cfg(feature = "geoip-download")]
+fn extract_mmdb(
+    archive: impl std::io::Read,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.extension().is_some_and(|e| e == "mmdb") {
+            entry.unpack(path)?;
+            return Ok(());
+        }
+    }
+    Err("Archive contains no database.".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_dir() {
+        let custom = PathBuf::from("/data/geoip");
+        assert_eq!(
+            data_dir_from(Some(custom.clone()), None, Some(PathBuf::from("/home/u"))),
+            custom
+        );
+        assert_eq!(
+            data_dir_from(
+                None,
+                Some(PathBuf::from("/xdg")),
+                Some(PathBuf::from("/home/u"))
+            ),
+            PathBuf::from("/xdg/lightning-censorship-simulator/geoip")
+        );
+        // relative XDG directories are invalid and ignored
+        assert_eq!(
+            data_dir_from(
+                None,
+                Some(PathBuf::from("xdg")),
+                Some(PathBuf::from("/home/u"))
+            ),
+            PathBuf::from("/home/u/.local/share/lightning-censorship-simulator/geoip")
+        );
+        assert_eq!(GeoIpEdition::Asn.file_name(), "GeoLite2-ASN.mmdb");
+        assert_eq!(GeoIpEdition::from_str("Country"), Ok(GeoIpEdition::Country));
+        assert!(GeoIpEdition::from_str("city").is_err());
+    }
+
+    #[cfg(feature = "geoip-download")]
+    #[test]
+    fn extract_database() {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            vec![],
+            flate2::Compression::default(),
+        ));
+        for (name, contents) in [
+            ("GeoLite2-ASN_20240116/LICENSE.txt", "license"),
+            ("GeoLite2-ASN_20240116/GeoLite2-ASN.mmdb", "database"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("GeoLite2-ASN.mmdb");
+        extract_mmdb(archive.as_slice(), &path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "database");
+        assert!(extract_mmdb(&b""[..], &path).is_err());
+    }
+}
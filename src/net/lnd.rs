@@ -0,0 +1,122 @@
+use log::info;
+use network_parser::GraphSource;
+use serde_json::{json, Value};
+use std::{error::Error, path::Path};
+use tonic_lnd::lnrpc::{ChannelEdge, ChannelGraph, ChannelGraphRequest, RoutingPolicy};
+
+/// Fetches the public channel graph of a running LND node over gRPC
+pub fn fetch_lnd_graph(
+    address: &str,
+    macaroon: &Path,
+    tls_cert: &Path,
+) -> Result<network_parser::Graph, Box<dyn Error>> {
+    let address = if address.contains("://") {
+        address.to_owned()
+    } else {
+        format!("https://{}", address)
+    };
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let graph = runtime.block_on(async {
+        let mut client =
+            tonic_lnd::connect(address, tls_cert.to_path_buf(), macaroon.to_path_buf()).await?;
+        let graph = client
+            .lightning()
+            .describe_graph(ChannelGraphRequest {
+                include_unannounced: false,
+                ..Default::default()
+            })
+            .await?
+            .into_inner();
+        Ok::<_, Box<dyn Error>>(graph)
+    })?;
+    info!(
+        "Fetched {} nodes and {} channels from LND.",
+        graph.nodes.len(),
+        graph.edges.len()
+    );
+    Ok(network_parser::Graph::from_json_str(
+        &describe_graph_json(&graph).to_string(),
+        GraphSource::Lnd,
+    )?)
+}
+
+/// The graph as it is printed by `lncli describegraph`
+fn describe_graph_json(graph: &ChannelGraph) -> Value {
+    let nodes: Vec<Value> = graph
+        .nodes
+        .iter()
+        .map(|node| {
+            let addresses: Vec<Value> = node
+                .addresses
+                .iter()
+                .map(|a| json!({"network": a.network, "addr": a.addr}))
+                .collect();
+            json!({"pub_key": node.pub_key, "addresses": addresses})
+        })
+        .collect();
+    let edges: Vec<Value> = graph.edges.iter().map(edge_json).collect();
+    json!({"nodes": nodes, "edges": edges})
+}
+
+fn edge_json(edge: &ChannelEdge) -> Value {
+    let policy = |policy: &Option<RoutingPolicy>| match policy {
+        Some(p) => json!({
+            "time_lock_delta": p.time_lock_delta,
+            "min_htlc": p.min_htlc.to_string(),
+            "fee_base_msat": p.fee_base_msat.to_string(),
+            "fee_rate_milli_msat": p.fee_rate_milli_msat.to_string(),
+            "disabled": p.disabled,
+            "max_htlc_msat": p.max_htlc_msat.to_string(),
+        }),
+        None => Value::Null,
+    };
+    json!({
+        "channel_id": edge.channel_id.to_string(),
+        "chan_point": edge.chan_point,
+        "capacity": edge.capacity.to_string(),
+        "node1_pub": edge.node1_pub,
+        "node2_pub": edge.node2_pub,
+        "node1_policy": policy(&edge.node1_policy),
+        "node2_policy": policy(&edge.node2_policy),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic_lnd::lnrpc::{LightningNode, NodeAddress};
+
+    #[test]
+    fn lncli_format() {
+        let graph = ChannelGraph {
+            nodes: vec![LightningNode {
+                pub_key: "02a".to_owned(),
+                addresses: vec![NodeAddress {
+                    network: "tcp".to_owned(),
+                    addr: "5.9.0.1:9735".to_owned(),
+                }],
+                ..Default::default()
+            }],
+            edges: vec![ChannelEdge {
+                channel_id: 902675318722461697,
+                capacity: 10000,
+                node1_pub: "02a".to_owned(),
+                node2_pub: "03b".to_owned(),
+                node1_policy: Some(RoutingPolicy {
+                    time_lock_delta: 40,
+                    fee_base_msat: 1000,
+                    max_htlc_msat: 9900000,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+        };
+        let actual = describe_graph_json(&graph);
+        assert_eq!(actual["nodes"][0]["addresses"][0]["addr"], "5.9.0.1:9735");
+        assert_eq!(actual["edges"][0]["channel_id"], "902675318722461697");
+        assert_eq!(actual["edges"][0]["node1_policy"]["fee_base_msat"], "1000");
+        assert_eq!(actual["edges"][0]["node2_policy"], Value::Null);
+    }
+}
@@ -0,0 +1,185 @@
+use super::{Asn, DbReader};
+use log::{debug, warn};
+use maxminddb::MaxMindDBError;
+use serde::Deserialize;
+use std::{
+    error::Error,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+/// A database mapping IP addresses to the AS announcing them
+pub trait AsnLookup: Send + Sync {
+    fn lookup_asn(&self, ip: IpAddr) -> Option<Asn>;
+}
+
+impl AsnLookup for DbReader {
+    fn lookup_asn(&self, ip: IpAddr) -> Option<Asn> {
+        DbReader::lookup_asn(self, ip)
+    }
+}
+
+/// The AS database the addresses are resolved with
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum AsnDatabase {
+    /// MaxMind's GeoLite2 ASN database, downloaded or bundled
+    #[default]
+    GeoLite2,
+    /// An IP2Location LITE ASN CSV file (IPv4 or IPv6)
+    Ip2Location(PathBuf),
+    /// An ipinfo.io ASN MMDB file
+    Ipinfo(PathBuf),
+}
+
+impl FromStr for AsnDatabase {
+    type Err = String;
+
+    /// "geolite2", "ip2location=<FILE>" or "ipinfo=<FILE>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some(("ip2location", path)) => Ok(Self::Ip2Location(PathBuf::from(path))),
+            Some(("ipinfo", path)) => Ok(Self::Ipinfo(PathBuf::from(path))),
+            None if s.eq_ignore_ascii_case("geolite2") => Ok(Self::GeoLite2),
+            _ => Err(format!("Unknown AS database {}", s)),
+        }
+    }
+}
+
+impl AsnDatabase {
+    pub fn open(&self) -> Result<Arc<dyn AsnLookup>, Box<dyn Error>> {
+        Ok(match self {
+            Self::GeoLite2 => Arc::new(DbReader::new()?),
+            Self::Ip2Location(path) => Arc::new(Ip2LocationReader::from_csv_file(path)?),
+            Self::Ipinfo(path) => Arc::new(IpinfoReader::open(path)?),
+        })
+    }
+}
+
+/// The address ranges of an IP2Location LITE ASN database, sorted by their first address
+pub struct Ip2LocationReader {
+    ranges: Vec<(u128, u128, Asn)>,
+}
+
+impl Ip2LocationReader {
+    /// Reads the header-less CSV file with the columns ip_from, ip_to, cidr, asn and as. Ranges
+    /// without an ASN ("-") are skipped
+    pub fn from_csv_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_path(path)?;
+        let mut ranges = vec![];
+        for record in reader.records() {
+            let record = record?;
+            let field = |i: usize| record.get(i).ok_or("Missing column in IP2Location file");
+            let Ok(asn) = field(3)?.parse::<Asn>() else {
+                continue;
+            };
+            ranges.push((field(0)?.parse()?, field(1)?.parse()?, asn));
+        }
+        ranges.sort_unstable();
+        debug!(
+            "Read {} ranges from IP2Location database {:#?}.",
+            ranges.len(),
+            path
+        );
+        Ok(Self { ranges })
+    }
+
+    fn lookup_number(&self, ip: u128) -> Option<Asn> {
+        let idx = self.ranges.partition_point(|(from, _, _)| *from <= ip);
+        let (_, to, asn) = self.ranges.get(idx.checked_sub(1)?)?;
+        (ip <= *to).then_some(*asn)
+    }
+}
+
+impl AsnLookup for Ip2LocationReader {
+    /// IPv4 addresses are looked up as numbers and, as in the IPv6 files, as IPv4-mapped addresses
+    fn lookup_asn(&self, ip: IpAddr) -> Option<Asn> {
+        match ip {
+            IpAddr::V4(ip) => {
+                let ip = u32::from(ip) as u128;
+                self.lookup_number(ip)
+                    .or_else(|| self.lookup_number(0xffff_0000_0000 | ip))
+            }
+            IpAddr::V6(ip) => self.lookup_number(u128::from(ip)),
+        }
+    }
+}
+
+/// An ipinfo.io database, whose ASNs are strings such as "AS15169"
+pub struct IpinfoReader {
+    reader: maxminddb::Reader<Vec<u8>>,
+}
+
+#[derive(Deserialize)]
+struct IpinfoAsn<'a> {
+    asn: Option<&'a str>,
+}
+
+impl IpinfoReader {
+    pub fn open(path: &Path) -> Result<Self, MaxMindDBError> {
+        let reader = maxminddb::Reader::open_readfile(path)?;
+        debug!("Succesfully opened ipinfo database {:#?}.", path);
+        Ok(Self { reader })
+    }
+}
+
+impl AsnLookup for IpinfoReader {
+    fn lookup_asn(&self, ip: IpAddr) -> Option<Asn> {
+        match self.reader.lookup::<IpinfoAsn>(ip) {
+            Ok(record) => record.asn.and_then(parse_ipinfo_asn),
+            Err(err) => {
+                warn!("ASN lookup for {} failed: {}", ip, err);
+                None
+            }
+        }
+    }
+}
+
+fn parse_ipinfo_asn(asn: &str) -> Option<Asn> {
+    asn.trim_start_matches("AS").parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn ip2location_lookup() {
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        fs::write(
+            file.path(),
+            "\"16777216\",\"16777471\",\"1.0.0.0/24\",\"13335\",\"CloudFlare Inc\"\n\
+             \"16777472\",\"16778239\",\"1.0.1.0/23\",\"-\",\"-\"\n\
+             \"281470816487424\",\"281470816487679\",\"8.8.8.0/24\",\"15169\",\"Google LLC\"\n\
+             \"58569013490477838532618886340862279680\",\"58569013569706001046883223934406230015\",\"2c0f:fb50::/32\",\"36040\",\"Google\"\n",
+        )
+        .unwrap();
+        let reader = Ip2LocationReader::from_csv_file(file.path()).unwrap();
+        let lookup = |ip: &str| reader.lookup_asn(IpAddr::from_str(ip).unwrap());
+        assert_eq!(lookup("1.0.0.1"), Some(13335));
+        assert_eq!(lookup("1.0.1.1"), None);
+        // IPv4-mapped range of the IPv6 file
+        assert_eq!(lookup("8.8.8.8"), Some(15169));
+        assert_eq!(lookup("2c0f:fb50::1"), Some(36040));
+        assert_eq!(lookup("2c0f:fb51::1"), None);
+        fs::write(file.path(), "\"1\",\"2\",\"-\"\n").unwrap();
+        assert!(Ip2LocationReader::from_csv_file(file.path()).is_err());
+    }
+
+    #[test]
+    fn select_database() {
+        assert_eq!(AsnDatabase::from_str("GeoLite2"), Ok(AsnDatabase::GeoLite2));
+        assert_eq!(
+            AsnDatabase::from_str("ipinfo=asn.mmdb"),
+            Ok(AsnDatabase::Ipinfo(PathBuf::from("asn.mmdb")))
+        );
+        assert!(AsnDatabase::from_str("ip2location").is_err());
+        assert_eq!(parse_ipinfo_asn("AS15169"), Some(15169));
+        assert_eq!(parse_ipinfo_asn(""), None);
+    }
+}
@@ -1,7 +1,57 @@
+mod aliases;
+mod anonymize;
 mod asn;
+mod asorg;
+mod attribution;
+mod bgp;
+mod capacity;
+mod centrality;
+mod concentration;
+mod convert;
+#[cfg(feature = "mmdb")]
+mod country;
+#[cfg(feature = "mmdb")]
 mod db_reader;
+mod filter;
+mod geoip;
+#[cfg(feature = "lnd-grpc")]
+mod lnd;
+#[cfg(feature = "mmdb")]
+mod lookup;
+mod operator;
+mod overrides;
+mod provider;
+mod relabel;
+#[cfg(feature = "mmdb")]
+mod resolver;
+mod tor;
 
 pub(crate) type Asn = u32;
 
-pub use asn::AsIpMap;
+pub use aliases::*;
+pub use anonymize::*;
+pub use asn::{AsIpMap, AsNodeRatios};
+pub use asorg::*;
+pub use attribution::*;
+pub use bgp::*;
+pub use capacity::*;
+pub use centrality::*;
+pub use concentration::*;
+pub use convert::*;
+#[cfg(feature = "mmdb")]
+pub use country::*;
+#[cfg(feature = "mmdb")]
 pub use db_reader::*;
+pub use filter::*;
+pub use geoip::*;
+#[cfg(feature = "lnd-grpc")]
+pub use lnd::*;
+#[cfg(feature = "mmdb")]
+pub use lookup::*;
+pub use operator::*;
+pub use overrides::*;
+pub use provider::*;
+pub use relabel::*;
+#[cfg(feature = "mmdb")]
+pub use resolver::*;
+pub use tor::*;
@@ -0,0 +1,78 @@
+use super::{AsIpMap, Asn};
+use simlib::{graph::Graph, ID};
+use std::{collections::HashSet, error::Error, fs, path::Path};
+
+/// Pseudo-ASN of a set of nodes run by a single operator. Taken from the reserved range so it
+/// cannot collide with a real AS
+pub static OPERATOR_ASN: Asn = 4294967295;
+
+/// Reads one node ID or public key per line. Empty lines and lines starting with '#' are ignored
+pub fn read_node_list(path: &Path) -> Result<Vec<ID>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+impl AsIpMap {
+    /// Moves the given nodes from their ASs to OPERATOR_ASN so that the drop strategies treat them
+    /// as a single adversary regardless of where they are hosted. Returns an error if a node is not
+    /// part of the graph
+    pub fn with_operator_nodes(&self, nodes: &[ID], graph: &Graph) -> Result<Self, String> {
+        let graph_nodes: HashSet<ID> = graph.get_nodes().into_iter().map(|n| n.id).collect();
+        if let Some(unknown) = nodes.iter().find(|n| !graph_nodes.contains(*n)) {
+            return Err(format!("Node {} is not part of the graph.", unknown));
+        }
+        if nodes.is_empty() {
+            return Err(String::from("No adversarial nodes given."));
+        }
+        let mut as_to_nodes = self.as_to_nodes().clone();
+        for as_nodes in as_to_nodes.values_mut() {
+            as_nodes.retain(|n| !nodes.contains(n));
+        }
+        as_to_nodes.retain(|_, as_nodes| !as_nodes.is_empty());
+        let mut seen = HashSet::new();
+        let operator_nodes = nodes.iter().filter(|n| seen.insert(*n)).cloned().collect();
+        as_to_nodes.insert(OPERATOR_ASN, operator_nodes);
+        Ok(Self::from(as_to_nodes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn operator_nodes() {
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        fs::write(file.path(), "# LSP\nbob\n\n dina \n").unwrap();
+        let nodes = read_node_list(file.path()).unwrap();
+        assert_eq!(nodes, vec![String::from("bob"), String::from("dina")]);
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let actual = as_ip_map.with_operator_nodes(&nodes, &graph).unwrap();
+        assert_eq!(actual.as_to_nodes()[&OPERATOR_ASN], nodes);
+        assert_eq!(actual.as_to_nodes()[&797], vec![String::from("chan")]);
+        assert_eq!(actual.as_to_nodes()[&24940], vec![String::from("alice")]);
+        assert_eq!(
+            actual.selected_asns(&[OPERATOR_ASN], &graph).unwrap().len(),
+            1
+        );
+        assert!(as_ip_map
+            .with_operator_nodes(&[String::from("erin")], &graph)
+            .is_err());
+    }
+}
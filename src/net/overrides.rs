@@ -0,0 +1,81 @@
+use super::Asn;
+use serde::Deserialize;
+use simlib::ID;
+use std::{collections::HashMap, error::Error, net::IpAddr, path::Path, str::FromStr};
+
+/// ASNs that take precedence over the database lookup, e.g. for nodes known to run behind a VPN
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AsnOverrides {
+    nodes: HashMap<ID, Asn>,
+    ips: HashMap<IpAddr, Asn>,
+}
+
+#[derive(Deserialize)]
+struct OverrideRecord {
+    node_or_ip: String,
+    asn: Asn,
+}
+
+impl AsnOverrides {
+    /// Reads a CSV file with the columns `node_or_ip` and `asn`. Keys that parse as an IP address
+    /// override the address, all others the node with that ID
+    pub fn from_csv_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut overrides = Self::default();
+        for record in csv::Reader::from_path(path)?.deserialize() {
+            let record: OverrideRecord = record?;
+            overrides.insert(record.node_or_ip.trim(), record.asn);
+        }
+        Ok(overrides)
+    }
+
+    pub fn insert(&mut self, node_or_ip: &str, asn: Asn) {
+        if let Ok(ip) = IpAddr::from_str(node_or_ip) {
+            self.ips.insert(ip, asn);
+        } else {
+            self.nodes.insert(node_or_ip.to_owned(), asn);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() + self.ips.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn node_asn(&self, node: &ID) -> Option<Asn> {
+        self.nodes.get(node).copied()
+    }
+
+    pub(crate) fn ip_asn(&self, ip: &IpAddr) -> Option<Asn> {
+        self.ips.get(ip).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn read_overrides() {
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        fs::write(
+            file.path(),
+            "node_or_ip,asn\nbob,13335\n5.9.0.1,16509\n2a01:4f8:0:1::7:1,13335\n",
+        )
+        .unwrap();
+        let overrides = AsnOverrides::from_csv_file(file.path()).unwrap();
+        assert_eq!(overrides.len(), 3);
+        assert_eq!(overrides.node_asn(&String::from("bob")), Some(13335));
+        assert_eq!(overrides.node_asn(&String::from("5.9.0.1")), None);
+        assert_eq!(
+            overrides.ip_asn(&IpAddr::from_str("5.9.0.1").unwrap()),
+            Some(16509)
+        );
+        fs::write(file.path(), "node_or_ip,asn\nbob,cloudflare\n").unwrap();
+        assert!(AsnOverrides::from_csv_file(file.path()).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use super::{AsIpMap, Asn};
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+#[cfg(test)]
+use std::println as info;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    io::Read,
+    path::Path,
+    str::FromStr,
+};
+
+/// ASNs of large hosting providers, used unless a provider file is given
+const BUNDLED_PROVIDERS: &str = include_str!("providers.csv");
+
+/// What a single adversary controls
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum AdversaryGranularity {
+    /// Each AS is an adversary
+    #[default]
+    As,
+    /// All ASs of a hosting provider form one adversary
+    Provider,
+}
+
+impl FromStr for AdversaryGranularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "as" => Ok(Self::As),
+            "provider" => Ok(Self::Provider),
+            _ => Err(format!("Unknown adversary granularity {}", s)),
+        }
+    }
+}
+
+/// A row of a provider file
+#[derive(Deserialize)]
+struct ProviderRecord {
+    asn: Asn,
+    provider: String,
+}
+
+/// The provider operating each ASN
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ProviderMap {
+    providers: HashMap<Asn, String>,
+}
+
+/// The ASs of a provider that are simulated as one adversary
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderGroup {
+    pub provider: String,
+    /// ASs of the provider with nodes in the graph, the lowest of which identifies the adversary
+    pub asns: Vec<Asn>,
+}
+
+/// The groups of the providers by the ASN the adversary is identified with
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ProviderGroups {
+    groups: HashMap<Asn, ProviderGroup>,
+}
+
+impl ProviderMap {
+    /// Reads a CSV file with the columns `asn` and `provider`
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let providers = Self::from_reader(std::fs::File::open(path)?)?;
+        info!(
+            "Read the providers of {} ASNs from {:#?}.",
+            providers.providers.len(),
+            path
+        );
+        Ok(providers)
+    }
+
+    /// The providers shipped with the simulator
+    pub fn bundled() -> Self {
+        Self::from_reader(BUNDLED_PROVIDERS.as_bytes()).expect("Valid bundled provider file")
+    }
+
+    pub fn from_reader(reader: impl Read) -> Result<Self, Box<dyn Error>> {
+        let mut providers = HashMap::new();
+        for record in csv::Reader::from_reader(reader).deserialize() {
+            let record: ProviderRecord = record?;
+            providers.insert(record.asn, record.provider);
+        }
+        Ok(Self { providers })
+    }
+
+    pub fn provider_of(&self, asn: Asn) -> Option<&String> {
+        self.providers.get(&asn)
+    }
+}
+
+impl ProviderGroups {
+    pub fn get(&self, asn: Asn) -> Option<&ProviderGroup> {
+        self.groups.get(&asn)
+    }
+
+    /// The ASN of the adversary the AS belongs to, the AS itself if it has no known provider
+    pub fn adversary_asn(&self, asn: Asn) -> Asn {
+        self.groups
+            .iter()
+            .find(|(_, group)| group.asns.contains(&asn))
+            .map(|(adversary, _)| *adversary)
+            .unwrap_or(asn)
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+impl AsIpMap {
+    /// Merges the nodes of each provider's ASs into the provider's lowest ASN. ASs without a known
+    /// provider are left as they are
+    pub fn group_by_provider(&self, providers: &ProviderMap) -> (Self, ProviderGroups) {
+        let mut by_provider: HashMap<&String, Vec<Asn>> = HashMap::new();
+        for asn in self.as_to_nodes().keys() {
+            if let Some(provider) = providers.provider_of(*asn) {
+                by_provider.entry(provider).or_default().push(*asn);
+            }
+        }
+        let mut as_to_nodes = self.as_to_nodes().clone();
+        let mut groups = HashMap::new();
+        for (provider, mut asns) in by_provider {
+            asns.sort();
+            let mut seen = HashSet::new();
+            let nodes: Vec<ID> = asns
+                .iter()
+                .filter_map(|asn| as_to_nodes.remove(asn))
+                .flatten()
+                .filter(|node| seen.insert(node.clone()))
+                .collect();
+            as_to_nodes.insert(asns[0], nodes);
+            groups.insert(
+                asns[0],
+                ProviderGroup {
+                    provider: provider.clone(),
+                    asns,
+                },
+            );
+        }
+        info!(
+            "Grouped the ASs of {} providers into one adversary each.",
+            groups.len()
+        );
+        (Self::from(as_to_nodes), ProviderGroups { groups })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+
+    #[test]
+    fn group_by_provider() {
+        let bundled = ProviderMap::bundled();
+        assert_eq!(bundled.provider_of(16509), Some(&String::from("Amazon")));
+        assert_eq!(bundled.provider_of(797), None);
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let providers =
+            ProviderMap::from_reader("asn,provider\n24940,Host\n797,Host\n".as_bytes()).unwrap();
+        let (grouped, groups) = as_ip_map.group_by_provider(&providers);
+        assert_eq!(grouped.as_to_nodes().len(), 1);
+        assert_eq!(grouped.as_to_nodes()[&797].len(), 4);
+        assert_eq!(grouped.asn_of(&String::from("alice")), Some(797));
+        let expected = ProviderGroup {
+            provider: String::from("Host"),
+            asns: vec![797, 24940],
+        };
+        assert_eq!(groups.get(797), Some(&expected));
+        assert_eq!(groups.adversary_asn(24940), 797);
+        assert_eq!(groups.adversary_asn(3356), 3356);
+        // only Hetzner's ASN is in the graph
+        let (grouped, groups) = as_ip_map.group_by_provider(&bundled);
+        assert_eq!(grouped, as_ip_map);
+        assert_eq!(groups.get(24940).unwrap().asns, vec![24940]);
+    }
+}
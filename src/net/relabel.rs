@@ -0,0 +1,207 @@
+use super::{AsIpMap, Asn};
+use crate::TOR_ASN;
+use rand::{rngs::StdRng, seq::SliceRandom};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use simlib::{graph::Graph, ID};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    str::FromStr,
+};
+
+static DEFAULT_PORT: u16 = 9735;
+
+/// Moves every node hosted in `from_asn` to another AS
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RelabelRule {
+    pub from_asn: Asn,
+    pub to: RelabelTarget,
+}
+
+/// Where the nodes of a RelabelRule are moved to
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RelabelTarget {
+    /// A single AS
+    Asn(Asn),
+    /// ASs sampled uniformly from those hosting at most `max_nodes` nodes, e.g. residential ISPs
+    #[serde(rename_all = "camelCase")]
+    Tail { max_nodes: usize },
+}
+
+impl RelabelRule {
+    /// Reads a JSON array of rules
+    pub fn from_json_file(path: &Path) -> Result<Vec<Self>, Box<dyn Error>> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+}
+
+impl AsIpMap {
+    /// Assigns each node affected by a rule the address of a node in the target AS so that it is
+    /// mapped to that AS. Rules are applied in order to the original mapping
+    pub fn relabel_addresses(
+        &self,
+        graph: &Graph,
+        rules: &[RelabelRule],
+        rng: &mut StdRng,
+    ) -> Result<HashMap<ID, IpAddr>, String> {
+        let addresses: HashMap<ID, IpAddr> = graph
+            .get_nodes()
+            .into_iter()
+            .filter_map(|n| {
+                n.addresses
+                    .iter()
+                    .find_map(|a| IpAddr::from_str(&a.addr).ok())
+                    .map(|ip| (n.id, ip))
+            })
+            .collect();
+        // sorted so that sampling is reproducible
+        let mut asns: Vec<&Asn> = self.as_to_nodes().keys().collect();
+        asns.sort();
+        let mut relabelled = HashMap::new();
+        for rule in rules.iter() {
+            let candidates: Vec<Asn> = match rule.to {
+                RelabelTarget::Asn(asn) => vec![asn],
+                RelabelTarget::Tail { max_nodes } => asns
+                    .iter()
+                    .filter(|asn| ***asn != TOR_ASN && ***asn != rule.from_asn)
+                    .filter(|asn| self.as_to_nodes()[asn].len() <= max_nodes)
+                    .map(|asn| **asn)
+                    .collect(),
+            };
+            let candidates: Vec<(Asn, Vec<IpAddr>)> = candidates
+                .into_iter()
+                .map(|asn| {
+                    let ips: Vec<IpAddr> = self
+                        .as_to_nodes()
+                        .get(&asn)
+                        .map(|nodes| {
+                            nodes
+                                .iter()
+                                .filter_map(|n| addresses.get(n).copied())
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    (asn, ips)
+                })
+                .filter(|(_, ips)| !ips.is_empty())
+                .collect();
+            if candidates.is_empty() {
+                return Err(format!(
+                    "No AS to move the nodes of AS {} to.",
+                    rule.from_asn
+                ));
+            }
+            for node in self.as_to_nodes().get(&rule.from_asn).into_iter().flatten() {
+                if let Some((_, ips)) = candidates.choose(rng) {
+                    if let Some(ip) = ips.choose(rng) {
+                        relabelled.insert(node.clone(), *ip);
+                    }
+                }
+            }
+        }
+        Ok(relabelled)
+    }
+}
+
+/// Replaces the addresses of the nodes in a graph file with the given ones. All other fields are
+/// left as they are
+pub fn relabel_graph_json(
+    graph: &mut Value,
+    addresses: &HashMap<ID, IpAddr>,
+    graph_source: &network_parser::GraphSource,
+) {
+    let id_key = match graph_source {
+        network_parser::GraphSource::Lnd => "pub_key",
+        network_parser::GraphSource::Lnresearch => "id",
+    };
+    if let Some(nodes) = graph.get_mut("nodes").and_then(|n| n.as_array_mut()) {
+        for node in nodes.iter_mut() {
+            let ip = node
+                .get(id_key)
+                .and_then(|id| id.as_str())
+                .and_then(|id| addresses.get(id));
+            if let Some(ip) = ip {
+                let addr = SocketAddr::new(*ip, DEFAULT_PORT);
+                node["addresses"] = match graph_source {
+                    network_parser::GraphSource::Lnd => {
+                        json!([{"network": "tcp", "addr": addr.to_string()}])
+                    }
+                    network_parser::GraphSource::Lnresearch => {
+                        let scheme = if ip.is_ipv4() { "ipv4" } else { "ipv6" };
+                        json!(format!("{}://{}", scheme, addr))
+                    }
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn relabel_nodes() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let rules = vec![RelabelRule {
+            from_asn: 797,
+            to: RelabelTarget::Asn(24940),
+        }];
+        let mut rng = StdRng::seed_from_u64(0);
+        let addresses = as_ip_map
+            .relabel_addresses(&graph, &rules, &mut rng)
+            .unwrap();
+        assert_eq!(addresses.len(), 2);
+        let hetzner = [
+            IpAddr::from_str("5.9.0.1").unwrap(),
+            IpAddr::from_str("2a01:4f8:0:1::7:1").unwrap(),
+        ];
+        assert!(addresses.values().all(|ip| hetzner.contains(ip)));
+        let rules = vec![RelabelRule {
+            from_asn: 797,
+            to: RelabelTarget::Tail { max_nodes: 1 },
+        }];
+        assert!(as_ip_map
+            .relabel_addresses(&graph, &rules, &mut rng)
+            .is_err());
+
+        let mut json: Value =
+            serde_json::from_reader(File::open("test_data/lnbook_example_lnr.json").unwrap())
+                .unwrap();
+        let addresses =
+            HashMap::from([(String::from("chan"), IpAddr::from_str("5.9.0.1").unwrap())]);
+        relabel_graph_json(&mut json, &addresses, &Lnresearch);
+        assert_eq!(json["nodes"][2]["addresses"], "ipv4://5.9.0.1:9735");
+        assert_eq!(json["nodes"][3]["addresses"], "ipv4://135.209.152.1:9735");
+
+        let mut json: Value =
+            serde_json::from_reader(File::open("test_data/trivial_connected_lnd.json").unwrap())
+                .unwrap();
+        let addresses = HashMap::from([(
+            String::from("036"),
+            IpAddr::from_str("2a01:4f8:0:1::7:1").unwrap(),
+        )]);
+        relabel_graph_json(&mut json, &addresses, &Lnd);
+        assert_eq!(
+            json["nodes"][2]["addresses"],
+            json!([{"network": "tcp", "addr": "[2a01:4f8:0:1::7:1]:9735"}])
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use super::{Asn, AsnLookup, AsnOverrides, DbReader};
+use crate::SimulatorError;
+use lru::LruCache;
+use simlib::{Node, ID};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+/// Maximum number of IP addresses whose ASN is kept in memory
+pub static IP_CACHE_SIZE: usize = 100_000;
+
+/// A node is identified by its ID and addresses as the addresses may change between graphs
+type NodeKey = (ID, Vec<String>, bool);
+
+/// Resolves IP addresses and nodes to ASNs and memoises the results. Clones share the database
+/// and the caches so a resolver can be built once and used across amounts, strategies and graphs
+#[derive(Clone)]
+pub struct AsnResolver {
+    db_reader: Arc<dyn AsnLookup>,
+    ip_cache: Arc<Mutex<LruCache<IpAddr, Option<Asn>>>>,
+    node_cache: Arc<RwLock<HashMap<NodeKey, Option<Asn>>>>,
+    num_db_lookups: Arc<AtomicUsize>,
+    overrides: Arc<AsnOverrides>,
+}
+
+impl AsnResolver {
+    pub fn new() -> Self {
+        Self::try_new().expect("Error opening database")
+    }
+
+    pub fn try_new() -> Result<Self, SimulatorError> {
+        Ok(Self::with_capacity(DbReader::new()?, IP_CACHE_SIZE))
+    }
+
+    /// Keeps up to `capacity` IP addresses in the cache, evicting the least recently used
+    pub fn with_capacity(db_reader: DbReader, capacity: usize) -> Self {
+        Self::with_lookup(Arc::new(db_reader), capacity)
+    }
+
+    /// Resolves the addresses with another AS database, see AsnDatabase
+    pub fn with_lookup(db_reader: Arc<dyn AsnLookup>, capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            db_reader,
+            ip_cache: Arc::new(Mutex::new(LruCache::new(capacity))),
+            node_cache: Arc::new(RwLock::new(HashMap::new())),
+            num_db_lookups: Arc::new(AtomicUsize::new(0)),
+            overrides: Arc::default(),
+        }
+    }
+
+    /// Resolves the nodes and addresses in `overrides` to the given ASNs instead of looking them up
+    pub fn with_overrides(mut self, overrides: AsnOverrides) -> Self {
+        self.overrides = Arc::new(overrides);
+        self
+    }
+
+    pub fn lookup_asn(&self, ip: IpAddr) -> Option<Asn> {
+        if let Some(asn) = self.overrides.ip_asn(&ip) {
+            return Some(asn);
+        }
+        if let Ok(mut cache) = self.ip_cache.lock() {
+            if let Some(asn) = cache.get(&ip) {
+                return *asn;
+            }
+        }
+        self.num_db_lookups.fetch_add(1, Ordering::Relaxed);
+        let asn = self.db_reader.lookup_asn(ip);
+        if let Ok(mut cache) = self.ip_cache.lock() {
+            cache.put(ip, asn);
+        }
+        asn
+    }
+
+    /// Returns the overridden or cached ASN of the node or resolves it with `lookup`
+    pub(crate) fn asn_for_node(
+        &self,
+        node: &Node,
+        include_tor: bool,
+        lookup: impl FnOnce(&Self) -> Option<Asn>,
+    ) -> Option<Asn> {
+        if let Some(asn) = self.overrides.node_asn(&node.id) {
+            return Some(asn);
+        }
+        let key = (
+            node.id.clone(),
+            node.addresses.iter().map(|a| a.addr.clone()).collect(),
+            include_tor,
+        );
+        if let Some(asn) = self
+            .node_cache
+            .read()
+            .ok()
+            .and_then(|c| c.get(&key).copied())
+        {
+            return asn;
+        }
+        let asn = lookup(self);
+        if let Ok(mut cache) = self.node_cache.write() {
+            cache.insert(key, asn);
+        }
+        asn
+    }
+
+    /// Number of lookups that were not answered from the cache
+    pub fn num_db_lookups(&self) -> usize {
+        self.num_db_lookups.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for AsnResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn cached_lookup() {
+        let resolver = AsnResolver::with_capacity(DbReader::new().unwrap(), 1);
+        let google: IpAddr = FromStr::from_str("8.8.8.8").unwrap();
+        let example: IpAddr = FromStr::from_str("93.184.216.34").unwrap();
+        assert_eq!(resolver.lookup_asn(google), Some(15169));
+        let clone = resolver.clone();
+        assert_eq!(clone.lookup_asn(google), Some(15169));
+        assert_eq!(resolver.num_db_lookups(), 1);
+        // evicts 8.8.8.8
+        assert_eq!(clone.lookup_asn(example), Some(15133));
+        assert_eq!(resolver.lookup_asn(google), Some(15169));
+        assert_eq!(resolver.num_db_lookups(), 3);
+        let node = Node::default();
+        assert_eq!(resolver.asn_for_node(&node, false, |_| Some(1)), Some(1));
+        assert_eq!(resolver.asn_for_node(&node, false, |_| Some(2)), Some(1));
+        assert_eq!(resolver.asn_for_node(&node, true, |_| Some(2)), Some(2));
+        let mut overrides = AsnOverrides::default();
+        overrides.insert(&node.id, 3);
+        overrides.insert("8.8.8.8", 4);
+        let resolver = resolver.with_overrides(overrides);
+        assert_eq!(resolver.asn_for_node(&node, false, |_| Some(1)), Some(3));
+        assert_eq!(resolver.lookup_asn(google), Some(4));
+        assert_eq!(resolver.num_db_lookups(), 3);
+    }
+}
@@ -0,0 +1,191 @@
+use super::Asn;
+#[cfg(feature = "mmdb")]
+use super::AsnResolver;
+#[cfg(not(test))]
+use log::warn;
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, SeedableRng};
+use simlib::{graph::Graph, Node, ID};
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(test)]
+use std::println as warn;
+#[cfg(feature = "mmdb")]
+use std::{error::Error, net::IpAddr, path::Path, str::FromStr};
+
+/// Models nodes that are only reachable via onion addresses. Their IP addresses are hidden, so no
+/// AS hosts them and they cannot be censored at the AS level. Given the guard relays of a Tor
+/// consensus, the AS of a node's guard sees its traffic and can censor it
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TorModel {
+    pub onion_only: BTreeSet<ID>,
+    pub guards: Option<GuardConsensus>,
+}
+
+/// Bandwidth of the guard relays in a Tor consensus per AS
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GuardConsensus {
+    pub bandwidth_per_asn: BTreeMap<Asn, u64>,
+}
+
+/// A relay entry of a consensus document
+#[cfg(feature = "mmdb")]
+#[derive(Default)]
+struct Relay {
+    ip: Option<IpAddr>,
+    is_guard: bool,
+    bandwidth: u64,
+}
+
+impl TorModel {
+    pub fn new(graph: &Graph) -> Self {
+        Self {
+            onion_only: graph
+                .get_nodes()
+                .into_iter()
+                .filter(is_onion_only)
+                .map(|n| n.id)
+                .collect(),
+            guards: None,
+        }
+    }
+
+    /// Onion-only nodes become censorable by the AS of their guard relay
+    pub fn with_guards(mut self, guards: GuardConsensus) -> Self {
+        self.guards = Some(guards);
+        self
+    }
+
+    /// Assigns each onion-only node the AS of a guard relay drawn proportionally to the bandwidth.
+    /// Empty without guards. The same seed yields the same guards
+    pub fn guard_asns(&self, seed: u64) -> BTreeMap<ID, Asn> {
+        let Some(guards) = &self.guards else {
+            return BTreeMap::default();
+        };
+        let (asns, bandwidths): (Vec<Asn>, Vec<u64>) = guards
+            .bandwidth_per_asn
+            .iter()
+            .map(|(a, b)| (*a, *b))
+            .unzip();
+        let Ok(dist) = WeightedIndex::new(bandwidths) else {
+            warn!("No guard relay has any bandwidth.");
+            return BTreeMap::default();
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.onion_only
+            .iter()
+            .map(|node| (node.clone(), asns[dist.sample(&mut rng)]))
+            .collect()
+    }
+}
+
+/// True if the node announces addresses and all of them are onion addresses
+pub(crate) fn is_onion_only(node: &Node) -> bool {
+    !node.addresses.is_empty() && node.addresses.iter().all(|a| a.addr.contains("onion"))
+}
+
+#[cfg(feature = "mmdb")]
+impl GuardConsensus {
+    /// Reads the relays with the Guard flag from a network-status consensus document and maps
+    /// their addresses to ASNs
+    pub fn from_consensus_file(
+        path: &Path,
+        resolver: &AsnResolver,
+    ) -> Result<Self, Box<dyn Error>> {
+        let consensus = Self::from_consensus(&std::fs::read_to_string(path)?, resolver);
+        if consensus.bandwidth_per_asn.is_empty() {
+            return Err(format!("No guard relays found in {}", path.display()).into());
+        }
+        Ok(consensus)
+    }
+
+    fn from_consensus(document: &str, resolver: &AsnResolver) -> Self {
+        let mut consensus = Self::default();
+        let mut relay: Option<Relay> = None;
+        for line in document.lines() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                // r <nickname> <identity> <digest> <date> <time> <IP> <ORPort> <DirPort>
+                Some("r") => {
+                    if let Some(relay) = relay.take() {
+                        consensus.add(relay, resolver);
+                    }
+                    relay = Some(Relay {
+                        ip: fields.nth(5).and_then(|ip| IpAddr::from_str(ip).ok()),
+                        ..Default::default()
+                    });
+                }
+                Some("s") => {
+                    if let Some(relay) = relay.as_mut() {
+                        relay.is_guard = fields.any(|flag| flag == "Guard");
+                    }
+                }
+                Some("w") => {
+                    if let Some(relay) = relay.as_mut() {
+                        relay.bandwidth = fields
+                            .find_map(|f| f.strip_prefix("Bandwidth="))
+                            .and_then(|b| b.parse().ok())
+                            .unwrap_or_default();
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(relay) = relay {
+            consensus.add(relay, resolver);
+        }
+        consensus
+    }
+
+    fn add(&mut self, relay: Relay, resolver: &AsnResolver) {
+        if !relay.is_guard {
+            return;
+        }
+        match relay.ip.and_then(|ip| resolver.lookup_asn(ip)) {
+            Some(asn) => *self.bandwidth_per_asn.entry(asn).or_default() += relay.bandwidth,
+            None => warn!("Skipping guard relay {:?} without ASN.", relay.ip),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DbReader;
+    use network_parser::GraphSource::*;
+
+    #[test]
+    fn guards_from_consensus() {
+        let resolver = AsnResolver::with_capacity(DbReader::new().unwrap(), 10);
+        let consensus = "network-status-version 3\n\
+            r guard1 AAAA BBBB 2023-01-01 00:00:00 8.8.8.8 9001 0\n\
+            s Fast Guard Running Stable Valid\n\
+            w Bandwidth=300\n\
+            r middle AAAA BBBB 2023-01-01 00:00:00 8.8.4.4 9001 0\n\
+            s Fast Running Valid\n\
+            w Bandwidth=1000\n\
+            r guard2 AAAA BBBB 2023-01-01 00:00:00 93.184.216.34 443 0\n\
+            s Guard Running\n\
+            w Bandwidth=100 Unmeasured=1\n";
+        let guards = GuardConsensus::from_consensus(consensus, &resolver);
+        assert_eq!(
+            guards.bandwidth_per_asn,
+            BTreeMap::from([(15169, 300), (15133, 100)])
+        );
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let mut model = TorModel::new(&graph);
+        assert!(model.onion_only.is_empty());
+        model.onion_only.insert(String::from("onion"));
+        assert!(model.guard_asns(19).is_empty());
+        let model = model.with_guards(guards);
+        let assigned = model.guard_asns(19);
+        assert_eq!(assigned.len(), 1);
+        assert!([15169, 15133].contains(&assigned["onion"]));
+        assert_eq!(assigned, model.guard_asns(19));
+    }
+}
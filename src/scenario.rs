@@ -0,0 +1,151 @@
+use crate::{net::Asn, PacketDropStrategy};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::Path, path::PathBuf, str::FromStr};
+
+/// An experiment described in a TOML file. Unset fields fall back to the command line defaults
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioConfig {
+    /// Payment volumes in sat
+    pub amounts: Option<Vec<usize>>,
+    /// The simulation is repeated for each seed
+    pub seeds: Option<Vec<u64>>,
+    pub num_pairs: Option<usize>,
+    pub num_adv_as: Option<usize>,
+    /// 0 for number of nodes, 1 for number of channels and 2 for betweenness
+    pub as_strategy: Option<usize>,
+    /// Names of the drop strategies to simulate, e.g. "all" or "intra-as"
+    pub drop_strategies: Option<Vec<String>>,
+    /// Adversarial ASs to simulate instead of the top-n
+    pub asns: Option<Vec<Asn>>,
+    pub dedup_overlap: Option<bool>,
+    /// Path to a blocklist scenario enforced by a coalition of ASs
+    pub blocklist: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    pub stream: Option<bool>,
+    pub include_baseline: Option<bool>,
+    pub fee_revenue: Option<bool>,
+    /// Map onion-only nodes to a pseudo-AS instead of ignoring them
+    pub include_tor: Option<bool>,
+    pub all_ases: Option<bool>,
+    /// Names of two drop strategies to compare per adversary
+    pub diff_strategies: Option<Vec<String>>,
+}
+
+impl ScenarioConfig {
+    pub fn from_toml_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// The scenario in the format read by `from_toml_file`
+    pub fn to_toml_string(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    /// Parses the names of the drop strategies if any are given
+    pub fn drop_strategies(&self) -> Result<Option<Vec<PacketDropStrategy>>, String> {
+        Self::parse_strategies(&self.drop_strategies)
+    }
+
+    /// Parses the names of the strategies to compare if any are given
+    pub fn diff_strategies(&self) -> Result<Option<Vec<PacketDropStrategy>>, String> {
+        Self::parse_strategies(&self.diff_strategies)
+    }
+
+    fn parse_strategies(
+        strategies: &Option<Vec<String>>,
+    ) -> Result<Option<Vec<PacketDropStrategy>>, String> {
+        strategies
+            .as_ref()
+            .map(|strategies| {
+                strategies
+                    .iter()
+                    .map(|s| PacketDropStrategy::from_str(s))
+                    .collect()
+            })
+            .transpose()
+    }
+
+    /// Fills the fields that are not set with those of `defaults`, e.g. a preset
+    pub fn or(self, defaults: Self) -> Self {
+        Self {
+            amounts: self.amounts.or(defaults.amounts),
+            seeds: self.seeds.or(defaults.seeds),
+            num_pairs: self.num_pairs.or(defaults.num_pairs),
+            num_adv_as: self.num_adv_as.or(defaults.num_adv_as),
+            as_strategy: self.as_strategy.or(defaults.as_strategy),
+            drop_strategies: self.drop_strategies.or(defaults.drop_strategies),
+            asns: self.asns.or(defaults.asns),
+            dedup_overlap: self.dedup_overlap.or(defaults.dedup_overlap),
+            blocklist: self.blocklist.or(defaults.blocklist),
+            output_dir: self.output_dir.or(defaults.output_dir),
+            stream: self.stream.or(defaults.stream),
+            include_baseline: self.include_baseline.or(defaults.include_baseline),
+            fee_revenue: self.fee_revenue.or(defaults.fee_revenue),
+            include_tor: self.include_tor.or(defaults.include_tor),
+            all_ases: self.all_ases.or(defaults.all_ases),
+            diff_strategies: self.diff_strategies.or(defaults.diff_strategies),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn read_scenario_config() {
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        fs::write(
+            file.path(),
+            r#"
+            amounts = [100, 1000]
+            seeds = [1, 2]
+            drop_strategies = ["all", "intra-as"]
+            asns = [797]
+            output_dir = "results"
+            stream = true
+            "#,
+        )
+        .unwrap();
+        let actual = ScenarioConfig::from_toml_file(file.path()).unwrap();
+        let expected = ScenarioConfig {
+            amounts: Some(vec![100, 1000]),
+            seeds: Some(vec![1, 2]),
+            drop_strategies: Some(vec![String::from("all"), String::from("intra-as")]),
+            asns: Some(vec![797]),
+            output_dir: Some(PathBuf::from("results")),
+            stream: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(actual, expected);
+        fs::write(file.path(), actual.to_toml_string().unwrap()).unwrap();
+        assert_eq!(
+            ScenarioConfig::from_toml_file(file.path()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            actual.drop_strategies(),
+            Ok(Some(vec![
+                PacketDropStrategy::All,
+                PacketDropStrategy::IntraAs
+            ]))
+        );
+        let config = ScenarioConfig {
+            drop_strategies: Some(vec![String::from("none")]),
+            ..Default::default()
+        };
+        assert!(config.drop_strategies().is_err());
+        fs::write(file.path(), "unknown = 1").unwrap();
+        assert!(ScenarioConfig::from_toml_file(file.path()).is_err());
+        let merged = ScenarioConfig {
+            seeds: Some(vec![3]),
+            ..Default::default()
+        }
+        .or(expected);
+        assert_eq!(merged.seeds, Some(vec![3]));
+        assert_eq!(merged.amounts, Some(vec![100, 1000]));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{net::Asn, ScenarioConfig, TOR_ASN};
+use std::str::FromStr;
+
+/// Drop strategies simulated by the presets unless stated otherwise
+static PRESET_DROP_STRATEGIES: [&str; 4] = ["all", "intra-as", "inter-as", "split-aware"];
+
+/// Payment volumes in sat simulated by the paper experiments
+static PRESET_AMOUNTS: [usize; 4] = [100, 10000, 1000000, 10000000];
+
+/// Large transit and hosting ASs registered in a country, by ISO 3166-1 alpha-2 code
+static COUNTRY_ASNS: [(&str, &[Asn]); 6] = [
+    ("CN", &[4134, 4837, 9808, 45090]),
+    ("DE", &[3320, 3209, 24940, 51167]),
+    ("FR", &[3215, 5410, 12322, 16276]),
+    ("NL", &[1136, 33915, 60781, 14061]),
+    ("RU", &[12389, 8359, 31133, 8402]),
+    ("US", &[7922, 7018, 701, 16509, 14618, 15169]),
+];
+
+/// A named, vetted experiment configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    pub name: String,
+    pub description: String,
+    pub config: ScenarioConfig,
+}
+
+impl FromStr for Scenario {
+    type Err = String;
+
+    /// The name of a preset, e.g. "strategy-comparison" or "nation-state-de"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "exposure-survey" => Ok(Self::exposure_survey()),
+            "strategy-comparison" => Ok(Self::strategy_comparison()),
+            "defence-evaluation" => Ok(Self::defence_evaluation()),
+            "top5-hosting-providers" => Ok(Self::top5_hosting_providers()),
+            "tor-adversary" => Ok(Self::tor_adversary()),
+            name => match name.strip_prefix("nation-state-") {
+                Some(country) => Self::nation_state(country),
+                None => Err(format!("Unknown preset {}", s)),
+            },
+        }
+    }
+}
+
+impl Scenario {
+    /// The exposure of every AS and the fees it earns, without simulating each AS's strategies
+    pub fn exposure_survey() -> Self {
+        Self {
+            name: String::from("exposure-survey"),
+            description: String::from("Exposure and fee revenue of every AS"),
+            config: ScenarioConfig {
+                amounts: Some(PRESET_AMOUNTS.to_vec()),
+                num_adv_as: Some(10),
+                as_strategy: Some(1),
+                drop_strategies: Some(vec![String::from("all")]),
+                all_ases: Some(true),
+                fee_revenue: Some(true),
+                stream: Some(true),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Every drop strategy of the top ASs by channels, comparing dropping everything with
+    /// dropping only intra-AS payments
+    pub fn strategy_comparison() -> Self {
+        Self {
+            name: String::from("strategy-comparison"),
+            description: String::from("All drop strategies of the top-5 ASs by channels"),
+            config: ScenarioConfig {
+                amounts: Some(PRESET_AMOUNTS.to_vec()),
+                num_adv_as: Some(5),
+                as_strategy: Some(1),
+                drop_strategies: Some(vec![
+                    String::from("all"),
+                    String::from("intra-probability"),
+                    String::from("intra-as"),
+                    String::from("inter-as"),
+                    String::from("split-aware"),
+                ]),
+                diff_strategies: Some(vec![String::from("all"), String::from("intra-as")]),
+                include_baseline: Some(true),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// How well multi-part payments evade the most central ASs
+    pub fn defence_evaluation() -> Self {
+        Self {
+            name: String::from("defence-evaluation"),
+            description: String::from("Split payments against the top-5 ASs by betweenness"),
+            config: ScenarioConfig {
+                amounts: Some(PRESET_AMOUNTS.to_vec()),
+                num_adv_as: Some(5),
+                as_strategy: Some(2),
+                drop_strategies: Some(vec![String::from("all"), String::from("split-aware")]),
+                diff_strategies: Some(vec![String::from("all"), String::from("split-aware")]),
+                dedup_overlap: Some(true),
+                include_baseline: Some(true),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The five ASs hosting the most nodes, e.g. cloud and hosting providers
+    pub fn top5_hosting_providers() -> Self {
+        Self {
+            name: String::from("top5-hosting-providers"),
+            description: String::from("The five ASs hosting the most nodes censor payments"),
+            config: ScenarioConfig {
+                num_adv_as: Some(5),
+                as_strategy: Some(0),
+                drop_strategies: Some(Self::drop_strategies()),
+                include_baseline: Some(true),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The major ASs of a country censor payments. Returns an error for countries without a list
+    /// of ASs
+    pub fn nation_state(country: &str) -> Result<Self, String> {
+        let country = country.to_uppercase();
+        let asns =
+            Self::country_asns(&country).ok_or(format!("No ASs known for country {}", country))?;
+        Ok(Self {
+            name: format!("nation-state-{}", country.to_lowercase()),
+            description: format!("The major ASs in {} censor payments", country),
+            config: ScenarioConfig {
+                asns: Some(asns),
+                drop_strategies: Some(Self::drop_strategies()),
+                dedup_overlap: Some(true),
+                include_baseline: Some(true),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Nodes only reachable via Tor are treated as a single adversary
+    pub fn tor_adversary() -> Self {
+        Self {
+            name: String::from("tor-adversary"),
+            description: String::from("All onion-only nodes censor payments"),
+            config: ScenarioConfig {
+                asns: Some(vec![TOR_ASN]),
+                include_tor: Some(true),
+                drop_strategies: Some(Self::drop_strategies()),
+                include_baseline: Some(true),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Countries supported by `nation_state`
+    pub fn countries() -> Vec<&'static str> {
+        COUNTRY_ASNS.iter().map(|(code, _)| *code).collect()
+    }
+
+    /// The major ASs of a country simulated by `nation_state`, if the country is supported
+    pub fn country_asns(country: &str) -> Option<Vec<Asn>> {
+        COUNTRY_ASNS
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(country))
+            .map(|(_, asns)| asns.to_vec())
+    }
+
+    fn drop_strategies() -> Vec<String> {
+        PRESET_DROP_STRATEGIES
+            .iter()
+            .map(|s| String::from(*s))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        for scenario in [
+            Scenario::top5_hosting_providers(),
+            Scenario::nation_state("de").unwrap(),
+            Scenario::tor_adversary(),
+        ] {
+            assert!(scenario.config.drop_strategies().unwrap().is_some());
+        }
+        let scenario = Scenario::nation_state("DE").unwrap();
+        assert_eq!(scenario.name, "nation-state-de");
+        assert!(scenario.config.asns.unwrap().contains(&24940));
+        assert!(Scenario::nation_state("XX").is_err());
+        assert_eq!(
+            Scenario::country_asns("nl"),
+            Some(vec![1136, 33915, 60781, 14061])
+        );
+        assert_eq!(Scenario::countries().len(), COUNTRY_ASNS.len());
+        for name in [
+            "exposure-survey",
+            "strategy-comparison",
+            "defence-evaluation",
+            "tor-adversary",
+            "nation-state-us",
+        ] {
+            let scenario = Scenario::from_str(name).unwrap();
+            assert_eq!(scenario.name, name);
+            assert!(scenario.config.drop_strategies().is_ok());
+            assert!(scenario.config.diff_strategies().is_ok());
+        }
+        assert!(Scenario::from_str("nation-state").is_err());
+    }
+}
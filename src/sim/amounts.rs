@@ -0,0 +1,264 @@
+use super::SimBuilder;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+use simlib::{graph::Graph, Simulation, ID};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The amounts of the simulated payments
+#[derive(Debug, Clone, PartialEq)]
+pub enum AmountModel {
+    /// Every amount is simulated on its own with the same amount for all payments
+    Fixed(Vec<usize>),
+    /// Each payment's amount is drawn from a log-uniform distribution between the bounds in sat
+    LogUniform { min_sat: usize, max_sat: usize },
+    /// Each payment's amount is drawn from the amounts in sat of a CSV file with the column
+    /// `amount_sat`, e.g. of real payments
+    Empirical {
+        path: PathBuf,
+        amounts_sat: Vec<usize>,
+    },
+}
+
+#[derive(Deserialize)]
+struct AmountRecord {
+    amount_sat: usize,
+}
+
+impl FromStr for AmountModel {
+    type Err = String;
+
+    /// "fixed=<SAT>,<SAT>,...", "log-uniform=<MIN_SAT>:<MAX_SAT>" or "empirical=<FILE>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |sat: &str| {
+            sat.parse::<usize>()
+                .map_err(|e| format!("Invalid amount {}: {}", sat, e))
+        };
+        match s.split_once('=') {
+            Some(("fixed", amounts)) => Ok(Self::Fixed(
+                amounts.split(',').map(parse).collect::<Result<_, _>>()?,
+            )),
+            Some(("log-uniform", bounds)) => {
+                let (min, max) = bounds
+                    .split_once(':')
+                    .ok_or(format!("Expected <MIN_SAT>:<MAX_SAT>, got {}", bounds))?;
+                let (min_sat, max_sat) = (parse(min)?, parse(max)?);
+                if min_sat == 0 || min_sat > max_sat {
+                    return Err(format!("Invalid amount range {}", bounds));
+                }
+                Ok(Self::LogUniform { min_sat, max_sat })
+            }
+            Some(("empirical", path)) => Self::read_empirical(Path::new(path))
+                .map_err(|e| format!("Error in amounts file {}: {}", path, e)),
+            _ => Err(format!("Unknown amount model {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for AmountModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(amounts) => {
+                let amounts: Vec<String> = amounts.iter().map(|a| a.to_string()).collect();
+                write!(f, "fixed={}", amounts.join(","))
+            }
+            Self::LogUniform { min_sat, max_sat } => {
+                write!(f, "log-uniform={}:{}", min_sat, max_sat)
+            }
+            Self::Empirical { path, .. } => write!(f, "empirical={}", path.display()),
+        }
+    }
+}
+
+impl AmountModel {
+    fn read_empirical(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut amounts_sat = vec![];
+        for record in csv::Reader::from_path(path)?.deserialize() {
+            let record: AmountRecord = record?;
+            if record.amount_sat > 0 {
+                amounts_sat.push(record.amount_sat);
+            }
+        }
+        if amounts_sat.is_empty() {
+            return Err("No positive amounts".into());
+        }
+        Ok(Self::Empirical {
+            path: path.to_path_buf(),
+            amounts_sat,
+        })
+    }
+
+    /// Draws an amount in sat for each of the payments, None if all payments of an amount share
+    /// it. Amounts are rounded to two significant digits so that payments of similar amounts are
+    /// simulated together
+    pub fn draw_sat(&self, num_payments: usize, seed: u64) -> Option<Vec<usize>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let amounts = match self {
+            Self::Fixed(_) => return None,
+            Self::LogUniform { min_sat, max_sat } => {
+                let (min, max) = ((*min_sat as f64).ln(), (*max_sat as f64).ln());
+                (0..num_payments)
+                    .map(|_| rng.gen_range(min..=max).exp().round() as usize)
+                    .collect::<Vec<_>>()
+            }
+            Self::Empirical { amounts_sat, .. } => (0..num_payments)
+                .map(|_| amounts_sat[rng.gen_range(0..amounts_sat.len())])
+                .collect(),
+        };
+        Some(amounts.into_iter().map(round_significant).collect())
+    }
+
+    /// Whether each payment gets its own amount
+    pub fn draws_per_payment(&self) -> bool {
+        !matches!(self, Self::Fixed(_))
+    }
+
+    /// The amounts in sat that are simulated one after the other. A model drawing amounts per
+    /// payment is simulated once and reported under the median of its draws
+    pub fn amounts_sat(&self, num_payments: usize, seed: u64) -> Vec<usize> {
+        match self.draw_sat(num_payments, seed) {
+            Some(mut amounts) => {
+                amounts.sort_unstable();
+                vec![amounts.get(amounts.len() / 2).copied().unwrap_or_default()]
+            }
+            None => match self {
+                Self::Fixed(amounts) => amounts.clone(),
+                _ => vec![],
+            },
+        }
+    }
+}
+
+fn round_significant(sat: usize) -> usize {
+    let magnitude = 10usize.pow((sat.max(1).ilog10()).saturating_sub(1));
+    ((sat + magnitude / 2) / magnitude * magnitude).max(1)
+}
+
+impl SimBuilder {
+    /// Simulates the pairs with the amounts drawn from the model, one simulation per distinct
+    /// amount. The payments keep the index of their pair as ID
+    pub(crate) fn simulate_drawn_amounts(
+        &self,
+        graph: Graph,
+        pairs: Vec<(ID, ID)>,
+        amounts_sat: Vec<usize>,
+    ) -> simlib::SimResult {
+        let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (i, amount) in amounts_sat.into_iter().enumerate() {
+            groups.entry(amount).or_default().push(i);
+        }
+        let mut result = simlib::SimResult {
+            total_num: pairs.len(),
+            ..Default::default()
+        };
+        for (amount, indices) in groups {
+            let mut sim = Simulation::new(
+                self.run,
+                graph.clone(),
+                simlib::to_millisatoshi(amount),
+                self.routing_metric,
+                self.payment_parts,
+                Some(vec![0]),
+                &[],
+            );
+            let group = sim.run(indices.iter().map(|i| pairs[*i].clone()), None, false);
+            result.num_succesful += group.num_succesful;
+            result.num_failed += group.num_failed;
+            for mut p in group.successful_payments {
+                p.payment_id = indices[p.payment_id];
+                result.successful_payments.push(p);
+            }
+            for mut p in group.failed_payments {
+                p.payment_id = indices[p.payment_id];
+                result.failed_payments.push(p);
+            }
+        }
+        result.successful_payments.sort_by_key(|p| p.payment_id);
+        result.failed_payments.sort_by_key(|p| p.payment_id);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsSelectionStrategy;
+    use network_parser::GraphSource::*;
+    use std::fs;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn parse_and_draw() {
+        assert_eq!(
+            AmountModel::from_str("fixed=100,1000"),
+            Ok(AmountModel::Fixed(vec![100, 1000]))
+        );
+        assert!(AmountModel::from_str("log-uniform=1000:100").is_err());
+        let model = AmountModel::from_str("log-uniform=100:1000000").unwrap();
+        assert_eq!(model.to_string(), "log-uniform=100:1000000");
+        let amounts = model.draw_sat(1000, 19).unwrap();
+        assert_eq!(amounts, model.draw_sat(1000, 19).unwrap());
+        assert!(amounts.iter().all(|a| (100..=1000000).contains(a)));
+        // roughly a quarter of the draws per order of magnitude
+        let below_1000 = amounts.iter().filter(|a| **a < 1000).count();
+        assert!((150..350).contains(&below_1000));
+        assert_eq!(AmountModel::Fixed(vec![10, 20]).draw_sat(5, 19), None);
+        assert_eq!(
+            AmountModel::Fixed(vec![10, 20]).amounts_sat(5, 19),
+            vec![10, 20]
+        );
+        assert_eq!(round_significant(12345), 12000);
+        assert_eq!(round_significant(155), 160);
+        assert_eq!(round_significant(7), 7);
+
+        let file = NamedTempFile::new().unwrap();
+        fs::write(file.path(), "amount_sat\n500\n0\n21000\n").unwrap();
+        let model = AmountModel::from_str(&format!("empirical={}", file.path().display())).unwrap();
+        assert!(model
+            .draw_sat(100, 19)
+            .unwrap()
+            .iter()
+            .all(|a| *a == 500 || *a == 21000));
+    }
+
+    #[test]
+    fn simulate_drawn_amounts() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let pairs = vec![
+            (String::from("alice"), String::from("dina")),
+            (String::from("bob"), String::from("chan")),
+            (String::from("alice"), String::from("bob")),
+        ];
+        let result = builder.simulate_drawn_amounts(graph.clone(), pairs, vec![1000, 50, 1000]);
+        assert_eq!(result.total_num, 3);
+        assert_eq!(result.num_succesful + result.num_failed, 3);
+        let mut ids: Vec<usize> = result
+            .successful_payments
+            .iter()
+            .chain(result.failed_payments.iter())
+            .map(|p| p.payment_id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![0, 1, 2]);
+        let bob_chan = result
+            .successful_payments
+            .iter()
+            .chain(result.failed_payments.iter())
+            .find(|p| p.payment_id == 1)
+            .unwrap();
+        assert_eq!(bob_chan.amount, simlib::to_millisatoshi(50));
+    }
+}
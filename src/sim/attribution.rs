@@ -0,0 +1,143 @@
+use super::{output::*, SimBuilder};
+use crate::{net::Asn, AsIpMap};
+use simlib::ID;
+use std::collections::HashMap;
+
+impl SimBuilder {
+    /// Returns the AS a victim would blame for each successful payment if it were censored by the
+    /// AS's nodes. The naive victim blames the last hop that responded, i.e., the one before the
+    /// first node of the AS on the path. Payments not traversing the AS are omitted
+    pub(crate) fn blamed_asns(
+        sim_result: &simlib::SimResult,
+        asn_nodes: &[ID],
+        as_ip_map: &AsIpMap,
+    ) -> HashMap<usize, Option<Asn>> {
+        let mut blamed = HashMap::new();
+        for p in sim_result.successful_payments.iter() {
+            for path in p.used_paths.iter() {
+                let hops = path.path.get_involved_nodes();
+                if let Some(first_adversary) = hops.iter().position(|h| asn_nodes.contains(h)) {
+                    // the sender can only blame itself
+                    let last_responsive = &hops[first_adversary.saturating_sub(1)];
+                    blamed.insert(p.payment_id, as_ip_map.asn_of(last_responsive));
+                    break;
+                }
+            }
+        }
+        blamed
+    }
+
+    /// Summarises whom the victims of the censored payments would accuse
+    pub(crate) fn misattribution(
+        blamed: &HashMap<usize, Option<Asn>>,
+        censored_result: &simlib::SimResult,
+        asn: Asn,
+    ) -> Misattribution {
+        Misattribution::from_accused(Self::accused_asns(blamed, censored_result), asn)
+    }
+
+    /// Number of censored payments blamed on each AS
+    pub(crate) fn accused_asns(
+        blamed: &HashMap<usize, Option<Asn>>,
+        censored_result: &simlib::SimResult,
+    ) -> HashMap<Option<Asn>, usize> {
+        let mut accused: HashMap<Option<Asn>, usize> = HashMap::new();
+        for p in censored_result.failed_payments.iter() {
+            if let Some(blamed_asn) = blamed.get(&p.payment_id) {
+                *accused.entry(*blamed_asn).or_default() += 1;
+            }
+        }
+        accused
+    }
+}
+
+impl Misattribution {
+    pub(crate) fn from_accused(accused: HashMap<Option<Asn>, usize>, asn: Asn) -> Self {
+        let num_censored_payments: usize = accused.values().sum();
+        let num_misattributed = num_censored_payments - accused.get(&Some(asn)).unwrap_or(&0);
+        let mut accused_asns: Vec<AccusedAs> = accused
+            .into_iter()
+            .map(|(asn, num_payments)| AccusedAs { asn, num_payments })
+            .collect();
+        accused_asns.sort_by(|a, b| b.num_payments.cmp(&a.num_payments).then(a.asn.cmp(&b.asn)));
+        Self {
+            num_censored_payments,
+            misattribution_rate: if num_censored_payments > 0 {
+                num_misattributed as f32 / num_censored_payments as f32
+            } else {
+                0.0
+            },
+            accused_asns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, payment::Payment, CandidatePath};
+    use std::{collections::VecDeque, path::Path};
+
+    #[test]
+    fn misattributed_censorship() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let asn = 24940;
+        let asn_nodes = vec!["alice".to_owned()];
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let sim_result = simlib::SimResult {
+            num_succesful: 3,
+            total_num: 3,
+            successful_payments: vec![
+                // chan is blamed
+                payment(0, vec!["dina", "chan", "alice", "bob"]),
+                // bob is in the same AS as alice
+                payment(1, vec!["bob", "alice"]),
+                // does not traverse the AS
+                payment(2, vec!["dina", "chan"]),
+            ],
+            ..Default::default()
+        };
+        let blamed = SimBuilder::blamed_asns(&sim_result, &asn_nodes, &as_ip_map);
+        let expected = HashMap::from([(0, Some(797)), (1, Some(24940))]);
+        assert_eq!(blamed, expected);
+        let (censored_result, _) =
+            SimBuilder::apply_all_dropped_strategy(sim_result, &asn_nodes, asn, &as_ip_map);
+        let actual = SimBuilder::misattribution(&blamed, &censored_result, asn);
+        let expected = Misattribution {
+            num_censored_payments: 2,
+            misattribution_rate: 0.5,
+            accused_asns: vec![
+                AccusedAs {
+                    asn: Some(797),
+                    num_payments: 1,
+                },
+                AccusedAs {
+                    asn: Some(24940),
+                    num_payments: 1,
+                },
+            ],
+        };
+        assert_eq!(actual, expected);
+    }
+}
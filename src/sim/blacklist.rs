@@ -0,0 +1,238 @@
+use super::{output::*, SimBuilder};
+use crate::read_node_list;
+use serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+use simlib::ID;
+use std::{collections::BTreeSet, fmt, path::Path, sync::Arc};
+
+/// Destinations censored by PacketDropStrategy::DestinationBlacklist. The nodes are read once and
+/// shared by every copy of the strategy
+#[derive(Clone, PartialEq, Eq)]
+pub struct NodeBlacklist {
+    /// Stem of the file the nodes were read from, which identifies the list in the results
+    pub name: String,
+    pub nodes: Arc<BTreeSet<ID>>,
+}
+
+impl NodeBlacklist {
+    /// Reads one node ID or public key per line
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let nodes: BTreeSet<ID> = read_node_list(path)
+            .map_err(|e| format!("Invalid blacklist {}: {}", path.display(), e))?
+            .into_iter()
+            .collect();
+        if nodes.is_empty() {
+            return Err(format!("Blacklist {} has no nodes.", path.display()));
+        }
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(Self::new(name, nodes))
+    }
+
+    pub fn new(name: String, nodes: BTreeSet<ID>) -> Self {
+        Self {
+            name,
+            nodes: Arc::new(nodes),
+        }
+    }
+
+    pub fn contains(&self, node: &ID) -> bool {
+        self.nodes.contains(node)
+    }
+}
+
+/// Only the name so that the list is not repeated in every strategy and drop reason
+impl fmt::Debug for NodeBlacklist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// The nodes are stored next to the name so that a blacklist read from a report can be applied
+/// again
+impl Serialize for NodeBlacklist {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("NodeBlacklist", 2)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("nodes", &*self.nodes)?;
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct StoredBlacklist {
+    name: String,
+    nodes: BTreeSet<ID>,
+}
+
+impl<'de> Deserialize<'de> for NodeBlacklist {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let stored = StoredBlacklist::deserialize(deserializer)?;
+        if stored.nodes.is_empty() {
+            return Err(de::Error::custom(format!(
+                "Blacklist {} has no nodes.",
+                stored.name
+            )));
+        }
+        Ok(Self::new(stored.name, stored.nodes))
+    }
+}
+
+/// How PacketDropStrategy::DestinationBlacklist censored the payments to the blacklisted nodes
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BlacklistCensorship {
+    pub num_blacklisted: usize,
+    /// Payments to a blacklisted node that succeeded in the baseline
+    pub num_targeted: usize,
+    pub num_censored: usize,
+    /// Share of the targeted payments that were censored
+    pub censorship_rate: f32,
+    /// Payments to other nodes that were dropped as they were routed through a blacklisted node
+    pub num_collateral: usize,
+}
+
+impl BlacklistCensorship {
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.num_blacklisted = other.num_blacklisted;
+        self.num_targeted += other.num_targeted;
+        self.num_censored += other.num_censored;
+        self.num_collateral += other.num_collateral;
+        self.update_rate();
+    }
+
+    fn update_rate(&mut self) {
+        self.censorship_rate = if self.num_targeted > 0 {
+            self.num_censored as f32 / self.num_targeted as f32
+        } else {
+            0.0
+        };
+    }
+}
+
+impl SimBuilder {
+    /// Payments with a part that one of the AS's nodes forwards to a blacklisted node are dropped.
+    /// The AS only sees the next hop, so payments to the blacklisted nodes are the target
+    pub(crate) fn apply_destination_blacklist_strategy(
+        sim_result: simlib::SimResult,
+        asn_nodes: &[ID],
+        blacklist: &NodeBlacklist,
+    ) -> (simlib::SimResult, PerSimAccuracy, BlacklistCensorship) {
+        let mut updated_results = simlib::SimResult {
+            num_failed: sim_result.num_failed,
+            num_succesful: 0,
+            total_num: sim_result.total_num,
+            successful_payments: vec![],
+            failed_payments: sim_result.failed_payments,
+            ..Default::default()
+        };
+        let mut censorship = BlacklistCensorship {
+            num_blacklisted: blacklist.nodes.len(),
+            ..Default::default()
+        };
+        let mut accuracy = PerSimAccuracy::default();
+        for mut p in sim_result.successful_payments {
+            let target = blacklist.contains(&p.dest);
+            let dropped = p.used_paths.iter().any(|path| {
+                path.path
+                    .get_involved_nodes()
+                    .windows(2)
+                    .any(|hops| asn_nodes.contains(&hops[0]) && blacklist.contains(&hops[1]))
+            });
+            accuracy.record(dropped, target);
+            if target {
+                censorship.num_targeted += 1;
+            }
+            if dropped {
+                if target {
+                    censorship.num_censored += 1;
+                } else {
+                    censorship.num_collateral += 1;
+                }
+                p.succeeded = false;
+                p.used_paths = vec![];
+                updated_results.num_failed += 1;
+                updated_results.failed_payments.push(p);
+            } else {
+                updated_results.num_succesful += 1;
+                updated_results.successful_payments.push(p);
+            }
+        }
+        censorship.update_rate();
+        (updated_results, accuracy.finalise(), censorship)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simlib::{payment::Payment, CandidatePath};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn destination_blacklist() {
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let sim_result = simlib::SimResult {
+            num_succesful: 5,
+            total_num: 5,
+            successful_payments: vec![
+                payment(0, vec!["alice", "bob", "chan", "dina"]),
+                payment(1, vec!["bob", "chan"]),
+                payment(2, vec!["alice", "dina"]),
+                payment(3, vec!["alice", "bob", "dina"]),
+                payment(4, vec!["alice", "chan"]),
+            ],
+            ..Default::default()
+        };
+        let blacklist = NodeBlacklist::new(
+            String::from("sanctioned"),
+            BTreeSet::from([String::from("chan")]),
+        );
+        assert_eq!(format!("{:?}", blacklist), "sanctioned");
+        let json = serde_json::to_string(&blacklist).unwrap();
+        assert_eq!(json, r#"{"name":"sanctioned","nodes":["chan"]}"#);
+        assert_eq!(
+            serde_json::from_str::<NodeBlacklist>(&json).unwrap(),
+            blacklist
+        );
+        assert!(serde_json::from_str::<NodeBlacklist>(r#""sanctioned""#).is_err());
+        assert!(
+            serde_json::from_str::<NodeBlacklist>(r#"{"name":"sanctioned","nodes":[]}"#).is_err()
+        );
+        let asn_nodes = vec![String::from("bob")];
+        let (result, accuracy, censorship) =
+            SimBuilder::apply_destination_blacklist_strategy(sim_result, &asn_nodes, &blacklist);
+        let mut failed: Vec<usize> = result
+            .failed_payments
+            .iter()
+            .map(|p| p.payment_id)
+            .collect();
+        failed.sort();
+        // dina is paid through chan, alice pays chan without the AS
+        assert_eq!(failed, vec![0, 1]);
+        assert_eq!(
+            censorship,
+            BlacklistCensorship {
+                num_blacklisted: 1,
+                num_targeted: 2,
+                num_censored: 1,
+                censorship_rate: 0.5,
+                num_collateral: 1,
+            }
+        );
+        assert_eq!((accuracy.tpos, accuracy.fpos, accuracy.fneg), (1, 1, 1));
+    }
+}
@@ -0,0 +1,282 @@
+use super::{output::SimResult, SimBuilder};
+use crate::{net::Asn, AsIpMap, AsMetadataMap, Scenario};
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+use std::{
+    collections::{BTreeSet, HashSet},
+    error::Error,
+    fs::File,
+    path::Path,
+};
+
+/// A list of destinations that every AS of a group (e.g. all ASs in a country) is required to
+/// block
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BlocklistScenario {
+    pub name: String,
+    /// The ASs enforcing the blocklist
+    #[serde(default)]
+    pub coalition_asns: Vec<Asn>,
+    /// ISO 3166 codes of the countries all of whose ASs enforce the blocklist
+    #[serde(default)]
+    pub coalition_countries: Vec<String>,
+    /// Organisations all of whose ASs enforce the blocklist, as registered in the AS metadata
+    #[serde(default)]
+    pub coalition_organizations: Vec<String>,
+    /// IDs of the nodes payments must not reach
+    pub blocklist: Vec<ID>,
+}
+
+/// How well the coalition enforced the blocklist
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BlocklistReport {
+    pub name: String,
+    /// Coalition members that have nodes in the graph
+    pub coalition_asns: Vec<Asn>,
+    pub num_coalition_nodes: usize,
+    /// Payments to a blocklisted node that succeeded in the baseline
+    pub num_blocklisted_payments: usize,
+    pub num_blocked: usize,
+    /// Payments that reached a blocklisted node without traversing the coalition
+    pub num_circumvented: usize,
+    pub efficacy: f32,
+    pub circumvention_rate: f32,
+    pub sim_result: SimResult,
+}
+
+impl BlocklistScenario {
+    pub fn from_json_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Adds the ASs of the coalition's countries and organisations to `coalition_asns`.
+    /// Countries are looked up in the AS metadata if given, otherwise in the ASs of
+    /// `Scenario::nation_state`. Organisations require the metadata
+    pub fn resolve_coalition(&mut self, as_metadata: Option<&AsMetadataMap>) -> Result<(), String> {
+        let mut asns: BTreeSet<Asn> = self.coalition_asns.iter().copied().collect();
+        for country in self.coalition_countries.iter() {
+            let members = match as_metadata {
+                Some(metadata) => metadata.asns_where(|m| {
+                    m.country
+                        .as_ref()
+                        .is_some_and(|c| c.eq_ignore_ascii_case(country))
+                }),
+                None => Scenario::country_asns(country).unwrap_or_default(),
+            };
+            if members.is_empty() {
+                return Err(format!("No ASs known for country {}", country));
+            }
+            asns.extend(members);
+        }
+        for organization in self.coalition_organizations.iter() {
+            let metadata = as_metadata.ok_or(format!(
+                "Organisation {} requires the AS metadata",
+                organization
+            ))?;
+            let members = metadata.asns_where(|m| {
+                m.organization
+                    .as_ref()
+                    .is_some_and(|o| o.eq_ignore_ascii_case(organization))
+            });
+            if members.is_empty() {
+                return Err(format!("No ASs known for organisation {}", organization));
+            }
+            asns.extend(members);
+        }
+        if asns.is_empty() {
+            return Err(format!("Blocklist {} has no coalition", self.name));
+        }
+        self.coalition_asns = asns.into_iter().collect();
+        Ok(())
+    }
+}
+
+impl SimBuilder {
+    /// Drops all payments to blocklisted nodes that traverse a node of the coalition. Only the ASs
+    /// in `coalition_asns` are members, so resolve the coalition first
+    pub fn apply_blocklist_scenario(
+        sim_result: simlib::SimResult,
+        scenario: &BlocklistScenario,
+        as_ip_map: &AsIpMap,
+    ) -> BlocklistReport {
+        let coalition_asns: Vec<Asn> = scenario
+            .coalition_asns
+            .iter()
+            .filter(|asn| as_ip_map.as_to_nodes().contains_key(asn))
+            .copied()
+            .collect();
+        let coalition_nodes: Vec<ID> = coalition_asns
+            .iter()
+            .flat_map(|asn| as_ip_map.as_to_nodes()[asn].iter().cloned())
+            .collect();
+        let blocklist: HashSet<&ID> = scenario.blocklist.iter().collect();
+        let mut updated_results = simlib::SimResult {
+            num_failed: sim_result.num_failed,
+            num_succesful: 0,
+            total_num: sim_result.total_num,
+            successful_payments: vec![],
+            failed_payments: sim_result.failed_payments,
+            ..Default::default()
+        };
+        let (mut num_blocklisted_payments, mut num_blocked) = (0, 0);
+        for mut p in sim_result.successful_payments {
+            if !blocklist.contains(&p.dest) {
+                updated_results.num_succesful += 1;
+                updated_results.successful_payments.push(p);
+                continue;
+            }
+            num_blocklisted_payments += 1;
+            if Self::payment_involves_asn(&p, &coalition_nodes) {
+                num_blocked += 1;
+                p.succeeded = false;
+                p.used_paths = vec![];
+                updated_results.num_failed += 1;
+                updated_results.failed_payments.push(p);
+            } else {
+                // the payment avoided every member of the coalition
+                updated_results.num_succesful += 1;
+                updated_results.successful_payments.push(p);
+            }
+        }
+        let num_circumvented = num_blocklisted_payments - num_blocked;
+        let rate = |n: usize| {
+            if num_blocklisted_payments > 0 {
+                n as f32 / num_blocklisted_payments as f32
+            } else {
+                0.0
+            }
+        };
+        BlocklistReport {
+            name: scenario.name.clone(),
+            num_coalition_nodes: coalition_nodes.len(),
+            coalition_asns,
+            num_blocklisted_payments,
+            num_blocked,
+            num_circumvented,
+            efficacy: rate(num_blocked),
+            circumvention_rate: rate(num_circumvented),
+            sim_result: SimResult::from_simlib_results(updated_results, coalition_nodes.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, payment::Payment, CandidatePath};
+    use std::collections::VecDeque;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn read_scenario() {
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        std::fs::write(
+            file.path(),
+            r#"{"name": "test", "coalitionAsns": [797], "blocklist": ["bob"]}"#,
+        )
+        .unwrap();
+        let actual = BlocklistScenario::from_json_file(file.path()).unwrap();
+        let expected = BlocklistScenario {
+            name: String::from("test"),
+            coalition_asns: vec![797],
+            blocklist: vec![String::from("bob")],
+            ..Default::default()
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn resolve_coalition() {
+        let mut scenario = BlocklistScenario {
+            name: String::from("test"),
+            coalition_asns: vec![797],
+            coalition_countries: vec![String::from("de")],
+            ..Default::default()
+        };
+        assert!(scenario.resolve_coalition(None).is_ok());
+        assert_eq!(scenario.coalition_asns, vec![797, 3209, 3320, 24940, 51167]);
+
+        let as2org = "# format:aut|changed|aut_name|org_id|opaque_id|source\n\
+                      24940|20230101|HETZNER-AS|ORG-HOA1-RIPE||RIPE\n\
+                      797|20230101|AS797|ORG-EX||ARIN\n\
+                      # format:org_id|changed|org_name|country|source\n\
+                      ORG-HOA1-RIPE|20230101|Hetzner Online GmbH|DE|RIPE\n\
+                      ORG-EX|20230101|Example|US|ARIN\n";
+        let metadata = AsMetadataMap::from_as2org(as2org.as_bytes()).unwrap();
+        let mut scenario = BlocklistScenario {
+            name: String::from("test"),
+            coalition_countries: vec![String::from("DE")],
+            coalition_organizations: vec![String::from("example")],
+            ..Default::default()
+        };
+        assert!(scenario.resolve_coalition(Some(&metadata)).is_ok());
+        assert_eq!(scenario.coalition_asns, vec![797, 24940]);
+
+        let mut scenario = BlocklistScenario {
+            name: String::from("test"),
+            coalition_organizations: vec![String::from("example")],
+            ..Default::default()
+        };
+        assert!(scenario.resolve_coalition(None).is_err());
+        scenario.coalition_organizations = vec![String::from("unknown")];
+        assert!(scenario.resolve_coalition(Some(&metadata)).is_err());
+    }
+
+    #[test]
+    fn enforce_blocklist() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &std::path::Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let scenario = BlocklistScenario {
+            name: String::from("test"),
+            coalition_asns: vec![797, 16509],
+            blocklist: vec![String::from("bob")],
+            ..Default::default()
+        };
+        let payment = |id, src: &str, hops: Vec<&str>| {
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.to_owned(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src.to_owned(), dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let sim_result = simlib::SimResult {
+            num_succesful: 3,
+            total_num: 3,
+            successful_payments: vec![
+                // blocked by chan
+                payment(0, "dina", vec!["dina", "chan", "bob"]),
+                // circumvents the coalition
+                payment(1, "alice", vec!["alice", "bob"]),
+                // not blocklisted
+                payment(2, "dina", vec!["dina", "chan", "alice"]),
+            ],
+            ..Default::default()
+        };
+        let actual = SimBuilder::apply_blocklist_scenario(sim_result, &scenario, &as_ip_map);
+        assert_eq!(actual.coalition_asns, vec![797]);
+        assert_eq!(actual.num_coalition_nodes, 2);
+        assert_eq!(actual.num_blocklisted_payments, 2);
+        assert_eq!(actual.num_blocked, 1);
+        assert_eq!(actual.num_circumvented, 1);
+        assert_eq!(actual.efficacy, 0.5);
+        assert_eq!(actual.circumvention_rate, 0.5);
+        assert_eq!(actual.sim_result.num_successful, 2);
+        assert_eq!(actual.sim_result.num_failed, 1);
+    }
+}
@@ -0,0 +1,166 @@
+use super::output::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Resamples drawn per rate unless another number is given
+pub static DEFAULT_BOOTSTRAP_RESAMPLES: usize = 1000;
+
+/// Percentile bootstrap 95% confidence interval of a rate, resampled over the payments
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfidenceInterval {
+    /// The rate in the attack's results, e.g. `perSimAccuracy/precision`
+    pub metric: String,
+    /// The rate as reported
+    pub estimate: f32,
+    pub lower: f32,
+    pub upper: f32,
+}
+
+impl ConfidenceInterval {
+    /// The 2.5th and 97.5th percentiles of the resampled rates
+    fn from_resamples(metric: &str, estimate: f32, mut resamples: Vec<f32>) -> Self {
+        resamples.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f32| {
+            resamples
+                .get((p * (resamples.len().saturating_sub(1)) as f32).round() as usize)
+                .copied()
+                .unwrap_or(estimate)
+        };
+        Self {
+            metric: metric.to_owned(),
+            estimate,
+            lower: percentile(0.025),
+            upper: percentile(0.975),
+        }
+    }
+}
+
+/// Draws as many payments as there are with replacement and counts them per outcome. Each payment
+/// falls into one outcome, so resampling the counts is the same as resampling the payments
+fn resample_counts<const N: usize>(counts: [usize; N], rng: &mut impl Rng) -> [usize; N] {
+    let total: usize = counts.iter().sum();
+    let mut resampled = [0; N];
+    for _ in 0..total {
+        let mut draw = rng.gen_range(0..total);
+        for (outcome, count) in counts.iter().enumerate() {
+            if draw < *count {
+                resampled[outcome] += 1;
+                break;
+            }
+            draw -= count;
+        }
+    }
+    resampled
+}
+
+impl AttackSim {
+    /// Bootstraps the success rate and the accuracy of the attack over its payments. Rates without
+    /// payments get no interval
+    pub fn bootstrap_intervals(&self, num_resamples: usize, seed: u64) -> Vec<ConfidenceInterval> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut intervals = vec![];
+        let (num_successful, num_failed) = self
+            .sim_results
+            .iter()
+            .fold((0, 0), |(s, f), r| (s + r.num_successful, f + r.num_failed));
+        if num_successful + num_failed > 0 {
+            let rate = |[s, f]: [usize; 2]| s as f32 / (s + f) as f32;
+            let resamples = (0..num_resamples)
+                .map(|_| rate(resample_counts([num_successful, num_failed], &mut rng)))
+                .collect();
+            intervals.push(ConfidenceInterval::from_resamples(
+                "successRate",
+                rate([num_successful, num_failed]),
+                resamples,
+            ));
+        }
+        if let Some(a) = &self.per_sim_accuracy {
+            let counts = [a.tpos, a.fpos, a.fneg, a.tneg];
+            let accuracy = |[tpos, fpos, fneg, tneg]: [usize; 4]| {
+                PerSimAccuracy {
+                    tpos,
+                    fpos,
+                    fneg,
+                    tneg,
+                    ..Default::default()
+                }
+                .finalise()
+            };
+            let resamples: Vec<PerSimAccuracy> = (0..num_resamples)
+                .map(|_| accuracy(resample_counts(counts, &mut rng)))
+                .collect();
+            let metrics: [(&str, f32, fn(&PerSimAccuracy) -> f32, usize); 3] = [
+                (
+                    "perSimAccuracy/precision",
+                    a.precision,
+                    |a| a.precision,
+                    a.tpos + a.fpos,
+                ),
+                (
+                    "perSimAccuracy/recall",
+                    a.recall,
+                    |a| a.recall,
+                    a.tpos + a.fneg,
+                ),
+                (
+                    "perSimAccuracy/f1",
+                    a.f1,
+                    |a| a.f1,
+                    a.tpos + a.fpos + a.fneg,
+                ),
+            ];
+            for (metric, estimate, value, num_payments) in metrics {
+                if num_payments > 0 {
+                    intervals.push(ConfidenceInterval::from_resamples(
+                        metric,
+                        estimate,
+                        resamples.iter().map(value).collect(),
+                    ));
+                }
+            }
+        }
+        intervals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bootstrap() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let resampled = resample_counts([3, 0, 7], &mut rng);
+        assert_eq!(resampled.iter().sum::<usize>(), 10);
+        assert_eq!(resampled[1], 0);
+
+        let attack_sim = AttackSim {
+            sim_results: vec![SimResult {
+                num_successful: 80,
+                num_failed: 20,
+                ..Default::default()
+            }],
+            per_sim_accuracy: Some(
+                PerSimAccuracy {
+                    tpos: 20,
+                    tneg: 80,
+                    ..Default::default()
+                }
+                .finalise(),
+            ),
+            ..Default::default()
+        };
+        let intervals = attack_sim.bootstrap_intervals(DEFAULT_BOOTSTRAP_RESAMPLES, 19);
+        assert_eq!(intervals, attack_sim.bootstrap_intervals(1000, 19));
+        let interval = |metric: &str| intervals.iter().find(|i| i.metric == metric).unwrap();
+        let success_rate = interval("successRate");
+        assert_eq!(success_rate.estimate, 0.8);
+        assert!(success_rate.lower < 0.8 && success_rate.lower > 0.6);
+        assert!(success_rate.upper > 0.8 && success_rate.upper < 0.95);
+        // a perfect precision stays perfect in every resample
+        let precision = interval("perSimAccuracy/precision");
+        assert_eq!((precision.lower, precision.upper), (1.0, 1.0));
+        assert!(AttackSim::default().bootstrap_intervals(10, 19).is_empty());
+    }
+}
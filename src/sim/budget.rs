@@ -0,0 +1,195 @@
+use super::{PerSimAccuracy, SimBuilder};
+use crate::{net::Asn, AsIpMap};
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::{payment::Payment, ID};
+#[cfg(test)]
+use std::println as info;
+
+/// Maximum shares of the payments it forwards the adversary drops unless others are given
+pub static DEFAULT_DROP_BUDGETS: [f32; 5] = [0.01, 0.05, 0.1, 0.25, 0.5];
+
+/// Censorship the AS achieves while dropping no more than a share of the payments its nodes
+/// forward, e.g. to stay below a plausibility threshold
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetCurve {
+    pub asn: String,
+    /// Successful payments of the baseline that traverse one of the AS's nodes
+    pub num_forwarded: usize,
+    pub points: Vec<BudgetPoint>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetPoint {
+    pub budget: f32,
+    pub num_dropped: usize,
+    /// Share of the baseline's successful payments the AS censors
+    pub censorship_rate: f32,
+    /// Dropped payments from or to the AS
+    pub accuracy: PerSimAccuracy,
+}
+
+impl SimBuilder {
+    /// Drops, per budget, the forwarded payments the AS is most confident are from or to it, up
+    /// to the budget's share of the forwarded payments
+    pub fn drop_budget_curve(
+        baseline: &simlib::SimResult,
+        asn: Asn,
+        asn_nodes: &[ID],
+        as_ip_map: &AsIpMap,
+        budgets: &[f32],
+    ) -> BudgetCurve {
+        let mut ranked: Vec<(f32, usize, bool)> = baseline
+            .successful_payments
+            .iter()
+            .filter_map(|p| {
+                Self::intra_as_confidence(p, asn, asn_nodes, as_ip_map).map(|confidence| {
+                    (
+                        confidence,
+                        p.payment_id,
+                        Self::payment_from_or_to_asn(p, asn, as_ip_map),
+                    )
+                })
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+        let num_targets = baseline
+            .successful_payments
+            .iter()
+            .filter(|p| Self::payment_from_or_to_asn(p, asn, as_ip_map))
+            .count();
+        let num_drops =
+            |budget: f32| ((budget * ranked.len() as f32).floor() as usize).min(ranked.len());
+        let points = budgets
+            .iter()
+            .map(|budget| {
+                let num_dropped = num_drops(*budget);
+                let tpos = ranked[..num_dropped].iter().filter(|r| r.2).count();
+                let accuracy = PerSimAccuracy {
+                    tpos,
+                    fpos: num_dropped - tpos,
+                    fneg: num_targets - tpos,
+                    tneg: baseline.num_succesful - num_targets - (num_dropped - tpos),
+                    ..Default::default()
+                }
+                .finalise();
+                BudgetPoint {
+                    budget: *budget,
+                    num_dropped,
+                    censorship_rate: if baseline.num_succesful > 0 {
+                        num_dropped as f32 / baseline.num_succesful as f32
+                    } else {
+                        0.0
+                    },
+                    accuracy,
+                }
+            })
+            .collect();
+        info!(
+            "AS {} forwards {} payments and drops up to {} within budget.",
+            asn,
+            ranked.len(),
+            budgets
+                .iter()
+                .map(|b| num_drops(*b))
+                .max()
+                .unwrap_or_default()
+        );
+        BudgetCurve {
+            asn: asn.to_string(),
+            num_forwarded: ranked.len(),
+            points,
+        }
+    }
+
+    /// Share of the neighbours the AS's nodes observe on the payment's paths that are in the AS.
+    /// A node that sends or receives a part knows the payment is from or to the AS. None if the
+    /// payment avoids the AS
+    fn intra_as_confidence(
+        payment: &Payment,
+        asn: Asn,
+        asn_nodes: &[ID],
+        as_ip_map: &AsIpMap,
+    ) -> Option<f32> {
+        let mut confidence: Option<f32> = None;
+        for path in payment.used_paths.iter() {
+            let hops = path.path.get_involved_nodes();
+            for (i, hop) in hops.iter().enumerate() {
+                if !asn_nodes.contains(hop) {
+                    continue;
+                }
+                let hop_confidence = if i == 0 || i == hops.len() - 1 {
+                    1.0
+                } else {
+                    [&hops[i - 1], &hops[i + 1]]
+                        .into_iter()
+                        .filter(|n| as_ip_map.asn_of(n) == Some(asn))
+                        .count() as f32
+                        / 2.0
+                };
+                confidence = Some(confidence.map_or(hop_confidence, |c| c.max(hop_confidence)));
+            }
+        }
+        confidence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simlib::CandidatePath;
+    use std::collections::{HashMap, VecDeque};
+
+    #[test]
+    fn drops_most_confident_first() {
+        let as_ip_map = AsIpMap::from(HashMap::from([
+            (1, vec![String::from("a"), String::from("b")]),
+            (
+                2,
+                vec![String::from("x"), String::from("y"), String::from("z")],
+            ),
+        ]));
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let baseline = simlib::SimResult {
+            num_succesful: 4,
+            total_num: 4,
+            successful_payments: vec![
+                // transit between two other ASs
+                payment(0, vec!["x", "a", "y"]),
+                // to the AS, whose recipient knows it although the forwarding node is unsure
+                payment(1, vec!["x", "a", "b"]),
+                // from the AS
+                payment(2, vec!["a", "x"]),
+                payment(3, vec!["x", "z"]),
+            ],
+            ..Default::default()
+        };
+        let nodes = as_ip_map.as_to_nodes()[&1].clone();
+        let actual =
+            SimBuilder::drop_budget_curve(&baseline, 1, &nodes, &as_ip_map, &[0.0, 0.34, 1.0]);
+        assert_eq!(actual.num_forwarded, 3);
+        let dropped: Vec<usize> = actual.points.iter().map(|p| p.num_dropped).collect();
+        assert_eq!(dropped, vec![0, 1, 3]);
+        // the sender and recipient are certain, so the budget is spent on them first
+        assert_eq!(actual.points[1].accuracy.tpos, 1);
+        assert_eq!(actual.points[1].accuracy.precision, 1.0);
+        assert_eq!(actual.points[1].censorship_rate, 0.25);
+        assert_eq!(actual.points[2].accuracy.fpos, 1);
+        assert_eq!(actual.points[2].accuracy.tneg, 1);
+    }
+}
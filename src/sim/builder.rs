@@ -1,12 +1,14 @@
+use super::{AmountModel, AmountProgress, AsOverlap};
 use crate::{
     net::{AsIpMap, Asn},
-    AsSelectionStrategy,
+    AsSelectionStrategy, OverlapPolicy,
 };
 #[cfg(not(test))]
-use log::info;
-use simlib::{graph::Graph, payment::Payment, ID};
+use log::{info, warn};
+use simlib::{graph::Graph, payment::Payment, PaymentParts, RoutingMetric, ID};
+use std::{collections::HashSet, sync::Arc};
 #[cfg(test)]
-use std::println as info;
+use std::{println as info, println as warn};
 
 pub struct SimBuilder {
     pub(crate) run: u64,
@@ -16,6 +18,37 @@ pub struct SimBuilder {
     /// The top-n adversarial ASs
     pub(crate) num_adv_as: usize,
     pub(crate) as_selection: AsSelectionStrategy,
+    /// How nodes shared by several adversarial ASs are handled
+    pub(crate) overlap_policy: OverlapPolicy,
+    /// Explicitly selected adversarial ASs which replace the top-n selection if set
+    pub(crate) target_asns: Option<Vec<Asn>>,
+    pub(crate) progress: Option<AmountProgress>,
+    /// The result of the last call to `simulate`, shared with the callers instead of copied
+    pub(crate) baseline: Option<Arc<simlib::SimResult>>,
+    /// Draws the amount of each payment instead of using `amt_msat` if set
+    pub(crate) amount_model: Option<AmountModel>,
+    /// How the senders pick their paths
+    pub(crate) routing_metric: RoutingMetric,
+    /// Whether the senders split payments into several parts
+    pub(crate) payment_parts: PaymentParts,
+}
+
+/// Parses a routing metric given on the command line, i.e., minfee or maxprob
+pub fn parse_routing_metric(s: &str) -> Result<RoutingMetric, String> {
+    match s.to_lowercase().as_str() {
+        "minfee" => Ok(RoutingMetric::MinFee),
+        "maxprob" => Ok(RoutingMetric::MaxProb),
+        _ => Err(format!("Unknown routing metric {}", s)),
+    }
+}
+
+/// Parses how payments are split given on the command line, i.e., single or split
+pub fn parse_payment_parts(s: &str) -> Result<PaymentParts, String> {
+    match s.to_lowercase().as_str() {
+        "single" => Ok(PaymentParts::Single),
+        "split" => Ok(PaymentParts::Split),
+        _ => Err(format!("Unknown payment parts {}", s)),
+    }
 }
 
 impl SimBuilder {
@@ -32,10 +65,74 @@ impl SimBuilder {
             amt_msat,
             num_adv_as,
             as_selection,
+            overlap_policy: OverlapPolicy::default(),
+            target_asns: None,
+            progress: None,
+            baseline: None,
+            amount_model: None,
+            routing_metric: RoutingMetric::MinFee,
+            payment_parts: PaymentParts::Split,
         }
     }
 
-    pub fn get_adverserial_asns(&self, as_ip_map: &AsIpMap) -> Vec<(Asn, Vec<ID>)> {
+    /// The seed of the simulation
+    pub fn run(&self) -> u64 {
+        self.run
+    }
+
+    pub fn with_overlap_policy(mut self, overlap_policy: OverlapPolicy) -> Self {
+        self.overlap_policy = overlap_policy;
+        self
+    }
+
+    /// Simulate the given ASs instead of the top-n
+    pub fn with_target_asns(mut self, target_asns: Vec<Asn>) -> Self {
+        self.target_asns = Some(target_asns);
+        self
+    }
+
+    /// Draw the amount of each payment from the model, seeded by the run. Fixed amounts are ignored
+    /// as they are simulated one builder per amount
+    pub fn with_amount_model(mut self, amount_model: AmountModel) -> Self {
+        self.amount_model = Some(amount_model);
+        self
+    }
+
+    /// Route the payments by the metric instead of the minimum fee
+    pub fn with_routing_metric(mut self, routing_metric: RoutingMetric) -> Self {
+        self.routing_metric = routing_metric;
+        self
+    }
+
+    /// Send the payments in a single part or split them, as by default
+    pub fn with_payment_parts(mut self, payment_parts: PaymentParts) -> Self {
+        self.payment_parts = payment_parts;
+        self
+    }
+
+    /// Report the progress of the simulation to the given bar
+    pub fn with_progress(mut self, progress: AmountProgress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn progress(&self) -> Option<&AmountProgress> {
+        self.progress.as_ref()
+    }
+
+    /// Returns the adversarial ASs with their nodes or an error if an explicitly selected AS has no
+    /// nodes in the graph
+    pub fn get_adverserial_asns(&self, as_ip_map: &AsIpMap) -> Result<Vec<(Asn, Vec<ID>)>, String> {
+        self.get_adverserial_asns_with_overlap(as_ip_map)
+            .map(|(adversaries, _)| adversaries)
+    }
+
+    /// As `get_adverserial_asns`, along with the nodes the ASs shared before the overlap policy
+    /// was applied
+    pub fn get_adverserial_asns_with_overlap(
+        &self,
+        as_ip_map: &AsIpMap,
+    ) -> Result<(Vec<(Asn, Vec<ID>)>, Vec<AsOverlap>), String> {
         let nodes = self.graph.get_nodes();
         let nodes_wo_address = nodes
             .iter()
@@ -46,15 +143,82 @@ impl SimBuilder {
             "{}% of nodes without a network address",
             (nodes_wo_address / nodes.len() as f32) * 100.0
         );
-        let num_adv_as = std::cmp::min(self.num_adv_as, as_ip_map.as_to_nodes.len());
-        info!(
-            "Simulating {} {:?} ASs as adversaries.",
-            num_adv_as, self.as_selection
-        );
-        match self.as_selection {
-            AsSelectionStrategy::MaxNodes => as_ip_map.top_n_asns_nodes(num_adv_as, &self.graph),
-            AsSelectionStrategy::MaxChannels => {
-                as_ip_map.top_n_asns_channels(num_adv_as, &self.graph)
+        let adversaries = if let Some(target_asns) = &self.target_asns {
+            info!("Simulating ASs {:?} as adversaries.", target_asns);
+            as_ip_map.selected_asns(target_asns, &self.graph)?
+        } else {
+            let num_adv_as = std::cmp::min(self.num_adv_as, as_ip_map.as_to_nodes().len());
+            info!(
+                "Simulating {} {:?} ASs as adversaries.",
+                num_adv_as, self.as_selection
+            );
+            crate::analysis::top_n_asns(
+                as_ip_map,
+                &self.graph,
+                num_adv_as,
+                self.as_selection,
+                self.run,
+            )
+        };
+        let overlap = Self::adversary_overlap(&adversaries);
+        for o in overlap.iter() {
+            warn!(
+                "ASs {} and {} share {} nodes.",
+                o.first_asn, o.second_asn, o.num_shared_nodes
+            );
+        }
+        if overlap.is_empty() {
+            Ok((adversaries, overlap))
+        } else {
+            Ok((
+                Self::apply_overlap_policy(adversaries, self.overlap_policy),
+                overlap,
+            ))
+        }
+    }
+
+    /// Returns the number of shared nodes for every pair of adversarial ASs that have at least one
+    /// node in common
+    pub fn adversary_overlap(adversaries: &[(Asn, Vec<ID>)]) -> Vec<AsOverlap> {
+        let mut overlap = vec![];
+        for (i, (first_asn, first_nodes)) in adversaries.iter().enumerate() {
+            let first_nodes: HashSet<&ID> = first_nodes.iter().collect();
+            for (second_asn, second_nodes) in adversaries.iter().skip(i + 1) {
+                let num_shared_nodes = second_nodes
+                    .iter()
+                    .filter(|n| first_nodes.contains(n))
+                    .count();
+                if num_shared_nodes > 0 {
+                    overlap.push(AsOverlap {
+                        first_asn: *first_asn,
+                        second_asn: *second_asn,
+                        num_shared_nodes,
+                    });
+                }
+            }
+        }
+        overlap
+    }
+
+    /// Removes nodes that already belong to a higher-ranked AS if the policy requires it
+    fn apply_overlap_policy(
+        adversaries: Vec<(Asn, Vec<ID>)>,
+        policy: OverlapPolicy,
+    ) -> Vec<(Asn, Vec<ID>)> {
+        match policy {
+            OverlapPolicy::Keep => adversaries,
+            OverlapPolicy::FirstWins => {
+                let mut seen = HashSet::new();
+                adversaries
+                    .into_iter()
+                    .map(|(asn, nodes)| {
+                        let nodes = nodes
+                            .into_iter()
+                            .filter(|n| seen.insert(n.clone()))
+                            .collect();
+                        (asn, nodes)
+                    })
+                    .collect()
             }
         }
     }
@@ -76,7 +240,10 @@ mod tests {
     use super::*;
     use network_parser::GraphSource::*;
     use simlib::{graph::Graph, CandidatePath};
-    use std::{collections::VecDeque, path::Path};
+    use std::{
+        collections::{HashMap, VecDeque},
+        path::Path,
+    };
 
     #[test]
     fn init() {
@@ -104,11 +271,32 @@ mod tests {
             amt_msat: 1000,
             num_adv_as: 1,
             as_selection: AsSelectionStrategy::MaxChannels,
+            overlap_policy: OverlapPolicy::Keep,
+            target_asns: None,
+            progress: None,
+            baseline: None,
+            amount_model: None,
+            routing_metric: RoutingMetric::MinFee,
+            payment_parts: PaymentParts::Split,
         };
         assert_eq!(actual.graph.node_count(), expected.graph.node_count());
         assert_eq!(actual.amt_msat, expected.amt_msat);
         assert_eq!(actual.num_adv_as, expected.num_adv_as);
         assert_eq!(actual.as_selection, expected.as_selection);
+        assert_eq!(actual.overlap_policy, expected.overlap_policy);
+    }
+
+    #[test]
+    fn routing_options() {
+        assert!(matches!(
+            parse_routing_metric("MaxProb"),
+            Ok(RoutingMetric::MaxProb)
+        ));
+        assert!(matches!(
+            parse_payment_parts("single"),
+            Ok(PaymentParts::Single)
+        ));
+        assert!(parse_routing_metric("shortest").is_err());
     }
 
     #[test]
@@ -131,9 +319,16 @@ mod tests {
             num_adv_as,
             AsSelectionStrategy::MaxNodes,
         );
-        let actual = sim_builder.get_adverserial_asns(&AsIpMap::new(&graph, true));
+        let as_ip_map = AsIpMap::new(&graph, true);
+        let actual = sim_builder.get_adverserial_asns(&as_ip_map);
         let expected = vec![(24940, vec!["bob".to_owned(), "alice".to_owned()])];
-        assert_eq!(actual, expected);
+        assert_eq!(actual, Ok(expected));
+        let sim_builder = sim_builder.with_target_asns(vec![797]);
+        let actual = sim_builder.get_adverserial_asns(&as_ip_map);
+        let expected = vec![(797, vec!["chan".to_owned(), "dina".to_owned()])];
+        assert_eq!(actual, Ok(expected));
+        let sim_builder = sim_builder.with_target_asns(vec![16509]);
+        assert!(sim_builder.get_adverserial_asns(&as_ip_map).is_err());
     }
 
     #[test]
@@ -168,4 +363,55 @@ mod tests {
         let actual = SimBuilder::payment_involves_asn(&payment, &asn_nodes);
         assert!(actual);
     }
+
+    #[test]
+    fn overlapping_adversaries() {
+        let adversaries = vec![
+            (1, vec!["alice".to_owned(), "bob".to_owned()]),
+            (2, vec!["bob".to_owned(), "chan".to_owned()]),
+            (3, vec!["dina".to_owned()]),
+        ];
+        let actual = SimBuilder::adversary_overlap(&adversaries);
+        let expected = vec![AsOverlap {
+            first_asn: 1,
+            second_asn: 2,
+            num_shared_nodes: 1,
+        }];
+        assert_eq!(actual, expected);
+        let actual = SimBuilder::apply_overlap_policy(adversaries.clone(), OverlapPolicy::Keep);
+        assert_eq!(actual, adversaries);
+        let actual = SimBuilder::apply_overlap_policy(adversaries, OverlapPolicy::FirstWins);
+        let expected = vec![
+            (1, vec!["alice".to_owned(), "bob".to_owned()]),
+            (2, vec!["chan".to_owned()]),
+            (3, vec!["dina".to_owned()]),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn overlap_before_dedup() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::from(HashMap::from([
+            (1, vec!["alice".to_owned(), "bob".to_owned()]),
+            (2, vec!["bob".to_owned(), "chan".to_owned()]),
+        ]));
+        let builder = SimBuilder::new(19, &graph, 1000, 2, AsSelectionStrategy::MaxNodes)
+            .with_target_asns(vec![1, 2])
+            .with_overlap_policy(OverlapPolicy::FirstWins);
+        let (adversaries, overlap) = builder
+            .get_adverserial_asns_with_overlap(&as_ip_map)
+            .unwrap();
+        // the overlap is still reported once the shared node was removed
+        assert_eq!(overlap.len(), 1);
+        assert_eq!(overlap[0].num_shared_nodes, 1);
+        assert!(SimBuilder::adversary_overlap(&adversaries).is_empty());
+    }
 }
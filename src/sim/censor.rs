@@ -1,16 +1,18 @@
-use super::{output::*, SimBuilder};
-use crate::{net::Asn, AsIpMap};
-use rand::{seq::SliceRandom, thread_rng, Rng};
-use simlib::ID;
+use super::{output::*, DecisionRng, SimBuilder};
+use crate::{net::Asn, AsIpMap, AsNodeRatios, SimulatorError};
+use simlib::{payment::Payment, ID};
+use std::collections::{BTreeMap, BTreeSet};
 
 impl SimBuilder {
-    /// Uniformly select a ratio then generate a Boolean outcome for that
+    /// Each of the AS's nodes forwarding the payment drops it with the node's ratio, or the AS's
+    /// mean ratio if the node has none. The AS's senders and receivers do not drop payments
     pub(crate) fn apply_prob_drop_strategy(
         sim_result: simlib::SimResult,
-        ratios: &Vec<f32>,
+        ratios: &AsNodeRatios,
         asn_nodes: &[ID],
         asn: Asn,
         as_ip_map: &AsIpMap,
+        rng: &mut DecisionRng,
     ) -> (simlib::SimResult, Option<PerSimAccuracy>) {
         let mut updated_results = simlib::SimResult {
             num_failed: sim_result.num_failed,
@@ -20,52 +22,54 @@ impl SimBuilder {
             failed_payments: sim_result.failed_payments,
             ..Default::default()
         };
-        let (mut tpos, mut fpos, mut fneg) = (0, 0, 0);
-        let mut rng = thread_rng();
+        let mut accuracy = PerSimAccuracy::default();
         for mut p in sim_result.successful_payments {
-            let dest_asn =
-                crate::find_key_for_value(&as_ip_map.as_to_nodes, &p.dest).unwrap_or_default();
-            if Self::payment_involves_asn(&p, asn_nodes) {
+            let target = Self::payment_from_or_to_asn(&p, asn, as_ip_map);
+            let forwarders: BTreeSet<ID> = p
+                .used_paths
+                .iter()
+                .flat_map(|path| Self::intermediate_hops(&path.path))
+                .filter(|hop| asn_nodes.contains(hop))
+                .cloned()
+                .collect();
+            if !forwarders.is_empty() {
                 // only payments affected by the censor
-                if let Some(prob) = ratios.choose(&mut rng) {
-                    let payment_fate = rng.gen_bool(*prob as f64);
+                let ratio = ratios.drop_probability(forwarders.iter());
+                if let Some(payment_fate) = rng.prob_drop(&p, ratio) {
+                    accuracy.record(payment_fate, target);
                     if payment_fate {
                         // dropped
                         p.succeeded = false;
                         p.used_paths = vec![];
                         updated_results.num_failed += 1;
                         updated_results.failed_payments.push(p);
-                        if dest_asn == asn {
-                            tpos += 1;
-                        } else {
-                            fpos += 1;
-                        }
                     } else {
                         // succeeded
                         updated_results.num_succesful += 1;
                         updated_results.successful_payments.push(p);
-                        if dest_asn == asn {
-                            fneg += 1;
-                        }
                     }
                 } else {
                     // weird case but lets leave the payment as is
+                    accuracy.record(false, target);
                     updated_results.num_succesful += 1;
                     updated_results.successful_payments.push(p);
                 }
             } else {
                 // no choice to make here
+                accuracy.record(false, target);
                 updated_results.num_succesful += 1;
                 updated_results.successful_payments.push(p);
             }
         }
-        (updated_results, Some(PerSimAccuracy { tpos, fpos, fneg }))
+        (updated_results, Some(accuracy.finalise()))
     }
 
-    /// All packets involving the AS's nodes are dropped
+    /// All packets involving the AS's nodes are dropped. Payments from/to the AS are the target
     pub(crate) fn apply_all_dropped_strategy(
         sim_result: simlib::SimResult,
         asn_nodes: &[ID],
+        asn: Asn,
+        as_ip_map: &AsIpMap,
     ) -> (simlib::SimResult, Option<PerSimAccuracy>) {
         let mut updated_results = simlib::SimResult {
             num_failed: sim_result.num_failed,
@@ -75,20 +79,253 @@ impl SimBuilder {
             failed_payments: sim_result.failed_payments,
             ..Default::default()
         };
+        let mut accuracy = PerSimAccuracy::default();
         for mut p in sim_result.successful_payments {
+            let target = Self::payment_from_or_to_asn(&p, asn, as_ip_map);
             if Self::payment_involves_asn(&p, asn_nodes) {
                 // dropped
+                accuracy.record(true, target);
                 p.succeeded = false;
                 p.used_paths = vec![];
                 updated_results.num_failed += 1;
                 updated_results.failed_payments.push(p);
             } else {
                 // does not involve any AS node so leave as is
+                accuracy.record(false, target);
+                updated_results.num_succesful += 1;
+                updated_results.successful_payments.push(p);
+            }
+        }
+        (updated_results, Some(accuracy.finalise()))
+    }
+
+    /// Only the parts of a payment that involve the AS's nodes are dropped. Payments from/to the AS
+    /// are the target
+    pub(crate) fn apply_split_aware_drop_strategy(
+        sim_result: simlib::SimResult,
+        asn_nodes: &[ID],
+        asn: Asn,
+        as_ip_map: &AsIpMap,
+    ) -> (simlib::SimResult, PerSimAccuracy, SplitCensorship) {
+        let mut updated_results = simlib::SimResult {
+            num_failed: sim_result.num_failed,
+            num_succesful: 0,
+            total_num: sim_result.total_num,
+            successful_payments: vec![],
+            failed_payments: sim_result.failed_payments,
+            ..Default::default()
+        };
+        let mut split_censorship = SplitCensorship::default();
+        let mut accuracy = PerSimAccuracy::default();
+        for mut p in sim_result.successful_payments {
+            let target = Self::payment_from_or_to_asn(&p, asn, as_ip_map);
+            let num_parts = p.used_paths.len();
+            let remaining_parts: Vec<_> = p
+                .used_paths
+                .iter()
+                .filter(|path| {
+                    !path
+                        .path
+                        .get_involved_nodes()
+                        .iter()
+                        .any(|hop| asn_nodes.contains(hop))
+                })
+                .cloned()
+                .collect();
+            if remaining_parts.len() == num_parts {
+                // does not involve any AS node so leave as is
+                accuracy.record(false, target);
+                updated_results.num_succesful += 1;
+                updated_results.successful_payments.push(p);
+                continue;
+            }
+            split_censorship.num_affected_payments += 1;
+            split_censorship.num_dropped_parts += num_parts - remaining_parts.len();
+            let remaining_amount: usize = remaining_parts.iter().map(|path| path.amount).sum();
+            if !remaining_parts.is_empty() && remaining_amount >= p.amount {
+                // the parts avoiding the AS suffice
+                accuracy.record(false, target);
+                p.used_paths = remaining_parts;
+                split_censorship.num_surviving_payments += 1;
+                updated_results.num_succesful += 1;
+                updated_results.successful_payments.push(p);
+            } else {
+                accuracy.record(true, target);
+                p.succeeded = false;
+                p.used_paths = vec![];
+                updated_results.num_failed += 1;
+                updated_results.failed_payments.push(p);
+            }
+        }
+        (updated_results, accuracy.finalise(), split_censorship)
+    }
+
+    /// Payments with a part that traverses the AS and carries an amount past the threshold are
+    /// dropped. Payments whose total amount is past the threshold are the target
+    pub(crate) fn apply_amount_threshold_strategy(
+        sim_result: simlib::SimResult,
+        asn_nodes: &[ID],
+        threshold_msat: usize,
+        above: bool,
+    ) -> (simlib::SimResult, PerSimAccuracy, Vec<AmountBucket>) {
+        let mut updated_results = simlib::SimResult {
+            num_failed: sim_result.num_failed,
+            num_succesful: 0,
+            total_num: sim_result.total_num,
+            successful_payments: vec![],
+            failed_payments: sim_result.failed_payments,
+            ..Default::default()
+        };
+        let past_threshold = |amount: usize| {
+            if above {
+                amount > threshold_msat
+            } else {
+                amount < threshold_msat
+            }
+        };
+        let mut buckets: BTreeMap<usize, AmountBucket> = BTreeMap::new();
+        let mut accuracy = PerSimAccuracy::default();
+        for mut p in sim_result.successful_payments {
+            let target = past_threshold(p.amount);
+            let lower_sat = AmountBucket::lower_sat(p.amount);
+            let bucket = buckets.entry(lower_sat).or_insert_with(|| AmountBucket {
+                lower_sat,
+                ..Default::default()
+            });
+            bucket.num_payments += 1;
+            let observed_parts: Vec<usize> = p
+                .used_paths
+                .iter()
+                .filter(|path| {
+                    path.path
+                        .get_involved_nodes()
+                        .iter()
+                        .any(|hop| asn_nodes.contains(hop))
+                })
+                .map(|path| path.amount)
+                .collect();
+            if !observed_parts.is_empty() {
+                bucket.num_observed += 1;
+            }
+            if observed_parts.into_iter().any(past_threshold) {
+                accuracy.record(true, target);
+                bucket.num_dropped += 1;
+                if !target {
+                    bucket.num_collateral += 1;
+                }
+                p.succeeded = false;
+                p.used_paths = vec![];
+                updated_results.num_failed += 1;
+                updated_results.failed_payments.push(p);
+            } else {
+                accuracy.record(false, target);
+                updated_results.num_succesful += 1;
+                updated_results.successful_payments.push(p);
+            }
+        }
+        (
+            updated_results,
+            accuracy.finalise(),
+            AmountBucket::finalise(buckets),
+        )
+    }
+
+    /// Payments traversing the AS are dropped unless they look like probes. A sender disguises a
+    /// payment as a probe with a probability of `evasion_permille` and the AS detects the disguise
+    /// with a probability of `detection_permille`
+    pub(crate) fn apply_probe_filter_strategy(
+        sim_result: simlib::SimResult,
+        asn_nodes: &[ID],
+        probe_max_msat: usize,
+        (evasion_permille, detection_permille): (u16, u16),
+        rng: &mut DecisionRng,
+    ) -> (simlib::SimResult, ProbeFiltering) {
+        let mut updated_results = simlib::SimResult {
+            num_failed: sim_result.num_failed,
+            num_succesful: 0,
+            total_num: sim_result.total_num,
+            successful_payments: vec![],
+            failed_payments: sim_result.failed_payments,
+            ..Default::default()
+        };
+        let mut filtering = ProbeFiltering::default();
+        for mut p in sim_result.successful_payments {
+            let dropped = if Self::payment_involves_asn(&p, asn_nodes) {
+                filtering.num_observed += 1;
+                if p.amount <= probe_max_msat {
+                    filtering.num_low_value += 1;
+                    false
+                } else if rng.chance(evasion_permille) {
+                    filtering.num_disguised += 1;
+                    let detected = rng.chance(detection_permille);
+                    if detected {
+                        filtering.num_detected += 1;
+                    }
+                    detected
+                } else {
+                    true
+                }
+            } else {
+                false
+            };
+            if dropped {
+                filtering.num_dropped += 1;
+                p.succeeded = false;
+                p.used_paths = vec![];
+                updated_results.num_failed += 1;
+                updated_results.failed_payments.push(p);
+            } else {
                 updated_results.num_succesful += 1;
                 updated_results.successful_payments.push(p);
             }
         }
-        (updated_results, None)
+        (updated_results, filtering.finalise())
+    }
+
+    /// No packets are dropped but each of the AS's nodes forwarding along a path holds the HTLC
+    /// for `hold_time_secs`. A split payment completes once its slowest part completes
+    pub(crate) fn apply_delay_strategy(
+        sim_result: simlib::SimResult,
+        asn_nodes: &[ID],
+        hold_time_secs: u32,
+        timeout_secs: u32,
+    ) -> (simlib::SimResult, DelayImpact) {
+        let mut added_latencies = BTreeMap::new();
+        for p in sim_result.successful_payments.iter() {
+            let latency = Self::added_latency(p, asn_nodes, hold_time_secs);
+            if latency > 0 {
+                *added_latencies.entry(latency).or_default() += 1;
+            }
+        }
+        let delay_impact =
+            DelayImpact::from_latencies(&added_latencies, timeout_secs, sim_result.num_succesful);
+        (sim_result, delay_impact)
+    }
+
+    /// The time the payment is held by the AS on its slowest path. Senders and receivers do not
+    /// hold HTLCs
+    pub(crate) fn added_latency(payment: &Payment, asn_nodes: &[ID], hold_time_secs: u32) -> u32 {
+        payment
+            .used_paths
+            .iter()
+            .map(|path| {
+                let num_holds = Self::intermediate_hops(&path.path)
+                    .filter(|hop| asn_nodes.contains(hop))
+                    .count() as u32;
+                num_holds * hold_time_secs
+            })
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The nodes forwarding along the path, i.e., without its sender and receiver
+    pub(super) fn intermediate_hops(path: &simlib::Path) -> impl Iterator<Item = &ID> {
+        let num_forwarders = path.hops.len().saturating_sub(2);
+        path.hops
+            .iter()
+            .skip(1)
+            .take(num_forwarders)
+            .map(|(id, ..)| id)
     }
 
     /// All packets coming from/to asn are dropped
@@ -105,31 +342,37 @@ impl SimBuilder {
             failed_payments: sim_result.failed_payments,
             ..Default::default()
         };
+        let mut accuracy = PerSimAccuracy::default();
         for mut p in sim_result.successful_payments {
-            let src_asn =
-                crate::find_key_for_value(&as_ip_map.as_to_nodes, &p.dest).unwrap_or_default();
-            let dest_asn =
-                crate::find_key_for_value(&as_ip_map.as_to_nodes, &p.source).unwrap_or_default();
+            let src_asn = as_ip_map.asn_of(&p.dest).unwrap_or_default();
+            let dest_asn = as_ip_map.asn_of(&p.source).unwrap_or_default();
+            let target = src_asn == asn || dest_asn == asn;
             if src_asn == asn && dest_asn == asn {
+                accuracy.record(true, target);
                 p.succeeded = false;
                 p.used_paths = vec![];
                 updated_results.num_failed += 1;
                 updated_results.failed_payments.push(p);
             } else {
                 // does not involve any AS node so leave as is
+                accuracy.record(false, target);
                 updated_results.num_succesful += 1;
                 updated_results.successful_payments.push(p);
             }
         }
-        (updated_results, None)
+        (updated_results, Some(accuracy.finalise()))
     }
 
-    /// All packets leaving asn are dropped
+    /// All packets leaving asn are dropped. Fails if asn is not in the AS map
     pub(crate) fn apply_inter_as_drop_strategy(
         sim_result: simlib::SimResult,
         asn: u32,
         as_ip_map: &AsIpMap,
-    ) -> (simlib::SimResult, Option<PerSimAccuracy>) {
+    ) -> Result<(simlib::SimResult, Option<PerSimAccuracy>), SimulatorError> {
+        let as_nodes = as_ip_map
+            .as_to_nodes()
+            .get(&asn)
+            .ok_or(SimulatorError::UnknownAsn(asn))?;
         let mut updated_results = simlib::SimResult {
             num_failed: sim_result.num_failed,
             num_succesful: 0,
@@ -138,39 +381,93 @@ impl SimBuilder {
             failed_payments: sim_result.failed_payments,
             ..Default::default()
         };
-        let as_nodes = as_ip_map.as_to_nodes.get(&asn).unwrap();
+        let mut accuracy = PerSimAccuracy::default();
         for mut p in sim_result.successful_payments {
+            let target = Self::payment_from_or_to_asn(&p, asn, as_ip_map);
             if Self::payment_involves_asn(&p, as_nodes) {
-                let src_asn =
-                    crate::find_key_for_value(&as_ip_map.as_to_nodes, &p.dest).unwrap_or_default();
-                let dest_asn = crate::find_key_for_value(&as_ip_map.as_to_nodes, &p.source)
-                    .unwrap_or_default();
+                let src_asn = as_ip_map.asn_of(&p.dest).unwrap_or_default();
+                let dest_asn = as_ip_map.asn_of(&p.source).unwrap_or_default();
                 if src_asn != asn || dest_asn != asn {
+                    accuracy.record(true, target);
                     p.succeeded = false;
                     p.used_paths = vec![];
                     updated_results.num_failed += 1;
                     updated_results.failed_payments.push(p.clone());
                 } else {
                     // does not leave the AS so leave as is
+                    accuracy.record(false, target);
                     updated_results.num_succesful += 1;
                     updated_results.successful_payments.push(p);
                 }
             } else {
                 // does not involve any AS node so leave as is
+                accuracy.record(false, target);
+                updated_results.num_succesful += 1;
+                updated_results.successful_payments.push(p);
+            }
+        }
+        Ok((updated_results, Some(accuracy.finalise())))
+    }
+
+    /// Payments with a part routed over a channel between two of the AS's nodes are dropped.
+    /// Payments from/to the AS are the target
+    pub(crate) fn apply_intra_as_channel_strategy(
+        sim_result: simlib::SimResult,
+        asn_nodes: &[ID],
+        asn: Asn,
+        as_ip_map: &AsIpMap,
+    ) -> (simlib::SimResult, Option<PerSimAccuracy>) {
+        let mut updated_results = simlib::SimResult {
+            num_failed: sim_result.num_failed,
+            num_succesful: 0,
+            total_num: sim_result.total_num,
+            successful_payments: vec![],
+            failed_payments: sim_result.failed_payments,
+            ..Default::default()
+        };
+        let mut accuracy = PerSimAccuracy::default();
+        for mut p in sim_result.successful_payments {
+            let target = Self::payment_from_or_to_asn(&p, asn, as_ip_map);
+            let crosses_intra_as_channel = p.used_paths.iter().any(|path| {
+                path.path
+                    .get_involved_nodes()
+                    .windows(2)
+                    .any(|hops| asn_nodes.contains(&hops[0]) && asn_nodes.contains(&hops[1]))
+            });
+            if crosses_intra_as_channel {
+                accuracy.record(true, target);
+                p.succeeded = false;
+                p.used_paths = vec![];
+                updated_results.num_failed += 1;
+                updated_results.failed_payments.push(p);
+            } else {
+                // the AS does not see the payment between its own nodes so leave as is
+                accuracy.record(false, target);
                 updated_results.num_succesful += 1;
                 updated_results.successful_payments.push(p);
             }
         }
-        (updated_results, None)
+        (updated_results, Some(accuracy.finalise()))
+    }
+
+    /// Ground truth for the accuracy of most strategies: the sender or receiver belongs to the AS
+    pub(super) fn payment_from_or_to_asn(payment: &Payment, asn: Asn, as_ip_map: &AsIpMap) -> bool {
+        [&payment.source, &payment.dest]
+            .into_iter()
+            .any(|n| as_ip_map.asn_of(n).is_some_and(|a| a == asn))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Decisions;
     use network_parser::GraphSource::*;
-    use simlib::{graph::Graph, payment::Payment, CandidatePath};
-    use std::{collections::VecDeque, path::Path};
+    use simlib::{graph::Graph, CandidatePath};
+    use std::{
+        collections::{HashMap, VecDeque},
+        path::Path,
+    };
 
     // TODO: Check returned accuracy scores
     #[test]
@@ -183,7 +480,7 @@ mod tests {
             .unwrap(),
             Lnresearch,
         );
-        let ratios = vec![1.0];
+        let ratios = AsNodeRatios::new(HashMap::from([(String::from("alice"), 1.0)]));
         let asn_nodes = vec!["alice".to_owned()];
         let as_ip_map = AsIpMap::new(&graph, false);
         let asn = 24940;
@@ -217,6 +514,7 @@ mod tests {
             &asn_nodes,
             asn,
             &as_ip_map,
+            &mut Decisions::new(0).rng_for(asn),
         );
         assert_eq!(actual_sim_result.total_num, sim_result.total_num);
         assert_eq!(
@@ -234,13 +532,13 @@ mod tests {
         assert_eq!(actual_sim_result.num_failed, sim_result.num_failed);
 
         let mut successful_payment =
-            Payment::new(0, String::from("dina"), String::from("alice"), 1, None);
+            Payment::new(0, String::from("dina"), String::from("bob"), 1, None);
         successful_payment.succeeded = true;
-        let mut path = simlib::Path::new(String::from("dina"), String::from("alice"));
+        let mut path = simlib::Path::new(String::from("dina"), String::from("bob"));
         path.hops = VecDeque::from([
             ("dina".to_string(), 0, 0, "".to_string()),
-            ("chan".to_string(), 0, 0, "c".to_string()),
-            ("alice".to_string(), 0, 0, "".to_string()),
+            ("alice".to_string(), 0, 0, "a".to_string()),
+            ("bob".to_string(), 0, 0, "".to_string()),
         ]);
         successful_payment.used_paths = vec![CandidatePath::new_with_path(path)];
         let sim_result = simlib::SimResult {
@@ -263,6 +561,7 @@ mod tests {
             &asn_nodes,
             asn,
             &as_ip_map,
+            &mut Decisions::new(0).rng_for(asn),
         );
         assert_eq!(actual_sim_result.total_num, sim_result.total_num);
         assert_eq!(
@@ -276,13 +575,42 @@ mod tests {
             actual_sim_result.failed_payments.len()
         );
 
-        let ratios = vec![0.0]; // no additional failures
+        // the receiver does not forward the payment, so it cannot drop it
+        let mut successful_payment =
+            Payment::new(0, String::from("dina"), String::from("alice"), 1, None);
+        successful_payment.succeeded = true;
+        let mut path = simlib::Path::new(String::from("dina"), String::from("alice"));
+        path.hops = VecDeque::from([
+            ("dina".to_string(), 0, 0, "".to_string()),
+            ("chan".to_string(), 0, 0, "c".to_string()),
+            ("alice".to_string(), 0, 0, "".to_string()),
+        ]);
+        successful_payment.used_paths = vec![CandidatePath::new_with_path(path)];
+        let endpoint_result = simlib::SimResult {
+            num_succesful: 1,
+            total_num: 1,
+            successful_payments: vec![successful_payment],
+            ..Default::default()
+        };
+        let (actual_sim_result, accuracy) = SimBuilder::apply_prob_drop_strategy(
+            endpoint_result,
+            &ratios,
+            &asn_nodes,
+            asn,
+            &as_ip_map,
+            &mut Decisions::new(0).rng_for(asn),
+        );
+        assert_eq!(actual_sim_result.num_succesful, 1);
+        assert_eq!(accuracy.unwrap().fneg, 1);
+
+        let ratios = AsNodeRatios::new(HashMap::from([(String::from("alice"), 0.0)])); // no additional failures
         let (actual_sim_result, _) = SimBuilder::apply_prob_drop_strategy(
             sim_result.clone(),
             &ratios,
             &asn_nodes,
             asn,
             &as_ip_map,
+            &mut Decisions::new(0).rng_for(asn),
         );
         assert_eq!(actual_sim_result.total_num, sim_result.total_num);
         assert_eq!(
@@ -290,10 +618,49 @@ mod tests {
             actual_sim_result.num_succesful + actual_sim_result.num_failed
         );
         assert_eq!(actual_sim_result.num_failed, sim_result.num_failed);
+
+        // payments sent from the AS are targets as well
+        let mut successful_payment =
+            Payment::new(0, String::from("bob"), String::from("dina"), 1, None);
+        successful_payment.succeeded = true;
+        let mut path = simlib::Path::new(String::from("bob"), String::from("dina"));
+        path.hops = VecDeque::from([
+            ("bob".to_string(), 0, 0, "".to_string()),
+            ("chan".to_string(), 0, 0, "c".to_string()),
+            ("dina".to_string(), 0, 0, "".to_string()),
+        ]);
+        successful_payment.used_paths = vec![CandidatePath::new_with_path(path)];
+        let sim_result = simlib::SimResult {
+            num_succesful: 1,
+            total_num: 1,
+            successful_payments: vec![successful_payment],
+            ..Default::default()
+        };
+        let (_, accuracy) = SimBuilder::apply_prob_drop_strategy(
+            sim_result,
+            &ratios,
+            &asn_nodes,
+            asn,
+            &as_ip_map,
+            &mut Decisions::new(0).rng_for(asn),
+        );
+        let accuracy = accuracy.unwrap();
+        assert_eq!(accuracy.fneg, 1);
+        assert_eq!(accuracy.tneg, 0);
     }
 
     #[test]
     fn apply_all_drop() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let asn = 24940;
         let asn_nodes = vec!["alice".to_owned()];
         let mut successful_payment =
             Payment::new(0, String::from("dina"), String::from("bob"), 1, None);
@@ -319,8 +686,8 @@ mod tests {
             )],
             ..Default::default()
         };
-        let (actual_sim_result, _) =
-            SimBuilder::apply_all_dropped_strategy(sim_result.clone(), &asn_nodes);
+        let (actual_sim_result, actual_accuracy) =
+            SimBuilder::apply_all_dropped_strategy(sim_result.clone(), &asn_nodes, asn, &as_ip_map);
         assert_eq!(actual_sim_result.total_num, sim_result.total_num);
         assert_eq!(actual_sim_result.num_failed, sim_result.num_failed);
         assert_eq!(
@@ -335,6 +702,10 @@ mod tests {
             actual_sim_result.num_failed,
             actual_sim_result.failed_payments.len()
         );
+        // both payments go to bob in the AS but avoid alice
+        let actual_accuracy = actual_accuracy.unwrap();
+        assert_eq!(actual_accuracy.fneg, 2);
+        assert_eq!(actual_accuracy.recall, 0.0);
         let mut successful_payment =
             Payment::new(0, String::from("dina"), String::from("alice"), 1, None);
         successful_payment.succeeded = true;
@@ -360,7 +731,7 @@ mod tests {
             ..Default::default()
         };
         let (actual_sim_result, actual_accuracy) =
-            SimBuilder::apply_all_dropped_strategy(sim_result.clone(), &asn_nodes);
+            SimBuilder::apply_all_dropped_strategy(sim_result.clone(), &asn_nodes, asn, &as_ip_map);
         assert_eq!(actual_sim_result.total_num, sim_result.total_num);
         assert_eq!(
             actual_sim_result.total_num,
@@ -372,7 +743,11 @@ mod tests {
             actual_sim_result.num_failed,
             actual_sim_result.failed_payments.len()
         );
-        assert!(actual_accuracy.is_none());
+        let actual_accuracy = actual_accuracy.unwrap();
+        assert_eq!(actual_accuracy.tpos, 2);
+        assert_eq!(actual_accuracy.fpos, 0);
+        assert_eq!(actual_accuracy.precision, 1.0);
+        assert_eq!(actual_accuracy.f1, 1.0);
     }
 
     #[test]
@@ -422,8 +797,13 @@ mod tests {
         ]);
         successful_payment.used_paths = vec![CandidatePath::new_with_path(path)];
         sim_result.successful_payments.push(successful_payment);
-        let (actual_sim_result, _) =
+        let (actual_sim_result, actual_accuracy) =
             SimBuilder::apply_intra_as_drop_strategy(sim_result.clone(), asn, &as_ip_map);
+        // dina to bob is sent from the AS but not dropped
+        let actual_accuracy = actual_accuracy.unwrap();
+        assert_eq!(actual_accuracy.tpos, 1);
+        assert_eq!(actual_accuracy.fneg, 1);
+        assert_eq!(actual_accuracy.recall, 0.5);
         assert_eq!(actual_sim_result.total_num, sim_result.total_num);
         assert_eq!(actual_sim_result.num_succesful, 1);
         assert_eq!(actual_sim_result.num_failed, 2); // the initial one + dina to chan
@@ -500,8 +880,13 @@ mod tests {
         ]);
         successful_payment.used_paths = vec![CandidatePath::new_with_path(path)];
         sim_result.successful_payments.push(successful_payment);
-        let (actual_sim_result, _) =
-            SimBuilder::apply_inter_as_drop_strategy(sim_result.clone(), asn, &as_ip_map);
+        let (actual_sim_result, actual_accuracy) =
+            SimBuilder::apply_inter_as_drop_strategy(sim_result.clone(), asn, &as_ip_map).unwrap();
+        // bob to alice is the only payment not from/to the AS
+        let actual_accuracy = actual_accuracy.unwrap();
+        assert_eq!(actual_accuracy.tpos, 1);
+        assert_eq!(actual_accuracy.fneg, 1);
+        assert_eq!(actual_accuracy.tneg, 1);
         assert_eq!(actual_sim_result.total_num, sim_result.total_num);
         assert_eq!(actual_sim_result.num_succesful, 2); // dina to bob, bob to alice
         assert_eq!(actual_sim_result.num_failed, 2);
@@ -519,9 +904,354 @@ mod tests {
         );
         let asn = 24940;
         let (actual_sim_result, _) =
-            SimBuilder::apply_inter_as_drop_strategy(sim_result.clone(), asn, &as_ip_map);
+            SimBuilder::apply_inter_as_drop_strategy(sim_result.clone(), asn, &as_ip_map).unwrap();
         assert_eq!(actual_sim_result.total_num, sim_result.total_num);
         assert_eq!(actual_sim_result.num_succesful, 2);
         assert_eq!(actual_sim_result.num_failed, 2); // dina to bob
+        assert!(matches!(
+            SimBuilder::apply_inter_as_drop_strategy(sim_result, 16509, &as_ip_map),
+            Err(SimulatorError::UnknownAsn(16509))
+        ));
+    }
+
+    #[test]
+    fn prob_drop_is_reproducible() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        // only chan counts as the AS's node on the path
+        let ratios = AsNodeRatios::new(HashMap::from([
+            (String::from("chan"), 0.5),
+            (String::from("dina"), 0.9),
+        ]));
+        let asn_nodes = vec!["chan".to_owned()];
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let asn = 797;
+        let mut successful_payment =
+            Payment::new(0, String::from("dina"), String::from("bob"), 1, None);
+        let mut path = simlib::Path::new(String::from("dina"), String::from("bob"));
+        path.hops = VecDeque::from([
+            ("dina".to_string(), 0, 0, "".to_string()),
+            ("chan".to_string(), 0, 0, "c".to_string()),
+            ("bob".to_string(), 0, 0, "".to_string()),
+        ]);
+        successful_payment.succeeded = true;
+        successful_payment.used_paths = vec![CandidatePath::new_with_path(path)];
+        let sim_result = simlib::SimResult {
+            num_succesful: 20,
+            total_num: 20,
+            successful_payments: vec![successful_payment; 20],
+            ..Default::default()
+        };
+        let outcome = |seed| {
+            let (result, accuracy) = SimBuilder::apply_prob_drop_strategy(
+                sim_result.clone(),
+                &ratios,
+                &asn_nodes,
+                asn,
+                &as_ip_map,
+                &mut Decisions::new(seed).rng_for(asn),
+            );
+            (result.num_failed, accuracy)
+        };
+        assert_eq!(outcome(19), outcome(19));
+    }
+
+    #[test]
+    fn apply_split_aware_drop() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let asn_nodes = vec!["alice".to_owned()];
+        let mut payment = Payment::new(0, String::from("dina"), String::from("bob"), 2, None);
+        payment.succeeded = true;
+        let mut path = simlib::Path::new(String::from("dina"), String::from("bob"));
+        path.hops = VecDeque::from([
+            ("dina".to_string(), 0, 0, "".to_string()),
+            ("chan".to_string(), 0, 0, "c".to_string()),
+            ("bob".to_string(), 0, 0, "".to_string()),
+        ]);
+        let mut clean_part = CandidatePath::new_with_path(path);
+        clean_part.amount = 1;
+        let mut path = simlib::Path::new(String::from("dina"), String::from("bob"));
+        path.hops = VecDeque::from([
+            ("dina".to_string(), 0, 0, "".to_string()),
+            ("alice".to_string(), 0, 0, "c".to_string()),
+            ("bob".to_string(), 0, 0, "".to_string()),
+        ]);
+        let mut censored_part = CandidatePath::new_with_path(path);
+        censored_part.amount = 1;
+        // one part is dropped and the other cannot carry the amount
+        payment.used_paths = vec![clean_part.clone(), censored_part.clone()];
+        let mut untouched = payment.clone();
+        untouched.used_paths = vec![clean_part.clone()];
+        untouched.amount = 1;
+        // the remaining part carries the full amount
+        let mut surviving = payment.clone();
+        clean_part.amount = 2;
+        surviving.used_paths = vec![clean_part, censored_part];
+        let sim_result = simlib::SimResult {
+            num_succesful: 3,
+            num_failed: 0,
+            total_num: 3,
+            successful_payments: vec![payment, untouched, surviving],
+            ..Default::default()
+        };
+        let (actual_sim_result, _, actual_split) = SimBuilder::apply_split_aware_drop_strategy(
+            sim_result.clone(),
+            &asn_nodes,
+            24940,
+            &as_ip_map,
+        );
+        assert_eq!(actual_sim_result.total_num, sim_result.total_num);
+        assert_eq!(actual_sim_result.num_succesful, 2);
+        assert_eq!(actual_sim_result.num_failed, 1);
+        assert_eq!(
+            actual_sim_result.num_succesful,
+            actual_sim_result.successful_payments.len()
+        );
+        assert_eq!(actual_sim_result.successful_payments[1].used_paths.len(), 1);
+        let expected = SplitCensorship {
+            num_affected_payments: 2,
+            num_surviving_payments: 1,
+            num_dropped_parts: 2,
+        };
+        assert_eq!(actual_split, expected);
+    }
+
+    #[test]
+    fn apply_intra_as_channel() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let asn_nodes = vec!["chan".to_owned(), "dina".to_owned()];
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let sim_result = simlib::SimResult {
+            num_succesful: 3,
+            num_failed: 0,
+            total_num: 3,
+            successful_payments: vec![
+                payment(0, vec!["alice", "bob", "chan", "dina"]),
+                // bob-chan leaves the AS, so the AS cannot see the payment
+                payment(1, vec!["bob", "chan"]),
+                payment(2, vec!["dina", "chan"]),
+            ],
+            ..Default::default()
+        };
+        let (actual_sim_result, actual_accuracy) =
+            SimBuilder::apply_intra_as_channel_strategy(sim_result, &asn_nodes, 797, &as_ip_map);
+        assert_eq!(actual_sim_result.num_succesful, 1);
+        assert_eq!(actual_sim_result.successful_payments[0].payment_id, 1);
+        assert_eq!(actual_sim_result.num_failed, 2);
+        let actual_accuracy = actual_accuracy.unwrap();
+        assert_eq!(actual_accuracy.tpos, 2);
+        assert_eq!(actual_accuracy.fneg, 1);
+    }
+
+    #[test]
+    fn apply_amount_threshold() {
+        let asn_nodes = vec!["alice".to_owned()];
+        let part = |hops: Vec<&str>, amount| {
+            let mut path = simlib::Path::new(hops[0].to_string(), hops[2].to_string());
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            let mut part = CandidatePath::new_with_path(path);
+            part.amount = amount;
+            part
+        };
+        let payment = |id, amount, parts: Vec<CandidatePath>| {
+            let mut p = Payment::new(id, String::from("dina"), String::from("bob"), amount, None);
+            p.succeeded = true;
+            p.used_paths = parts;
+            p
+        };
+        let sim_result = simlib::SimResult {
+            num_succesful: 4,
+            total_num: 4,
+            successful_payments: vec![
+                payment(
+                    0,
+                    1000000,
+                    vec![part(vec!["dina", "alice", "bob"], 1000000)],
+                ),
+                // a large payment whose small part traverses the AS
+                payment(
+                    1,
+                    20000000,
+                    vec![
+                        part(vec!["dina", "chan", "bob"], 16000000),
+                        part(vec!["dina", "alice", "bob"], 4000000),
+                    ],
+                ),
+                payment(
+                    2,
+                    20000000,
+                    vec![part(vec!["dina", "alice", "bob"], 20000000)],
+                ),
+                payment(3, 2000000, vec![part(vec!["dina", "chan", "bob"], 2000000)]),
+            ],
+            ..Default::default()
+        };
+        // censor payments below 5000 sat
+        let (actual_sim_result, accuracy, actual) =
+            SimBuilder::apply_amount_threshold_strategy(sim_result, &asn_nodes, 5000000, false);
+        assert_eq!(actual_sim_result.num_succesful, 2);
+        assert_eq!(actual_sim_result.num_failed, 2);
+        assert_eq!((accuracy.tpos, accuracy.fpos), (1, 1));
+        let expected = vec![
+            AmountBucket {
+                lower_sat: 1000,
+                num_payments: 2,
+                num_observed: 1,
+                num_dropped: 1,
+                num_collateral: 0,
+                effectiveness: 1.0,
+            },
+            AmountBucket {
+                lower_sat: 10000,
+                num_payments: 2,
+                num_observed: 2,
+                num_dropped: 1,
+                num_collateral: 1,
+                effectiveness: 0.5,
+            },
+        ];
+        assert_eq!(actual, expected);
+        assert_eq!(AmountBucket::lower_sat(999), 0);
+    }
+
+    #[test]
+    fn apply_probe_filter() {
+        let asn_nodes = vec!["chan".to_owned()];
+        let payment = |id, amount, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), amount, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let sim_result = simlib::SimResult {
+            num_succesful: 3,
+            total_num: 3,
+            successful_payments: vec![
+                payment(0, 1000, vec!["dina", "chan", "bob"]),
+                payment(1, 5000000, vec!["dina", "chan", "bob"]),
+                payment(2, 5000000, vec!["dina", "bob"]),
+            ],
+            ..Default::default()
+        };
+        let decisions = Decisions::new(0);
+        let filter = |evasion, detection| {
+            SimBuilder::apply_probe_filter_strategy(
+                sim_result.clone(),
+                &asn_nodes,
+                1000,
+                (evasion, detection),
+                &mut decisions.rng_for(797),
+            )
+        };
+        // the low-value payment passes as a probe
+        let (actual_sim_result, actual) = filter(0, 0);
+        assert_eq!(actual_sim_result.num_failed, 1);
+        assert_eq!(actual_sim_result.failed_payments[0].payment_id, 1);
+        let expected = ProbeFiltering {
+            num_observed: 2,
+            num_low_value: 1,
+            num_disguised: 0,
+            num_detected: 0,
+            num_dropped: 1,
+            evasion_rate: 0.5,
+        };
+        assert_eq!(actual, expected);
+        // every disguise is detected
+        let (_, actual) = filter(1000, 1000);
+        assert_eq!((actual.num_disguised, actual.num_detected), (1, 1));
+        assert_eq!(actual.num_dropped, 1);
+        // no disguise is detected
+        let (actual_sim_result, actual) = filter(1000, 0);
+        assert_eq!(actual_sim_result.num_failed, 0);
+        assert_eq!(actual.evasion_rate, 1.0);
+    }
+
+    #[test]
+    fn apply_delay() {
+        let asn_nodes = vec!["alice".to_owned(), "chan".to_owned()];
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let sim_result = simlib::SimResult {
+            num_succesful: 4,
+            total_num: 4,
+            successful_payments: vec![
+                payment(0, vec!["dina", "bob"]),
+                payment(1, vec!["dina", "chan", "bob"]),
+                payment(2, vec!["dina", "chan", "alice", "bob"]),
+                payment(3, vec!["bob", "alice"]),
+            ],
+            ..Default::default()
+        };
+        let (actual_sim_result, actual) =
+            SimBuilder::apply_delay_strategy(sim_result.clone(), &asn_nodes, 30, 45);
+        // no payment fails
+        assert_eq!(actual_sim_result.num_succesful, sim_result.num_succesful);
+        assert_eq!(actual_sim_result.num_failed, 0);
+        // alice receives the last payment and thus holds nothing
+        let expected = DelayImpact {
+            num_delayed_payments: 2,
+            mean_added_latency_secs: 45.0,
+            median_added_latency_secs: 30,
+            p90_added_latency_secs: 30,
+            max_added_latency_secs: 60,
+            num_exceeding_timeout: 1,
+            timeout_rate: 0.25,
+        };
+        assert_eq!(actual, expected);
     }
 }
@@ -0,0 +1,271 @@
+use super::{output::*, SimBuilder};
+use crate::{net::Asn, PacketDropStrategy, SimulatorError};
+#[cfg(not(test))]
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+#[cfg(test)]
+use std::{println as info, println as warn};
+
+/// The results of one adversary's strategy as stored in the checkpoint
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AttackRecord<T> {
+    strategy: String,
+    asn: Asn,
+    attack_sim: T,
+    /// The flat fields of the payments per result, which are not part of the report
+    payment_fields: Vec<Vec<PaymentFields>>,
+}
+
+/// Persists the baseline of each amount and the results of each strategy and adversary as they
+/// complete, so that an interrupted run can be resumed with the same arguments
+pub struct Checkpoint {
+    dir: PathBuf,
+    /// Serialises the appends of adversaries completing in parallel
+    lock: Mutex<()>,
+}
+
+impl Checkpoint {
+    pub fn new(dir: PathBuf) -> Result<Self, SimulatorError> {
+        fs::create_dir_all(&dir)?;
+        info!("Checkpoints will be written to {}.", dir.display());
+        Ok(Self {
+            dir,
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    fn baseline_path(&self, run: u64, amt_sat: usize) -> PathBuf {
+        self.dir
+            .join(format!("baseline-run{}-{}sat.json", run, amt_sat))
+    }
+
+    fn attacks_path(&self, run: u64, amt_sat: usize) -> PathBuf {
+        self.dir
+            .join(format!("attacks-run{}-{}sat.ndjson", run, amt_sat))
+    }
+
+    /// Identifies the strategy in the checkpoint. Blacklists are identified by their name
+    pub(crate) fn strategy_key(strategy: &PacketDropStrategy) -> String {
+        format!("{:?}", strategy)
+    }
+
+    /// The baseline of the amount if it was stored before
+    pub(crate) fn baseline(
+        &self,
+        run: u64,
+        amt_sat: usize,
+    ) -> Result<Option<simlib::SimResult>, SimulatorError> {
+        let path = self.baseline_path(run, amt_sat);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let baseline = serde_json::from_reader(BufReader::new(File::open(&path)?))?;
+        info!(
+            "Resumed the baseline of {} sat from {}.",
+            amt_sat,
+            path.display()
+        );
+        Ok(Some(baseline))
+    }
+
+    /// Writes to a temporary file first so that an interrupted write leaves no partial baseline
+    pub(crate) fn save_baseline(
+        &self,
+        run: u64,
+        amt_sat: usize,
+        baseline: &simlib::SimResult,
+    ) -> Result<(), SimulatorError> {
+        let path = self.baseline_path(run, amt_sat);
+        let tmp_path = path.with_extension("json.tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer(&mut writer, baseline)?;
+        writer.flush()?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    /// The results of the given strategies' adversaries that completed before. A line cut off by
+    /// the interruption is skipped
+    pub(crate) fn completed_attacks(
+        &self,
+        run: u64,
+        amt_sat: usize,
+        strategies: &[PacketDropStrategy],
+    ) -> Result<HashMap<(String, Asn), AttackSim>, SimulatorError> {
+        let path = self.attacks_path(run, amt_sat);
+        let mut completed = HashMap::new();
+        if !path.exists() {
+            return Ok(completed);
+        }
+        let strategies: HashMap<String, PacketDropStrategy> = strategies
+            .iter()
+            .map(|s| (Self::strategy_key(s), s.clone()))
+            .collect();
+        for line in BufReader::new(File::open(&path)?).lines() {
+            let line = line?;
+            let record: AttackRecord<AttackSim> = match serde_json::from_str(&line) {
+                Ok(record) => record,
+                Err(e) => {
+                    warn!(
+                        "Skipping incomplete checkpoint in {}: {}",
+                        path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let Some(strategy) = strategies.get(&record.strategy) else {
+                continue;
+            };
+            let mut attack_sim = record.attack_sim;
+            for (sim_result, fields) in attack_sim.sim_results.iter_mut().zip(record.payment_fields)
+            {
+                for (payment, fields) in sim_result.payments.iter_mut().zip(fields) {
+                    payment.fields = fields;
+                    if let Some(reason) = payment.drop_reason.as_mut() {
+                        reason.strategy = strategy.clone();
+                    }
+                }
+            }
+            completed.insert((record.strategy, record.asn), attack_sim);
+        }
+        info!(
+            "Resumed {} completed attacks of {} sat from {}.",
+            completed.len(),
+            amt_sat,
+            path.display()
+        );
+        Ok(completed)
+    }
+
+    /// Appends the results of the adversary's strategy and flushes them to disk
+    pub(crate) fn save_attack(
+        &self,
+        (run, amt_sat): (u64, usize),
+        strategy: &PacketDropStrategy,
+        asn: Asn,
+        attack_sim: &AttackSim,
+    ) -> Result<(), SimulatorError> {
+        let record = AttackRecord {
+            strategy: Self::strategy_key(strategy),
+            asn,
+            attack_sim,
+            payment_fields: attack_sim
+                .sim_results
+                .iter()
+                .map(|r| r.payments.iter().map(|p| p.fields.clone()).collect())
+                .collect(),
+        };
+        let line = serde_json::to_string(&record)?;
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.attacks_path(run, amt_sat))?;
+        writeln!(file, "{}", line)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+impl SimBuilder {
+    /// Reads the baseline from the checkpoint if it was stored by an earlier run. Otherwise
+    /// simulates the pairs and stores the baseline
+    pub fn simulate_or_resume(
+        &mut self,
+        pairs: impl Iterator<Item = (ID, ID)> + Clone,
+        checkpoint: &Checkpoint,
+    ) -> Result<Arc<simlib::SimResult>, SimulatorError> {
+        let amt_sat = self.amt_msat / 1000;
+        if let Some(baseline) = checkpoint.baseline(self.run, amt_sat)? {
+            let baseline = Arc::new(baseline);
+            self.baseline = Some(baseline.clone());
+            return Ok(baseline);
+        }
+        let baseline = self.simulate(pairs);
+        checkpoint.save_baseline(self.run, amt_sat, &baseline)?;
+        Ok(baseline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AsIpMap, AsSelectionStrategy, Decisions};
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn resume_from_checkpoint() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let dir = TempDir::new().unwrap();
+        let checkpoint = Checkpoint::new(dir.path().to_path_buf()).unwrap();
+        let pairs = vec![(String::from("alice"), String::from("dina"))];
+        let mut builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let baseline = builder
+            .simulate_or_resume(pairs.clone().into_iter(), &checkpoint)
+            .unwrap();
+        // the pairs are not simulated again
+        let mut builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let resumed = builder
+            .simulate_or_resume(std::iter::empty(), &checkpoint)
+            .unwrap();
+        assert_eq!(resumed.total_num, baseline.total_num);
+        assert_eq!(resumed.num_succesful, baseline.num_succesful);
+
+        let nodes = as_ip_map.as_to_nodes()[&24940].clone();
+        let attack_sim = SimBuilder::per_asn_simulation(
+            (*baseline).clone(),
+            24940,
+            &nodes,
+            &PacketDropStrategy::All,
+            None,
+            &as_ip_map,
+            &Decisions::new(19),
+            None,
+        )
+        .unwrap();
+        checkpoint
+            .save_attack((19, 1), &PacketDropStrategy::All, 24940, &attack_sim)
+            .unwrap();
+        let completed = checkpoint
+            .completed_attacks(19, 1, &[PacketDropStrategy::All])
+            .unwrap();
+        let key = (Checkpoint::strategy_key(&PacketDropStrategy::All), 24940);
+        let actual = &completed[&key];
+        assert_eq!(actual.asn, attack_sim.asn);
+        assert_eq!(actual.sim_results, attack_sim.sim_results);
+        assert_eq!(actual.per_sim_accuracy, attack_sim.per_sim_accuracy);
+        // other strategies are simulated again
+        assert!(checkpoint
+            .completed_attacks(19, 1, &[PacketDropStrategy::IntraAs])
+            .unwrap()
+            .is_empty());
+    }
+}
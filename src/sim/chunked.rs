@@ -0,0 +1,353 @@
+use super::{
+    output::*, BlacklistCensorship, DecisionRng, Decisions, LocationCensorship, LocationCounts,
+    Observation, RateLimiting, SimBuilder,
+};
+use crate::{net::Asn, AsIpMap, AsNodeRatios, PacketDropStrategy, SimulatorError};
+use rayon::prelude::*;
+use simlib::ID;
+use std::collections::{BTreeMap, HashMap};
+
+/// Counters of a single adversary that are updated chunk by chunk
+#[derive(Default)]
+struct AttackCounters {
+    num_nodes: usize,
+    num_successful: usize,
+    num_failed: usize,
+    num_failed_baseline: usize,
+    accuracy: Option<PerSimAccuracy>,
+    split_censorship: Option<SplitCensorship>,
+    /// Number of delayed payments per added latency
+    added_latencies: BTreeMap<u32, usize>,
+    amount_buckets: Option<BTreeMap<usize, AmountBucket>>,
+    probe_filtering: Option<ProbeFiltering>,
+    blacklist_censorship: Option<BlacklistCensorship>,
+    rate_limiting: Option<RateLimiting>,
+    accused: HashMap<Option<Asn>, usize>,
+    baseline_fees: usize,
+    remaining_fees: usize,
+    observation: Observation,
+    locations: LocationCounts,
+}
+
+impl SimBuilder {
+    /// Applies the strategy of every adversary to the successful payments of the baseline,
+    /// `chunk_size` payments at a time and the adversaries in parallel. Only counters are kept
+    /// between chunks so memory does not grow with the number of adversaries. The results
+    /// therefore do not contain the individual payments
+    pub fn chunked_asn_simulation(
+        baseline_result: &simlib::SimResult,
+        adversaries: &[(Asn, Vec<ID>)],
+        strategy: &PacketDropStrategy,
+        ratios: &HashMap<Asn, AsNodeRatios>,
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+        chunk_size: usize,
+    ) -> Result<Vec<AttackSim>, SimulatorError> {
+        // same RNGs as per_asn_simulation so both yield identical results
+        let mut rngs: Vec<DecisionRng> = adversaries
+            .iter()
+            .map(|(asn, _)| decisions.rng_for(*asn))
+            .collect();
+        let mut counters: Vec<AttackCounters> = adversaries
+            .iter()
+            .map(|(_, nodes)| AttackCounters {
+                num_nodes: nodes.len(),
+                num_failed: baseline_result.num_failed,
+                num_failed_baseline: baseline_result.num_failed,
+                ..Default::default()
+            })
+            .collect();
+        for chunk in baseline_result
+            .successful_payments
+            .chunks(std::cmp::max(chunk_size, 1))
+        {
+            let chunk_result = simlib::SimResult {
+                num_succesful: chunk.len(),
+                total_num: chunk.len(),
+                successful_payments: chunk.to_vec(),
+                ..Default::default()
+            };
+            counters
+                .par_iter_mut()
+                .zip(rngs.par_iter_mut())
+                .zip(adversaries.par_iter())
+                .try_for_each(|((counters, rng), (asn, nodes))| {
+                    counters.update(
+                        &chunk_result,
+                        *asn,
+                        nodes,
+                        strategy,
+                        ratios.get(asn),
+                        as_ip_map,
+                        rng,
+                    )
+                })?;
+        }
+        Ok(adversaries
+            .iter()
+            .zip(counters)
+            .map(|((asn, _), counters)| counters.into_attack_sim(*asn, strategy))
+            .collect())
+    }
+}
+
+impl AttackCounters {
+    #[allow(clippy::too_many_arguments)]
+    fn update(
+        &mut self,
+        chunk: &simlib::SimResult,
+        asn: Asn,
+        nodes: &[ID],
+        strategy: &PacketDropStrategy,
+        ratios: Option<&AsNodeRatios>,
+        as_ip_map: &AsIpMap,
+        rng: &mut DecisionRng,
+    ) -> Result<(), SimulatorError> {
+        let blamed = if let PacketDropStrategy::Delay { hold_time_secs, .. } = *strategy {
+            for p in chunk.successful_payments.iter() {
+                let latency = SimBuilder::added_latency(p, nodes, hold_time_secs);
+                if latency > 0 {
+                    *self.added_latencies.entry(latency).or_default() += 1;
+                }
+            }
+            HashMap::default()
+        } else {
+            SimBuilder::blamed_asns(chunk, nodes, as_ip_map)
+        };
+        self.baseline_fees += SimBuilder::fees_earned(chunk, nodes);
+        let locations = SimBuilder::payment_locations(chunk, as_ip_map);
+        let outcome = SimBuilder::apply_drop_strategy(
+            chunk.clone(),
+            asn,
+            nodes,
+            strategy,
+            ratios,
+            as_ip_map,
+            rng,
+        )?;
+        for (accused_asn, num_payments) in SimBuilder::accused_asns(&blamed, &outcome.result) {
+            *self.accused.entry(accused_asn).or_default() += num_payments;
+        }
+        self.remaining_fees += SimBuilder::fees_earned(&outcome.result, nodes);
+        LocationCensorship::count(&mut self.locations, &locations, &outcome.result);
+        self.observation.merge(&outcome.observation);
+        self.num_nodes = outcome.num_nodes;
+        self.num_successful += outcome.result.num_succesful;
+        self.num_failed += outcome.result.num_failed;
+        if let Some(accuracy) = outcome.per_sim_accuracy {
+            self.accuracy
+                .get_or_insert_with(Default::default)
+                .merge(&accuracy);
+        }
+        if let Some(amount_buckets) = outcome.amount_buckets {
+            AmountBucket::merge(
+                self.amount_buckets.get_or_insert_with(Default::default),
+                &amount_buckets,
+            );
+        }
+        if let Some(probe_filtering) = outcome.probe_filtering {
+            self.probe_filtering
+                .get_or_insert_with(Default::default)
+                .merge(&probe_filtering);
+        }
+        if let Some(blacklist_censorship) = outcome.blacklist_censorship {
+            self.blacklist_censorship
+                .get_or_insert_with(Default::default)
+                .merge(&blacklist_censorship);
+        }
+        if let Some(rate_limiting) = outcome.rate_limiting {
+            self.rate_limiting
+                .get_or_insert_with(Default::default)
+                .merge(&rate_limiting);
+        }
+        if let Some(split_censorship) = outcome.split_censorship {
+            self.split_censorship
+                .get_or_insert_with(Default::default)
+                .merge(&split_censorship);
+        }
+        Ok(())
+    }
+
+    fn into_attack_sim(self, asn: Asn, strategy: &PacketDropStrategy) -> AttackSim {
+        let (delay_impact, misattribution) = match *strategy {
+            PacketDropStrategy::Delay { timeout_secs, .. } => (
+                Some(DelayImpact::from_latencies(
+                    &self.added_latencies,
+                    timeout_secs,
+                    self.num_successful,
+                )),
+                None,
+            ),
+            _ => (None, Some(Misattribution::from_accused(self.accused, asn))),
+        };
+        AttackSim {
+            asn: asn.to_string(),
+            sim_results: vec![SimResult {
+                num_nodes_under_attack: self.num_nodes,
+                num_successful: self.num_successful,
+                num_failed: self.num_failed,
+                num_failed_baseline: self.num_failed_baseline,
+                num_censored: self.num_failed - self.num_failed_baseline,
+                num_censored_then_reroute_failed: 0,
+                payments: vec![],
+            }],
+            per_sim_accuracy: self.accuracy.map(PerSimAccuracy::finalise),
+            split_censorship: self.split_censorship,
+            delay_impact,
+            amount_buckets: self.amount_buckets.map(AmountBucket::finalise),
+            probe_filtering: self.probe_filtering.map(ProbeFiltering::finalise),
+            blacklist_censorship: self.blacklist_censorship,
+            rate_limiting: match *strategy {
+                PacketDropStrategy::RateLimit { max_drops, .. } => {
+                    self.rate_limiting.map(|r| r.finalise(max_drops))
+                }
+                _ => None,
+            },
+            misattribution,
+            economic_impact: Some(EconomicImpact::new(self.baseline_fees, self.remaining_fees)),
+            observation: Some(self.observation),
+            location_censorship: LocationCensorship::top_pairs(self.locations),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NodeBlacklist;
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, payment::Payment, CandidatePath};
+    use std::{
+        collections::{BTreeSet, VecDeque},
+        path::Path,
+    };
+
+    #[test]
+    fn chunked_equals_per_asn() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let adversaries: Vec<(Asn, Vec<ID>)> = as_ip_map
+            .as_to_nodes()
+            .iter()
+            .map(|(asn, nodes)| (*asn, nodes.clone()))
+            .collect();
+        let ratios = as_ip_map.get_intra_as_node_ratios(&graph);
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let baseline_result = simlib::SimResult {
+            num_succesful: 5,
+            num_failed: 1,
+            total_num: 6,
+            successful_payments: vec![
+                payment(0, vec!["dina", "chan", "bob"]),
+                payment(1, vec!["dina", "chan"]),
+                payment(2, vec!["alice", "bob"]),
+                payment(3, vec!["dina", "chan", "alice"]),
+                payment(4, vec!["bob", "alice", "chan"]),
+            ],
+            failed_payments: vec![Payment::new(
+                5,
+                String::from("chan"),
+                String::from("bob"),
+                1,
+                None,
+            )],
+            ..Default::default()
+        };
+        for strategy in [
+            PacketDropStrategy::All,
+            PacketDropStrategy::IntraProbability,
+            PacketDropStrategy::IntraAs,
+            PacketDropStrategy::InterAs,
+            PacketDropStrategy::SplitAware,
+            PacketDropStrategy::IntraAsChannel,
+            PacketDropStrategy::Delay {
+                hold_time_secs: 30,
+                timeout_secs: 45,
+            },
+            PacketDropStrategy::AmountThreshold {
+                threshold_msat: 0,
+                above: true,
+            },
+            PacketDropStrategy::ProbeFilter {
+                probe_max_msat: 0,
+                evasion_permille: 500,
+                detection_permille: 500,
+            },
+            PacketDropStrategy::DestinationBlacklist {
+                blacklist: NodeBlacklist::new(
+                    String::from("sanctioned"),
+                    BTreeSet::from([String::from("bob")]),
+                ),
+            },
+            // the windows straddle the chunks
+            PacketDropStrategy::RateLimit {
+                max_drops: 1,
+                window_secs: 3,
+                interval_secs: 1,
+            },
+        ] {
+            let actual = SimBuilder::chunked_asn_simulation(
+                &baseline_result,
+                &adversaries,
+                &strategy,
+                &ratios,
+                &as_ip_map,
+                &Decisions::new(19),
+                2,
+            )
+            .unwrap();
+            assert_eq!(actual.len(), adversaries.len());
+            for ((asn, nodes), actual) in adversaries.iter().zip(actual) {
+                let expected = SimBuilder::per_asn_simulation(
+                    baseline_result.clone(),
+                    *asn,
+                    nodes,
+                    &strategy,
+                    ratios.get(asn),
+                    &as_ip_map,
+                    &Decisions::new(19),
+                    None,
+                )
+                .unwrap();
+                let counts = |sim: &AttackSim| {
+                    sim.sim_results
+                        .iter()
+                        .map(|r| (r.num_nodes_under_attack, r.num_successful, r.num_failed))
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(counts(&actual), counts(&expected));
+                assert!(actual.sim_results[0].payments.is_empty());
+                assert_eq!(actual.per_sim_accuracy, expected.per_sim_accuracy);
+                assert_eq!(actual.split_censorship, expected.split_censorship);
+                assert_eq!(actual.delay_impact, expected.delay_impact);
+                assert_eq!(actual.amount_buckets, expected.amount_buckets);
+                assert_eq!(actual.probe_filtering, expected.probe_filtering);
+                assert_eq!(actual.blacklist_censorship, expected.blacklist_censorship);
+                assert_eq!(actual.rate_limiting, expected.rate_limiting);
+                assert_eq!(actual.misattribution, expected.misattribution);
+                assert_eq!(actual.economic_impact, expected.economic_impact);
+                assert_eq!(actual.observation, expected.observation);
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use super::SimBuilder;
+use crate::net::Asn;
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+use std::collections::{BTreeSet, HashMap};
+#[cfg(test)]
+use std::println as info;
+
+/// Censorship coverage of every pair of the top-k ASs of the ranking colluding, each dropping every
+/// payment that traverses one of their nodes
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PairCoalitions {
+    pub num_payments: usize,
+    /// The ASs in the order of the ranking, indexing the rows and columns of `coverage`
+    pub asns: Vec<String>,
+    /// Share of the payments traversing at least one AS of the pair, that of the AS alone on the
+    /// diagonal
+    pub coverage: Vec<Vec<f32>>,
+    /// The pair with the highest coverage, ties going to the higher-ranked ASs
+    pub best: Option<PairCoverage>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PairCoverage {
+    pub first_asn: String,
+    pub second_asn: String,
+    pub num_covered: usize,
+    pub coverage: f32,
+    /// Payments the pair covers beyond the better of its two ASs alone
+    pub num_complementary: usize,
+}
+
+impl SimBuilder {
+    /// Counts the successful payments each AS and each pair of ASs of the ranking covers. Pairs are
+    /// counted by inclusion-exclusion over the ASs each payment traverses, so the baseline is
+    /// scanned once
+    pub fn pair_coalitions(
+        baseline: &simlib::SimResult,
+        ranking: &[(Asn, Vec<ID>)],
+    ) -> PairCoalitions {
+        let mut node_ranks: HashMap<&ID, Vec<usize>> = HashMap::new();
+        for (rank, (_, nodes)) in ranking.iter().enumerate() {
+            for node in nodes {
+                node_ranks.entry(node).or_default().push(rank);
+            }
+        }
+        let k = ranking.len();
+        let mut singles = vec![0; k];
+        // payments traversing both ASs, upper triangle only
+        let mut both = vec![vec![0; k]; k];
+        for payment in baseline.successful_payments.iter() {
+            let ranks: BTreeSet<usize> = payment
+                .used_paths
+                .iter()
+                .flat_map(|path| path.path.get_involved_nodes())
+                .flat_map(|n| node_ranks.get(&n).cloned().unwrap_or_default())
+                .collect();
+            let ranks: Vec<usize> = ranks.into_iter().collect();
+            for (i, first) in ranks.iter().enumerate() {
+                singles[*first] += 1;
+                for second in ranks[i + 1..].iter() {
+                    both[*first][*second] += 1;
+                }
+            }
+        }
+        let num_payments = baseline.successful_payments.len();
+        let share = |num_covered: usize| {
+            if num_payments > 0 {
+                num_covered as f32 / num_payments as f32
+            } else {
+                0.0
+            }
+        };
+        let mut coverage = vec![vec![0.0; k]; k];
+        let mut best: Option<PairCoverage> = None;
+        for first in 0..k {
+            coverage[first][first] = share(singles[first]);
+            for second in first + 1..k {
+                let num_covered = singles[first] + singles[second] - both[first][second];
+                coverage[first][second] = share(num_covered);
+                coverage[second][first] = share(num_covered);
+                if best.as_ref().map_or(true, |b| num_covered > b.num_covered) {
+                    best = Some(PairCoverage {
+                        first_asn: ranking[first].0.to_string(),
+                        second_asn: ranking[second].0.to_string(),
+                        num_covered,
+                        coverage: share(num_covered),
+                        num_complementary: num_covered - singles[first].max(singles[second]),
+                    });
+                }
+            }
+        }
+        if let Some(best) = &best {
+            info!(
+                "ASs {} and {} together cover {:.1}% of the payments.",
+                best.first_asn,
+                best.second_asn,
+                best.coverage * 100.0
+            );
+        }
+        PairCoalitions {
+            num_payments,
+            asns: ranking.iter().map(|(asn, _)| asn.to_string()).collect(),
+            coverage,
+            best,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simlib::{payment::Payment, CandidatePath};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn pairwise_coverage() {
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let baseline = simlib::SimResult {
+            num_succesful: 4,
+            total_num: 4,
+            successful_payments: vec![
+                payment(0, vec!["alice", "bob"]),
+                payment(1, vec!["chan", "dina"]),
+                payment(2, vec!["dina", "chan"]),
+                payment(3, vec!["bob", "chan"]),
+            ],
+            ..Default::default()
+        };
+        let ranking = vec![
+            (3, vec![String::from("chan"), String::from("dina")]),
+            (2, vec![String::from("bob")]),
+            (1, vec![String::from("alice")]),
+        ];
+        let actual = SimBuilder::pair_coalitions(&baseline, &ranking);
+        assert_eq!(actual.asns, vec!["3", "2", "1"]);
+        assert_eq!(
+            actual.coverage,
+            vec![
+                vec![0.75, 1.0, 1.0],
+                vec![1.0, 0.5, 0.5],
+                vec![1.0, 0.5, 0.25]
+            ]
+        );
+        let expected = PairCoverage {
+            first_asn: String::from("3"),
+            second_asn: String::from("2"),
+            num_covered: 4,
+            coverage: 1.0,
+            num_complementary: 1,
+        };
+        assert_eq!(actual.best, Some(expected));
+        let single = SimBuilder::pair_coalitions(&baseline, &ranking[..1]);
+        assert_eq!(single.best, None);
+    }
+}
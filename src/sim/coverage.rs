@@ -0,0 +1,167 @@
+use super::{Decisions, SimBuilder};
+use crate::{net::Asn, AsIpMap, PacketDropStrategy, SimulatorError};
+#[cfg(not(test))]
+use log::info;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+#[cfg(test)]
+use std::println as info;
+
+/// Shares of its nodes each adversary controls when the coverage is swept unless others are given
+pub static DEFAULT_COVERAGE_LEVELS: [f32; 5] = [0.1, 0.25, 0.5, 0.75, 1.0];
+
+/// How the censorship power of the AS degrades if it only controls some of its nodes
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageCurve {
+    pub asn: String,
+    pub strategy: PacketDropStrategy,
+    pub points: Vec<CoveragePoint>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CoveragePoint {
+    pub coverage: f32,
+    /// Nodes of the AS the censor controls
+    pub num_nodes: usize,
+    pub num_successful: usize,
+    /// Share of the baseline's successful payments the AS censors
+    pub censorship_rate: f32,
+}
+
+impl SimBuilder {
+    /// The nodes of the AS the censor controls, drawn from the seed. Lower coverages control a
+    /// subset of the nodes of higher ones
+    pub fn covered_nodes(asn: Asn, nodes: &[ID], coverage: f32, seed: u64) -> Vec<ID> {
+        let mut rng = StdRng::seed_from_u64(seed ^ asn as u64);
+        let mut covered = nodes.to_vec();
+        covered.shuffle(&mut rng);
+        covered.truncate(((coverage * nodes.len() as f32).round() as usize).min(nodes.len()));
+        covered
+    }
+
+    /// Restricts every adversary to the share of its nodes it controls
+    pub(crate) fn partial_coverage(
+        adversaries: Vec<(Asn, Vec<ID>)>,
+        coverage: f32,
+        seed: u64,
+    ) -> Vec<(Asn, Vec<ID>)> {
+        adversaries
+            .into_iter()
+            .map(|(asn, nodes)| (asn, Self::covered_nodes(asn, &nodes, coverage, seed)))
+            .collect()
+    }
+
+    /// Simulates the strategy of every adversary controlling each share of its nodes. The
+    /// decisions are drawn from the seed and not recorded
+    pub fn coverage_curves(
+        &self,
+        baseline: &simlib::SimResult,
+        adversaries: &[(Asn, Vec<ID>)],
+        strategy: &PacketDropStrategy,
+        levels: &[f32],
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+    ) -> Result<Vec<CoverageCurve>, SimulatorError> {
+        let intra_as_channel_ratios = if *strategy == PacketDropStrategy::IntraProbability {
+            as_ip_map.get_intra_as_node_ratios(&self.graph)
+        } else {
+            Default::default()
+        };
+        let mut curves = vec![];
+        for (asn, nodes) in adversaries {
+            let mut points = vec![];
+            for coverage in levels.iter().copied() {
+                let covered = Self::covered_nodes(*asn, nodes, coverage, decisions.seed());
+                let num_successful = if covered.is_empty() {
+                    baseline.num_succesful
+                } else {
+                    Self::apply_drop_strategy(
+                        baseline.clone(),
+                        *asn,
+                        &covered,
+                        strategy,
+                        intra_as_channel_ratios.get(asn),
+                        as_ip_map,
+                        &mut Decisions::new(decisions.seed()).rng_for(*asn),
+                    )?
+                    .result
+                    .num_succesful
+                };
+                points.push(CoveragePoint {
+                    coverage,
+                    num_nodes: covered.len(),
+                    num_successful,
+                    censorship_rate: if baseline.num_succesful > 0 {
+                        1.0 - num_successful as f32 / baseline.num_succesful as f32
+                    } else {
+                        0.0
+                    },
+                });
+            }
+            info!(
+                "AS {} censors {:?} of the payments with {:?} of its nodes.",
+                asn,
+                points.iter().map(|p| p.censorship_rate).collect::<Vec<_>>(),
+                levels
+            );
+            curves.push(CoverageCurve {
+                asn: asn.to_string(),
+                strategy: strategy.clone(),
+                points,
+            });
+        }
+        Ok(curves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsSelectionStrategy;
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use std::path::Path;
+
+    #[test]
+    fn partial_control() {
+        let nodes: Vec<ID> = (0..10).map(|i| i.to_string()).collect();
+        let half = SimBuilder::covered_nodes(1, &nodes, 0.5, 19);
+        assert_eq!(half.len(), 5);
+        assert_eq!(half, SimBuilder::covered_nodes(1, &nodes, 0.5, 19));
+        let quarter = SimBuilder::covered_nodes(1, &nodes, 0.25, 19);
+        assert!(quarter.iter().all(|n| half.contains(n)));
+        assert_eq!(SimBuilder::covered_nodes(1, &nodes, 1.0, 19).len(), 10);
+
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let mut builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let pairs = vec![(String::from("alice"), String::from("bob"))];
+        let baseline = builder.simulate(pairs.into_iter());
+        let adversaries = vec![(24940, as_ip_map.as_to_nodes()[&24940].clone())];
+        let curves = builder
+            .coverage_curves(
+                &baseline,
+                &adversaries,
+                &PacketDropStrategy::All,
+                &[0.0, 1.0],
+                &as_ip_map,
+                &Decisions::new(19),
+            )
+            .unwrap();
+        assert_eq!(curves.len(), 1);
+        // without any node the AS cannot censor the payment between its nodes
+        assert_eq!(curves[0].points[0].num_nodes, 0);
+        assert_eq!(curves[0].points[0].censorship_rate, 0.0);
+        assert_eq!(curves[0].points[1].censorship_rate, 1.0);
+    }
+}
@@ -0,0 +1,214 @@
+use super::{Decisions, SimBuilder};
+use crate::{net::Asn, AsIpMap, AsNodeRatios, PacketDropStrategy, SimulatorError};
+use serde::{Deserialize, Serialize};
+use simlib::{payment::Payment, ID};
+use std::collections::{HashMap, HashSet};
+
+/// Payments censored by one strategy but not by another for the same adversary
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyDiff {
+    pub asn: String,
+    pub first: PacketDropStrategy,
+    pub second: PacketDropStrategy,
+    /// Censored by the first strategy only
+    pub only_first: Disagreement,
+    /// Censored by the second strategy only
+    pub only_second: Disagreement,
+}
+
+/// Characteristics of a set of payments in the baseline
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Disagreement {
+    pub num_payments: usize,
+    pub total_amount_msat: usize,
+    /// Mean number of hops of the paths the payments used in the baseline
+    pub mean_path_length: f32,
+    /// Sorted in descending order of number of payments
+    pub endpoint_asns: Vec<EndpointAsns>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointAsns {
+    /// None if the sender could not be mapped to an ASN
+    pub source_asn: Option<u32>,
+    pub dest_asn: Option<u32>,
+    pub num_payments: usize,
+}
+
+impl SimBuilder {
+    /// Applies both strategies of an AS to the baseline and summarises the payments that only one
+    /// of them censors
+    pub fn diff_strategies(
+        baseline_result: &simlib::SimResult,
+        asn: Asn,
+        nodes: &[ID],
+        strategies: (&PacketDropStrategy, &PacketDropStrategy),
+        ratios: Option<&AsNodeRatios>,
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+    ) -> Result<StrategyDiff, SimulatorError> {
+        let censored_by = |strategy: &PacketDropStrategy| {
+            let mut rng = decisions.rng_for(asn);
+            let outcome = Self::apply_drop_strategy(
+                baseline_result.clone(),
+                asn,
+                nodes,
+                strategy,
+                ratios,
+                as_ip_map,
+                &mut rng,
+            )?;
+            Ok::<_, SimulatorError>(Self::censored_payment_ids(baseline_result, &outcome.result))
+        };
+        let first = censored_by(strategies.0)?;
+        let second = censored_by(strategies.1)?;
+        let summarise = |ids: HashSet<&usize>| {
+            let payments: Vec<&Payment> = baseline_result
+                .successful_payments
+                .iter()
+                .filter(|p| ids.contains(&p.payment_id))
+                .collect();
+            Self::summarise_disagreement(&payments, as_ip_map)
+        };
+        Ok(StrategyDiff {
+            asn: asn.to_string(),
+            first: strategies.0.clone(),
+            second: strategies.1.clone(),
+            only_first: summarise(first.difference(&second).collect()),
+            only_second: summarise(second.difference(&first).collect()),
+        })
+    }
+
+    /// IDs of the payments that succeeded in the baseline but failed after applying a strategy
+    fn censored_payment_ids(
+        baseline_result: &simlib::SimResult,
+        censored_result: &simlib::SimResult,
+    ) -> HashSet<usize> {
+        let successful: HashSet<usize> = baseline_result
+            .successful_payments
+            .iter()
+            .map(|p| p.payment_id)
+            .collect();
+        censored_result
+            .failed_payments
+            .iter()
+            .map(|p| p.payment_id)
+            .filter(|id| successful.contains(id))
+            .collect()
+    }
+
+    fn summarise_disagreement(payments: &[&Payment], as_ip_map: &AsIpMap) -> Disagreement {
+        let mut endpoints: HashMap<(Option<Asn>, Option<Asn>), usize> = HashMap::new();
+        let (mut num_paths, mut num_hops) = (0, 0);
+        for p in payments.iter() {
+            let source_asn = as_ip_map.asn_of(&p.source);
+            let dest_asn = as_ip_map.asn_of(&p.dest);
+            *endpoints.entry((source_asn, dest_asn)).or_default() += 1;
+            for path in p.used_paths.iter() {
+                num_paths += 1;
+                num_hops += path.path.hops.len().saturating_sub(1);
+            }
+        }
+        let mut endpoint_asns: Vec<EndpointAsns> = endpoints
+            .into_iter()
+            .map(|((source_asn, dest_asn), num_payments)| EndpointAsns {
+                source_asn,
+                dest_asn,
+                num_payments,
+            })
+            .collect();
+        endpoint_asns.sort_by(|a, b| {
+            b.num_payments
+                .cmp(&a.num_payments)
+                .then((a.source_asn, a.dest_asn).cmp(&(b.source_asn, b.dest_asn)))
+        });
+        Disagreement {
+            num_payments: payments.len(),
+            total_amount_msat: payments.iter().map(|p| p.amount).sum(),
+            mean_path_length: if num_paths > 0 {
+                num_hops as f32 / num_paths as f32
+            } else {
+                0.0
+            },
+            endpoint_asns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, CandidatePath};
+    use std::{collections::VecDeque, path::Path};
+
+    #[test]
+    fn diff_all_and_intra_as() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let asn = 797;
+        let asn_nodes = as_ip_map.as_to_nodes().get(&asn).unwrap().clone();
+        // traverses chan but ends outside the AS so only dropped by All
+        let mut outbound = Payment::new(0, String::from("dina"), String::from("bob"), 5, None);
+        let mut path = simlib::Path::new(String::from("dina"), String::from("bob"));
+        path.hops = VecDeque::from([
+            ("dina".to_string(), 0, 0, "".to_string()),
+            ("chan".to_string(), 0, 0, "c".to_string()),
+            ("bob".to_string(), 0, 0, "".to_string()),
+        ]);
+        outbound.succeeded = true;
+        outbound.used_paths = vec![CandidatePath::new_with_path(path)];
+        // stays within the AS so dropped by both
+        let mut internal = Payment::new(1, String::from("dina"), String::from("chan"), 1, None);
+        let mut path = simlib::Path::new(String::from("dina"), String::from("chan"));
+        path.hops = VecDeque::from([
+            ("dina".to_string(), 0, 0, "".to_string()),
+            ("chan".to_string(), 0, 0, "".to_string()),
+        ]);
+        internal.succeeded = true;
+        internal.used_paths = vec![CandidatePath::new_with_path(path)];
+        let baseline_result = simlib::SimResult {
+            num_succesful: 2,
+            total_num: 2,
+            successful_payments: vec![outbound, internal],
+            ..Default::default()
+        };
+        let actual = SimBuilder::diff_strategies(
+            &baseline_result,
+            asn,
+            &asn_nodes,
+            (&PacketDropStrategy::All, &PacketDropStrategy::IntraAs),
+            None,
+            &as_ip_map,
+            &Decisions::new(0),
+        )
+        .unwrap();
+        let expected = StrategyDiff {
+            asn: String::from("797"),
+            first: PacketDropStrategy::All,
+            second: PacketDropStrategy::IntraAs,
+            only_first: Disagreement {
+                num_payments: 1,
+                total_amount_msat: 5,
+                mean_path_length: 2.0,
+                endpoint_asns: vec![EndpointAsns {
+                    source_asn: Some(797),
+                    dest_asn: Some(24940),
+                    num_payments: 1,
+                }],
+            },
+            only_second: Disagreement::default(),
+        };
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,217 @@
+use super::{AttackSim, Decisions, SimBuilder};
+use crate::{net::Asn, AsIpMap, PacketDropStrategy, SimulatorError};
+use serde::{Deserialize, Serialize};
+use simlib::{graph::Graph, ID};
+use std::collections::HashMap;
+
+/// Senders route defensively by avoiding paths that stay within a single AS. The simulation only
+/// minimises fees, so each hop between two nodes of the same AS costs the senders an additional
+/// base fee. The penalty is part of the recorded fees of the defensive baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AsDiverseRouting {
+    pub penalty_msat: usize,
+}
+
+/// How much the censorship rate of each adversary drops when the senders route defensively
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DefensiveRouting {
+    pub penalty_msat: usize,
+    pub num_penalised_channels: usize,
+    pub baseline_success_rate: f32,
+    pub defensive_baseline_success_rate: f32,
+    pub rows: Vec<DefensiveRoutingRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DefensiveRoutingRow {
+    pub strategy: PacketDropStrategy,
+    pub asn: String,
+    /// Share of the baseline's successful payments the adversary censored
+    pub censorship_rate: f32,
+    pub defensive_censorship_rate: f32,
+    /// `censorship_rate - defensive_censorship_rate`
+    pub reduction: f32,
+}
+
+/// The censorship rate of each strategy and adversary in the order they were simulated
+pub(crate) type CensorshipRates = Vec<(PacketDropStrategy, String, f32)>;
+
+impl AsDiverseRouting {
+    /// Adds the penalty to the base fee of every channel between two nodes of the same AS and
+    /// returns the number of penalised channels
+    pub fn apply(&self, graph: &mut Graph, as_ip_map: &AsIpMap) -> usize {
+        let mut num_penalised = 0;
+        for (src, edges) in graph.edges.iter_mut() {
+            let Some(src_asn) = as_ip_map.asn_of(src) else {
+                continue;
+            };
+            for edge in edges
+                .iter_mut()
+                .filter(|e| as_ip_map.asn_of(&e.destination) == Some(src_asn))
+            {
+                edge.fee_base_msat += self.penalty_msat;
+                num_penalised += 1;
+            }
+        }
+        num_penalised
+    }
+}
+
+impl SimBuilder {
+    /// Share of the baseline's successful payments that did not succeed under attack
+    pub(crate) fn censorship_rate(baseline: &simlib::SimResult, attack_sim: &AttackSim) -> f32 {
+        let num_successful: usize = attack_sim
+            .sim_results
+            .iter()
+            .map(|r| r.num_successful)
+            .sum();
+        if baseline.num_succesful > 0 {
+            baseline.num_succesful.saturating_sub(num_successful) as f32
+                / baseline.num_succesful as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Simulates the baseline's pairs with AS-diverse routing and applies every strategy of the
+    /// adversaries to it. The decisions are drawn from the seed and not recorded
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn defensive_routing(
+        &self,
+        baseline: &simlib::SimResult,
+        routing: AsDiverseRouting,
+        adversaries: &[(Asn, Vec<ID>)],
+        strategies: &[PacketDropStrategy],
+        rates: CensorshipRates,
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+    ) -> Result<DefensiveRouting, SimulatorError> {
+        let mut graph = self.graph.clone();
+        let num_penalised_channels = routing.apply(&mut graph, as_ip_map);
+        let defensive_baseline =
+            self.simulate_on_graph(graph, Self::baseline_pairs(baseline).into_iter());
+        let ratios = if strategies.contains(&PacketDropStrategy::IntraProbability) {
+            as_ip_map.get_intra_as_node_ratios(&self.graph)
+        } else {
+            HashMap::default()
+        };
+        let success_rate = |result: &simlib::SimResult| {
+            if result.total_num > 0 {
+                result.num_succesful as f32 / result.total_num as f32
+            } else {
+                0.0
+            }
+        };
+        let mut rows = vec![];
+        for (strategy, asn, censorship_rate) in rates {
+            let Some((asn_num, nodes)) = adversaries.iter().find(|(a, _)| a.to_string() == asn)
+            else {
+                continue;
+            };
+            let outcome = Self::apply_drop_strategy(
+                defensive_baseline.clone(),
+                *asn_num,
+                nodes,
+                &strategy,
+                ratios.get(asn_num),
+                as_ip_map,
+                &mut Decisions::new(decisions.seed()).rng_for(*asn_num),
+            )?;
+            let defensive_censorship_rate = if defensive_baseline.num_succesful > 0 {
+                defensive_baseline
+                    .num_succesful
+                    .saturating_sub(outcome.result.num_succesful) as f32
+                    / defensive_baseline.num_succesful as f32
+            } else {
+                0.0
+            };
+            rows.push(DefensiveRoutingRow {
+                strategy,
+                asn,
+                censorship_rate,
+                defensive_censorship_rate,
+                reduction: censorship_rate - defensive_censorship_rate,
+            });
+        }
+        Ok(DefensiveRouting {
+            penalty_msat: routing.penalty_msat,
+            num_penalised_channels,
+            baseline_success_rate: success_rate(baseline),
+            defensive_baseline_success_rate: success_rate(&defensive_baseline),
+            rows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsSelectionStrategy;
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+
+    #[test]
+    fn as_diverse_routing() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let routing = AsDiverseRouting { penalty_msat: 5000 };
+        let mut penalised = graph.clone();
+        let num_penalised = routing.apply(&mut penalised, &as_ip_map);
+        let (intra_797, _) = as_ip_map.get_sum_of_as_channels(&graph)[&797];
+        let (intra_24940, _) = as_ip_map.get_sum_of_as_channels(&graph)[&24940];
+        assert_eq!(num_penalised, (intra_797 + intra_24940) as usize);
+        let fee = |g: &Graph, src: &str, dest: &str| {
+            g.get_edges_for_node(&src.to_string())
+                .unwrap()
+                .into_iter()
+                .find(|e| e.destination == dest)
+                .map(|e| e.fee_base_msat)
+        };
+        // alice and bob share an AS, bob and chan do not
+        assert_eq!(
+            fee(&penalised, "alice", "bob"),
+            fee(&graph, "alice", "bob").map(|f| f + 5000)
+        );
+        assert_eq!(fee(&penalised, "bob", "chan"), fee(&graph, "bob", "chan"));
+
+        let builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let pairs = vec![(String::from("alice"), String::from("dina"))];
+        let baseline = builder.simulate_on_graph(graph.clone(), pairs.into_iter());
+        let adversaries = vec![(797, as_ip_map.as_to_nodes()[&797].clone())];
+        let report = builder
+            .defensive_routing(
+                &baseline,
+                routing,
+                &adversaries,
+                &[PacketDropStrategy::All],
+                vec![
+                    (PacketDropStrategy::All, String::from("797"), 1.0),
+                    (PacketDropStrategy::All, String::from("16509"), 1.0),
+                ],
+                &as_ip_map,
+                &Decisions::new(19),
+            )
+            .unwrap();
+        assert_eq!(report.num_penalised_channels, num_penalised);
+        // the receiver is in the AS, so no route avoids it and unknown ASs are skipped
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(
+            report.rows[0].defensive_censorship_rate,
+            if report.defensive_baseline_success_rate > 0.0 {
+                1.0
+            } else {
+                0.0
+            }
+        );
+    }
+}
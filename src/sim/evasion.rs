@@ -0,0 +1,299 @@
+use super::{Decisions, SimBuilder};
+use crate::{net::Asn, AsIpMap, PacketDropStrategy, SimulatorError};
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::{graph::Graph, ID};
+use std::collections::{BTreeMap, HashSet};
+#[cfg(test)]
+use std::println as info;
+
+/// Victims open channels around the adversary between rounds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvasionConfig {
+    /// Rounds after the initial attack
+    pub rounds: usize,
+    /// Maximum number of channels opened over all rounds
+    pub budget: usize,
+    pub channel_capacity_msat: usize,
+    pub strategy: PacketDropStrategy,
+}
+
+/// How the success rate under attack recovers as the victims open new channels
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EvasionReport {
+    pub asn: String,
+    pub strategy: PacketDropStrategy,
+    /// Round 0 is the attack on the unmodified graph
+    pub rounds: Vec<EvasionRound>,
+    /// The first round without censored payments, None if the payments did not recover
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rounds_to_recover: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EvasionRound {
+    pub round: usize,
+    /// Channels opened before the round
+    pub num_new_channels: usize,
+    /// Successful payments without the adversary
+    pub num_baseline_successful: usize,
+    pub num_successful: usize,
+    /// Payments that succeeded without the adversary and failed under attack
+    pub num_censored: usize,
+    pub success_rate: f32,
+    /// Share of the payments censored in round 0 that are no longer censored
+    pub recovered_share: f32,
+}
+
+impl SimBuilder {
+    /// Simulates the attack of a single AS over several rounds. After each round, the senders of
+    /// censored payments open one channel each to a peer outside the AS until the budget is spent:
+    /// to the receiver if it is outside the AS, otherwise to the best-connected node outside the
+    /// AS. The pairs are those of the baseline and the decisions are drawn from the seed and not
+    /// recorded
+    pub fn evasion_simulation(
+        &self,
+        baseline: &simlib::SimResult,
+        asn: Asn,
+        nodes: &[ID],
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+        config: &EvasionConfig,
+    ) -> Result<EvasionReport, SimulatorError> {
+        let pairs = Self::baseline_pairs(baseline);
+        let mut graph = self.graph.clone();
+        let mut report = EvasionReport {
+            asn: asn.to_string(),
+            strategy: config.strategy.clone(),
+            ..Default::default()
+        };
+        let mut round_baseline = baseline.clone();
+        let mut budget = config.budget;
+        let mut num_new_channels = 0;
+        for round in 0..=config.rounds {
+            if round > 0 {
+                round_baseline = self.simulate_on_graph(graph.clone(), pairs.clone().into_iter());
+            }
+            let ratios = if config.strategy == PacketDropStrategy::IntraProbability {
+                as_ip_map.get_intra_as_node_ratios(&graph).remove(&asn)
+            } else {
+                None
+            };
+            let outcome = Self::apply_drop_strategy(
+                round_baseline.clone(),
+                asn,
+                nodes,
+                &config.strategy,
+                ratios.as_ref(),
+                as_ip_map,
+                &mut Decisions::new(decisions.seed()).rng_for(asn),
+            )?;
+            let succeeded: HashSet<usize> = outcome
+                .result
+                .successful_payments
+                .iter()
+                .map(|p| p.payment_id)
+                .collect();
+            let censored: Vec<(ID, ID)> = round_baseline
+                .successful_payments
+                .iter()
+                .filter(|p| !succeeded.contains(&p.payment_id))
+                .map(|p| (p.source.clone(), p.dest.clone()))
+                .collect();
+            let num_censored = censored.len();
+            let initially_censored = report
+                .rounds
+                .first()
+                .map(|r| r.num_censored)
+                .unwrap_or(num_censored);
+            report.rounds.push(EvasionRound {
+                round,
+                num_new_channels,
+                num_baseline_successful: round_baseline.num_succesful,
+                num_successful: outcome.result.num_succesful,
+                num_censored,
+                success_rate: if pairs.is_empty() {
+                    0.0
+                } else {
+                    outcome.result.num_succesful as f32 / pairs.len() as f32
+                },
+                recovered_share: if initially_censored > 0 {
+                    1.0 - num_censored.min(initially_censored) as f32 / initially_censored as f32
+                } else {
+                    1.0
+                },
+            });
+            if num_censored == 0 {
+                report.rounds_to_recover = Some(round);
+                break;
+            }
+            if round < config.rounds {
+                let opened = Self::open_evasion_channels(
+                    &mut graph,
+                    &censored,
+                    nodes,
+                    budget,
+                    config.channel_capacity_msat,
+                );
+                budget -= opened;
+                num_new_channels = opened;
+            }
+        }
+        info!(
+            "Completed {} rounds of evasion of AS {}.",
+            report.rounds.len(),
+            asn
+        );
+        Ok(report)
+    }
+
+    /// Opens a channel for each sender of censored payments, those with the most censored payments
+    /// first, and returns the number of channels opened
+    fn open_evasion_channels(
+        graph: &mut Graph,
+        censored: &[(ID, ID)],
+        asn_nodes: &[ID],
+        budget: usize,
+        capacity_msat: usize,
+    ) -> usize {
+        let mut per_sender: BTreeMap<&ID, Vec<&ID>> = BTreeMap::new();
+        for (src, dest) in censored.iter() {
+            if !asn_nodes.contains(src) {
+                per_sender.entry(src).or_default().push(dest);
+            }
+        }
+        let mut senders: Vec<(&ID, Vec<&ID>)> = per_sender.into_iter().collect();
+        senders.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+        let degree =
+            |graph: &Graph, node: &ID| graph.get_edges_for_node(node).unwrap_or_default().len();
+        let mut opened = 0;
+        for (sender, dests) in senders {
+            if opened == budget {
+                break;
+            }
+            let neighbours: HashSet<ID> = graph
+                .get_edges_for_node(sender)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|e| e.destination)
+                .collect();
+            let candidate = |node: &ID| {
+                node != sender && !asn_nodes.contains(node) && !neighbours.contains(node)
+            };
+            let peer = match dests.into_iter().find(|d| candidate(*d)) {
+                Some(dest) => Some(dest.clone()),
+                None => graph
+                    .get_nodes()
+                    .into_iter()
+                    .map(|n| n.id)
+                    .filter(|n| candidate(n))
+                    .max_by(|a, b| degree(graph, a).cmp(&degree(graph, b)).then(b.cmp(a))),
+            };
+            if let Some(peer) = peer {
+                if Self::open_channel(graph, sender, &peer, capacity_msat) {
+                    opened += 1;
+                }
+            }
+        }
+        opened
+    }
+
+    /// Adds a channel in both directions with the policies of an existing channel of each side.
+    /// False if either side has no channel to copy the policy from
+    fn open_channel(graph: &mut Graph, a: &ID, b: &ID, capacity_msat: usize) -> bool {
+        let template = |graph: &Graph, node: &ID| {
+            graph
+                .get_edges_for_node(node)
+                .and_then(|edges| edges.into_iter().next())
+        };
+        let (Some(mut a_to_b), Some(mut b_to_a)) = (template(graph, a), template(graph, b)) else {
+            return false;
+        };
+        a_to_b.destination = b.clone();
+        a_to_b.capacity = capacity_msat;
+        b_to_a.destination = a.clone();
+        b_to_a.capacity = capacity_msat;
+        graph.edges.entry(a.clone()).or_default().push(a_to_b);
+        graph.edges.entry(b.clone()).or_default().push(b_to_a);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsSelectionStrategy;
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+
+    #[test]
+    fn evade_by_opening_channels() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let pairs = vec![(String::from("alice"), String::from("dina"))];
+        let baseline = builder.simulate_on_graph(graph.clone(), pairs.into_iter());
+        let nodes = as_ip_map.as_to_nodes()[&797].clone();
+        let config = EvasionConfig {
+            rounds: 2,
+            budget: 1,
+            channel_capacity_msat: 1_000_000_000,
+            strategy: PacketDropStrategy::All,
+        };
+        let report = builder
+            .evasion_simulation(
+                &baseline,
+                797,
+                &nodes,
+                &as_ip_map,
+                &Decisions::new(19),
+                &config,
+            )
+            .unwrap();
+        assert_eq!(report.asn, "797");
+        assert_eq!(report.rounds[0].num_new_channels, 0);
+        assert!(report.rounds.len() <= config.rounds + 1);
+        // the receiver is in the AS, so a new channel cannot avoid it
+        assert!(report.rounds.iter().all(|r| r.num_successful == 0));
+        assert_eq!(
+            report.rounds_to_recover.is_none(),
+            report.rounds[0].num_censored > 0
+        );
+
+        let mut modified = graph.clone();
+        let degree = |g: &Graph, n: &str| g.get_edges_for_node(&n.to_string()).unwrap().len();
+        // dina is outside the adversary and not a peer of alice yet
+        let censored = vec![(String::from("alice"), String::from("dina"))];
+        let adversary = vec![String::from("chan")];
+        assert_eq!(
+            SimBuilder::open_evasion_channels(&mut modified, &censored, &adversary, 0, 1000),
+            0
+        );
+        assert_eq!(
+            SimBuilder::open_evasion_channels(&mut modified, &censored, &adversary, 1, 1000),
+            1
+        );
+        assert_eq!(degree(&modified, "alice"), degree(&graph, "alice") + 1);
+        assert_eq!(degree(&modified, "dina"), degree(&graph, "dina") + 1);
+        assert!(modified
+            .get_edges_for_node(&String::from("alice"))
+            .unwrap()
+            .iter()
+            .any(|e| e.destination == "dina" && e.capacity == 1000));
+        // the peers are connected now and bob is alice's only other candidate
+        assert_eq!(
+            SimBuilder::open_evasion_channels(&mut modified, &censored, &adversary, 1, 1000),
+            0
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use super::SimBuilder;
+use crate::{net::Asn, AsIpMap};
+use csv::Writer;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    path::PathBuf,
+};
+
+/// Share of the baseline's successful payments that traverse at least one node of an AS, i.e.,
+/// the payments the AS could censor by dropping everything
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AsExposure {
+    pub asn: u32,
+    pub num_nodes: usize,
+    pub num_exposed_payments: usize,
+    pub exposure: f32,
+}
+
+impl SimBuilder {
+    /// Computes the exposure of every AS in a single pass over the payments in descending order of
+    /// exposure. This is considerably cheaper than applying a strategy for each AS
+    pub fn exposure_scores(sim_result: &simlib::SimResult, as_ip_map: &AsIpMap) -> Vec<AsExposure> {
+        let mut exposed: HashMap<Asn, usize> = HashMap::new();
+        for p in sim_result.successful_payments.iter() {
+            let asns: HashSet<Asn> = p
+                .used_paths
+                .iter()
+                .flat_map(|path| path.path.get_involved_nodes())
+                .filter_map(|n| as_ip_map.asn_of(&n))
+                .collect();
+            for asn in asns {
+                *exposed.entry(asn).or_default() += 1;
+            }
+        }
+        let mut exposure: Vec<AsExposure> = as_ip_map
+            .as_to_nodes()
+            .iter()
+            .map(|(asn, nodes)| {
+                let num_exposed_payments = exposed.get(asn).copied().unwrap_or_default();
+                AsExposure {
+                    asn: *asn,
+                    num_nodes: nodes.len(),
+                    num_exposed_payments,
+                    exposure: if sim_result.num_succesful > 0 {
+                        num_exposed_payments as f32 / sim_result.num_succesful as f32
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+        exposure.sort_by(|a, b| {
+            b.num_exposed_payments
+                .cmp(&a.num_exposed_payments)
+                .then(a.asn.cmp(&b.asn))
+        });
+        exposure
+    }
+}
+
+/// Writes the exposure table to a CSV file, creating the parent directory if needed
+pub fn write_exposure_csv(
+    exposure: &[AsExposure],
+    output_path: &PathBuf,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = Writer::from_path(output_path)?;
+    writer.serialize(("asn", "num_nodes", "num_exposed_payments", "exposure"))?;
+    for e in exposure.iter() {
+        writer.serialize((e.asn, e.num_nodes, e.num_exposed_payments, e.exposure))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, payment::Payment, CandidatePath};
+    use std::{collections::VecDeque, path::Path};
+
+    #[test]
+    fn exposure() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let sim_result = simlib::SimResult {
+            num_succesful: 4,
+            total_num: 4,
+            successful_payments: vec![
+                payment(0, vec!["dina", "chan", "bob"]),
+                payment(1, vec!["dina", "chan"]),
+                payment(2, vec!["alice", "bob"]),
+                payment(3, vec!["dina", "chan", "alice"]),
+            ],
+            ..Default::default()
+        };
+        let actual = SimBuilder::exposure_scores(&sim_result, &as_ip_map);
+        let expected = vec![
+            AsExposure {
+                asn: 797,
+                num_nodes: 2,
+                num_exposed_payments: 3,
+                exposure: 0.75,
+            },
+            AsExposure {
+                asn: 24940,
+                num_nodes: 2,
+                num_exposed_payments: 3,
+                exposure: 0.75,
+            },
+        ];
+        assert_eq!(actual, expected);
+    }
+}
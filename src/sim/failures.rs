@@ -0,0 +1,134 @@
+use super::{Decisions, SimBuilder};
+use crate::{
+    net::{AsIpMap, AsNodeRatios, Asn},
+    PacketDropStrategy, SimulatorError,
+};
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+
+/// How many of the payments that already failed in the baseline the adversary's strategy would
+/// have censored had they succeeded
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BaselineFailures {
+    pub num_failed: usize,
+    pub num_censorable: usize,
+    /// Share of the failed payments the strategy would have censored
+    pub censorable_rate: f32,
+}
+
+impl SimBuilder {
+    /// Applies the strategy to the baseline's failed payments as if they had succeeded. A failed
+    /// payment is judged by the paths the simulation recorded for it, so one without any is only
+    /// censored by strategies that select payments by their sender or receiver. The decisions are
+    /// drawn from the seed and not recorded
+    pub(crate) fn baseline_failures(
+        baseline_result: &simlib::SimResult,
+        asn: Asn,
+        nodes: &[ID],
+        strategy: &PacketDropStrategy,
+        ratios: Option<&AsNodeRatios>,
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+    ) -> Result<BaselineFailures, SimulatorError> {
+        let num_failed = baseline_result.failed_payments.len();
+        let as_succeeded = simlib::SimResult {
+            num_succesful: num_failed,
+            total_num: num_failed,
+            successful_payments: baseline_result
+                .failed_payments
+                .iter()
+                .cloned()
+                .map(|mut p| {
+                    p.succeeded = true;
+                    p
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let outcome = Self::apply_drop_strategy(
+            as_succeeded,
+            asn,
+            nodes,
+            strategy,
+            ratios,
+            as_ip_map,
+            &mut Decisions::new(decisions.seed()).rng_for(asn),
+        )?;
+        let num_censorable = outcome.result.failed_payments.len();
+        Ok(BaselineFailures {
+            num_failed,
+            num_censorable,
+            censorable_rate: if num_failed > 0 {
+                num_censorable as f32 / num_failed as f32
+            } else {
+                0.0
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simlib::{payment::Payment, CandidatePath};
+    use std::collections::{HashMap, VecDeque};
+
+    #[test]
+    fn classify_failed_payments() {
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let baseline = simlib::SimResult {
+            num_failed: 3,
+            total_num: 3,
+            failed_payments: vec![
+                payment(0, vec!["alice", "bob"]),
+                payment(1, vec!["chan", "dina"]),
+                payment(2, vec!["bob", "chan", "dina"]),
+            ],
+            ..Default::default()
+        };
+        let as_ip_map = AsIpMap::from(HashMap::from([
+            (24940, vec![String::from("alice"), String::from("bob")]),
+            (797, vec![String::from("chan"), String::from("dina")]),
+        ]));
+        let nodes = as_ip_map.as_to_nodes()[&24940].clone();
+        let actual = SimBuilder::baseline_failures(
+            &baseline,
+            24940,
+            &nodes,
+            &PacketDropStrategy::All,
+            None,
+            &as_ip_map,
+            &Decisions::new(19),
+        )
+        .unwrap();
+        let expected = BaselineFailures {
+            num_failed: 3,
+            num_censorable: 2,
+            censorable_rate: 2.0 / 3.0,
+        };
+        assert_eq!(actual, expected);
+        let none = SimBuilder::baseline_failures(
+            &simlib::SimResult::default(),
+            24940,
+            &nodes,
+            &PacketDropStrategy::All,
+            None,
+            &as_ip_map,
+            &Decisions::new(19),
+        )
+        .unwrap();
+        assert_eq!(none, BaselineFailures::default());
+    }
+}
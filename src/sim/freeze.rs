@@ -0,0 +1,178 @@
+use super::SimBuilder;
+use crate::net::Asn;
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::{graph::Graph, ID};
+use std::collections::HashSet;
+#[cfg(test)]
+use std::println as info;
+
+/// Instead of dropping payments, the adversary freezes a share of the liquidity of every channel of
+/// its nodes, e.g. as the result of a legal seizure
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiquidityFreeze {
+    /// Share of each channel's capacity that can no longer be used, between 0 and 1
+    pub frozen_share: f32,
+}
+
+/// How the payments across the network fare after the AS froze its nodes' liquidity
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FreezeImpact {
+    pub asn: String,
+    pub frozen_share: f32,
+    /// Channels from or to a node of the AS
+    pub num_frozen_channels: usize,
+    pub frozen_capacity_msat: usize,
+    pub num_baseline_successful: usize,
+    pub num_successful: usize,
+    /// Payments that succeeded in the baseline and failed after the freeze
+    pub num_failed_by_freeze: usize,
+    /// Of these, payments whose sender and receiver are both outside the AS
+    pub num_failed_downstream: usize,
+    pub baseline_success_rate: f32,
+    pub success_rate: f32,
+}
+
+impl LiquidityFreeze {
+    /// Reduces the capacity of every channel from or to one of the nodes by the frozen share and
+    /// returns the number of channels and the frozen capacity
+    pub fn apply(&self, graph: &mut Graph, nodes: &[ID]) -> (usize, usize) {
+        let nodes: HashSet<&ID> = nodes.iter().collect();
+        let (mut num_channels, mut frozen_msat) = (0, 0);
+        for (src, edges) in graph.edges.iter_mut() {
+            let src_frozen = nodes.contains(src);
+            for edge in edges
+                .iter_mut()
+                .filter(|e| src_frozen || nodes.contains(&e.destination))
+            {
+                let frozen = (edge.capacity as f64 * self.frozen_share as f64) as usize;
+                edge.capacity -= frozen;
+                frozen_msat += frozen;
+                num_channels += 1;
+            }
+        }
+        (num_channels, frozen_msat)
+    }
+}
+
+impl SimBuilder {
+    /// Simulates the baseline's pairs after the AS froze the liquidity of its nodes. The AS does
+    /// not drop any payments
+    pub fn liquidity_freeze(
+        &self,
+        baseline: &simlib::SimResult,
+        asn: Asn,
+        nodes: &[ID],
+        freeze: LiquidityFreeze,
+    ) -> FreezeImpact {
+        let mut graph = self.graph.clone();
+        let (num_frozen_channels, frozen_capacity_msat) = freeze.apply(&mut graph, nodes);
+        let frozen = self.simulate_on_graph(graph, Self::baseline_pairs(baseline).into_iter());
+        let succeeded: HashSet<usize> = frozen
+            .successful_payments
+            .iter()
+            .map(|p| p.payment_id)
+            .collect();
+        let (mut num_failed_by_freeze, mut num_failed_downstream) = (0, 0);
+        for p in baseline
+            .successful_payments
+            .iter()
+            .filter(|p| !succeeded.contains(&p.payment_id))
+        {
+            num_failed_by_freeze += 1;
+            if !nodes.contains(&p.source) && !nodes.contains(&p.dest) {
+                num_failed_downstream += 1;
+            }
+        }
+        let success_rate = |result: &simlib::SimResult| {
+            if result.total_num > 0 {
+                result.num_succesful as f32 / result.total_num as f32
+            } else {
+                0.0
+            }
+        };
+        info!(
+            "Freezing {} channels of AS {} failed {} payments.",
+            num_frozen_channels, asn, num_failed_by_freeze
+        );
+        FreezeImpact {
+            asn: asn.to_string(),
+            frozen_share: freeze.frozen_share,
+            num_frozen_channels,
+            frozen_capacity_msat,
+            num_baseline_successful: baseline.num_succesful,
+            num_successful: frozen.num_succesful,
+            num_failed_by_freeze,
+            num_failed_downstream,
+            baseline_success_rate: success_rate(baseline),
+            success_rate: success_rate(&frozen),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AsIpMap, AsSelectionStrategy};
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+
+    #[test]
+    fn freeze_liquidity() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let nodes = as_ip_map.as_to_nodes()[&797].clone();
+        let capacity = |g: &Graph, src: &str, dest: &str| {
+            g.get_edges_for_node(&src.to_string())
+                .unwrap()
+                .into_iter()
+                .find(|e| e.destination == dest)
+                .map(|e| e.capacity)
+                .unwrap()
+        };
+        let mut frozen = graph.clone();
+        let half = LiquidityFreeze { frozen_share: 0.5 };
+        let (num_channels, frozen_msat) = half.apply(&mut frozen, &nodes);
+        // bob-chan and chan-dina in both directions
+        assert_eq!(num_channels, 4);
+        assert_eq!(
+            capacity(&frozen, "bob", "chan"),
+            capacity(&graph, "bob", "chan") - capacity(&graph, "bob", "chan") / 2
+        );
+        assert_eq!(
+            capacity(&frozen, "alice", "bob"),
+            capacity(&graph, "alice", "bob")
+        );
+        let expected_msat: usize = [
+            ("bob", "chan"),
+            ("chan", "bob"),
+            ("chan", "dina"),
+            ("dina", "chan"),
+        ]
+        .iter()
+        .map(|(src, dest)| capacity(&graph, src, dest) / 2)
+        .sum();
+        assert_eq!(frozen_msat, expected_msat);
+
+        let mut builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let pairs = vec![(String::from("alice"), String::from("bob"))];
+        let baseline = builder.simulate(pairs.into_iter());
+        let all = LiquidityFreeze { frozen_share: 1.0 };
+        let impact = builder.liquidity_freeze(&baseline, 797, &nodes, all);
+        assert_eq!(impact.asn, "797");
+        // alice pays bob without touching the AS
+        assert_eq!(impact.num_successful, baseline.num_succesful);
+        assert_eq!(impact.num_failed_by_freeze, 0);
+        assert_eq!(impact.success_rate, impact.baseline_success_rate);
+    }
+}
@@ -0,0 +1,217 @@
+use super::{Decisions, SimBuilder};
+use crate::{net::Asn, AsIpMap, PacketDropStrategy, SimulatorError};
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::{graph::Graph, ID};
+use std::collections::{HashMap, HashSet};
+#[cfg(test)]
+use std::println as info;
+
+/// Market shares, i.e., shares of all mapped nodes, an AS acquires unless others are given
+pub static DEFAULT_GROWTH_STEPS: [f32; 5] = [0.01, 0.02, 0.05, 0.1, 0.2];
+
+/// The AS acquires the best-connected nodes of other ASs before it attacks, e.g. as hosting
+/// providers consolidate
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsGrowth {
+    pub asn: Asn,
+    /// Additional shares of the mapped nodes the AS acquires, each simulated on its own
+    pub steps: Vec<f32>,
+}
+
+/// How the censorship power of the AS grows with its market share
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GrowthCurve {
+    pub asn: String,
+    pub strategy: PacketDropStrategy,
+    /// The AS as it is, followed by every step
+    pub points: Vec<GrowthPoint>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GrowthPoint {
+    pub acquired_share: f32,
+    pub num_acquired_nodes: usize,
+    pub num_nodes: usize,
+    /// Share of the mapped nodes in the AS after the acquisition
+    pub market_share: f32,
+    pub num_successful: usize,
+    /// Share of the baseline's successful payments the AS censors
+    pub censorship_rate: f32,
+}
+
+impl AsGrowth {
+    /// Moves the `num_nodes` nodes with the most channels that are not in the AS into it
+    pub fn acquire(&self, as_ip_map: &AsIpMap, graph: &Graph, num_nodes: usize) -> AsIpMap {
+        let own: HashSet<&ID> = as_ip_map
+            .as_to_nodes()
+            .get(&self.asn)
+            .into_iter()
+            .flatten()
+            .collect();
+        let mut others: Vec<(usize, &ID)> = as_ip_map
+            .as_to_nodes()
+            .iter()
+            .filter(|(asn, _)| **asn != self.asn)
+            .flat_map(|(_, nodes)| nodes.iter())
+            .filter(|n| !own.contains(n))
+            .map(|n| (graph.get_edges_for_node(n).unwrap_or_default().len(), n))
+            .collect();
+        others.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+        let acquired: HashSet<ID> = others
+            .into_iter()
+            .take(num_nodes)
+            .map(|(_, n)| n.clone())
+            .collect();
+        let mut as_to_nodes: HashMap<Asn, Vec<ID>> = as_ip_map
+            .as_to_nodes()
+            .iter()
+            .map(|(asn, nodes)| {
+                let nodes = nodes
+                    .iter()
+                    .filter(|n| !acquired.contains(*n))
+                    .cloned()
+                    .collect();
+                (*asn, nodes)
+            })
+            .filter(|(_, nodes): &(Asn, Vec<ID>)| !nodes.is_empty())
+            .collect();
+        let mut acquired: Vec<ID> = acquired.into_iter().collect();
+        acquired.sort();
+        as_to_nodes.entry(self.asn).or_default().extend(acquired);
+        AsIpMap::from(as_to_nodes)
+    }
+}
+
+impl SimBuilder {
+    /// Simulates the strategy of the AS before and after each acquisition step. The decisions are
+    /// drawn from the seed and not recorded
+    pub fn growth_curve(
+        &self,
+        baseline: &simlib::SimResult,
+        growth: &AsGrowth,
+        strategy: &PacketDropStrategy,
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+    ) -> Result<GrowthCurve, SimulatorError> {
+        let num_mapped: usize = as_ip_map
+            .as_to_nodes()
+            .values()
+            .flatten()
+            .collect::<HashSet<_>>()
+            .len();
+        let mut points = vec![];
+        for acquired_share in std::iter::once(0.0).chain(growth.steps.iter().copied()) {
+            let num_acquired_nodes = (acquired_share * num_mapped as f32).round() as usize;
+            let grown_map = growth.acquire(as_ip_map, &self.graph, num_acquired_nodes);
+            let nodes = grown_map
+                .as_to_nodes()
+                .get(&growth.asn)
+                .cloned()
+                .unwrap_or_default();
+            let ratios = if *strategy == PacketDropStrategy::IntraProbability {
+                grown_map
+                    .get_intra_as_node_ratios(&self.graph)
+                    .remove(&growth.asn)
+            } else {
+                None
+            };
+            let num_successful = if nodes.is_empty() {
+                baseline.num_succesful
+            } else {
+                Self::apply_drop_strategy(
+                    baseline.clone(),
+                    growth.asn,
+                    &nodes,
+                    strategy,
+                    ratios.as_ref(),
+                    &grown_map,
+                    &mut Decisions::new(decisions.seed()).rng_for(growth.asn),
+                )?
+                .result
+                .num_succesful
+            };
+            points.push(GrowthPoint {
+                acquired_share,
+                num_acquired_nodes,
+                num_nodes: nodes.len(),
+                market_share: if num_mapped > 0 {
+                    nodes.len() as f32 / num_mapped as f32
+                } else {
+                    0.0
+                },
+                num_successful,
+                censorship_rate: if baseline.num_succesful > 0 {
+                    1.0 - num_successful as f32 / baseline.num_succesful as f32
+                } else {
+                    0.0
+                },
+            });
+        }
+        info!(
+            "AS {} censors {:.2} of the payments after acquiring {:?} of the nodes.",
+            growth.asn,
+            points.last().map(|p| p.censorship_rate).unwrap_or_default(),
+            growth.steps.last()
+        );
+        Ok(GrowthCurve {
+            asn: growth.asn.to_string(),
+            strategy: strategy.clone(),
+            points,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsSelectionStrategy;
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+
+    #[test]
+    fn acquire_nodes() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let growth = AsGrowth {
+            asn: 797,
+            steps: vec![0.25, 0.5],
+        };
+        // bob has two channels, alice one
+        let grown = growth.acquire(&as_ip_map, &graph, 1);
+        assert_eq!(grown.as_to_nodes()[&24940], vec![String::from("alice")]);
+        assert_eq!(grown.as_to_nodes()[&797].len(), 3);
+        let grown = growth.acquire(&as_ip_map, &graph, 2);
+        assert!(!grown.as_to_nodes().contains_key(&24940));
+
+        let mut builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let pairs = vec![(String::from("alice"), String::from("bob"))];
+        let baseline = builder.simulate(pairs.into_iter());
+        let curve = builder
+            .growth_curve(
+                &baseline,
+                &growth,
+                &PacketDropStrategy::All,
+                &as_ip_map,
+                &Decisions::new(19),
+            )
+            .unwrap();
+        assert_eq!(curve.points.len(), 3);
+        // alice pays bob without touching the AS until it acquires bob
+        assert_eq!(curve.points[0].censorship_rate, 0.0);
+        assert_eq!(curve.points[0].market_share, 0.5);
+        assert_eq!(curve.points[1].num_acquired_nodes, 1);
+        assert_eq!(curve.points[1].censorship_rate, 1.0);
+        assert_eq!(curve.points[2].market_share, 1.0);
+    }
+}
@@ -0,0 +1,253 @@
+use super::output::*;
+use log::info;
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fmt::Write,
+    fs::{self, File},
+    io::Write as IoWrite,
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// How the report is written in addition to the JSON file
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    /// A self-contained HTML page with tables and bar charts
+    Html,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "html" => Ok(Self::Html),
+            _ => Err(format!("Unknown report format {}", s)),
+        }
+    }
+}
+
+static STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:1.5em}\
+th,td{border:1px solid #ccc;padding:.3em .6em;text-align:right}\
+th:first-child,td:first-child{text-align:left}\
+th{background:#f3f3f3}";
+
+/// Width in px of a bar for a rate of 100%
+static BAR_WIDTH: f32 = 120.0;
+
+impl Report {
+    /// Writes `simulation-run<run>.html` to the directory
+    pub fn write_html(&self, output_dir: PathBuf) -> Result<PathBuf, Box<dyn Error>> {
+        fs::create_dir_all(&output_dir)?;
+        let mut path = output_dir;
+        path.push(format!("simulation-run{}.html", self.0));
+        File::create(&path)?.write_all(self.to_html().as_bytes())?;
+        info!("HTML report written to {}.", path.display());
+        Ok(path)
+    }
+
+    /// Renders the censorship rate of each AS and strategy per amount
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>Simulation run {run}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+            <h1>Simulation run {run}</h1>\n\
+            <p>The censorship rate is the share of the payments that succeeded without an \
+            adversary and failed under the strategy of the AS.</p>\n",
+            run = self.0
+        );
+        if let Some(c) = &self.2 {
+            let _ = writeln!(
+                html,
+                "<p>{} ASs host the nodes. Gini coefficient of nodes {:.3}, channels {:.3} and \
+                capacity {:.3}.</p>",
+                c.num_asns, c.nodes.gini, c.channels.gini, c.capacity.gini
+            );
+        }
+        let mut outputs: Vec<&SimOutput> = self.1.iter().collect();
+        outputs.sort_by_key(|o| o.amt_sat);
+        for output in outputs {
+            Self::write_amount(&mut html, output);
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn write_amount(html: &mut String, output: &SimOutput) {
+        let _ = write!(
+            html,
+            "<h2>{} sat</h2>\n<p>{} payments</p>\n",
+            output.amt_sat, output.total_num_payments
+        );
+        if let Some(baseline) = &output.baseline {
+            let result = &baseline.result;
+            let _ = writeln!(
+                html,
+                "<p>Baseline success rate: {}</p>",
+                percent(ratio(
+                    result.num_successful,
+                    result.num_successful + result.num_failed
+                ))
+            );
+        }
+        let strategies: Vec<String> = output
+            .per_strategy_results
+            .iter()
+            .map(|r| escape(&format!("{:?}", r.strategy)))
+            .collect();
+        html.push_str(
+            "<h3>Strategies</h3>\n<table>\n<tr><th>Strategy</th><th>ASs</th>\
+            <th>Mean censorship rate</th><th></th></tr>\n",
+        );
+        for (strategy, results) in strategies.iter().zip(output.per_strategy_results.iter()) {
+            let rates: Vec<f32> = results
+                .attack_results
+                .iter()
+                .filter_map(censorship_rate)
+                .collect();
+            let mean = ratio_f32(rates.iter().sum(), rates.len());
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                strategy,
+                results.attack_results.len(),
+                percent(mean),
+                bar(mean)
+            );
+        }
+        html.push_str("</table>\n");
+        let asns: BTreeSet<&str> = output
+            .per_strategy_results
+            .iter()
+            .flat_map(|r| r.attack_results.iter().map(|a| a.asn.as_str()))
+            .collect();
+        html.push_str("<h3>Censorship rate per AS</h3>\n<table>\n<tr><th>ASN</th>");
+        for strategy in strategies.iter() {
+            let _ = write!(html, "<th>{}</th>", strategy);
+        }
+        html.push_str("</tr>\n");
+        for asn in asns {
+            let _ = write!(html, "<tr><td>{}</td>", escape(asn));
+            for results in output.per_strategy_results.iter() {
+                match results
+                    .attack_results
+                    .iter()
+                    .find(|a| a.asn == asn)
+                    .and_then(censorship_rate)
+                {
+                    Some(rate) => {
+                        let _ = write!(html, "<td>{} {}</td>", percent(rate), bar(rate));
+                    }
+                    None => html.push_str("<td>-</td>"),
+                }
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n");
+    }
+}
+
+/// Share of the baseline's successful payments the AS censored
+fn censorship_rate(attack: &AttackSim) -> Option<f32> {
+    attack.observation.as_ref().map(|o| {
+        let censored = o.observed_dropped + o.unobserved_dropped;
+        ratio(
+            censored,
+            censored + o.observed_forwarded + o.unobserved_forwarded,
+        )
+    })
+}
+
+fn ratio(a: usize, b: usize) -> f32 {
+    ratio_f32(a as f32, b)
+}
+
+fn ratio_f32(a: f32, b: usize) -> f32 {
+    if b > 0 {
+        a / b as f32
+    } else {
+        0.0
+    }
+}
+
+fn percent(rate: f32) -> String {
+    format!("{:.1}%", rate * 100.0)
+}
+
+/// An inline SVG bar whose width is proportional to the rate
+fn bar(rate: f32) -> String {
+    format!(
+        "<svg width=\"{w}\" height=\"10\"><rect width=\"{w}\" height=\"10\" fill=\"#eee\"/>\
+        <rect width=\"{:.1}\" height=\"10\" fill=\"#c0392b\"/></svg>",
+        rate.clamp(0.0, 1.0) * BAR_WIDTH,
+        w = BAR_WIDTH
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Observation, PacketDropStrategy};
+
+    #[test]
+    fn render_html() {
+        let attack = |asn: &str, dropped, forwarded| AttackSim {
+            asn: asn.to_string(),
+            observation: Some(Observation {
+                observed_dropped: dropped,
+                observed_forwarded: forwarded,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let report = Report(
+            19,
+            vec![SimOutput {
+                amt_sat: 1000,
+                total_num_payments: 4,
+                per_strategy_results: vec![
+                    PerStrategyResults {
+                        strategy: PacketDropStrategy::All,
+                        attack_results: vec![attack("797", 1, 3), attack("24940", 4, 0)],
+                        other_asns: None,
+                    },
+                    PerStrategyResults {
+                        strategy: PacketDropStrategy::Delay {
+                            hold_time_secs: 30,
+                            timeout_secs: 60,
+                        },
+                        attack_results: vec![attack("797", 0, 4)],
+                        other_asns: None,
+                    },
+                ],
+                ..Default::default()
+            }],
+            None,
+        );
+        let html = report.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h2>1000 sat</h2>"));
+        // mean of 25% and 100%
+        assert!(html.contains("<tr><td>All</td><td>2</td><td>62.5%</td>"));
+        assert!(html.contains("<td>24940</td><td>100.0% <svg"));
+        assert!(html.contains("<td>-</td>"));
+        assert!(html.contains("<th>Delay { hold_time_secs: 30, timeout_secs: 60 }</th>"));
+        assert_eq!(escape("<a&b>"), "&lt;a&amp;b&gt;");
+        assert_eq!(ReportFormat::from_str("HTML"), Ok(ReportFormat::Html));
+        assert!(ReportFormat::from_str("pdf").is_err());
+    }
+}
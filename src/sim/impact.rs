@@ -0,0 +1,205 @@
+use super::{RetryPolicy, SimBuilder};
+use crate::{net::Asn, ConcentrationMetrics};
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::{payment::Payment, ID};
+use std::collections::{HashMap, HashSet};
+#[cfg(test)]
+use std::println as info;
+
+/// Number of nodes listed per ranking of the impact
+const NUM_LISTED_NODES: usize = 10;
+
+/// How traffic reroutes once the senders avoid the nodes of the censoring AS altogether
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkImpact {
+    pub asn: String,
+    /// Successful payments of the baseline that traverse one of the AS's nodes
+    pub num_censored: usize,
+    /// Censored payments that succeed on the network without the AS's nodes
+    pub num_rerouted: usize,
+    /// Routing fees the AS's nodes earned in the baseline and lose by censoring
+    pub lost_fees_msat: usize,
+    /// Gini coefficient of the payments forwarded per node before and after rerouting
+    pub forwarding_gini_baseline: f64,
+    pub forwarding_gini: f64,
+    /// Channel peers of the AS with the largest change of their forwarding share
+    pub neighbours: Vec<ForwardingShift>,
+    /// Nodes whose forwarding share grows the most
+    pub top_gainers: Vec<ForwardingShift>,
+}
+
+/// Share of the successful payments a node forwards before and after rerouting
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardingShift {
+    pub node: ID,
+    pub baseline_share: f32,
+    pub share: f32,
+}
+
+impl SimBuilder {
+    /// Reroutes the payments traversing the AS on the graph without its nodes and compares the
+    /// payments each node forwards to the baseline
+    pub fn network_impact(
+        &self,
+        baseline: &simlib::SimResult,
+        asn: Asn,
+        nodes: &[ID],
+    ) -> NetworkImpact {
+        let (censored, surviving): (Vec<&Payment>, Vec<&Payment>) = baseline
+            .successful_payments
+            .iter()
+            .partition(|p| Self::payment_involves_asn(p, nodes));
+        let excluded: HashSet<ID> = nodes.iter().cloned().collect();
+        let rerouted = if censored.is_empty() {
+            simlib::SimResult::default()
+        } else {
+            self.simulate_on_graph(
+                RetryPolicy::exclude_nodes(&self.graph, &excluded),
+                censored
+                    .iter()
+                    .map(|p| (p.source.clone(), p.dest.clone()))
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )
+        };
+        let before = Self::forwarded_per_node(baseline.successful_payments.iter());
+        let after = Self::forwarded_per_node(
+            surviving
+                .into_iter()
+                .chain(rerouted.successful_payments.iter()),
+        );
+        let num_before = baseline.num_succesful;
+        let num_after = num_before - censored.len() + rerouted.num_succesful;
+        let share = |counts: &HashMap<ID, usize>, node: &ID, total: usize| {
+            if total > 0 {
+                counts.get(node).copied().unwrap_or_default() as f32 / total as f32
+            } else {
+                0.0
+            }
+        };
+        let shift = |node: &ID| ForwardingShift {
+            node: node.clone(),
+            baseline_share: share(&before, node, num_before),
+            share: share(&after, node, num_after),
+        };
+        let ranked = |candidates: HashSet<&ID>, by_gain: bool| {
+            let mut shifts: Vec<ForwardingShift> = candidates.into_iter().map(shift).collect();
+            let change = |s: &ForwardingShift| {
+                let change = s.share - s.baseline_share;
+                if by_gain {
+                    change
+                } else {
+                    change.abs()
+                }
+            };
+            shifts.sort_by(|a, b| change(b).total_cmp(&change(a)).then(a.node.cmp(&b.node)));
+            shifts.truncate(NUM_LISTED_NODES);
+            shifts
+        };
+        let neighbours: HashSet<ID> = nodes
+            .iter()
+            .flat_map(|n| self.graph.get_edges_for_node(n).unwrap_or_default())
+            .map(|e| e.destination)
+            .filter(|n| !excluded.contains(n))
+            .collect();
+        let neighbours = ranked(neighbours.iter().collect(), false);
+        let top_gainers = ranked(
+            after.keys().filter(|n| !excluded.contains(*n)).collect(),
+            true,
+        );
+        let gini = |counts: &HashMap<ID, usize>| {
+            ConcentrationMetrics::new(&counts.values().copied().collect::<Vec<_>>()).gini
+        };
+        info!(
+            "{} of {} payments censored by AS {} reroute around it.",
+            rerouted.num_succesful,
+            censored.len(),
+            asn
+        );
+        NetworkImpact {
+            asn: asn.to_string(),
+            num_censored: censored.len(),
+            num_rerouted: rerouted.num_succesful,
+            lost_fees_msat: Self::fees_earned(baseline, nodes),
+            forwarding_gini_baseline: gini(&before),
+            forwarding_gini: gini(&after),
+            neighbours,
+            top_gainers,
+        }
+    }
+
+    /// Number of payments each intermediate hop forwards, counting a payment once per node
+    fn forwarded_per_node<'a>(payments: impl Iterator<Item = &'a Payment>) -> HashMap<ID, usize> {
+        let mut forwarded = HashMap::new();
+        for payment in payments {
+            let hops: HashSet<ID> = payment
+                .used_paths
+                .iter()
+                .flat_map(|path| {
+                    let hops = path.path.get_involved_nodes();
+                    let num_hops = hops.len();
+                    hops.into_iter().skip(1).take(num_hops.saturating_sub(2))
+                })
+                .collect();
+            for hop in hops {
+                *forwarded.entry(hop).or_default() += 1;
+            }
+        }
+        forwarded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AsIpMap, AsSelectionStrategy};
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use std::path::Path;
+
+    #[test]
+    fn reroute_around_censor() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let mut builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let pairs = vec![
+            (String::from("alice"), String::from("dina")),
+            (String::from("alice"), String::from("bob")),
+        ];
+        let baseline = builder.simulate(pairs.into_iter());
+        // chan is the only way to dina
+        let nodes = vec![String::from("chan")];
+        let impact = builder.network_impact(&baseline, 797, &nodes);
+        assert_eq!(impact.asn, "797");
+        assert_eq!(
+            impact.num_censored,
+            baseline
+                .successful_payments
+                .iter()
+                .filter(|p| p.dest == "dina")
+                .count()
+        );
+        assert_eq!(impact.num_rerouted, 0);
+        assert!(impact.top_gainers.iter().all(|s| s.node != "chan"));
+        assert!(impact
+            .neighbours
+            .iter()
+            .all(|s| s.node == "bob" || s.node == "dina"));
+        let nodes = as_ip_map.as_to_nodes()[&24940].clone();
+        let impact = builder.network_impact(&baseline, 24940, &nodes);
+        // alice sends every payment
+        assert_eq!(impact.num_censored, baseline.num_succesful);
+        assert_eq!(impact.forwarding_gini, 0.0);
+    }
+}
@@ -0,0 +1,134 @@
+use super::SimBuilder;
+use crate::{net::Asn, AsIpMap};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Number of sender and receiver AS pairs listed per adversary
+pub const NUM_LOCATION_PAIRS: usize = 10;
+
+/// The ASs of the sender and the receiver of each payment by its ID, None for unmapped nodes
+pub(crate) type PaymentLocations = HashMap<usize, (Option<Asn>, Option<Asn>)>;
+
+/// Number of payments and censored payments per pair of sender and receiver AS
+pub(crate) type LocationCounts = HashMap<(Option<Asn>, Option<Asn>), (usize, usize)>;
+
+/// How many of the payments from the sender's AS to the receiver's AS the adversary censors
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationCensorship {
+    /// None if the sender has no address mapped to an AS, e.g. onion-only nodes
+    pub sender_asn: Option<Asn>,
+    pub receiver_asn: Option<Asn>,
+    /// Successful payments of the baseline between the ASs
+    pub num_payments: usize,
+    pub num_censored: usize,
+    pub censorship_rate: f32,
+}
+
+impl SimBuilder {
+    /// The ASs of the sender and the receiver of each successful payment
+    pub(crate) fn payment_locations(
+        sim_result: &simlib::SimResult,
+        as_ip_map: &AsIpMap,
+    ) -> PaymentLocations {
+        sim_result
+            .successful_payments
+            .iter()
+            .map(|p| {
+                (
+                    p.payment_id,
+                    (as_ip_map.asn_of(&p.source), as_ip_map.asn_of(&p.dest)),
+                )
+            })
+            .collect()
+    }
+}
+
+impl LocationCensorship {
+    /// Adds the payments and those of them that failed in the censored result to the counts
+    pub(crate) fn count(
+        counts: &mut LocationCounts,
+        locations: &PaymentLocations,
+        censored_result: &simlib::SimResult,
+    ) {
+        for location in locations.values() {
+            counts.entry(*location).or_default().0 += 1;
+        }
+        for p in censored_result.failed_payments.iter() {
+            if let Some(location) = locations.get(&p.payment_id) {
+                counts.entry(*location).or_default().1 += 1;
+            }
+        }
+    }
+
+    /// The pairs of ASs with the most censored payments
+    pub(crate) fn top_pairs(counts: LocationCounts) -> Vec<Self> {
+        let mut pairs: Vec<Self> = counts
+            .into_iter()
+            .filter(|(_, (_, num_censored))| *num_censored > 0)
+            .map(
+                |((sender_asn, receiver_asn), (num_payments, num_censored))| Self {
+                    sender_asn,
+                    receiver_asn,
+                    num_payments,
+                    num_censored,
+                    censorship_rate: num_censored as f32 / num_payments as f32,
+                },
+            )
+            .collect();
+        pairs.sort_by(|a, b| {
+            b.num_censored
+                .cmp(&a.num_censored)
+                .then(b.censorship_rate.total_cmp(&a.censorship_rate))
+                .then((a.sender_asn, a.receiver_asn).cmp(&(b.sender_asn, b.receiver_asn)))
+        });
+        pairs.truncate(NUM_LOCATION_PAIRS);
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn censorship_by_location() {
+        let payment = |id: usize, source: &str, dest: &str| {
+            simlib::payment::Payment::new(id, String::from(source), String::from(dest), 1000, None)
+        };
+        let baseline = simlib::SimResult {
+            num_succesful: 3,
+            total_num: 3,
+            successful_payments: vec![
+                payment(0, "alice", "dina"),
+                payment(1, "bob", "chan"),
+                payment(2, "chan", "dina"),
+            ],
+            ..Default::default()
+        };
+        let censored = simlib::SimResult {
+            num_succesful: 1,
+            num_failed: 2,
+            total_num: 3,
+            successful_payments: vec![payment(2, "chan", "dina")],
+            failed_payments: vec![payment(0, "alice", "dina"), payment(1, "bob", "chan")],
+            ..Default::default()
+        };
+        let as_ip_map = AsIpMap::from(HashMap::from([
+            (24940, vec![String::from("alice"), String::from("bob")]),
+            (797, vec![String::from("chan"), String::from("dina")]),
+        ]));
+        let locations = SimBuilder::payment_locations(&baseline, &as_ip_map);
+        let mut counts = LocationCounts::default();
+        LocationCensorship::count(&mut counts, &locations, &censored);
+        let actual = LocationCensorship::top_pairs(counts);
+        let expected = vec![LocationCensorship {
+            sender_asn: Some(24940),
+            receiver_asn: Some(797),
+            num_payments: 2,
+            num_censored: 2,
+            censorship_rate: 1.0,
+        }];
+        assert_eq!(actual, expected);
+    }
+}
@@ -1,7 +1,91 @@
+mod amounts;
+mod attribution;
+mod blacklist;
+mod blocklist;
+mod bootstrap;
+mod budget;
 mod builder;
 mod censor;
+mod checkpoint;
+mod chunked;
+mod collusion;
+mod coverage;
+mod diff;
+mod diversity;
+mod evasion;
+mod exposure;
+mod failures;
+mod freeze;
+mod growth;
+mod html;
+mod impact;
+mod location;
+mod observation;
 mod output;
+mod paths;
+mod payment_log;
+mod pipeline;
+mod placement;
+mod progress;
+mod prune;
+mod ranking;
+mod rate_limit;
+mod replay;
+mod report_diff;
+mod retry;
+mod revenue;
 mod runner;
+mod sample_size;
+mod sampling;
+mod series;
+mod sweep;
+mod telemetry;
+mod tor;
+mod transit;
+mod uncertainty;
+mod utility;
+mod validation;
 
+pub use amounts::*;
+pub use blacklist::*;
+pub use blocklist::*;
+pub use bootstrap::*;
+pub use budget::*;
 pub use builder::*;
+pub use checkpoint::*;
+pub use collusion::*;
+pub use coverage::*;
+pub use diff::*;
+pub use diversity::*;
+pub use evasion::*;
+pub use exposure::*;
+pub use failures::*;
+pub use freeze::*;
+pub use growth::*;
+pub use html::*;
+pub use impact::*;
+pub use location::*;
+pub use observation::*;
 pub use output::*;
+pub use paths::*;
+pub use payment_log::*;
+pub use pipeline::*;
+pub use placement::*;
+pub use progress::*;
+pub use prune::*;
+pub use ranking::*;
+pub use rate_limit::*;
+pub use replay::*;
+pub use report_diff::*;
+pub use retry::*;
+pub use revenue::*;
+pub use sample_size::*;
+pub use sampling::*;
+pub use series::*;
+pub use sweep::*;
+pub use telemetry::*;
+pub use tor::*;
+pub use transit::*;
+pub use uncertainty::*;
+pub use utility::*;
+pub use validation::*;
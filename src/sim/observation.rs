@@ -0,0 +1,197 @@
+use super::SimBuilder;
+use serde::{Deserialize, Serialize};
+use simlib::{payment::Payment, ID};
+use std::collections::{HashMap, HashSet};
+
+/// What an AS could see of the payments that succeeded in the baseline combined with what it
+/// dropped under its strategy. The four quadrants add up to the baseline's successful payments
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Observation {
+    /// Payments with at least one of the AS's nodes on a path
+    pub num_observed: usize,
+    /// Observed payments whose sender belongs to the AS or is the direct predecessor of an AS node
+    pub num_sender_exposed: usize,
+    /// Observed payments whose receiver belongs to the AS or is the direct successor of an AS node
+    pub num_receiver_exposed: usize,
+    pub observed_dropped: usize,
+    /// Observed dropped payments sent by one of the AS's nodes
+    pub dropped_as_sender: usize,
+    /// Observed dropped payments to one of the AS's nodes. Payments within the AS count as both
+    pub dropped_as_receiver: usize,
+    /// Observed dropped payments the AS only forwarded, i.e., neither sent nor received
+    pub dropped_as_forwarder: usize,
+    /// Payments the AS saw but let through, i.e., a privacy rather than a censorship risk
+    pub observed_forwarded: usize,
+    pub unobserved_dropped: usize,
+    pub unobserved_forwarded: usize,
+}
+
+/// What the AS learns about the endpoints of a single payment
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PaymentObservation {
+    pub(crate) sender_exposed: bool,
+    pub(crate) receiver_exposed: bool,
+    /// The AS's role in the payment as the sender's or the receiver's AS
+    pub(crate) is_sender: bool,
+    pub(crate) is_receiver: bool,
+}
+
+impl SimBuilder {
+    /// Returns what the AS sees of each successful payment that traverses one of its nodes
+    pub(crate) fn observe(
+        sim_result: &simlib::SimResult,
+        asn_nodes: &[ID],
+    ) -> HashMap<usize, PaymentObservation> {
+        sim_result
+            .successful_payments
+            .iter()
+            .filter(|p| Self::payment_involves_asn(p, asn_nodes))
+            .map(|p| (p.payment_id, Self::observe_payment(p, asn_nodes)))
+            .collect()
+    }
+
+    fn observe_payment(payment: &Payment, asn_nodes: &[ID]) -> PaymentObservation {
+        let mut observation = PaymentObservation {
+            is_sender: asn_nodes.contains(&payment.source),
+            is_receiver: asn_nodes.contains(&payment.dest),
+            ..Default::default()
+        };
+        for path in payment.used_paths.iter() {
+            let hops = path.path.get_involved_nodes();
+            let in_as = |i: usize| hops.get(i).is_some_and(|n| asn_nodes.contains(n));
+            let last = hops.len().saturating_sub(1);
+            observation.sender_exposed |= in_as(0) || in_as(1);
+            observation.receiver_exposed |= in_as(last) || (last > 0 && in_as(last - 1));
+        }
+        observation
+    }
+}
+
+impl Observation {
+    /// Combines what the AS observed with the payments that failed after applying its strategy.
+    /// `censorable` are the IDs of the payments that succeeded in the baseline
+    pub(crate) fn new(
+        observed: &HashMap<usize, PaymentObservation>,
+        censorable: &HashSet<usize>,
+        result: &simlib::SimResult,
+    ) -> Self {
+        let dropped: HashSet<usize> = result
+            .failed_payments
+            .iter()
+            .map(|p| p.payment_id)
+            .filter(|id| censorable.contains(id))
+            .collect();
+        let dropped_observations: Vec<&PaymentObservation> =
+            dropped.iter().filter_map(|id| observed.get(id)).collect();
+        let observed_dropped = dropped_observations.len();
+        Self {
+            num_observed: observed.len(),
+            num_sender_exposed: observed.values().filter(|o| o.sender_exposed).count(),
+            num_receiver_exposed: observed.values().filter(|o| o.receiver_exposed).count(),
+            observed_dropped,
+            dropped_as_sender: dropped_observations.iter().filter(|o| o.is_sender).count(),
+            dropped_as_receiver: dropped_observations
+                .iter()
+                .filter(|o| o.is_receiver)
+                .count(),
+            dropped_as_forwarder: dropped_observations
+                .iter()
+                .filter(|o| !o.is_sender && !o.is_receiver)
+                .count(),
+            observed_forwarded: observed.len() - observed_dropped,
+            unobserved_dropped: dropped.len() - observed_dropped,
+            unobserved_forwarded: censorable.len()
+                - observed.len()
+                - (dropped.len() - observed_dropped),
+        }
+    }
+
+    /// Adds the counts of another chunk of payments
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.num_observed += other.num_observed;
+        self.num_sender_exposed += other.num_sender_exposed;
+        self.num_receiver_exposed += other.num_receiver_exposed;
+        self.observed_dropped += other.observed_dropped;
+        self.dropped_as_sender += other.dropped_as_sender;
+        self.dropped_as_receiver += other.dropped_as_receiver;
+        self.dropped_as_forwarder += other.dropped_as_forwarder;
+        self.observed_forwarded += other.observed_forwarded;
+        self.unobserved_dropped += other.unobserved_dropped;
+        self.unobserved_forwarded += other.unobserved_forwarded;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simlib::CandidatePath;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn observation_quadrants() {
+        let payment = |id: usize, hops: &[&str]| {
+            let mut payment = Payment::new(
+                id,
+                hops[0].to_string(),
+                hops[hops.len() - 1].to_string(),
+                1,
+                None,
+            );
+            let mut path = simlib::Path::new(payment.source.clone(), payment.dest.clone());
+            path.hops = hops
+                .iter()
+                .map(|h| (h.to_string(), 0, 0, "".to_string()))
+                .collect::<VecDeque<_>>();
+            payment.succeeded = true;
+            payment.used_paths = vec![CandidatePath::new_with_path(path)];
+            payment
+        };
+        let baseline = simlib::SimResult {
+            num_succesful: 3,
+            total_num: 3,
+            successful_payments: vec![
+                payment(0, &["dina", "chan", "alice", "bob"]),
+                payment(1, &["alice", "chan", "bob"]),
+                payment(2, &["dina", "bob"]),
+            ],
+            ..Default::default()
+        };
+        let asn_nodes = vec![String::from("chan")];
+        let observed = SimBuilder::observe(&baseline, &asn_nodes);
+        assert_eq!(observed.len(), 2);
+        assert_eq!(
+            observed[&0],
+            PaymentObservation {
+                sender_exposed: true,
+                receiver_exposed: false,
+                is_sender: false,
+                is_receiver: false,
+            }
+        );
+        let censorable: HashSet<usize> = HashSet::from([0, 1, 2]);
+        let mut dropped = baseline.successful_payments[0].clone();
+        dropped.succeeded = false;
+        let result = simlib::SimResult {
+            successful_payments: baseline.successful_payments[1..].to_vec(),
+            failed_payments: vec![dropped],
+            ..Default::default()
+        };
+        let mut actual = Observation::new(&observed, &censorable, &result);
+        let expected = Observation {
+            num_observed: 2,
+            num_sender_exposed: 2,
+            num_receiver_exposed: 1,
+            observed_dropped: 1,
+            dropped_as_sender: 0,
+            dropped_as_receiver: 0,
+            dropped_as_forwarder: 1,
+            observed_forwarded: 1,
+            unobserved_dropped: 0,
+            unobserved_forwarded: 1,
+        };
+        assert_eq!(actual, expected);
+        actual.merge(&expected);
+        assert_eq!(actual.num_observed, 4);
+    }
+}
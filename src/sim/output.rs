@@ -1,44 +1,226 @@
-use log::{error, info};
-use serde::Serialize;
-use simlib::io::PaymentInfo;
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::{io::PaymentInfo, payment::Payment, ID};
 use std::{
+    collections::{BTreeMap, HashMap},
     error::Error,
     fs::{self, File},
-    path::PathBuf,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
 };
 
-use crate::PacketDropStrategy;
+use super::{
+    AdversaryUtility, AttributionBounds, BaselineFailures, BlacklistCensorship, BlocklistReport,
+    BudgetCurve, CoalitionPlacement, CoalitionSweep, ConfidenceInterval, CoverageCurve,
+    DefensiveRouting, EvasionReport, FreezeImpact, GrowthCurve, LocationCensorship, NetworkImpact,
+    Observation, OtherAsns, PairCoalitions, PairDraw, PathTable, RateLimiting, RetryOutcome,
+    SampleSize, SamplingWeights, SharedPaths, StrategyDiff, Telemetry, TorComparison,
+    TransitCensorship, ValueRanking,
+};
+use crate::{
+    net::Asn, AsIpMap, AsMetadata, Concentration, NodeAliases, PacketDropStrategy, ProviderGroup,
+    SimulatorError,
+};
+
+/// Version of the report's layout, stored as `schemaVersion`. Reports without one are version 1,
+/// which stored the run, the amounts and the concentration as an array. Version 2 stored only the
+/// name of a strategy's blacklist, version 3 stores its nodes as well
+pub const REPORT_SCHEMA_VERSION: u32 = 3;
 
-#[derive(Debug, Default, Serialize)]
+/// The run, the results of each amount and how concentrated the network is across ASs
+#[derive(Debug, Default)]
+pub struct Report(pub u64, pub Vec<SimOutput>, pub Option<Concentration>);
+
+/// The layout of a report file
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Report(pub u64, pub Vec<SimOutput>);
+struct VersionedReport<O, C> {
+    schema_version: u32,
+    run: u64,
+    outputs: O,
+    concentration: C,
+}
 
-#[derive(Debug, Default, Clone, Serialize)]
+impl Serialize for Report {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        VersionedReport {
+            schema_version: REPORT_SCHEMA_VERSION,
+            run: self.0,
+            outputs: &self.1,
+            concentration: &self.2,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SimOutput {
     pub amt_sat: usize,
     pub total_num_payments: usize,
+    /// Streamed as separate lines when streaming and thus empty in the amount's summary line
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub per_strategy_results: Vec<PerStrategyResults>,
+    /// Pairs of adversarial ASs that share nodes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adversary_overlap: Vec<AsOverlap>,
+    /// Results without any nodes under attack, only included if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BaselineResult>,
+    /// Payments censored by only one of two strategies, per adversarial AS
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strategy_diffs: Vec<StrategyDiff>,
+    /// Outcome of the blocklist scenario, if one was simulated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocklist: Option<BlocklistReport>,
+    /// Weights the destinations were drawn with, not present if they were drawn uniformly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling_weights: Option<SamplingWeights>,
+    /// Whether the amount simulated the pairs shared by all amounts or drew its own, shared in
+    /// reports of version 1
+    #[serde(default)]
+    pub pair_draw: PairDraw,
+    /// The model each payment's amount was drawn from, not present if all payments carry `amtSat`.
+    /// `amtSat` is then the median of the drawn amounts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_model: Option<String>,
+    /// Adversaries ranked by the value they could censor at this amount, if re-ranked per amount
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_ranking: Option<ValueRanking>,
+    /// The results with onion-only nodes excluded and included side by side, if both were simulated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tor_comparison: Option<TorComparison>,
+    /// Distinct paths the payments refer to by index, if the paths were deduplicated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paths: Option<SharedPaths>,
+    /// How the success rate recovers as victims open channels around each adversary, if simulated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evasion: Vec<EvasionReport>,
+    /// Censorship rates with AS-diverse routing next to the regular rates, if simulated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defensive_routing: Option<DefensiveRouting>,
+    /// Impact of each adversary freezing its nodes' liquidity instead of dropping, if simulated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub liquidity_freezes: Vec<FreezeImpact>,
+    /// The smallest coalition censoring the target share of the payments, if searched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coalition_placement: Option<CoalitionPlacement>,
+    /// Censorship rates of the coalitions of the top-n ASs, if swept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_as_sweep: Option<CoalitionSweep>,
+    /// Coverage of every pair of the top-k ASs colluding, if evaluated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pair_coalitions: Option<PairCoalitions>,
+    /// Payments each transit AS censors by carrying the IP traffic of channels, if AS paths were
+    /// inferred
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transit_censorship: Vec<TransitCensorship>,
+    /// Censorship rate of the growing AS per strategy and acquisition step, if simulated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub growth_curves: Vec<GrowthCurve>,
+    /// Censorship of each adversary per share of its forwarded payments it may drop, if requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub drop_budgets: Vec<BudgetCurve>,
+    /// Censorship rate per strategy and adversary controlling each share of its nodes, if swept
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coverage_curves: Vec<CoverageCurve>,
+    /// How the payments each adversary censors reroute around it, if requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_impact: Vec<NetworkImpact>,
+    /// Time and memory of the baseline and of each strategy per adversary, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telemetry: Option<Telemetry>,
+}
+
+/// The baseline is stored once per amount and referenced by its ID in each AttackSim
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BaselineResult {
+    pub id: String,
+    #[serde(flatten)]
+    pub result: SimResult,
 }
 
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PerStrategyResults {
     pub strategy: PacketDropStrategy,
-    /// Includes baseline results when no nodes are under attack
     pub attack_results: Vec<AttackSim>,
+    /// Adversaries that affected too few payments, if pruned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub other_asns: Option<OtherAsns>,
 }
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AttackSim {
     pub asn: String,
-    pub sim_results: Vec<SimResult>, // the first list is for the baseline
+    /// ID of the baseline these results can be compared to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_id: Option<String>,
+    pub sim_results: Vec<SimResult>,
+    /// Not present in the baseline or when all payments are dropped, so there is only one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_sim_accuracy: Option<PerSimAccuracy>,
+    /// Only present for PacketDropStrategy::SplitAware
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split_censorship: Option<SplitCensorship>,
+    /// Only present for PacketDropStrategy::Delay
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay_impact: Option<DelayImpact>,
+    /// Only present for PacketDropStrategy::AmountThreshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_buckets: Option<Vec<AmountBucket>>,
+    /// Only present for PacketDropStrategy::ProbeFilter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probe_filtering: Option<ProbeFiltering>,
+    /// Only present for PacketDropStrategy::DestinationBlacklist
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blacklist_censorship: Option<BlacklistCensorship>,
+    /// Only present for PacketDropStrategy::RateLimit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limiting: Option<RateLimiting>,
+    /// Only present if onion-only nodes are modelled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tor_exposure: Option<TorExposure>,
+    /// Whom the victims would blame, not present for PacketDropStrategy::Delay
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub misattribution: Option<Misattribution>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub economic_impact: Option<EconomicImpact>,
+    /// Pairs of sender and receiver ASs with the most censored payments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub location_censorship: Vec<LocationCensorship>,
+    /// What the AS saw of the payments and what it dropped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub observation: Option<Observation>,
+    /// Number of payments each rate is computed from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample_sizes: Vec<SampleSize>,
+    /// Bootstrapped 95% confidence intervals of the rates, if requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub confidence_intervals: Vec<ConfidenceInterval>,
+    /// Only present if senders retry censored payments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<RetryOutcome>,
+    /// Only present if the nodes' candidate ASs are tracked
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub per_sim_accuracy: Option<PerSimAccuracy>, // not present in baseline or when all are
-                                     // dropped so we only have one
+    pub attribution_bounds: Option<AttributionBounds>,
+    /// Only present for PacketDropStrategy::IntraProbability if a cost model is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub utility: Option<AdversaryUtility>,
+    /// Name, country and customer cone of the AS if its metadata is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_metadata: Option<AsMetadata>,
+    /// The provider and its ASs if the adversaries are hosting providers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderGroup>,
+    /// How many of the baseline's failed payments the strategy would have censored, if classified
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_failures: Option<BaselineFailures>,
 }
 
-#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SimResult {
     /// Number of nodes under attack which we only use for the baseline
@@ -46,34 +228,358 @@ pub struct SimResult {
     /// Successful payments
     pub num_successful: usize,
     pub num_failed: usize,
-    pub payments: Vec<PaymentInfo>,
+    /// Failed payments by cause, they add up to `numFailed` except in reports of version 1, which
+    /// did not classify them
+    #[serde(default)]
+    pub num_failed_baseline: usize,
+    #[serde(default)]
+    pub num_censored: usize,
+    #[serde(default)]
+    pub num_censored_then_reroute_failed: usize,
+    pub payments: Vec<ExportedPayment>,
+}
+
+/// A payment as exported to the report
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedPayment {
+    #[serde(flatten)]
+    pub info: PaymentInfo,
+    /// Only present for payments that succeeded in the baseline but were dropped by a strategy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drop_reason: Option<DropReason>,
+    /// Only present for failed payments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_cause: Option<FailureCause>,
+    /// Indices of the parts' paths in the amount's paths, only present if the paths were
+    /// deduplicated. The paths are then omitted from the payment
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_ids: Vec<usize>,
+    /// Aliases of the sender, the recipient and the hops of the parts in the baseline, only
+    /// present if the nodes' aliases are given
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<ID, String>,
+    /// ASNs of the sender, the hops and the recipient in the order they are first traversed, only
+    /// present for the successful payments of the baseline. Unmapped nodes are skipped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub as_path: Vec<Asn>,
+    /// Flat fields of the payment for columnar output formats
+    #[serde(skip)]
+    pub fields: PaymentFields,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PaymentFields {
+    pub payment_id: usize,
+    pub source: String,
+    pub dest: String,
+    pub amount_msat: usize,
+    pub succeeded: bool,
+    pub num_parts: usize,
+}
+
+/// Why a payment failed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FailureCause {
+    /// The payment already failed without an adversary, e.g. for lack of liquidity
+    FailedBaseline,
+    /// The payment succeeded in the baseline and was dropped by the adversary
+    Censored,
+    /// The payment was dropped and the sender's retries around the adversary failed as well
+    CensoredThenRerouteFailed,
+}
+
+/// Why a strategy dropped a payment
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DropReason {
+    /// Not read back, `Checkpoint` restores it from the strategies of the run
+    #[serde(skip_deserializing)]
+    pub strategy: PacketDropStrategy,
+    pub asn: Asn,
+    pub rule: DropRule,
+}
+
+/// The rule of a strategy that matched a dropped payment
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DropRule {
+    /// One of the AS's nodes is on a path of the payment
+    PathThroughAs,
+    /// The payment was dropped by a random draw
+    ProbDraw,
+    /// Sender and receiver belong to the AS
+    SrcAndDestInAs,
+    /// The payment passes the AS but its sender or receiver belongs to a different AS
+    LeavesAs,
+    /// The parts of the payment that avoid the AS do not add up to the amount
+    NoPartsAvoidAs,
+    /// A part of the payment is routed over a channel between two of the AS's nodes
+    ChannelWithinAs,
+    /// A part traversing the AS carries an amount the AS censors
+    AmountPastThreshold,
+    /// The payment does not look like a probe or was detected despite its disguise
+    RealLookingPayment,
+    /// A part of the payment was forwarded by the AS to a blacklisted node
+    BlacklistedNextHop,
+    /// One of the AS's nodes is on a path and the AS had drops left in the window
+    WithinRateLimit,
 }
 
-/// Number of correctly and falsely identified intra-AS payments for PacketDropStrategy::Intra
-#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+/// Number of correctly and falsely dropped payments w.r.t. the payments a strategy targets
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PerSimAccuracy {
     pub tpos: usize,
     pub fpos: usize,
     pub fneg: usize,
+    /// Not counted in reports of version 1
+    #[serde(default)]
+    pub tneg: usize,
+    /// Derived from the counts when a report of version 1 is read
+    #[serde(default)]
+    pub precision: f32,
+    #[serde(default)]
+    pub recall: f32,
+    #[serde(default)]
+    pub f1: f32,
+}
+
+/// How splitting payments into parts affected PacketDropStrategy::SplitAware
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitCensorship {
+    /// Payments with at least one part traversing the AS
+    pub num_affected_payments: usize,
+    /// Affected payments that succeeded because the remaining parts carried the full amount
+    pub num_surviving_payments: usize,
+    pub num_dropped_parts: usize,
+}
+
+/// Latency added by PacketDropStrategy::Delay
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DelayImpact {
+    pub num_delayed_payments: usize,
+    /// Added latency of the delayed payments
+    pub mean_added_latency_secs: f32,
+    pub median_added_latency_secs: u32,
+    pub p90_added_latency_secs: u32,
+    pub max_added_latency_secs: u32,
+    pub num_exceeding_timeout: usize,
+    /// Share of all successful payments that were delayed by more than the timeout
+    pub timeout_rate: f32,
+}
+
+/// Outcome of PacketDropStrategy::AmountThreshold for the payments whose amount is at least
+/// `lower_sat` and less than ten times that
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AmountBucket {
+    pub lower_sat: usize,
+    /// Payments that succeeded in the baseline
+    pub num_payments: usize,
+    /// Payments with at least one part traversing the AS
+    pub num_observed: usize,
+    pub num_dropped: usize,
+    /// Dropped payments whose total amount is on the side of the threshold the AS does not target,
+    /// e.g. large payments split into parts below the threshold
+    pub num_collateral: usize,
+    /// Share of the observed payments that were dropped
+    pub effectiveness: f32,
+}
+
+/// How probe-like payments evaded PacketDropStrategy::ProbeFilter
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeFiltering {
+    /// Payments with at least one node of the AS on a path
+    pub num_observed: usize,
+    /// Observed payments whose amount looks like a probe
+    pub num_low_value: usize,
+    /// Observed payments of a higher amount that their senders disguised as probes
+    pub num_disguised: usize,
+    /// Disguised payments the AS recognised and dropped
+    pub num_detected: usize,
+    pub num_dropped: usize,
+    /// Share of the observed payments that were not dropped
+    pub evasion_rate: f32,
+}
+
+/// How onion-only nodes protected the payments from the AS
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TorExposure {
+    pub num_onion_only_nodes: usize,
+    /// Onion-only nodes whose guard relay is in the AS
+    pub num_censorable_nodes: usize,
+    /// Successful baseline payments with an onion-only node on a path
+    pub num_tor_payments: usize,
+    /// Payments that were not censored but would have been if the AS could censor every onion-only
+    /// node
+    pub num_escaped: usize,
+}
+
+/// Routing fees the adversary's nodes gave up by censoring payments they would have forwarded
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EconomicImpact {
+    /// Fees the AS's nodes earned in the baseline
+    pub baseline_fees_msat: usize,
+    pub lost_fees_msat: usize,
+    /// Share of the baseline fees that were lost
+    pub lost_fee_share: f32,
+}
+
+/// ASs the victims of censored payments would accuse when blaming the last hop that responded
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Misattribution {
+    pub num_censored_payments: usize,
+    /// Share of censored payments blamed on an AS other than the adversary
+    pub misattribution_rate: f32,
+    /// Sorted in descending order of number of payments
+    pub accused_asns: Vec<AccusedAs>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccusedAs {
+    /// None if the accused node could not be mapped to an ASN
+    pub asn: Option<u32>,
+    pub num_payments: usize,
+}
+
+/// Number of nodes two adversarial ASs have in common
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AsOverlap {
+    pub first_asn: u32,
+    pub second_asn: u32,
+    pub num_shared_nodes: usize,
 }
 
 impl Report {
-    pub fn write_to_file(&self, path: PathBuf) -> Result<(), Box<dyn Error>> {
-        if fs::create_dir_all(&path).is_ok() {
-            self.to_json_file(path)?;
-        } else {
-            error!("Directory creation failed.");
+    /// Reads a compressed or plain JSON report, migrating it to the current schema
+    pub fn read_from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::from_json(read_report_json(path)?)
+    }
+
+    /// Rejects reports written by a newer version of the simulator
+    pub fn from_json(mut report: serde_json::Value) -> Result<Self, Box<dyn Error>> {
+        let version = Self::schema_version(&report)?;
+        if version > REPORT_SCHEMA_VERSION {
+            return Err(format!(
+                "Report schema version {} is newer than the supported version {}",
+                version, REPORT_SCHEMA_VERSION
+            )
+            .into());
+        }
+        if version == 1 {
+            report = Self::migrate_v1(report);
+        }
+        if version <= 2 {
+            report = Self::migrate_v2(report)?;
+        }
+        let mut report: VersionedReport<Vec<SimOutput>, Option<Concentration>> =
+            serde_json::from_value(report)?;
+        if version == 1 {
+            report
+                .outputs
+                .iter_mut()
+                .flat_map(|output| output.per_strategy_results.iter_mut())
+                .flat_map(|results| results.attack_results.iter_mut())
+                .for_each(|attack| {
+                    attack.per_sim_accuracy =
+                        attack.per_sim_accuracy.take().map(PerSimAccuracy::finalise)
+                });
+        }
+        Ok(Self(report.run, report.outputs, report.concentration))
+    }
+
+    fn schema_version(report: &serde_json::Value) -> Result<u32, Box<dyn Error>> {
+        match report {
+            serde_json::Value::Array(_) => Ok(1),
+            serde_json::Value::Object(fields) => fields
+                .get("schemaVersion")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32)
+                .ok_or_else(|| "Report without a schema version".into()),
+            _ => Err("Report is neither an array nor an object".into()),
         }
+    }
+
+    /// `[run, outputs, concentration]` to the named fields of version 2
+    fn migrate_v1(report: serde_json::Value) -> serde_json::Value {
+        let mut fields = match report {
+            serde_json::Value::Array(fields) => fields.into_iter(),
+            _ => vec![].into_iter(),
+        };
+        serde_json::json!({
+            "schemaVersion": 2,
+            "run": fields.next().unwrap_or_default(),
+            "outputs": fields.next().unwrap_or_else(|| serde_json::json!([])),
+            "concentration": fields.next().unwrap_or_default(),
+        })
+    }
+
+    /// Blacklists stored by their name to the name and nodes of version 3. The nodes cannot be
+    /// recovered, so reports with a blacklist are rejected
+    fn migrate_v2(mut report: serde_json::Value) -> Result<serde_json::Value, Box<dyn Error>> {
+        if let Some(name) = Self::name_only_blacklist(&report) {
+            return Err(format!(
+                "Report schema version 2 stores only the name of blacklist {}, not its nodes. \
+                 Rerun the simulation to read its results",
+                name
+            )
+            .into());
+        }
+        report["schemaVersion"] = serde_json::json!(3);
+        Ok(report)
+    }
+
+    /// The name of the first blacklist of PacketDropStrategy::DestinationBlacklist that is stored
+    /// without its nodes
+    fn name_only_blacklist(value: &serde_json::Value) -> Option<&str> {
+        match value {
+            serde_json::Value::Object(fields) => fields
+                .get("DestinationBlacklist")
+                .and_then(|strategy| strategy["blacklist"].as_str())
+                .or_else(|| fields.values().find_map(Self::name_only_blacklist)),
+            serde_json::Value::Array(values) => values.iter().find_map(Self::name_only_blacklist),
+            _ => None,
+        }
+    }
+
+    pub fn write_to_file(&self, path: PathBuf) -> Result<(), SimulatorError> {
+        fs::create_dir_all(&path)?;
+        self.to_json_file(path)
+    }
+
+    /// Writes the report to `simulation-run<run>.json.zst` in the directory
+    #[cfg(feature = "zstd")]
+    pub fn write_to_zstd_file(&self, path: PathBuf) -> Result<(), SimulatorError> {
+        fs::create_dir_all(&path)?;
+        let mut file_output_path = path;
+        file_output_path.push(format!("simulation-run{:?}.json.zst", self.0));
+        let encoder = zstd::Encoder::new(File::create(&file_output_path)?, 0)?;
+        let mut writer = BufWriter::new(encoder);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+        info!(
+            "Simulation output written to {}.",
+            file_output_path.display()
+        );
         Ok(())
     }
 
-    fn to_json_file(&self, output_path: PathBuf) -> Result<(), Box<dyn Error>> {
+    fn to_json_file(&self, output_path: PathBuf) -> Result<(), SimulatorError> {
         let run_as_string = format!("{}{:?}", "simulation-run", self.0);
         let mut file_output_path = output_path;
         file_output_path.push(format!("{}{}", run_as_string, ".json"));
-        let file = File::create(file_output_path.clone()).expect("Error creating file.");
-        serde_json::to_writer_pretty(file, self).expect("Error writing to JSON file.");
+        let file = File::create(file_output_path.clone())?;
+        serde_json::to_writer_pretty(file, self)?;
         info!(
             "Simulation output written to {}.",
             file_output_path.display()
@@ -81,23 +587,786 @@ impl Report {
         Ok(())
     }
 }
+#[cfg(feature = "parquet")]
+impl Report {
+    /// Writes one row per payment to `simulation-run<run>.parquet` in the directory, one row group
+    /// per amount, and returns its path
+    pub fn write_to_parquet(&self, output_dir: PathBuf) -> Result<PathBuf, Box<dyn Error>> {
+        use arrow::{
+            array::{ArrayRef, BooleanArray, StringArray, UInt32Array, UInt64Array},
+            datatypes::{DataType, Field, Schema},
+            record_batch::RecordBatch,
+        };
+        use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+        use std::sync::Arc;
+
+        fs::create_dir_all(&output_dir)?;
+        let mut path = output_dir;
+        path.push(format!("simulation-run{}.parquet", self.0));
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("run", DataType::UInt64, false),
+            Field::new("amt_sat", DataType::UInt64, false),
+            Field::new("strategy", DataType::Utf8, false),
+            Field::new("asn", DataType::Utf8, true),
+            Field::new("payment_id", DataType::UInt64, false),
+            Field::new("source", DataType::Utf8, false),
+            Field::new("dest", DataType::Utf8, false),
+            Field::new("amount_msat", DataType::UInt64, false),
+            Field::new("succeeded", DataType::Boolean, false),
+            Field::new("num_parts", DataType::UInt32, false),
+            Field::new("drop_rule", DataType::Utf8, true),
+        ]));
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer = ArrowWriter::try_new(File::create(&path)?, schema.clone(), Some(props))?;
+        for output in self.1.iter() {
+            // (strategy, asn, payment)
+            let mut rows: Vec<(String, Option<&str>, &ExportedPayment)> = vec![];
+            if let Some(baseline) = &output.baseline {
+                rows.extend(
+                    baseline
+                        .result
+                        .payments
+                        .iter()
+                        .map(|p| (String::from("baseline"), None, p)),
+                );
+            }
+            for results in output.per_strategy_results.iter() {
+                let strategy = format!("{:?}", results.strategy);
+                for attack in results.attack_results.iter() {
+                    for sim_result in attack.sim_results.iter() {
+                        rows.extend(
+                            sim_result
+                                .payments
+                                .iter()
+                                .map(|p| (strategy.clone(), Some(attack.asn.as_str()), p)),
+                        );
+                    }
+                }
+            }
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(UInt64Array::from(vec![self.0; rows.len()])),
+                Arc::new(UInt64Array::from(vec![output.amt_sat as u64; rows.len()])),
+                Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.0))),
+                Arc::new(StringArray::from_iter(rows.iter().map(|r| r.1))),
+                Arc::new(UInt64Array::from_iter_values(
+                    rows.iter().map(|r| r.2.fields.payment_id as u64),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    rows.iter().map(|r| &r.2.fields.source),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    rows.iter().map(|r| &r.2.fields.dest),
+                )),
+                Arc::new(UInt64Array::from_iter_values(
+                    rows.iter().map(|r| r.2.fields.amount_msat as u64),
+                )),
+                Arc::new(BooleanArray::from_iter(
+                    rows.iter().map(|r| Some(r.2.fields.succeeded)),
+                )),
+                Arc::new(UInt32Array::from_iter_values(
+                    rows.iter().map(|r| r.2.fields.num_parts as u32),
+                )),
+                Arc::new(StringArray::from_iter(rows.iter().map(|r| {
+                    r.2.drop_reason
+                        .as_ref()
+                        .map(|reason| format!("{:?}", reason.rule))
+                }))),
+            ];
+            writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+        }
+        writer.close()?;
+        info!("Simulation output written to {}.", path.display());
+        Ok(path)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+static SQLITE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    seed INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE TABLE IF NOT EXISTS amounts (
+    id INTEGER PRIMARY KEY,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    amt_sat INTEGER NOT NULL,
+    total_num_payments INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS strategies (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS asns (
+    id INTEGER PRIMARY KEY,
+    asn TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS results (
+    id INTEGER PRIMARY KEY,
+    amount_id INTEGER NOT NULL REFERENCES amounts(id),
+    strategy_id INTEGER NOT NULL REFERENCES strategies(id),
+    asn_id INTEGER REFERENCES asns(id),
+    num_nodes_under_attack INTEGER NOT NULL,
+    num_successful INTEGER NOT NULL,
+    num_failed INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS payments (
+    result_id INTEGER NOT NULL REFERENCES results(id),
+    payment_id INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    dest TEXT NOT NULL,
+    amount_msat INTEGER NOT NULL,
+    succeeded INTEGER NOT NULL,
+    num_parts INTEGER NOT NULL,
+    drop_rule TEXT
+);";
+
+#[cfg(feature = "sqlite")]
+impl Report {
+    /// Appends the report to the SQLite database at `path`, creating the tables if they do not
+    /// exist. Each call adds a new run, so the results of several campaigns can be queried together.
+    /// The baseline is stored as the strategy `baseline` without an ASN. Returns the ID of the run
+    pub fn write_to_sqlite(&self, path: &std::path::Path) -> Result<i64, Box<dyn Error>> {
+        use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+        fn id_of(
+            tx: &Transaction,
+            table: &str,
+            column: &str,
+            value: &str,
+        ) -> rusqlite::Result<i64> {
+            let select = format!("SELECT id FROM {table} WHERE {column} = ?1");
+            if let Some(id) = tx
+                .query_row(&select, params![value], |row| row.get(0))
+                .optional()?
+            {
+                return Ok(id);
+            }
+            tx.execute(
+                &format!("INSERT INTO {table} ({column}) VALUES (?1)"),
+                params![value],
+            )?;
+            Ok(tx.last_insert_rowid())
+        }
+
+        fn insert_result(
+            tx: &Transaction,
+            amount_id: i64,
+            strategy_id: i64,
+            asn_id: Option<i64>,
+            result: &SimResult,
+        ) -> rusqlite::Result<()> {
+            tx.execute(
+                "INSERT INTO results (amount_id, strategy_id, asn_id, num_nodes_under_attack, \
+                num_successful, num_failed) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    amount_id,
+                    strategy_id,
+                    asn_id,
+                    result.num_nodes_under_attack as i64,
+                    result.num_successful as i64,
+                    result.num_failed as i64
+                ],
+            )?;
+            let result_id = tx.last_insert_rowid();
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO payments (result_id, payment_id, source, dest, amount_msat, \
+                succeeded, num_parts, drop_rule) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for p in result.payments.iter() {
+                insert.execute(params![
+                    result_id,
+                    p.fields.payment_id as i64,
+                    p.fields.source,
+                    p.fields.dest,
+                    p.fields.amount_msat as i64,
+                    p.fields.succeeded,
+                    p.fields.num_parts as i64,
+                    p.drop_reason
+                        .as_ref()
+                        .map(|reason| format!("{:?}", reason.rule))
+                ])?;
+            }
+            Ok(())
+        }
+
+        let mut conn = Connection::open(path)?;
+        conn.execute_batch(SQLITE_SCHEMA)?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO runs (seed) VALUES (?1)",
+            params![self.0 as i64],
+        )?;
+        let run_id = tx.last_insert_rowid();
+        for output in self.1.iter() {
+            tx.execute(
+                "INSERT INTO amounts (run_id, amt_sat, total_num_payments) VALUES (?1, ?2, ?3)",
+                params![
+                    run_id,
+                    output.amt_sat as i64,
+                    output.total_num_payments as i64
+                ],
+            )?;
+            let amount_id = tx.last_insert_rowid();
+            if let Some(baseline) = &output.baseline {
+                let strategy_id = id_of(&tx, "strategies", "name", "baseline")?;
+                insert_result(&tx, amount_id, strategy_id, None, &baseline.result)?;
+            }
+            for results in output.per_strategy_results.iter() {
+                let strategy_id = id_of(
+                    &tx,
+                    "strategies",
+                    "name",
+                    &format!("{:?}", results.strategy),
+                )?;
+                for attack in results.attack_results.iter() {
+                    let asn_id = id_of(&tx, "asns", "asn", &attack.asn)?;
+                    for sim_result in attack.sim_results.iter() {
+                        insert_result(&tx, amount_id, strategy_id, Some(asn_id), sim_result)?;
+                    }
+                }
+            }
+        }
+        tx.commit()?;
+        info!(
+            "Simulation output appended to {} as run {}.",
+            path.display(),
+            run_id
+        );
+        Ok(run_id)
+    }
+}
+
+/// Writes the results of each strategy to a newline-delimited JSON file as soon as they are
+/// available instead of keeping every payment in memory until the end of the simulation
+pub struct ReportWriter {
+    run: u64,
+    path: PathBuf,
+    writer: Mutex<BufWriter<Box<dyn Write + Send>>>,
+}
+
+/// The first bytes of a zstd frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Opens a JSON or newline-delimited JSON report, decompressing it if it is zstd-compressed
+pub fn open_report(path: &Path) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if !reader.fill_buf()?.starts_with(&ZSTD_MAGIC) {
+        return Ok(Box::new(reader));
+    }
+    decompress(reader, path)
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(reader: BufReader<File>, _path: &Path) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(
+        reader,
+    )?)))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress(_reader: BufReader<File>, path: &Path) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    Err(format!(
+        "{} is compressed, which requires the zstd feature",
+        path.display()
+    )
+    .into())
+}
+
+/// Reads a compressed or plain JSON report, e.g. simulation-run<RUN>.json.zst
+pub fn read_report_json(path: &Path) -> Result<serde_json::Value, Box<dyn Error>> {
+    let mut content = String::new();
+    open_report(path)?.read_to_string(&mut content)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// A single line of the streamed report
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StrategyRecord<'a> {
+    run: u64,
+    amt_sat: usize,
+    total_num_payments: usize,
+    #[serde(flatten)]
+    results: &'a PerStrategyResults,
+}
+
+/// The first line of the streamed report
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HeaderRecord<'a> {
+    schema_version: u32,
+    run: u64,
+    concentration: &'a Concentration,
+}
+
+/// The line holding the baseline of an amount in the streamed report
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BaselineRecord<'a> {
+    run: u64,
+    amt_sat: usize,
+    total_num_payments: usize,
+    baseline: &'a BaselineResult,
+}
+
+/// The line holding the deduplicated paths of an amount in the streamed report
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PathsRecord<'a> {
+    run: u64,
+    amt_sat: usize,
+    paths: &'a SharedPaths,
+}
+
+/// The line holding the remaining results of an amount, e.g. the adversaries' overlap, in the
+/// streamed report
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SummaryRecord<'a> {
+    run: u64,
+    #[serde(flatten)]
+    output: &'a SimOutput,
+}
+
+impl ReportWriter {
+    /// Creates `simulation-run<run>.ndjson` in the directory, truncating any existing file
+    pub fn new(output_dir: PathBuf, run: u64) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(&output_dir)?;
+        let mut path = output_dir;
+        path.push(format!("{}{:?}{}", "simulation-run", run, ".ndjson"));
+        let file = File::create(&path)?;
+        info!("Simulation output will be streamed to {}.", path.display());
+        Ok(Self {
+            run,
+            path,
+            writer: Mutex::new(BufWriter::new(Box::new(file))),
+        })
+    }
+
+    /// Creates `simulation-run<run>.ndjson.zst` in the directory instead. The frame is completed
+    /// when the writer is dropped
+    #[cfg(feature = "zstd")]
+    pub fn new_compressed(output_dir: PathBuf, run: u64) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(&output_dir)?;
+        let mut path = output_dir;
+        path.push(format!("simulation-run{:?}.ndjson.zst", run));
+        let encoder = zstd::Encoder::new(File::create(&path)?, 0)?.auto_finish();
+        info!("Simulation output will be streamed to {}.", path.display());
+        Ok(Self {
+            run,
+            path,
+            writer: Mutex::new(BufWriter::new(Box::new(encoder))),
+        })
+    }
+
+    /// Serialises the concentration of the network as a single line and flushes it to disk
+    pub fn write_header(&self, concentration: &Concentration) -> Result<(), Box<dyn Error>> {
+        let record = HeaderRecord {
+            schema_version: REPORT_SCHEMA_VERSION,
+            run: self.run,
+            concentration,
+        };
+        self.write_line(&record)
+    }
+
+    /// Serialises the results of one strategy as a single line and flushes it to disk
+    pub fn write_strategy_results(
+        &self,
+        amt_sat: usize,
+        total_num_payments: usize,
+        results: &PerStrategyResults,
+    ) -> Result<(), Box<dyn Error>> {
+        let record = StrategyRecord {
+            run: self.run,
+            amt_sat,
+            total_num_payments,
+            results,
+        };
+        self.write_line(&record)
+    }
+
+    /// Serialises the baseline of an amount as a single line and flushes it to disk
+    pub fn write_baseline(
+        &self,
+        amt_sat: usize,
+        total_num_payments: usize,
+        baseline: &BaselineResult,
+    ) -> Result<(), Box<dyn Error>> {
+        let record = BaselineRecord {
+            run: self.run,
+            amt_sat,
+            total_num_payments,
+            baseline,
+        };
+        self.write_line(&record)
+    }
+
+    /// Serialises the paths the payments of an amount refer to as a single line and flushes it to
+    /// disk
+    pub fn write_paths(&self, amt_sat: usize, paths: &SharedPaths) -> Result<(), Box<dyn Error>> {
+        let record = PathsRecord {
+            run: self.run,
+            amt_sat,
+            paths,
+        };
+        self.write_line(&record)
+    }
+
+    /// Serialises the per-amount results not covered by the other lines as a single line and
+    /// flushes it to disk. Write the strategies, baseline and paths first and take them out of the
+    /// output so that they are not repeated
+    pub fn write_summary(&self, output: &SimOutput) -> Result<(), Box<dyn Error>> {
+        let record = SummaryRecord {
+            run: self.run,
+            output,
+        };
+        self.write_line(&record)
+    }
+
+    fn write_line(&self, record: &impl Serialize) -> Result<(), Box<dyn Error>> {
+        let line = serde_json::to_string(record)?;
+        let mut writer = self.writer.lock().map_err(|e| e.to_string())?;
+        writeln!(writer, "{}", line)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+impl PerSimAccuracy {
+    /// Counts the outcome of a single payment that succeeded in the baseline
+    pub(crate) fn record(&mut self, dropped: bool, target: bool) {
+        match (dropped, target) {
+            (true, true) => self.tpos += 1,
+            (true, false) => self.fpos += 1,
+            (false, true) => self.fneg += 1,
+            (false, false) => self.tneg += 1,
+        }
+    }
+
+    /// Adds the counts of another simulation, e.g. over a different chunk of payments
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.tpos += other.tpos;
+        self.fpos += other.fpos;
+        self.fneg += other.fneg;
+        self.tneg += other.tneg;
+    }
+
+    /// Derives precision, recall and F1 from the counts. Undefined scores are set to 0
+    pub(crate) fn finalise(mut self) -> Self {
+        let ratio = |a: usize, b: usize| if b > 0 { a as f32 / b as f32 } else { 0.0 };
+        self.precision = ratio(self.tpos, self.tpos + self.fpos);
+        self.recall = ratio(self.tpos, self.tpos + self.fneg);
+        self.f1 = if self.precision + self.recall > 0.0 {
+            2.0 * self.precision * self.recall / (self.precision + self.recall)
+        } else {
+            0.0
+        };
+        self
+    }
+}
+
+impl SplitCensorship {
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.num_affected_payments += other.num_affected_payments;
+        self.num_surviving_payments += other.num_surviving_payments;
+        self.num_dropped_parts += other.num_dropped_parts;
+    }
+}
+
+impl AmountBucket {
+    /// The bucket of an amount in msat, i.e., its order of magnitude in sat
+    pub(crate) fn lower_sat(amount_msat: usize) -> usize {
+        let sat = amount_msat / 1000;
+        if sat == 0 {
+            0
+        } else {
+            10usize.pow(sat.ilog10())
+        }
+    }
+
+    /// Adds the counts of the buckets in `other` to those with the same bounds in `buckets`
+    pub(crate) fn merge(buckets: &mut BTreeMap<usize, Self>, other: &[Self]) {
+        for bucket in other.iter() {
+            let merged = buckets.entry(bucket.lower_sat).or_insert_with(|| Self {
+                lower_sat: bucket.lower_sat,
+                ..Default::default()
+            });
+            merged.num_payments += bucket.num_payments;
+            merged.num_observed += bucket.num_observed;
+            merged.num_dropped += bucket.num_dropped;
+            merged.num_collateral += bucket.num_collateral;
+        }
+    }
+
+    /// Sorts the buckets by amount and derives the effectiveness from the counts
+    pub(crate) fn finalise(buckets: BTreeMap<usize, Self>) -> Vec<Self> {
+        buckets
+            .into_values()
+            .map(|mut bucket| {
+                bucket.effectiveness = if bucket.num_observed > 0 {
+                    bucket.num_dropped as f32 / bucket.num_observed as f32
+                } else {
+                    0.0
+                };
+                bucket
+            })
+            .collect()
+    }
+}
+
+impl ProbeFiltering {
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.num_observed += other.num_observed;
+        self.num_low_value += other.num_low_value;
+        self.num_disguised += other.num_disguised;
+        self.num_detected += other.num_detected;
+        self.num_dropped += other.num_dropped;
+    }
+
+    /// Derives the evasion rate from the counts
+    pub(crate) fn finalise(mut self) -> Self {
+        self.evasion_rate = if self.num_observed > 0 {
+            (self.num_observed - self.num_dropped) as f32 / self.num_observed as f32
+        } else {
+            0.0
+        };
+        self
+    }
+}
+
+impl DelayImpact {
+    /// Summarises the added latencies given as the number of payments per latency
+    pub(crate) fn from_latencies(
+        added_latencies: &BTreeMap<u32, usize>,
+        timeout_secs: u32,
+        num_successful: usize,
+    ) -> Self {
+        let num_delayed_payments: usize = added_latencies.values().sum();
+        let percentile = |p: usize| {
+            if num_delayed_payments == 0 {
+                return 0;
+            }
+            let index = (num_delayed_payments - 1) * p / 100;
+            let mut seen = 0;
+            for (latency, count) in added_latencies.iter() {
+                seen += count;
+                if seen > index {
+                    return *latency;
+                }
+            }
+            0
+        };
+        let num_exceeding_timeout = added_latencies
+            .range(timeout_secs.saturating_add(1)..)
+            .map(|(_, count)| count)
+            .sum();
+        Self {
+            num_delayed_payments,
+            mean_added_latency_secs: if num_delayed_payments > 0 {
+                added_latencies
+                    .iter()
+                    .map(|(latency, count)| *latency as usize * count)
+                    .sum::<usize>() as f32
+                    / num_delayed_payments as f32
+            } else {
+                0.0
+            },
+            median_added_latency_secs: percentile(50),
+            p90_added_latency_secs: percentile(90),
+            max_added_latency_secs: percentile(100),
+            num_exceeding_timeout,
+            timeout_rate: if num_successful > 0 {
+                num_exceeding_timeout as f32 / num_successful as f32
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+impl EconomicImpact {
+    pub(crate) fn new(baseline_fees_msat: usize, remaining_fees_msat: usize) -> Self {
+        let lost_fees_msat = baseline_fees_msat.saturating_sub(remaining_fees_msat);
+        Self {
+            baseline_fees_msat,
+            lost_fees_msat,
+            lost_fee_share: if baseline_fees_msat > 0 {
+                lost_fees_msat as f32 / baseline_fees_msat as f32
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+impl PacketDropStrategy {
+    /// The rule that matches payments dropped by the strategy. None if no payments are dropped
+    pub fn drop_rule(&self) -> Option<DropRule> {
+        match self {
+            Self::All => Some(DropRule::PathThroughAs),
+            Self::IntraProbability => Some(DropRule::ProbDraw),
+            Self::IntraAs => Some(DropRule::SrcAndDestInAs),
+            Self::InterAs => Some(DropRule::LeavesAs),
+            Self::SplitAware => Some(DropRule::NoPartsAvoidAs),
+            Self::IntraAsChannel => Some(DropRule::ChannelWithinAs),
+            Self::Delay { .. } => None,
+            Self::AmountThreshold { .. } => Some(DropRule::AmountPastThreshold),
+            Self::ProbeFilter { .. } => Some(DropRule::RealLookingPayment),
+            Self::DestinationBlacklist { .. } => Some(DropRule::BlacklistedNextHop),
+            Self::RateLimit { .. } => Some(DropRule::WithinRateLimit),
+        }
+    }
+}
+
+impl BaselineResult {
+    pub fn new(amt_sat: usize, sim_results: simlib::SimResult, paths: Option<&PathTable>) -> Self {
+        Self {
+            id: format!("baseline-{}sat", amt_sat),
+            result: SimResult::with_drop_reasons(sim_results, 0, |_| None, paths),
+        }
+    }
+}
+
+impl ExportedPayment {
+    /// Refers to the payment's paths in `paths` instead of storing them if a table is given
+    fn new(payment: &Payment, drop_reason: Option<DropReason>, paths: Option<&PathTable>) -> Self {
+        let (info, path_ids) = match paths {
+            Some(paths) => {
+                let path_ids = paths.intern_payment(payment);
+                let mut without_paths = payment.clone();
+                without_paths.used_paths = vec![];
+                (PaymentInfo::from_payment(&without_paths), path_ids)
+            }
+            None => (PaymentInfo::from_payment(payment), vec![]),
+        };
+        let failure_cause = match (payment.succeeded, &drop_reason) {
+            (true, _) => None,
+            (false, Some(_)) => Some(FailureCause::Censored),
+            (false, None) => Some(FailureCause::FailedBaseline),
+        };
+        Self {
+            info,
+            drop_reason,
+            failure_cause,
+            path_ids,
+            aliases: BTreeMap::new(),
+            as_path: vec![],
+            fields: PaymentFields {
+                payment_id: payment.payment_id,
+                source: payment.source.clone(),
+                dest: payment.dest.clone(),
+                amount_msat: payment.amount,
+                succeeded: payment.succeeded,
+                num_parts: payment.used_paths.len(),
+            },
+        }
+    }
+}
+
 impl SimResult {
+    /// The sender, recipient and hops of each payment of the baseline by payment ID
+    pub(crate) fn payment_nodes(baseline: &simlib::SimResult) -> HashMap<usize, Vec<ID>> {
+        baseline
+            .successful_payments
+            .iter()
+            .chain(baseline.failed_payments.iter())
+            .map(|p| {
+                let mut nodes = vec![p.source.clone(), p.dest.clone()];
+                nodes.extend(
+                    p.used_paths
+                        .iter()
+                        .flat_map(|path| path.path.get_involved_nodes()),
+                );
+                (p.payment_id, nodes)
+            })
+            .collect()
+    }
+
+    /// Labels the nodes of each payment with their aliases. The hops are those of the baseline as
+    /// dropped payments have no paths
+    pub(crate) fn label_nodes(
+        &mut self,
+        payment_nodes: &HashMap<usize, Vec<ID>>,
+        aliases: &NodeAliases,
+    ) {
+        for payment in self.payments.iter_mut() {
+            payment.aliases = payment_nodes
+                .get(&payment.fields.payment_id)
+                .into_iter()
+                .flatten()
+                .filter_map(|node| aliases.get(node).map(|alias| (node.clone(), alias.clone())))
+                .collect();
+        }
+    }
+
+    /// Records the ASs each successful payment of the baseline traverses, each AS once
+    pub(crate) fn record_as_paths(&mut self, baseline: &simlib::SimResult, as_ip_map: &AsIpMap) {
+        let as_paths: HashMap<usize, Vec<Asn>> = baseline
+            .successful_payments
+            .iter()
+            .map(|p| {
+                let mut as_path: Vec<Asn> = vec![];
+                for asn in p
+                    .used_paths
+                    .iter()
+                    .flat_map(|path| path.path.get_involved_nodes())
+                    .filter_map(|node| as_ip_map.asn_of(&node))
+                {
+                    if !as_path.contains(&asn) {
+                        as_path.push(asn);
+                    }
+                }
+                (p.payment_id, as_path)
+            })
+            .collect();
+        for payment in self.payments.iter_mut().filter(|p| p.fields.succeeded) {
+            if let Some(as_path) = as_paths.get(&payment.fields.payment_id) {
+                payment.as_path = as_path.clone();
+            }
+        }
+    }
+
     pub fn from_simlib_results(sim_results: simlib::SimResult, num_nodes: usize) -> Self {
-        let mut payments: Vec<PaymentInfo> = sim_results
+        Self::with_drop_reasons(sim_results, num_nodes, |_| None, None)
+    }
+
+    /// Attaches the reason returned by `drop_reason` to each failed payment. The payments' paths
+    /// are interned in `paths` if a table is given
+    pub(crate) fn with_drop_reasons(
+        sim_results: simlib::SimResult,
+        num_nodes: usize,
+        drop_reason: impl Fn(&Payment) -> Option<DropReason>,
+        paths: Option<&PathTable>,
+    ) -> Self {
+        let mut payments: Vec<ExportedPayment> = sim_results
             .successful_payments
             .iter()
-            .map(PaymentInfo::from_payment)
+            .map(|p| ExportedPayment::new(p, None, paths))
             .collect();
         payments.extend(
             sim_results
                 .failed_payments
                 .iter()
-                .map(PaymentInfo::from_payment),
+                .map(|p| ExportedPayment::new(p, drop_reason(p), paths)),
         );
+        let num_censored = payments
+            .iter()
+            .filter(|p| p.failure_cause == Some(FailureCause::Censored))
+            .count();
         Self {
             num_nodes_under_attack: num_nodes,
             num_successful: sim_results.num_succesful,
             num_failed: sim_results.num_failed,
+            num_failed_baseline: sim_results.num_failed.saturating_sub(num_censored),
+            num_censored,
+            num_censored_then_reroute_failed: 0,
             payments,
         }
     }
@@ -137,24 +1406,95 @@ mod tests {
             ..Default::default()
         };
         let actual = SimResult::from_simlib_results(sim_result.clone(), 0);
-        let mut payments: Vec<PaymentInfo> = sim_result
+        let mut payments: Vec<ExportedPayment> = sim_result
             .successful_payments
             .iter()
-            .map(PaymentInfo::from_payment)
+            .map(|p| ExportedPayment::new(p, None, None))
             .collect();
         payments.extend(
             sim_result
                 .failed_payments
                 .iter()
-                .map(PaymentInfo::from_payment),
+                .map(|p| ExportedPayment::new(p, None, None)),
         );
+        assert_eq!(payments[2].fields.payment_id, 1);
+        assert!(!payments[2].fields.succeeded);
+        assert_eq!(payments[0].fields.num_parts, 1);
         let expected = SimResult {
             num_nodes_under_attack: 0,
             num_successful: 2,
             num_failed: 1,
+            num_failed_baseline: 1,
+            num_censored: 0,
+            num_censored_then_reroute_failed: 0,
             payments,
         };
+        assert_eq!(
+            expected.payments[2].failure_cause,
+            Some(FailureCause::FailedBaseline)
+        );
         assert_eq!(actual, expected);
+        let as_ip_map = AsIpMap::from(HashMap::from([
+            (24940, vec![String::from("alice"), String::from("bob")]),
+            (797, vec![String::from("chan"), String::from("dina")]),
+        ]));
+        let mut with_as_paths = expected.clone();
+        with_as_paths.record_as_paths(&sim_result, &as_ip_map);
+        assert_eq!(with_as_paths.payments[0].as_path, vec![797, 24940]);
+        assert!(with_as_paths.payments[2].as_path.is_empty());
+        let reason = DropReason {
+            strategy: PacketDropStrategy::All,
+            asn: 797,
+            rule: DropRule::PathThroughAs,
+        };
+        let paths = PathTable::default();
+        let deduplicated =
+            SimResult::with_drop_reasons(sim_result.clone(), 0, |_| None, Some(&paths));
+        assert_eq!(paths.len(), 1);
+        assert_eq!(deduplicated.payments[0].path_ids, vec![0]);
+        assert_eq!(deduplicated.payments[1].path_ids, vec![0]);
+        assert!(deduplicated.payments[2].path_ids.is_empty());
+        assert_eq!(deduplicated.payments[0].fields, expected.payments[0].fields);
+        assert!(
+            serde_json::to_string(&deduplicated.payments[0])
+                .unwrap()
+                .len()
+                < serde_json::to_string(&expected.payments[0]).unwrap().len()
+        );
+        let actual = SimResult::with_drop_reasons(sim_result, 0, |_| Some(reason.clone()), None);
+        assert!(actual.payments[..2].iter().all(|p| p.drop_reason.is_none()));
+        assert_eq!(actual.payments[2].drop_reason, Some(reason));
+        assert_eq!(
+            actual.payments[2].failure_cause,
+            Some(FailureCause::Censored)
+        );
+        assert_eq!((actual.num_failed_baseline, actual.num_censored), (0, 1));
+        let json = serde_json::to_value(&actual.payments[2]).unwrap();
+        assert_eq!(json["dropReason"]["rule"], "pathThroughAs");
+        assert_eq!(
+            PacketDropStrategy::All.drop_rule(),
+            Some(DropRule::PathThroughAs)
+        );
+    }
+
+    #[test]
+    fn accuracy_scores() {
+        let mut accuracy = PerSimAccuracy::default();
+        accuracy.record(true, true);
+        accuracy.record(true, true);
+        accuracy.record(true, false);
+        accuracy.record(false, true);
+        accuracy.record(false, false);
+        let actual = accuracy.finalise();
+        assert_eq!(
+            (actual.tpos, actual.fpos, actual.fneg, actual.tneg),
+            (2, 1, 1, 1)
+        );
+        assert_eq!(actual.precision, 2.0 / 3.0);
+        assert_eq!(actual.recall, 2.0 / 3.0);
+        assert!((actual.f1 - 2.0 / 3.0).abs() < f32::EPSILON);
+        let actual = PerSimAccuracy::default().finalise();
+        assert_eq!(actual.f1, 0.0);
     }
 
     #[test]
@@ -162,5 +1502,290 @@ mod tests {
         let path = TempDir::new().expect("Error opening tempfile");
         let report = Report::default();
         assert!(report.write_to_file(PathBuf::from(path.path())).is_ok());
+        let json = read_report_json(&path.path().join("simulation-run0.json")).unwrap();
+        assert_eq!(json["schemaVersion"], REPORT_SCHEMA_VERSION);
+        assert_eq!(json["run"], 0);
+    }
+
+    #[test]
+    fn read_and_migrate() {
+        let path = TempDir::new().expect("Error opening tempfile");
+        let output = SimOutput {
+            amt_sat: 1000,
+            total_num_payments: 10,
+            per_strategy_results: vec![PerStrategyResults {
+                strategy: PacketDropStrategy::All,
+                attack_results: vec![AttackSim {
+                    asn: String::from("24940"),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let report = Report(19, vec![output.clone()], None);
+        report.write_to_file(PathBuf::from(path.path())).unwrap();
+        let read = Report::read_from_file(&path.path().join("simulation-run19.json")).unwrap();
+        assert_eq!(read.0, 19);
+        assert_eq!(read.1[0].amt_sat, 1000);
+        assert_eq!(
+            read.1[0].per_strategy_results[0].attack_results[0].asn,
+            "24940"
+        );
+        // version 1 stored the fields as an array, without the causes of failures and the pairs
+        let legacy = r#"[
+  19,
+  [
+    {
+      "amtSat": 1000,
+      "totalNumPayments": 10,
+      "perStrategyResults": [
+        {
+          "strategy": "IntraAs",
+          "attackResults": [
+            {
+              "asn": "24940",
+              "simResults": [
+                {
+                  "numNodesUnderAttack": 3,
+                  "numSuccessful": 6,
+                  "numFailed": 4,
+                  "payments": []
+                }
+              ],
+              "perSimAccuracy": {
+                "tpos": 2,
+                "fpos": 2,
+                "fneg": 0
+              }
+            }
+          ]
+        }
+      ]
+    }
+  ]
+]"#;
+        let migrated = Report::from_json(serde_json::from_str(legacy).unwrap()).unwrap();
+        assert_eq!(migrated.0, 19);
+        assert!(migrated.2.is_none());
+        let output = &migrated.1[0];
+        assert_eq!(output.total_num_payments, 10);
+        assert_eq!(output.pair_draw, PairDraw::Shared);
+        let attack = &output.per_strategy_results[0].attack_results[0];
+        assert_eq!(
+            output.per_strategy_results[0].strategy,
+            PacketDropStrategy::IntraAs
+        );
+        assert_eq!(attack.sim_results[0].num_failed, 4);
+        assert_eq!(attack.sim_results[0].num_censored, 0);
+        let accuracy = attack.per_sim_accuracy.as_ref().unwrap();
+        assert_eq!(accuracy.tneg, 0);
+        assert_eq!(accuracy.precision, 0.5);
+        assert_eq!(accuracy.recall, 1.0);
+        // version 2 stored only the name of a blacklist
+        let name_only = serde_json::json!({
+            "schemaVersion": 2,
+            "run": 19,
+            "outputs": [{
+                "amtSat": 1000,
+                "totalNumPayments": 10,
+                "perStrategyResults": [{
+                    "strategy": {"DestinationBlacklist": {"blacklist": "sanctioned"}},
+                    "attackResults": []
+                }],
+                "pairDraw": {"mode": "shared"}
+            }],
+            "concentration": null
+        });
+        let error = Report::from_json(name_only).unwrap_err().to_string();
+        assert!(error.contains("blacklist sanctioned"));
+        let mut without_blacklist = serde_json::to_value(&report).unwrap();
+        without_blacklist["schemaVersion"] = serde_json::json!(2);
+        assert_eq!(Report::from_json(without_blacklist).unwrap().0, 19);
+        let newer = serde_json::json!({"schemaVersion": REPORT_SCHEMA_VERSION + 1});
+        assert!(Report::from_json(newer).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compressed() {
+        let path = TempDir::new().expect("Error opening tempfile");
+        let report = Report(19, vec![], None);
+        assert!(report
+            .write_to_zstd_file(PathBuf::from(path.path()))
+            .is_ok());
+        let file = path.path().join("simulation-run19.json.zst");
+        assert!(fs::read(&file).unwrap().starts_with(&ZSTD_MAGIC));
+        assert_eq!(read_report_json(&file).unwrap()["run"], 19);
+
+        let writer = ReportWriter::new_compressed(PathBuf::from(path.path()), 19).unwrap();
+        let results = PerStrategyResults::default();
+        assert!(writer.write_strategy_results(100, 10, &results).is_ok());
+        assert!(writer.write_strategy_results(1000, 10, &results).is_ok());
+        let file = writer.path().clone();
+        drop(writer);
+        let lines: Vec<serde_json::Value> = open_report(&file)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(&l.unwrap()).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["amtSat"], 1000);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let path = TempDir::new().expect("Error opening tempfile");
+        let mut payment = Payment::new(3, String::from("dina"), String::from("bob"), 1, None);
+        payment.succeeded = true;
+        let result = SimResult::from_simlib_results(
+            simlib::SimResult {
+                num_succesful: 1,
+                total_num: 1,
+                successful_payments: vec![payment],
+                ..Default::default()
+            },
+            0,
+        );
+        let report = Report(
+            19,
+            vec![SimOutput {
+                amt_sat: 100,
+                per_strategy_results: vec![PerStrategyResults {
+                    strategy: PacketDropStrategy::All,
+                    attack_results: vec![AttackSim {
+                        asn: String::from("797"),
+                        sim_results: vec![result.clone(), result],
+                        ..Default::default()
+                    }],
+                    other_asns: None,
+                }],
+                ..Default::default()
+            }],
+            None,
+        );
+        let file = report.write_to_parquet(PathBuf::from(path.path())).unwrap();
+        let reader = SerializedFileReader::new(File::open(file).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        assert_eq!(
+            reader
+                .metadata()
+                .file_metadata()
+                .schema()
+                .get_fields()
+                .len(),
+            11
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite() {
+        let path = TempDir::new().expect("Error opening tempfile");
+        let db = path.path().join("results.db");
+        let mut payment = Payment::new(3, String::from("dina"), String::from("bob"), 1, None);
+        payment.succeeded = true;
+        let result = SimResult::from_simlib_results(
+            simlib::SimResult {
+                num_succesful: 1,
+                total_num: 1,
+                successful_payments: vec![payment],
+                ..Default::default()
+            },
+            0,
+        );
+        let report = Report(
+            19,
+            vec![SimOutput {
+                amt_sat: 100,
+                per_strategy_results: vec![PerStrategyResults {
+                    strategy: PacketDropStrategy::All,
+                    attack_results: vec![AttackSim {
+                        asn: String::from("797"),
+                        sim_results: vec![result],
+                        ..Default::default()
+                    }],
+                    other_asns: None,
+                }],
+                ..Default::default()
+            }],
+            None,
+        );
+        assert_eq!(report.write_to_sqlite(&db).unwrap(), 1);
+        // a second campaign reuses the strategies and ASNs
+        assert_eq!(report.write_to_sqlite(&db).unwrap(), 2);
+        let conn = rusqlite::Connection::open(&db).unwrap();
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(count("runs"), 2);
+        assert_eq!(count("strategies"), 1);
+        assert_eq!(count("asns"), 1);
+        assert_eq!(count("payments"), 2);
+        let dest: String = conn
+            .query_row(
+                "SELECT p.dest FROM payments p JOIN results r ON p.result_id = r.id \
+                JOIN asns a ON r.asn_id = a.id WHERE a.asn = '797' LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(dest, "bob");
+    }
+
+    #[test]
+    fn stream() {
+        let path = TempDir::new().expect("Error opening tempfile");
+        let writer = ReportWriter::new(PathBuf::from(path.path()), 19).unwrap();
+        let results = PerStrategyResults::default();
+        assert!(writer.write_strategy_results(100, 10, &results).is_ok());
+        assert!(writer.write_strategy_results(1000, 10, &results).is_ok());
+        let content = fs::read_to_string(writer.path()).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["run"], 19);
+        assert_eq!(lines[1]["amtSat"], 1000);
+        assert_eq!(lines[1]["totalNumPayments"], 10);
+        assert_eq!(lines[1]["strategy"], "All");
+        let baseline = BaselineResult::new(100, simlib::SimResult::default(), None);
+        assert!(writer.write_baseline(100, 10, &baseline).is_ok());
+        let content = fs::read_to_string(writer.path()).unwrap();
+        let last: serde_json::Value =
+            serde_json::from_str(content.lines().last().unwrap()).unwrap();
+        assert_eq!(last["baseline"]["id"], "baseline-100sat");
+        assert_eq!(last["baseline"]["numNodesUnderAttack"], 0);
+        let paths = PathTable::default();
+        paths.intern(&[String::from("alice"), String::from("bob")]);
+        assert!(writer.write_paths(100, &paths.shared_paths()).is_ok());
+        let content = fs::read_to_string(writer.path()).unwrap();
+        let last: serde_json::Value =
+            serde_json::from_str(content.lines().last().unwrap()).unwrap();
+        assert_eq!(last["paths"][0][1], "bob");
+        let summary = SimOutput {
+            amt_sat: 100,
+            total_num_payments: 10,
+            adversary_overlap: vec![AsOverlap {
+                first_asn: 1,
+                second_asn: 2,
+                num_shared_nodes: 1,
+            }],
+            ..Default::default()
+        };
+        assert!(writer.write_summary(&summary).is_ok());
+        let content = fs::read_to_string(writer.path()).unwrap();
+        let last: serde_json::Value =
+            serde_json::from_str(content.lines().last().unwrap()).unwrap();
+        assert_eq!(last["run"], 19);
+        assert_eq!(last["adversaryOverlap"][0]["numSharedNodes"], 1);
+        assert!(last.get("perStrategyResults").is_none());
     }
 }
@@ -0,0 +1,129 @@
+use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
+use simlib::{payment::Payment, ID};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+/// Stores each distinct path once. Most payments of an amount take the same routes in the baseline
+/// and under every strategy, so payments refer to their paths by index instead of repeating the
+/// hops
+#[derive(Debug, Default)]
+pub struct PathTable {
+    interned: Mutex<InternedPaths>,
+}
+
+#[derive(Debug, Default)]
+struct InternedPaths {
+    /// Indices of the paths with the same hash
+    ids: HashMap<u64, Vec<usize>>,
+    paths: Vec<Arc<[ID]>>,
+}
+
+/// The distinct paths of an amount, serialised as a list of hop lists. A payment's `pathIds` are
+/// indices into the list
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SharedPaths(pub Vec<Arc<[ID]>>);
+
+impl PathTable {
+    /// Returns the index of the path, adding it if it has not been seen before
+    pub fn intern(&self, hops: &[ID]) -> usize {
+        let mut hasher = DefaultHasher::new();
+        hops.hash(&mut hasher);
+        let hash = hasher.finish();
+        let mut interned = self.interned.lock().expect("Path table poisoned.");
+        let InternedPaths { ids, paths } = &mut *interned;
+        let candidates = ids.entry(hash).or_default();
+        if let Some(id) = candidates.iter().find(|id| *paths[**id] == *hops) {
+            return *id;
+        }
+        let id = paths.len();
+        paths.push(Arc::from(hops));
+        candidates.push(id);
+        id
+    }
+
+    /// Interns the paths of the payment's parts in order
+    pub(crate) fn intern_payment(&self, payment: &Payment) -> Vec<usize> {
+        payment
+            .used_paths
+            .iter()
+            .map(|part| self.intern(&part.path.get_involved_nodes()))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.interned
+            .lock()
+            .expect("Path table poisoned.")
+            .paths
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The paths interned so far. The hops are shared, not copied
+    pub fn shared_paths(&self) -> SharedPaths {
+        SharedPaths(
+            self.interned
+                .lock()
+                .expect("Path table poisoned.")
+                .paths
+                .clone(),
+        )
+    }
+}
+
+impl Serialize for SharedPaths {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for hops in self.0.iter() {
+            seq.serialize_element(&hops[..])?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedPaths {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let paths = Vec::<Vec<ID>>::deserialize(deserializer)?;
+        Ok(Self(paths.into_iter().map(Arc::from).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simlib::CandidatePath;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn deduplicate_paths() {
+        let table = PathTable::default();
+        let hops = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<ID>>();
+        assert_eq!(table.intern(&hops(&["alice", "bob"])), 0);
+        assert_eq!(table.intern(&hops(&["dina", "chan", "bob"])), 1);
+        assert_eq!(table.intern(&hops(&["alice", "bob"])), 0);
+        assert_eq!(table.len(), 2);
+        let mut payment = Payment::new(0, String::from("dina"), String::from("bob"), 1, None);
+        for part in [vec!["dina", "chan", "bob"], vec!["dina", "bob"]] {
+            let mut path = simlib::Path::new(String::from("dina"), String::from("bob"));
+            path.hops = VecDeque::from_iter(
+                part.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            payment.used_paths.push(CandidatePath::new_with_path(path));
+        }
+        assert_eq!(table.intern_payment(&payment), vec![1, 2]);
+        let shared = table.shared_paths();
+        // the table and the snapshot share the hops
+        assert!(Arc::ptr_eq(&shared.0[1], &table.shared_paths().0[1]));
+        assert_eq!(
+            serde_json::to_value(&shared).unwrap(),
+            serde_json::json!([["alice", "bob"], ["dina", "chan", "bob"], ["dina", "bob"]])
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use super::{AttackSim, FailureCause};
+use crate::{net::Asn, AsIpMap, PacketDropStrategy, SimulatorError};
+use log::info;
+use serde::Serialize;
+use simlib::{payment::Payment, ID};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+/// Whether dropping the payment was right w.r.t. the payments the strategy targets
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Classification {
+    TruePositive,
+    FalsePositive,
+    FalseNegative,
+    TrueNegative,
+}
+
+/// A payment of the baseline under one adversary's strategy
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentEvent<'a> {
+    pub run: u64,
+    pub amt_sat: usize,
+    pub strategy: &'a PacketDropStrategy,
+    pub asn: Asn,
+    pub payment_id: usize,
+    pub source: &'a ID,
+    pub dest: &'a ID,
+    /// ASNs of the hops of each part in the baseline, null for unmapped nodes
+    pub path_asns: Vec<Vec<Option<Asn>>>,
+    pub succeeded_baseline: bool,
+    pub censored: bool,
+    /// Only present for payments that succeeded in the baseline if the strategy targets payments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification: Option<Classification>,
+}
+
+/// Writes one JSON line per payment, strategy and adversary as the strategies complete
+pub struct PaymentLog {
+    path: PathBuf,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl PaymentLog {
+    pub fn new(path: PathBuf) -> Result<Self, SimulatorError> {
+        let file = File::create(&path)?;
+        info!("Payments will be logged to {}.", path.display());
+        Ok(Self {
+            path,
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Logs every payment of the baseline for each adversary of the strategy. The adversaries are
+    /// in the order of `attack_results`
+    pub(crate) fn write_strategy(
+        &self,
+        (run, amt_sat): (u64, usize),
+        strategy: &PacketDropStrategy,
+        baseline: &simlib::SimResult,
+        attack_asns: &[(Asn, Vec<ID>)],
+        attack_results: &[AttackSim],
+        as_ip_map: &AsIpMap,
+    ) -> Result<(), SimulatorError> {
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        for ((asn, _), attack_sim) in attack_asns.iter().zip(attack_results.iter()) {
+            let censored: HashSet<usize> = attack_sim
+                .sim_results
+                .iter()
+                .flat_map(|r| r.payments.iter())
+                .filter(|p| {
+                    matches!(
+                        p.failure_cause,
+                        Some(FailureCause::Censored | FailureCause::CensoredThenRerouteFailed)
+                    )
+                })
+                .map(|p| p.fields.payment_id)
+                .collect();
+            let payments = baseline
+                .successful_payments
+                .iter()
+                .map(|p| (p, true))
+                .chain(baseline.failed_payments.iter().map(|p| (p, false)));
+            for (p, succeeded_baseline) in payments {
+                let censored = censored.contains(&p.payment_id);
+                let classification = match succeeded_baseline {
+                    true => Self::targets(strategy, p, *asn, as_ip_map).map(|target| {
+                        match (censored, target) {
+                            (true, true) => Classification::TruePositive,
+                            (true, false) => Classification::FalsePositive,
+                            (false, true) => Classification::FalseNegative,
+                            (false, false) => Classification::TrueNegative,
+                        }
+                    }),
+                    false => None,
+                };
+                let event = PaymentEvent {
+                    run,
+                    amt_sat,
+                    strategy,
+                    asn: *asn,
+                    payment_id: p.payment_id,
+                    source: &p.source,
+                    dest: &p.dest,
+                    path_asns: p
+                        .used_paths
+                        .iter()
+                        .map(|path| {
+                            path.path
+                                .get_involved_nodes()
+                                .iter()
+                                .map(|hop| as_ip_map.asn_of(hop))
+                                .collect()
+                        })
+                        .collect(),
+                    succeeded_baseline,
+                    censored,
+                    classification,
+                };
+                serde_json::to_writer(&mut *writer, &event)?;
+                writeln!(writer)?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Whether the strategy targets the payment, as in its accuracy. None if the strategy has no
+    /// target, e.g. PacketDropStrategy::Delay
+    fn targets(
+        strategy: &PacketDropStrategy,
+        payment: &Payment,
+        asn: Asn,
+        as_ip_map: &AsIpMap,
+    ) -> Option<bool> {
+        let in_as = |node: &ID| as_ip_map.asn_of(node) == Some(asn);
+        match *strategy {
+            PacketDropStrategy::IntraProbability => Some(in_as(&payment.dest)),
+            PacketDropStrategy::All
+            | PacketDropStrategy::IntraAs
+            | PacketDropStrategy::InterAs
+            | PacketDropStrategy::SplitAware
+            | PacketDropStrategy::IntraAsChannel
+            | PacketDropStrategy::RateLimit { .. } => {
+                Some(in_as(&payment.source) || in_as(&payment.dest))
+            }
+            PacketDropStrategy::AmountThreshold {
+                threshold_msat,
+                above,
+            } => Some(if above {
+                payment.amount > threshold_msat
+            } else {
+                payment.amount < threshold_msat
+            }),
+            PacketDropStrategy::Delay { .. }
+            | PacketDropStrategy::ProbeFilter { .. }
+            | PacketDropStrategy::DestinationBlacklist { .. } => None,
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AsSelectionStrategy, Decisions, SimBuilder};
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    #[test]
+    fn log_payments() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let mut builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let pairs = vec![
+            (String::from("alice"), String::from("bob")),
+            (String::from("chan"), String::from("dina")),
+        ];
+        let baseline = builder.simulate(pairs.into_iter());
+        let attack_asns = vec![(24940, as_ip_map.as_to_nodes()[&24940].clone())];
+        let attack_sim = SimBuilder::per_asn_simulation(
+            baseline.clone(),
+            24940,
+            &attack_asns[0].1,
+            &PacketDropStrategy::All,
+            None,
+            &as_ip_map,
+            &Decisions::new(19),
+            None,
+        )
+        .unwrap();
+        let dir = TempDir::new().unwrap();
+        let log = PaymentLog::new(dir.path().join("payments.jsonl")).unwrap();
+        log.write_strategy(
+            (19, 1000),
+            &PacketDropStrategy::All,
+            &baseline,
+            &attack_asns,
+            &[attack_sim],
+            &as_ip_map,
+        )
+        .unwrap();
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(log.path())
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        let alice = lines.iter().find(|l| l["source"] == "alice").unwrap();
+        assert_eq!(alice["censored"], true);
+        assert_eq!(alice["classification"], "truePositive");
+        assert_eq!(alice["pathAsns"], serde_json::json!([[24940, 24940]]));
+        // the AS is not on the path between chan and dina
+        let chan = lines.iter().find(|l| l["source"] == "chan").unwrap();
+        assert_eq!(chan["censored"], false);
+        assert_eq!(chan["classification"], "trueNegative");
+    }
+}
@@ -0,0 +1,1073 @@
+use super::{
+    diversity::CensorshipRates, output::*, tor::Outcomes, AdversaryOutcome, AsDiverseRouting,
+    AsGrowth, BlocklistScenario, Checkpoint, CostModel, Decisions, EvasionConfig, LiquidityFreeze,
+    NumAsSweep, PairDraw, PathTable, RetryPolicy, SamplingWeights, SimBuilder, StageTelemetry,
+    Telemetry, TorComparison, DEFAULT_MIN_SAMPLE_SIZE,
+};
+use crate::{
+    AsIpMap, AsMetadataMap, AsnCandidates, NodeAliases, PacketDropStrategy, PaymentLog,
+    ProviderGroups, SimulatorError, TorModel, TransitMap,
+};
+use rayon::prelude::*;
+use simlib::ID;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// Drop strategies simulated unless others are given
+pub static DEFAULT_DROP_STRATEGIES: [PacketDropStrategy; 5] = [
+    PacketDropStrategy::All,
+    PacketDropStrategy::IntraAs,
+    PacketDropStrategy::InterAs,
+    PacketDropStrategy::SplitAware,
+    PacketDropStrategy::IntraAsChannel,
+];
+
+/// Runs the baseline and the strategies of every adversary for a single amount, i.e., the pipeline
+/// of the simulator binary
+pub struct SimulationRunner<'a> {
+    builder: SimBuilder,
+    as_ip_map: &'a AsIpMap,
+    drop_strategies: Vec<PacketDropStrategy>,
+    diff_strategies: Option<(PacketDropStrategy, PacketDropStrategy)>,
+    chunk_size: Option<usize>,
+    include_baseline: bool,
+    blocklist: Option<&'a BlocklistScenario>,
+    sampling_weights: Option<SamplingWeights>,
+    decisions: Option<&'a Decisions>,
+    tor_model: Option<&'a TorModel>,
+    /// AS map built with the other treatment of onion-only nodes and whether it includes them
+    tor_comparison: Option<(&'a AsIpMap, bool)>,
+    /// Interns the paths of the baseline's and strategies' payments if set
+    path_table: Option<PathTable>,
+    evasion: Option<EvasionConfig>,
+    defensive_routing: Option<AsDiverseRouting>,
+    liquidity_freeze: Option<LiquidityFreeze>,
+    /// Rates computed from fewer payments are flagged
+    min_sample_size: usize,
+    /// Number of resamples the confidence intervals of the rates are bootstrapped with, if any
+    bootstrap_resamples: Option<usize>,
+    /// Weighs the accuracy of PacketDropStrategy::IntraProbability if set
+    cost_model: Option<CostModel>,
+    retry_policy: Option<RetryPolicy>,
+    /// Adversaries affecting fewer payments are only reported in aggregate
+    min_affected: Option<usize>,
+    /// Share of the payments the searched coalition must censor
+    placement_target: Option<f32>,
+    num_as_sweep: Option<NumAsSweep>,
+    /// Number of top-ranked ASs whose pairs are evaluated as coalitions
+    pair_coalitions: Option<usize>,
+    /// Bounds the results by the nodes whose addresses map to several ASs if set
+    asn_candidates: Option<&'a AsnCandidates>,
+    /// Reports the payments the transit ASs of the channels censor if set
+    transit_map: Option<&'a TransitMap>,
+    growth: Option<AsGrowth>,
+    /// Labels the nodes of the exported payments if set
+    node_aliases: Option<&'a NodeAliases>,
+    /// Logs every payment per strategy and adversary if set
+    payment_log: Option<&'a PaymentLog>,
+    /// Annotates the adversaries in the results if set
+    as_metadata: Option<&'a AsMetadataMap>,
+    /// Lists the ASs of each adversarial provider in its results if set
+    providers: Option<&'a ProviderGroups>,
+    /// Share of its nodes each adversary controls, all if not set
+    coverage: Option<f32>,
+    coverage_levels: Option<Vec<f32>>,
+    /// Maximum shares of the forwarded payments each adversary may drop
+    drop_budgets: Option<Vec<f32>>,
+    /// Reroutes the payments each adversary censors around its nodes if set
+    network_impact: bool,
+    /// Classifies the baseline's failed payments by whether each strategy would censor them if set
+    classify_failed: bool,
+    /// Stores the results of each strategy and adversary as they complete and skips those stored
+    /// by an interrupted run if set
+    checkpoint: Option<&'a Checkpoint>,
+    /// Stages measured so far, e.g. the baseline, if telemetry is recorded
+    telemetry: Option<Telemetry>,
+    /// Used if no decisions are given
+    seed_decisions: Decisions,
+}
+
+impl<'a> SimulationRunner<'a> {
+    pub fn new(builder: SimBuilder, as_ip_map: &'a AsIpMap) -> Self {
+        Self {
+            seed_decisions: Decisions::new(builder.run),
+            builder,
+            as_ip_map,
+            drop_strategies: DEFAULT_DROP_STRATEGIES.to_vec(),
+            diff_strategies: None,
+            chunk_size: None,
+            include_baseline: false,
+            blocklist: None,
+            sampling_weights: None,
+            decisions: None,
+            tor_model: None,
+            tor_comparison: None,
+            path_table: None,
+            evasion: None,
+            defensive_routing: None,
+            liquidity_freeze: None,
+            min_sample_size: DEFAULT_MIN_SAMPLE_SIZE,
+            bootstrap_resamples: None,
+            cost_model: None,
+            retry_policy: None,
+            min_affected: None,
+            placement_target: None,
+            pair_coalitions: None,
+            num_as_sweep: None,
+            asn_candidates: None,
+            transit_map: None,
+            growth: None,
+            node_aliases: None,
+            payment_log: None,
+            as_metadata: None,
+            providers: None,
+            coverage: None,
+            coverage_levels: None,
+            drop_budgets: None,
+            network_impact: false,
+            classify_failed: false,
+            checkpoint: None,
+            telemetry: None,
+        }
+    }
+
+    pub fn with_drop_strategies(mut self, drop_strategies: Vec<PacketDropStrategy>) -> Self {
+        self.drop_strategies = drop_strategies;
+        self
+    }
+
+    /// Compare the payments censored by the two strategies for each adversary
+    pub fn with_diff_strategies(
+        mut self,
+        first: PacketDropStrategy,
+        second: PacketDropStrategy,
+    ) -> Self {
+        self.diff_strategies = Some((first, second));
+        self
+    }
+
+    /// Simulate the adversaries in parallel over chunks of `chunk_size` payments
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Store the baseline in the output and refer to it from each result
+    pub fn with_baseline(mut self, include_baseline: bool) -> Self {
+        self.include_baseline = include_baseline;
+        self
+    }
+
+    /// Also apply the blocklist scenario to the baseline
+    pub fn with_blocklist(mut self, blocklist: &'a BlocklistScenario) -> Self {
+        self.blocklist = Some(blocklist);
+        self
+    }
+
+    /// Record the weights the payment pairs were drawn with in the output
+    pub fn with_sampling_weights(mut self, sampling_weights: SamplingWeights) -> Self {
+        self.sampling_weights = Some(sampling_weights);
+        self
+    }
+
+    /// Take the stochastic decisions from `decisions`, e.g. to record or replay them, instead of
+    /// drawing them from the seed of the builder
+    pub fn with_decisions(mut self, decisions: &'a Decisions) -> Self {
+        self.decisions = Some(decisions);
+        self
+    }
+
+    /// Treat onion-only nodes as hidden from the ASs, except from the AS of their guard relay, and
+    /// report how many payments escaped thanks to them
+    pub fn with_tor_model(mut self, tor_model: &'a TorModel) -> Self {
+        self.tor_model = Some(tor_model);
+        self
+    }
+
+    /// Also simulate the strategies with an AS map that treats onion-only nodes the other way, i.e.,
+    /// `include_tor` is the opposite of the primary map's, and report both side by side. The
+    /// comparison's decisions are drawn from the seed and not recorded
+    pub fn with_tor_comparison(mut self, as_ip_map: &'a AsIpMap, include_tor: bool) -> Self {
+        self.tor_comparison = Some((as_ip_map, include_tor));
+        self
+    }
+
+    /// Store each distinct path once per amount and refer to it by index from the payments
+    pub fn with_path_dedup(mut self, dedup_paths: bool) -> Self {
+        self.path_table = dedup_paths.then(PathTable::default);
+        self
+    }
+
+    /// Also simulate each adversary over several rounds in which the victims open channels around
+    /// it
+    pub fn with_evasion(mut self, evasion: EvasionConfig) -> Self {
+        self.evasion = Some(evasion);
+        self
+    }
+
+    /// Also simulate the pairs with AS-diverse routing and report how much the censorship rate of
+    /// each adversary drops
+    pub fn with_defensive_routing(mut self, routing: AsDiverseRouting) -> Self {
+        self.defensive_routing = Some(routing);
+        self
+    }
+
+    /// Also simulate each adversary freezing a share of its nodes' liquidity instead of dropping
+    /// payments
+    pub fn with_liquidity_freeze(mut self, freeze: LiquidityFreeze) -> Self {
+        self.liquidity_freeze = Some(freeze);
+        self
+    }
+
+    /// Flag the rates of each adversary that are computed from fewer payments
+    pub fn with_min_sample_size(mut self, min_sample_size: usize) -> Self {
+        self.min_sample_size = min_sample_size;
+        self
+    }
+
+    /// Bootstrap the 95% confidence intervals of each adversary's rates over its payments
+    pub fn with_bootstrap(mut self, num_resamples: usize) -> Self {
+        self.bootstrap_resamples = Some(num_resamples);
+        self
+    }
+
+    /// Report the net utility of PacketDropStrategy::IntraProbability to each adversary under the
+    /// cost model
+    pub fn with_cost_model(mut self, cost_model: CostModel) -> Self {
+        self.cost_model = Some(cost_model);
+        self
+    }
+
+    /// Let the senders retry censored payments and report which succeed eventually. Ignored when
+    /// simulating in chunks as the payments are not kept
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Summarise the adversaries whose strategy affected fewer than `min_affected` payments in
+    /// the strategy's `otherAsns` instead of reporting their results
+    pub fn with_min_affected(mut self, min_affected: usize) -> Self {
+        self.min_affected = Some(min_affected);
+        self
+    }
+
+    /// Also search the smallest coalition of ASs that censors the target share of the payments
+    pub fn with_placement_target(mut self, target_share: f32) -> Self {
+        self.placement_target = Some(target_share);
+        self
+    }
+
+    /// Also report the coverage of every pair of the top-k ASs of the ranking colluding
+    pub fn with_pair_coalitions(mut self, k: usize) -> Self {
+        self.pair_coalitions = Some(k);
+        self
+    }
+
+    /// Also report the censorship rate of every coalition of the top-n ASs of the ranking, n in the
+    /// sweep, independently of the selected adversaries
+    pub fn with_num_as_sweep(mut self, sweep: NumAsSweep) -> Self {
+        self.num_as_sweep = Some(sweep);
+        self
+    }
+
+    /// Report each adversary's results if it controlled only its unambiguous nodes or also the
+    /// nodes with an address in its AS that are attributed to other ASs
+    pub fn with_asn_candidates(mut self, candidates: &'a AsnCandidates) -> Self {
+        self.asn_candidates = Some(candidates);
+        self
+    }
+
+    /// Also simulate the explicitly selected or the top-n transit ASs censoring the channels whose
+    /// IP traffic they carry
+    pub fn with_transit_map(mut self, transit_map: &'a TransitMap) -> Self {
+        self.transit_map = Some(transit_map);
+        self
+    }
+
+    /// Also simulate every strategy of the AS after it acquired the best-connected nodes of other
+    /// ASs, step by step, and report its censorship rate against its market share
+    pub fn with_growth(mut self, growth: AsGrowth) -> Self {
+        self.growth = Some(growth);
+        self
+    }
+
+    /// Add the aliases of the sender, recipient and hops to each exported payment
+    pub fn with_node_aliases(mut self, node_aliases: &'a NodeAliases) -> Self {
+        self.node_aliases = Some(node_aliases);
+        self
+    }
+
+    /// Write one line per payment of the baseline to the log for each strategy and adversary
+    pub fn with_payment_log(mut self, payment_log: &'a PaymentLog) -> Self {
+        self.payment_log = Some(payment_log);
+        self
+    }
+
+    /// Add the name, country and customer cone size of each adversarial AS to its results
+    pub fn with_as_metadata(mut self, as_metadata: &'a AsMetadataMap) -> Self {
+        self.as_metadata = Some(as_metadata);
+        self
+    }
+
+    /// Add the provider and its ASs to the results of each adversarial provider
+    pub fn with_providers(mut self, providers: &'a ProviderGroups) -> Self {
+        self.providers = Some(providers);
+        self
+    }
+
+    /// Let each adversary control only a random share of its nodes, drawn from the seed
+    pub fn with_coverage(mut self, coverage: f32) -> Self {
+        self.coverage = Some(coverage);
+        self
+    }
+
+    /// Also simulate every strategy of each adversary controlling each share of its nodes
+    pub fn with_coverage_levels(mut self, levels: Vec<f32>) -> Self {
+        self.coverage_levels = Some(levels);
+        self
+    }
+
+    /// Also report the censorship of each adversary that drops no more than each share of the
+    /// payments it forwards, most likely intra-AS payments first
+    pub fn with_drop_budgets(mut self, budgets: Vec<f32>) -> Self {
+        self.drop_budgets = Some(budgets);
+        self
+    }
+
+    /// Also estimate how the payments each adversary censors reroute around its nodes and how
+    /// the forwarding shares of the other nodes shift
+    pub fn with_network_impact(mut self, network_impact: bool) -> Self {
+        self.network_impact = network_impact;
+        self
+    }
+
+    /// Also count the payments that failed in the baseline which each adversary's strategy would
+    /// have censored had they succeeded
+    pub fn with_classify_failed(mut self, classify_failed: bool) -> Self {
+        self.classify_failed = classify_failed;
+        self
+    }
+
+    /// Resume the strategies and adversaries completed by an earlier run with the same arguments
+    /// and store those that complete now
+    pub fn with_checkpoint(mut self, checkpoint: &'a Checkpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Record the time and memory of the baseline and of each strategy per adversary after the
+    /// given stages, e.g. a baseline simulated outside of the runner
+    pub fn with_telemetry(mut self, stages: Vec<StageTelemetry>) -> Self {
+        self.telemetry = Some(Telemetry { stages });
+        self
+    }
+
+    pub fn builder(&self) -> &SimBuilder {
+        &self.builder
+    }
+
+    /// Simulates the pairs without an adversary and applies every strategy to the outcome. Returns
+    /// an error if an explicitly selected AS has no nodes in the graph
+    pub fn run(
+        mut self,
+        pairs: impl Iterator<Item = (ID, ID)> + Clone,
+    ) -> Result<SimOutput, SimulatorError> {
+        let baseline = match self.telemetry.as_mut() {
+            Some(telemetry) => {
+                let builder = &mut self.builder;
+                let (baseline, stage) = StageTelemetry::measure(
+                    None,
+                    None,
+                    || builder.simulate(pairs),
+                    |b| b.total_num,
+                );
+                telemetry.stages.push(stage);
+                baseline
+            }
+            None => self.builder.simulate(pairs),
+        };
+        let mut per_strategy_results = vec![];
+        let mut sim_output = self.run_attacks(baseline, |r| per_strategy_results.push(r))?;
+        sim_output.per_strategy_results = per_strategy_results;
+        Ok(sim_output)
+    }
+
+    /// Passes the results for each packet drop strategy to `on_strategy_complete` as soon as they
+    /// are available. The returned output holds everything else, e.g. the overlap between the
+    /// adversarial ASs and the difference between the diff strategies per AS
+    pub fn run_attacks(
+        &self,
+        baseline: Arc<simlib::SimResult>,
+        mut on_strategy_complete: impl FnMut(PerStrategyResults),
+    ) -> Result<SimOutput, SimulatorError> {
+        // the overlap is reported as selected, before the policy deduplicated the nodes
+        let (mut attack_asns, adversary_overlap) = self
+            .builder
+            .get_adverserial_asns_with_overlap(self.as_ip_map)
+            .map_err(SimulatorError::Selection)?;
+        let decisions = self.decisions.unwrap_or(&self.seed_decisions);
+        if let Some(tor_model) = self.tor_model {
+            attack_asns =
+                SimBuilder::add_guarded_nodes(attack_asns, &tor_model.guard_asns(decisions.seed()));
+        }
+        // the sweep draws its own shares of all nodes
+        let swept_asns = self.coverage_levels.is_some().then(|| attack_asns.clone());
+        if let Some(coverage) = self.coverage {
+            attack_asns = SimBuilder::partial_coverage(attack_asns, coverage, decisions.seed());
+        }
+        let as_ip_map = self.as_ip_map;
+        let amt_sat = self.builder.amt_msat / 1000;
+        let mut baseline_result = if self.include_baseline {
+            Some(BaselineResult::new(
+                amt_sat,
+                (*baseline).clone(),
+                self.path_table.as_ref(),
+            ))
+        } else {
+            None
+        };
+        let payment_nodes = self
+            .node_aliases
+            .map(|_| SimResult::payment_nodes(&baseline));
+        if let (Some(b), Some(aliases), Some(nodes)) =
+            (baseline_result.as_mut(), self.node_aliases, &payment_nodes)
+        {
+            b.result.label_nodes(nodes, aliases);
+        }
+        if let Some(b) = baseline_result.as_mut() {
+            b.result.record_as_paths(&baseline, as_ip_map);
+        }
+        let baseline_id = baseline_result.as_ref().map(|b| b.id.clone());
+        let completed = match self.checkpoint {
+            Some(checkpoint) => {
+                checkpoint.completed_attacks(self.builder.run, amt_sat, &self.drop_strategies)?
+            }
+            None => HashMap::new(),
+        };
+        if let Some(path_table) = &self.path_table {
+            // the strategies only remove paths, so interning the baseline's first keeps the
+            // indices independent of the order the adversaries complete in
+            for p in baseline.successful_payments.iter() {
+                path_table.intern_payment(p);
+            }
+        }
+        let blocklist = self.blocklist.map(|scenario| {
+            SimBuilder::apply_blocklist_scenario((*baseline).clone(), scenario, as_ip_map)
+        });
+        let progress = self.builder.progress();
+        if let Some(progress) = progress {
+            progress.add_steps((self.drop_strategies.len() * attack_asns.len()) as u64);
+        }
+        let strategy_diffs = if let Some((first, second)) = &self.diff_strategies {
+            let intra_as_channel_ratios = if *first == PacketDropStrategy::IntraProbability
+                || *second == PacketDropStrategy::IntraProbability
+            {
+                as_ip_map.get_intra_as_node_ratios(&self.builder.graph)
+            } else {
+                HashMap::default()
+            };
+            attack_asns
+                .iter()
+                .map(|(asn, nodes)| {
+                    SimBuilder::diff_strategies(
+                        &baseline,
+                        *asn,
+                        nodes,
+                        (first, second),
+                        intra_as_channel_ratios.get(asn),
+                        as_ip_map,
+                        decisions,
+                    )
+                })
+                .collect::<Result<_, _>>()?
+        } else {
+            vec![]
+        };
+        let mut outcomes: Outcomes = vec![];
+        let mut censorship_rates: CensorshipRates = vec![];
+        let mut telemetry = self.telemetry.clone();
+        for strategy in self.drop_strategies.iter() {
+            let start = telemetry.is_some().then(Instant::now);
+            let asn_stages = Mutex::new(vec![]);
+            let intra_as_channel_ratios = if *strategy == PacketDropStrategy::IntraProbability {
+                as_ip_map.get_intra_as_node_ratios(&self.builder.graph)
+            } else {
+                HashMap::default()
+            };
+            if let Some(progress) = progress {
+                progress.set_message(format!("{:?}", strategy));
+            }
+            let strategy_key = Checkpoint::strategy_key(strategy);
+            let resumed: Option<Vec<AttackSim>> = attack_asns
+                .iter()
+                .map(|(asn, _)| completed.get(&(strategy_key.clone(), *asn)).cloned())
+                .collect();
+            let mut attack_results = if let (Some(_), Some(resumed)) = (self.chunk_size, resumed) {
+                // the chunks cover all adversaries at once, so they are only skipped together
+                if let Some(progress) = progress {
+                    progress.inc(attack_asns.len() as u64);
+                }
+                resumed
+            } else if let Some(chunk_size) = self.chunk_size {
+                let attack_results = SimBuilder::chunked_asn_simulation(
+                    &baseline,
+                    &attack_asns,
+                    strategy,
+                    &intra_as_channel_ratios,
+                    as_ip_map,
+                    decisions,
+                    chunk_size,
+                )?;
+                if let Some(checkpoint) = self.checkpoint {
+                    for ((asn, _), attack_sim) in attack_asns.iter().zip(attack_results.iter()) {
+                        checkpoint.save_attack(
+                            (self.builder.run, amt_sat),
+                            strategy,
+                            *asn,
+                            attack_sim,
+                        )?;
+                    }
+                }
+                if let Some(progress) = progress {
+                    progress.inc(attack_asns.len() as u64);
+                }
+                if let Some(start) = start {
+                    let num_payments = baseline.total_num * attack_asns.len();
+                    asn_stages.lock().unwrap().push(StageTelemetry::since(
+                        start,
+                        Some(strategy.clone()),
+                        None,
+                        num_payments,
+                    ));
+                }
+                attack_results
+            } else {
+                // the adversaries are independent, their results are collected in order
+                attack_asns
+                    .par_iter()
+                    .map(|(asn, nodes)| {
+                        if let Some(attack_sim) = completed.get(&(strategy_key.clone(), *asn)) {
+                            if let Some(progress) = progress {
+                                progress.inc(1);
+                            }
+                            return Ok(attack_sim.clone());
+                        }
+                        let start = start.map(|_| Instant::now());
+                        let mut attack_sim = SimBuilder::per_asn_simulation(
+                            (*baseline).clone(),
+                            *asn,
+                            nodes,
+                            strategy,
+                            intra_as_channel_ratios.get(asn),
+                            as_ip_map,
+                            decisions,
+                            self.path_table.as_ref(),
+                        )?;
+                        if let Some(policy) = self.retry_policy {
+                            if !matches!(strategy, PacketDropStrategy::Delay { .. }) {
+                                attack_sim.retries = Some(self.builder.retry_censored(
+                                    &baseline,
+                                    &attack_sim,
+                                    *asn,
+                                    nodes,
+                                    strategy,
+                                    intra_as_channel_ratios.get(asn),
+                                    as_ip_map,
+                                    decisions,
+                                    policy,
+                                )?);
+                                SimBuilder::record_reroute_failures(&mut attack_sim);
+                            }
+                        }
+                        if let Some(checkpoint) = self.checkpoint {
+                            checkpoint.save_attack(
+                                (self.builder.run, amt_sat),
+                                strategy,
+                                *asn,
+                                &attack_sim,
+                            )?;
+                        }
+                        if let Some(progress) = progress {
+                            progress.inc(1);
+                        }
+                        if let Some(start) = start {
+                            asn_stages.lock().unwrap().push(StageTelemetry::since(
+                                start,
+                                Some(strategy.clone()),
+                                Some(asn.to_string()),
+                                baseline.total_num,
+                            ));
+                        }
+                        Ok(attack_sim)
+                    })
+                    .collect::<Result<_, SimulatorError>>()?
+            };
+            for attack_sim in attack_results.iter_mut() {
+                attack_sim.baseline_id = baseline_id.clone();
+                attack_sim.sample_sizes = attack_sim.effective_sample_sizes(self.min_sample_size);
+                if let Some(num_resamples) = self.bootstrap_resamples {
+                    attack_sim.confidence_intervals =
+                        attack_sim.bootstrap_intervals(num_resamples, decisions.seed());
+                }
+                if let Some(cost_model) = &self.cost_model {
+                    if *strategy == PacketDropStrategy::IntraProbability {
+                        attack_sim.utility = attack_sim.utility(cost_model);
+                    }
+                }
+                if let (Some(aliases), Some(nodes)) = (self.node_aliases, &payment_nodes) {
+                    for sim_result in attack_sim.sim_results.iter_mut() {
+                        sim_result.label_nodes(nodes, aliases);
+                    }
+                }
+                if let Some(as_metadata) = self.as_metadata {
+                    attack_sim.as_metadata = attack_sim
+                        .asn
+                        .parse()
+                        .ok()
+                        .and_then(|asn| as_metadata.get(asn))
+                        .cloned();
+                }
+                if let Some(providers) = self.providers {
+                    attack_sim.provider = attack_sim
+                        .asn
+                        .parse()
+                        .ok()
+                        .and_then(|asn| providers.get(asn))
+                        .cloned();
+                }
+            }
+            if let Some(tor_model) = self.tor_model {
+                for (attack_sim, (asn, nodes)) in attack_results.iter_mut().zip(attack_asns.iter())
+                {
+                    let num_successful = attack_sim
+                        .sim_results
+                        .iter()
+                        .map(|r| r.num_successful)
+                        .sum();
+                    attack_sim.tor_exposure = Some(SimBuilder::tor_exposure(
+                        &baseline,
+                        *asn,
+                        nodes,
+                        strategy,
+                        intra_as_channel_ratios.get(asn),
+                        as_ip_map,
+                        decisions,
+                        tor_model,
+                        num_successful,
+                    )?);
+                }
+            }
+            if let Some(candidates) = self.asn_candidates {
+                for (attack_sim, (asn, nodes)) in attack_results.iter_mut().zip(attack_asns.iter())
+                {
+                    attack_sim.attribution_bounds = Some(SimBuilder::attribution_bounds(
+                        &baseline,
+                        *asn,
+                        nodes,
+                        strategy,
+                        intra_as_channel_ratios.get(asn),
+                        as_ip_map,
+                        decisions,
+                        candidates,
+                    )?);
+                }
+            }
+            if self.classify_failed {
+                for (attack_sim, (asn, nodes)) in attack_results.iter_mut().zip(attack_asns.iter())
+                {
+                    attack_sim.baseline_failures = Some(SimBuilder::baseline_failures(
+                        &baseline,
+                        *asn,
+                        nodes,
+                        strategy,
+                        intra_as_channel_ratios.get(asn),
+                        as_ip_map,
+                        decisions,
+                    )?);
+                }
+            }
+            if self.tor_comparison.is_some() {
+                outcomes.extend(
+                    attack_results
+                        .iter()
+                        .map(|a| (strategy.clone(), a.asn.clone(), AdversaryOutcome::new(a))),
+                );
+            }
+            if self.defensive_routing.is_some() {
+                censorship_rates.extend(attack_results.iter().map(|a| {
+                    (
+                        strategy.clone(),
+                        a.asn.clone(),
+                        SimBuilder::censorship_rate(&baseline, a),
+                    )
+                }));
+            }
+            if let Some(telemetry) = telemetry.as_mut() {
+                // the adversaries complete in any order
+                let mut stages = asn_stages.into_inner().unwrap();
+                stages.sort_by_key(|s| {
+                    attack_asns
+                        .iter()
+                        .position(|(asn, _)| s.asn == Some(asn.to_string()))
+                });
+                telemetry.stages.extend(stages);
+            }
+            if let Some(payment_log) = self.payment_log {
+                payment_log.write_strategy(
+                    (self.builder.run, amt_sat),
+                    strategy,
+                    &baseline,
+                    &attack_asns,
+                    &attack_results,
+                    as_ip_map,
+                )?;
+            }
+            let mut results = PerStrategyResults {
+                strategy: strategy.clone(),
+                attack_results,
+                other_asns: None,
+            };
+            if let Some(min_affected) = self.min_affected {
+                results.prune(baseline.num_succesful, min_affected);
+            }
+            on_strategy_complete(results)
+        }
+        let tor_comparison = match self.tor_comparison {
+            Some((comparison_map, include_tor)) => {
+                let comparison = (
+                    comparison_map,
+                    self.comparison_outcomes(&baseline, comparison_map)?,
+                );
+                let primary = (as_ip_map, outcomes);
+                Some(if include_tor {
+                    TorComparison::new(primary, comparison)
+                } else {
+                    TorComparison::new(comparison, primary)
+                })
+            }
+            None => None,
+        };
+        let evasion = match &self.evasion {
+            Some(config) => attack_asns
+                .par_iter()
+                .map(|(asn, nodes)| {
+                    self.builder
+                        .evasion_simulation(&baseline, *asn, nodes, as_ip_map, decisions, config)
+                })
+                .collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+        let defensive_routing = match self.defensive_routing {
+            Some(routing) => Some(self.builder.defensive_routing(
+                &baseline,
+                routing,
+                &attack_asns,
+                &self.drop_strategies,
+                censorship_rates,
+                as_ip_map,
+                decisions,
+            )?),
+            None => None,
+        };
+        let liquidity_freezes = match self.liquidity_freeze {
+            Some(freeze) => attack_asns
+                .par_iter()
+                .map(|(asn, nodes)| {
+                    self.builder
+                        .liquidity_freeze(&baseline, *asn, nodes, freeze)
+                })
+                .collect(),
+            None => vec![],
+        };
+        let coalition_placement = self
+            .placement_target
+            .map(|target_share| SimBuilder::optimal_coalition(&baseline, as_ip_map, target_share));
+        let num_as_sweep = self.num_as_sweep.map(|sweep| {
+            let ranking = crate::analysis::top_n_asns(
+                as_ip_map,
+                &self.builder.graph,
+                sweep.max,
+                self.builder.as_selection,
+                self.builder.run,
+            );
+            SimBuilder::coalition_sweep(&baseline, &ranking, sweep)
+        });
+        let pair_coalitions = self.pair_coalitions.map(|k| {
+            let ranking = crate::analysis::top_n_asns(
+                as_ip_map,
+                &self.builder.graph,
+                k,
+                self.builder.as_selection,
+                self.builder.run,
+            );
+            SimBuilder::pair_coalitions(&baseline, &ranking)
+        });
+        let transit_censorship = match self.transit_map {
+            Some(transit_map) => {
+                let transit_asns = match &self.builder.target_asns {
+                    Some(asns) => asns.clone(),
+                    None => transit_map
+                        .top_n_transit_asns(self.builder.num_adv_as)
+                        .into_iter()
+                        .map(|(asn, _)| asn)
+                        .collect(),
+                };
+                transit_asns
+                    .par_iter()
+                    .map(|asn| {
+                        SimBuilder::transit_censorship(&baseline, *asn, as_ip_map, transit_map)
+                    })
+                    .collect()
+            }
+            None => vec![],
+        };
+        let growth_curves = match &self.growth {
+            Some(growth) => self
+                .drop_strategies
+                .par_iter()
+                .map(|strategy| {
+                    self.builder
+                        .growth_curve(&baseline, growth, strategy, as_ip_map, decisions)
+                })
+                .collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+        let coverage_curves = match (&self.coverage_levels, &swept_asns) {
+            (Some(levels), Some(swept_asns)) => self
+                .drop_strategies
+                .par_iter()
+                .map(|strategy| {
+                    self.builder.coverage_curves(
+                        &baseline, swept_asns, strategy, levels, as_ip_map, decisions,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .flatten()
+                .collect(),
+            _ => vec![],
+        };
+        let drop_budgets = match &self.drop_budgets {
+            Some(budgets) => attack_asns
+                .par_iter()
+                .map(|(asn, nodes)| {
+                    SimBuilder::drop_budget_curve(&baseline, *asn, nodes, as_ip_map, budgets)
+                })
+                .collect(),
+            None => vec![],
+        };
+        let network_impact = if self.network_impact {
+            attack_asns
+                .par_iter()
+                .map(|(asn, nodes)| self.builder.network_impact(&baseline, *asn, nodes))
+                .collect()
+        } else {
+            vec![]
+        };
+        Ok(SimOutput {
+            amt_sat,
+            total_num_payments: baseline.total_num,
+            per_strategy_results: vec![],
+            adversary_overlap,
+            baseline: baseline_result,
+            strategy_diffs,
+            blocklist,
+            sampling_weights: self.sampling_weights.clone(),
+            pair_draw: PairDraw::Shared,
+            amount_model: None,
+            value_ranking: None,
+            tor_comparison,
+            paths: self.path_table.as_ref().map(PathTable::shared_paths),
+            evasion,
+            defensive_routing,
+            liquidity_freezes,
+            coalition_placement,
+            num_as_sweep,
+            pair_coalitions,
+            transit_censorship,
+            growth_curves,
+            drop_budgets,
+            coverage_curves,
+            network_impact,
+            telemetry,
+        })
+    }
+
+    /// Simulates every strategy of the adversaries selected from the comparison map
+    fn comparison_outcomes(
+        &self,
+        baseline: &simlib::SimResult,
+        comparison_map: &AsIpMap,
+    ) -> Result<Outcomes, SimulatorError> {
+        let attack_asns = self
+            .builder
+            .get_adverserial_asns(comparison_map)
+            .map_err(SimulatorError::Selection)?;
+        let mut outcomes = vec![];
+        for strategy in self.drop_strategies.iter() {
+            let intra_as_channel_ratios = if *strategy == PacketDropStrategy::IntraProbability {
+                comparison_map.get_intra_as_node_ratios(&self.builder.graph)
+            } else {
+                HashMap::default()
+            };
+            for (asn, nodes) in attack_asns.iter() {
+                let attack_sim = SimBuilder::per_asn_simulation(
+                    baseline.clone(),
+                    *asn,
+                    nodes,
+                    strategy,
+                    intra_as_channel_ratios.get(asn),
+                    comparison_map,
+                    &self.seed_decisions,
+                    None,
+                )?;
+                outcomes.push((
+                    strategy.clone(),
+                    attack_sim.asn.clone(),
+                    AdversaryOutcome::new(&attack_sim),
+                ));
+            }
+        }
+        Ok(outcomes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AsSelectionStrategy, MappingSummary};
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use std::path::Path;
+
+    #[test]
+    fn baseline_to_as_results() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/trivial_connected_lnd.json"),
+                Lnd,
+            )
+            .unwrap(),
+            Lnd,
+        );
+        let amt_msat = 1000;
+        let num_adv_as = 1;
+        let run = 0;
+        let num_pairs = 3;
+        let mut sim_builder = SimBuilder::new(
+            run,
+            &graph,
+            amt_msat,
+            num_adv_as,
+            AsSelectionStrategy::MaxNodes,
+        );
+        let pairs = simlib::Simulation::draw_n_pairs_for_simulation(&graph, num_pairs);
+        let baseline_result = sim_builder.simulate(pairs.clone());
+        let mut actual = vec![];
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let decisions = Decisions::new(run);
+        let runner = SimulationRunner::new(sim_builder, &as_ip_map)
+            .with_diff_strategies(PacketDropStrategy::All, PacketDropStrategy::InterAs)
+            .with_baseline(true)
+            .with_decisions(&decisions)
+            .with_path_dedup(true);
+        let output = runner
+            .run_attacks(baseline_result, |r| actual.push(r))
+            .unwrap();
+        assert_eq!(actual.len(), DEFAULT_DROP_STRATEGIES.len());
+        assert!(output.adversary_overlap.is_empty());
+        assert_eq!(output.strategy_diffs.len(), num_adv_as);
+        assert_eq!(output.total_num_payments, num_pairs);
+        let num_paths = output.paths.as_ref().map(|p| p.0.len()).unwrap();
+        assert!(actual
+            .iter()
+            .flat_map(|r| r.attack_results.iter())
+            .flat_map(|a| a.sim_results.iter())
+            .chain(output.baseline.iter().map(|b| &b.result))
+            .flat_map(|r| r.payments.iter())
+            .all(|p| p.path_ids.iter().all(|id| *id < num_paths)));
+        let baseline_id = output.baseline.map(|b| b.id);
+        assert_eq!(baseline_id, Some(String::from("baseline-1sat")));
+        for attack_sim in actual.iter().flat_map(|r| r.attack_results.iter()) {
+            assert_eq!(attack_sim.baseline_id, baseline_id);
+            assert_eq!(attack_sim.sim_results.len(), 1);
+        }
+        // the whole pipeline
+        let tor_model = TorModel::new(&graph);
+        let output = SimulationRunner::new(
+            SimBuilder::new(
+                run,
+                &graph,
+                amt_msat,
+                num_adv_as,
+                AsSelectionStrategy::MaxNodes,
+            ),
+            &as_ip_map,
+        )
+        .with_drop_strategies(vec![PacketDropStrategy::All])
+        .with_tor_model(&tor_model)
+        .with_telemetry(vec![])
+        .run(pairs)
+        .unwrap();
+        assert_eq!(output.per_strategy_results.len(), 1);
+        let stages = output.telemetry.unwrap().stages;
+        // the baseline and the single adversary
+        assert_eq!(stages.len(), 1 + num_adv_as);
+        assert_eq!(stages[0].strategy, None);
+        assert_eq!(stages[0].num_payments, num_pairs);
+        assert_eq!(stages[1].strategy, Some(PacketDropStrategy::All));
+        assert_eq!(
+            stages[1].asn.as_ref(),
+            Some(&output.per_strategy_results[0].attack_results[0].asn)
+        );
+        assert!(output.tor_comparison.is_none());
+        for attack_sim in output.per_strategy_results[0].attack_results.iter() {
+            assert_eq!(attack_sim.tor_exposure, Some(TorExposure::default()));
+        }
+        assert!(output.baseline.is_none());
+        assert!(output.paths.is_none());
+        let tor_map = AsIpMap::new(&graph, true);
+        let output = SimulationRunner::new(
+            SimBuilder::new(
+                run,
+                &graph,
+                amt_msat,
+                num_adv_as,
+                AsSelectionStrategy::MaxNodes,
+            ),
+            &as_ip_map,
+        )
+        .with_drop_strategies(vec![PacketDropStrategy::All])
+        .with_tor_comparison(&tor_map, true)
+        .run(simlib::Simulation::draw_n_pairs_for_simulation(
+            &graph, num_pairs,
+        ))
+        .unwrap();
+        let comparison = output.tor_comparison.unwrap();
+        assert_eq!(comparison.without_tor, MappingSummary::new(&as_ip_map));
+        assert_eq!(comparison.with_tor, MappingSummary::new(&tor_map));
+        assert!(comparison
+            .rows
+            .iter()
+            .all(|r| r.without_tor.is_some() || r.with_tor.is_some()));
+        assert!(SimulationRunner::new(
+            SimBuilder::new(
+                run,
+                &graph,
+                amt_msat,
+                num_adv_as,
+                AsSelectionStrategy::MaxNodes
+            )
+            .with_target_asns(vec![16509]),
+            &as_ip_map,
+        )
+        .run(simlib::Simulation::draw_n_pairs_for_simulation(
+            &graph, num_pairs
+        ))
+        .is_err());
+    }
+}
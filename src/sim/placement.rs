@@ -0,0 +1,164 @@
+use super::SimBuilder;
+use crate::net::{AsIpMap, Asn};
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+use std::collections::{BTreeMap, HashMap, HashSet};
+#[cfg(test)]
+use std::println as info;
+
+/// The smallest coalition of ASs found by a greedy set cover that, by dropping every payment
+/// traversing one of them, censors at least the target share of the baseline's successful payments
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CoalitionPlacement {
+    pub target_share: f32,
+    pub num_payments: usize,
+    /// The ASs in the order they were added
+    pub steps: Vec<PlacementStep>,
+    /// Share of the payments that traverse at least one AS of the coalition
+    pub coverage: f32,
+    /// False if the payments traversing any AS do not add up to the target share
+    pub reached: bool,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlacementStep {
+    pub asn: String,
+    /// Payments traversing the AS but none of the ASs added before
+    pub num_newly_covered: usize,
+    /// Coverage of the coalition up to and including the AS
+    pub coverage: f32,
+}
+
+impl SimBuilder {
+    /// Greedily adds the AS traversed by the most payments not yet covered by the coalition until
+    /// the coalition covers the target share of the successful payments or no AS adds any
+    pub fn optimal_coalition(
+        baseline: &simlib::SimResult,
+        as_ip_map: &AsIpMap,
+        target_share: f32,
+    ) -> CoalitionPlacement {
+        let mut node_to_asns: HashMap<&ID, Vec<Asn>> = HashMap::new();
+        for (asn, nodes) in as_ip_map.as_to_nodes().iter() {
+            for node in nodes {
+                node_to_asns.entry(node).or_default().push(*asn);
+            }
+        }
+        let mut uncovered: Vec<HashSet<Asn>> = baseline
+            .successful_payments
+            .iter()
+            .map(|p| {
+                p.used_paths
+                    .iter()
+                    .flat_map(|path| path.path.get_involved_nodes())
+                    .flat_map(|n| node_to_asns.get(&n).cloned().unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        let num_payments = uncovered.len();
+        let target = (target_share as f64 * num_payments as f64).ceil() as usize;
+        let coverage = |num_covered: usize| {
+            if num_payments > 0 {
+                num_covered as f32 / num_payments as f32
+            } else {
+                0.0
+            }
+        };
+        let mut placement = CoalitionPlacement {
+            target_share,
+            num_payments,
+            ..Default::default()
+        };
+        let mut num_covered = 0;
+        while num_covered < target {
+            // ordered so that ties go to the lower ASN
+            let mut counts: BTreeMap<Asn, usize> = BTreeMap::new();
+            for asns in uncovered.iter() {
+                for asn in asns {
+                    *counts.entry(*asn).or_default() += 1;
+                }
+            }
+            let Some((asn, num_newly_covered)) =
+                counts.into_iter().rev().max_by_key(|(_, count)| *count)
+            else {
+                break;
+            };
+            uncovered.retain(|asns| !asns.contains(&asn));
+            num_covered += num_newly_covered;
+            placement.steps.push(PlacementStep {
+                asn: asn.to_string(),
+                num_newly_covered,
+                coverage: coverage(num_covered),
+            });
+        }
+        placement.coverage = coverage(num_covered);
+        placement.reached = num_covered >= target;
+        info!(
+            "{} ASs cover {:.1}% of the payments.",
+            placement.steps.len(),
+            placement.coverage * 100.0
+        );
+        placement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, payment::Payment, CandidatePath};
+    use std::{collections::VecDeque, path::Path};
+
+    #[test]
+    fn greedy_coalition() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let baseline = simlib::SimResult {
+            num_succesful: 4,
+            total_num: 4,
+            successful_payments: vec![
+                payment(0, vec!["alice", "bob"]),
+                payment(1, vec!["chan", "dina"]),
+                payment(2, vec!["dina", "chan"]),
+                payment(3, vec!["bob", "chan"]),
+            ],
+            ..Default::default()
+        };
+        let half = SimBuilder::optimal_coalition(&baseline, &as_ip_map, 0.5);
+        assert_eq!(half.steps.len(), 1);
+        assert_eq!(half.steps[0].asn, "797");
+        assert_eq!(half.steps[0].num_newly_covered, 3);
+        assert!(half.reached);
+        let all = SimBuilder::optimal_coalition(&baseline, &as_ip_map, 1.0);
+        let asns: Vec<&str> = all.steps.iter().map(|s| s.asn.as_str()).collect();
+        assert_eq!(asns, vec!["797", "24940"]);
+        assert_eq!(all.coverage, 1.0);
+        assert!(all.reached);
+        let empty = SimBuilder::optimal_coalition(&simlib::SimResult::default(), &as_ip_map, 0.9);
+        assert!(empty.steps.is_empty());
+        assert!(empty.reached);
+    }
+}
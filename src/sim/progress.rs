@@ -0,0 +1,118 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::borrow::Cow;
+
+/// A progress bar per simulated amount and one for the overall progress and ETA. The number of
+/// steps grows as the work becomes known, e.g. once the adversaries of an amount are selected
+pub struct SimProgress {
+    bars: MultiProgress,
+    overall: ProgressBar,
+}
+
+/// Progress of a single amount that also advances the overall bar
+#[derive(Clone)]
+pub struct AmountProgress {
+    bar: ProgressBar,
+    overall: ProgressBar,
+}
+
+impl SimProgress {
+    /// Draws the bars to stderr
+    pub fn new() -> Self {
+        Self::with_draw_target(ProgressDrawTarget::stderr())
+    }
+
+    /// Tracks the progress without drawing anything
+    pub fn hidden() -> Self {
+        Self::with_draw_target(ProgressDrawTarget::hidden())
+    }
+
+    fn with_draw_target(draw_target: ProgressDrawTarget) -> Self {
+        let bars = MultiProgress::with_draw_target(draw_target);
+        let overall = bars.add(ProgressBar::new(0));
+        overall.set_style(
+            ProgressStyle::with_template(
+                "{prefix:>12} [{elapsed_precise}] [{bar:40}] {pos}/{len} ETA {eta}",
+            )
+            .expect("Invalid progress bar template.")
+            .progress_chars("=> "),
+        );
+        overall.set_prefix("total");
+        Self { bars, overall }
+    }
+
+    /// Adds a bar for the given amount
+    pub fn amount_bar(&self, amt_sat: usize) -> AmountProgress {
+        let bar = self.bars.add(ProgressBar::new(0));
+        bar.set_style(
+            ProgressStyle::with_template("{prefix:>12} [{bar:40}] {pos}/{len} {msg}")
+                .expect("Invalid progress bar template.")
+                .progress_chars("=> "),
+        );
+        bar.set_prefix(format!("{}sat", amt_sat));
+        AmountProgress {
+            bar,
+            overall: self.overall.clone(),
+        }
+    }
+
+    pub fn finish(&self) {
+        self.overall.finish();
+    }
+
+    /// Number of completed and known steps over all amounts
+    pub fn position(&self) -> (u64, u64) {
+        (
+            self.overall.position(),
+            self.overall.length().unwrap_or_default(),
+        )
+    }
+}
+
+impl Default for SimProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AmountProgress {
+    /// Announces `n` more steps to complete
+    pub fn add_steps(&self, n: u64) {
+        self.bar.inc_length(n);
+        self.overall.inc_length(n);
+    }
+
+    /// Marks `n` steps as completed
+    pub fn inc(&self, n: u64) {
+        self.bar.inc(n);
+        self.overall.inc(n);
+    }
+
+    pub fn set_message(&self, msg: impl Into<Cow<'static, str>>) {
+        self.bar.set_message(msg);
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_with_message("done");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overall_progress() {
+        let progress = SimProgress::hidden();
+        let first = progress.amount_bar(100);
+        let second = progress.amount_bar(1000);
+        first.add_steps(3);
+        second.add_steps(2);
+        first.inc(3);
+        second.inc(1);
+        assert_eq!(progress.position(), (4, 5));
+        first.finish();
+        second.inc(1);
+        progress.finish();
+        assert_eq!(progress.position(), (5, 5));
+    }
+}
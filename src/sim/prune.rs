@@ -0,0 +1,100 @@
+use super::{AttackSim, PerStrategyResults};
+use crate::PacketDropStrategy;
+use serde::{Deserialize, Serialize};
+
+/// Totals of the adversaries that affected too few payments to be reported individually
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OtherAsns {
+    pub asns: Vec<String>,
+    pub num_successful: usize,
+    pub num_failed: usize,
+    /// Payments the adversaries dropped or, for PacketDropStrategy::Delay, delayed
+    pub num_affected: usize,
+}
+
+impl AttackSim {
+    /// Number of the baseline's successful payments the adversary dropped or delayed
+    pub fn num_affected(
+        &self,
+        strategy: &PacketDropStrategy,
+        num_baseline_successful: usize,
+    ) -> usize {
+        match *strategy {
+            PacketDropStrategy::Delay { .. } => self
+                .delay_impact
+                .as_ref()
+                .map(|d| d.num_delayed_payments)
+                .unwrap_or_default(),
+            _ => num_baseline_successful
+                .saturating_sub(self.sim_results.iter().map(|r| r.num_successful).sum()),
+        }
+    }
+}
+
+impl PerStrategyResults {
+    /// Moves the adversaries that affected fewer than `min_affected` payments into `other_asns`
+    pub fn prune(&mut self, num_baseline_successful: usize, min_affected: usize) {
+        let strategy = &self.strategy;
+        let (kept, pruned): (Vec<AttackSim>, Vec<AttackSim>) = self
+            .attack_results
+            .drain(..)
+            .partition(|a| a.num_affected(strategy, num_baseline_successful) >= min_affected);
+        self.attack_results = kept;
+        if pruned.is_empty() {
+            return;
+        }
+        let mut other = OtherAsns::default();
+        for attack_sim in pruned {
+            other.num_affected += attack_sim.num_affected(strategy, num_baseline_successful);
+            for result in attack_sim.sim_results.iter() {
+                other.num_successful += result.num_successful;
+                other.num_failed += result.num_failed;
+            }
+            other.asns.push(attack_sim.asn);
+        }
+        self.other_asns = Some(other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimResult;
+
+    #[test]
+    fn prune_attack_results() {
+        let attack = |asn: &str, num_successful| AttackSim {
+            asn: asn.to_owned(),
+            sim_results: vec![SimResult {
+                num_successful,
+                num_failed: 10 - num_successful,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut results = PerStrategyResults {
+            strategy: PacketDropStrategy::All,
+            attack_results: vec![attack("797", 2), attack("24940", 7), attack("13335", 8)],
+            other_asns: None,
+        };
+        results.prune(8, 3);
+        let kept: Vec<&str> = results
+            .attack_results
+            .iter()
+            .map(|a| a.asn.as_str())
+            .collect();
+        assert_eq!(kept, vec!["797"]);
+        assert_eq!(
+            results.other_asns,
+            Some(OtherAsns {
+                asns: vec![String::from("24940"), String::from("13335")],
+                num_successful: 15,
+                num_failed: 5,
+                num_affected: 1,
+            })
+        );
+        results.prune(8, 0);
+        assert_eq!(results.attack_results.len(), 1);
+    }
+}
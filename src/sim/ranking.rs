@@ -0,0 +1,200 @@
+use super::{SimBuilder, SimOutput};
+use crate::{net::Asn, AsIpMap};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Value of the successful payments' parts that traverse at least one node of an AS. Larger
+/// payments take other paths than small ones, so the value an AS could censor depends on the amount
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueExposure {
+    pub asn: u32,
+    pub exposed_msat: usize,
+    /// Share of the value of all successful payments
+    pub value_exposure: f32,
+    /// Rank (starting at 1) at the next smaller amount, None if the AS was not ranked there
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_rank: Option<usize>,
+}
+
+/// The adversaries of an amount ranked by value exposure and how they differ from the adversaries
+/// of the next smaller amount
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueRanking {
+    pub ranked: Vec<ValueExposure>,
+    /// ASs that were not ranked at the next smaller amount
+    pub entered: Vec<u32>,
+    /// ASs ranked at the next smaller amount that are no longer ranked
+    pub left: Vec<u32>,
+}
+
+impl SimBuilder {
+    /// Computes the value exposure of every AS in descending order of exposed value
+    pub fn value_exposure_scores(
+        sim_result: &simlib::SimResult,
+        as_ip_map: &AsIpMap,
+    ) -> Vec<ValueExposure> {
+        let mut exposed: HashMap<Asn, usize> = HashMap::new();
+        let mut total_msat = 0;
+        for p in sim_result.successful_payments.iter() {
+            for part in p.used_paths.iter() {
+                total_msat += part.amount;
+                let asns: HashSet<Asn> = part
+                    .path
+                    .get_involved_nodes()
+                    .iter()
+                    .filter_map(|n| as_ip_map.asn_of(n))
+                    .collect();
+                for asn in asns {
+                    *exposed.entry(asn).or_default() += part.amount;
+                }
+            }
+        }
+        let mut exposure: Vec<ValueExposure> = as_ip_map
+            .as_to_nodes()
+            .keys()
+            .map(|asn| {
+                let exposed_msat = exposed.get(asn).copied().unwrap_or_default();
+                ValueExposure {
+                    asn: *asn,
+                    exposed_msat,
+                    value_exposure: if total_msat > 0 {
+                        exposed_msat as f32 / total_msat as f32
+                    } else {
+                        0.0
+                    },
+                    previous_rank: None,
+                }
+            })
+            .collect();
+        exposure.sort_by(|a, b| b.exposed_msat.cmp(&a.exposed_msat).then(a.asn.cmp(&b.asn)));
+        exposure
+    }
+}
+
+impl ValueRanking {
+    /// Ranks the `n` ASs with the highest value exposure
+    pub fn new(mut scores: Vec<ValueExposure>, n: usize) -> Self {
+        scores.truncate(n);
+        Self {
+            ranked: scores,
+            ..Default::default()
+        }
+    }
+
+    pub fn asns(&self) -> Vec<u32> {
+        self.ranked.iter().map(|e| e.asn).collect()
+    }
+
+    /// Compares the ranking of each amount to that of the next smaller amount
+    pub fn compare_across_amounts(outputs: &mut [SimOutput]) {
+        outputs.sort_by_key(|o| o.amt_sat);
+        let mut previous: Option<(usize, Vec<u32>)> = None;
+        for output in outputs.iter_mut() {
+            let Some(ranking) = output.value_ranking.as_mut() else {
+                continue;
+            };
+            let asns = ranking.asns();
+            if let Some((previous_amt_sat, previous_asns)) = &previous {
+                for exposure in ranking.ranked.iter_mut() {
+                    exposure.previous_rank = previous_asns
+                        .iter()
+                        .position(|asn| *asn == exposure.asn)
+                        .map(|i| i + 1);
+                }
+                ranking.entered = asns
+                    .iter()
+                    .filter(|asn| !previous_asns.contains(asn))
+                    .copied()
+                    .collect();
+                ranking.left = previous_asns
+                    .iter()
+                    .filter(|asn| !asns.contains(asn))
+                    .copied()
+                    .collect();
+                info!(
+                    "From {} to {} sat, ASs {:?} entered and {:?} left the ranking.",
+                    previous_amt_sat, output.amt_sat, ranking.entered, ranking.left
+                );
+            }
+            previous = Some((output.amt_sat, asns));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, payment::Payment, CandidatePath};
+    use std::{collections::VecDeque, path::Path};
+
+    #[test]
+    fn value_ranking() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let part = |hops: Vec<&str>, amount| {
+            let mut path = simlib::Path::new(hops[0].to_string(), hops[hops.len() - 1].to_string());
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            let mut part = CandidatePath::new_with_path(path);
+            part.amount = amount;
+            part
+        };
+        let mut p = Payment::new(0, String::from("dina"), String::from("bob"), 4000, None);
+        p.succeeded = true;
+        // a large part via AS 797 and a small one within AS 24940
+        p.used_paths = vec![
+            part(vec!["dina", "chan", "bob"], 3000),
+            part(vec!["alice", "bob"], 1000),
+        ];
+        let sim_result = simlib::SimResult {
+            num_succesful: 1,
+            total_num: 1,
+            successful_payments: vec![p],
+            ..Default::default()
+        };
+        let scores = SimBuilder::value_exposure_scores(&sim_result, &as_ip_map);
+        assert_eq!(
+            scores
+                .iter()
+                .map(|e| (e.asn, e.exposed_msat))
+                .collect::<Vec<_>>(),
+            vec![(24940, 4000), (797, 3000)]
+        );
+        assert_eq!(scores[1].value_exposure, 0.75);
+        let ranking = |amt_sat, asns: Vec<u32>| SimOutput {
+            amt_sat,
+            value_ranking: Some(ValueRanking {
+                ranked: asns
+                    .into_iter()
+                    .map(|asn| ValueExposure {
+                        asn,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut outputs = vec![ranking(1000, vec![3, 1]), ranking(100, vec![1, 2])];
+        ValueRanking::compare_across_amounts(&mut outputs);
+        let large = outputs[1].value_ranking.as_ref().unwrap();
+        assert_eq!(large.entered, vec![3]);
+        assert_eq!(large.left, vec![2]);
+        assert_eq!(large.ranked[0].previous_rank, None);
+        assert_eq!(large.ranked[1].previous_rank, Some(1));
+        assert_eq!(ValueRanking::new(scores, 1).asns(), vec![24940]);
+    }
+}
@@ -0,0 +1,215 @@
+use super::{output::*, DecisionRng, SimBuilder};
+use crate::{net::Asn, AsIpMap};
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+use std::collections::{BTreeMap, HashSet};
+
+/// How PacketDropStrategy::RateLimit throttled the payments traversing the AS
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimiting {
+    /// Payments with at least one node of the AS on a path
+    pub num_observed: usize,
+    pub num_dropped: usize,
+    /// Share of the observed payments that were dropped
+    pub censorship_rate: f32,
+    /// Windows from the first to the last observed payment
+    pub num_windows: usize,
+    /// Windows in which the AS used up its drops
+    pub num_saturated_windows: usize,
+    /// Largest number of observed payments the AS let through as its drops were used up, carried
+    /// over to later windows and worked off by the drops they leave unused
+    pub max_backlog: usize,
+    /// Backlog after the last window
+    pub final_backlog: usize,
+    /// Observed and dropped payments per window
+    #[serde(skip)]
+    windows: BTreeMap<u64, (usize, usize)>,
+}
+
+/// The window a payment arrives in. Payments arrive every `interval_secs` in the order of their IDs
+pub(crate) fn arrival_window(payment_id: usize, window_secs: u32, interval_secs: u32) -> u64 {
+    payment_id as u64 * interval_secs as u64 / window_secs.max(1) as u64
+}
+
+impl RateLimiting {
+    fn record(&mut self, window: u64, dropped: bool) {
+        let counts = self.windows.entry(window).or_default();
+        counts.0 += 1;
+        if dropped {
+            counts.1 += 1;
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: &Self) {
+        for (window, (num_observed, num_dropped)) in other.windows.iter() {
+            let counts = self.windows.entry(*window).or_default();
+            counts.0 += num_observed;
+            counts.1 += num_dropped;
+        }
+    }
+
+    /// Derives the counts, the rate and the backlog from the windows
+    pub(crate) fn finalise(mut self, max_drops: u32) -> Self {
+        let max_drops = max_drops as usize;
+        self.num_observed = self.windows.values().map(|(observed, _)| observed).sum();
+        self.num_dropped = self.windows.values().map(|(_, dropped)| dropped).sum();
+        self.censorship_rate = if self.num_observed > 0 {
+            self.num_dropped as f32 / self.num_observed as f32
+        } else {
+            0.0
+        };
+        self.num_windows = match (self.windows.keys().next(), self.windows.keys().last()) {
+            (Some(first), Some(last)) => (last - first + 1) as usize,
+            _ => 0,
+        };
+        self.num_saturated_windows = self
+            .windows
+            .values()
+            .filter(|(_, dropped)| *dropped >= max_drops)
+            .count();
+        let (mut backlog, mut max_backlog) = (0, 0);
+        let mut previous: Option<u64> = None;
+        for (window, (num_observed, _)) in self.windows.iter() {
+            // windows without payments work off the backlog as well
+            if let Some(previous) = previous {
+                let num_idle = (window - previous - 1) as usize;
+                backlog = backlog.saturating_sub(num_idle.saturating_mul(max_drops));
+            }
+            backlog = (backlog + num_observed).saturating_sub(max_drops);
+            max_backlog = max_backlog.max(backlog);
+            previous = Some(*window);
+        }
+        self.max_backlog = max_backlog;
+        self.final_backlog = backlog;
+        self
+    }
+}
+
+impl SimBuilder {
+    /// Payments traversing the AS are dropped in the order they arrive until the AS dropped
+    /// `max_drops` payments in the window. The drops per window are kept by `rng` so that chunks
+    /// of the payments share the limit. Payments from/to the AS are the target
+    pub(crate) fn apply_rate_limit_strategy(
+        sim_result: simlib::SimResult,
+        asn_nodes: &[ID],
+        asn: Asn,
+        as_ip_map: &AsIpMap,
+        (max_drops, window_secs, interval_secs): (u32, u32, u32),
+        rng: &mut DecisionRng,
+    ) -> (simlib::SimResult, PerSimAccuracy, RateLimiting) {
+        let mut updated_results = simlib::SimResult {
+            num_failed: sim_result.num_failed,
+            num_succesful: 0,
+            total_num: sim_result.total_num,
+            successful_payments: vec![],
+            failed_payments: sim_result.failed_payments,
+            ..Default::default()
+        };
+        let mut observed: Vec<usize> = sim_result
+            .successful_payments
+            .iter()
+            .filter(|p| Self::payment_involves_asn(p, asn_nodes))
+            .map(|p| p.payment_id)
+            .collect();
+        observed.sort_unstable();
+        let mut limiting = RateLimiting::default();
+        let dropped: HashSet<usize> = observed
+            .into_iter()
+            .filter(|id| {
+                let window = arrival_window(*id, window_secs, interval_secs);
+                let dropped = rng.take_drop(window, max_drops);
+                limiting.record(window, dropped);
+                dropped
+            })
+            .collect();
+        let mut accuracy = PerSimAccuracy::default();
+        for mut p in sim_result.successful_payments {
+            let target = Self::payment_from_or_to_asn(&p, asn, as_ip_map);
+            if dropped.contains(&p.payment_id) {
+                accuracy.record(true, target);
+                p.succeeded = false;
+                p.used_paths = vec![];
+                updated_results.num_failed += 1;
+                updated_results.failed_payments.push(p);
+            } else {
+                accuracy.record(false, target);
+                updated_results.num_succesful += 1;
+                updated_results.successful_payments.push(p);
+            }
+        }
+        (
+            updated_results,
+            accuracy.finalise(),
+            limiting.finalise(max_drops),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Decisions;
+    use simlib::{payment::Payment, CandidatePath};
+    use std::collections::{HashMap, VecDeque};
+
+    #[test]
+    fn rate_limited_drops() {
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        // windows of two payments, the last one after an idle window
+        let baseline = simlib::SimResult {
+            num_succesful: 6,
+            total_num: 6,
+            successful_payments: vec![
+                payment(1, vec!["alice", "bob", "chan"]),
+                payment(0, vec!["dina", "chan"]),
+                payment(2, vec!["alice", "chan"]),
+                payment(3, vec!["bob", "chan", "dina"]),
+                payment(4, vec!["alice", "bob"]),
+                payment(8, vec!["chan", "dina"]),
+            ],
+            ..Default::default()
+        };
+        let as_ip_map = AsIpMap::from(HashMap::from([
+            (24940, vec![String::from("alice"), String::from("bob")]),
+            (797, vec![String::from("chan"), String::from("dina")]),
+        ]));
+        let nodes = as_ip_map.as_to_nodes()[&797].clone();
+        let decisions = Decisions::new(19);
+        let (result, _, limiting) = SimBuilder::apply_rate_limit_strategy(
+            baseline,
+            &nodes,
+            797,
+            &as_ip_map,
+            (1, 2, 1),
+            &mut decisions.rng_for(797),
+        );
+        let mut dropped: Vec<usize> = result
+            .failed_payments
+            .iter()
+            .map(|p| p.payment_id)
+            .collect();
+        dropped.sort();
+        assert_eq!(dropped, vec![0, 2, 8]);
+        assert_eq!(result.num_succesful, 3);
+        assert_eq!(limiting.num_observed, 5);
+        assert_eq!(limiting.num_dropped, 3);
+        assert_eq!(limiting.censorship_rate, 0.6);
+        assert_eq!(limiting.num_windows, 5);
+        assert_eq!(limiting.num_saturated_windows, 3);
+        assert_eq!(limiting.max_backlog, 2);
+        assert_eq!(limiting.final_backlog, 0);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::net::Asn;
+#[cfg(not(test))]
+use log::warn;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use simlib::{payment::Payment, ID};
+#[cfg(test)]
+use std::println as warn;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Every stochastic decision of a simulation run, stored so the run can be reproduced exactly
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Replay {
+    pub run: u64,
+    /// The src/dest pairs drawn for the simulation
+    pub pairs: Vec<(ID, ID)>,
+    /// Outcomes of PacketDropStrategy::IntraProbability
+    pub prob_drops: Vec<ProbDrop>,
+}
+
+/// Whether an AS dropped a payment and the ratio the decision was based on
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbDrop {
+    pub asn: Asn,
+    pub payment_id: usize,
+    pub amount_msat: usize,
+    pub ratio: f32,
+    pub dropped: bool,
+}
+
+/// The seed of a simulation and, if enabled, where its decisions are recorded to or replayed from
+#[derive(Debug, Default)]
+pub struct Decisions {
+    seed: u64,
+    recorded: Option<Mutex<Vec<ProbDrop>>>,
+    replayed: Option<HashMap<(Asn, usize, usize), ProbDrop>>,
+}
+
+/// Makes the stochastic decisions of a single AS and counts its drops under a rate limit
+pub struct DecisionRng<'a> {
+    rng: StdRng,
+    asn: Asn,
+    decisions: &'a Decisions,
+    /// Payments dropped per window by PacketDropStrategy::RateLimit, kept across chunks
+    window_drops: HashMap<u64, u32>,
+}
+
+impl Replay {
+    pub fn from_json_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Writes `replay-run<run>.json` to the directory and returns its path
+    pub fn write_to_file(&self, output_dir: PathBuf) -> Result<PathBuf, Box<dyn Error>> {
+        fs::create_dir_all(&output_dir)?;
+        let mut path = output_dir;
+        path.push(format!("replay-run{}.json", self.run));
+        serde_json::to_writer(File::create(&path)?, self)?;
+        Ok(path)
+    }
+}
+
+impl Decisions {
+    /// Decisions are drawn from RNGs seeded with `seed`
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Decisions are drawn as in `new` and recorded
+    pub fn recording(seed: u64) -> Self {
+        Self {
+            seed,
+            recorded: Some(Mutex::new(vec![])),
+            replayed: None,
+        }
+    }
+
+    /// Decisions are taken from the replay. Decisions that were not recorded are drawn as in `new`
+    pub fn replaying(replay: &Replay) -> Self {
+        Self {
+            seed: replay.run,
+            recorded: None,
+            replayed: Some(
+                replay
+                    .prob_drops
+                    .iter()
+                    .map(|d| ((d.asn, d.payment_id, d.amount_msat), d.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The decisions of an AS are independent of the order in which the ASs are simulated
+    pub(crate) fn rng_for(&self, asn: Asn) -> DecisionRng<'_> {
+        DecisionRng {
+            rng: StdRng::seed_from_u64(self.seed ^ asn as u64),
+            asn,
+            decisions: self,
+            window_drops: HashMap::new(),
+        }
+    }
+
+    /// Returns the recorded decisions sorted by amount, AS and payment
+    pub fn into_replay(self, pairs: Vec<(ID, ID)>) -> Replay {
+        let mut prob_drops = self
+            .recorded
+            .map(|r| r.into_inner().unwrap_or_default())
+            .unwrap_or_default();
+        prob_drops.sort_by_key(|d| (d.amount_msat, d.asn, d.payment_id));
+        prob_drops.dedup();
+        Replay {
+            run: self.seed,
+            pairs,
+            prob_drops,
+        }
+    }
+}
+
+impl DecisionRng<'_> {
+    /// Counts a drop in the window unless the AS already dropped `max_drops` payments in it
+    pub(crate) fn take_drop(&mut self, window: u64, max_drops: u32) -> bool {
+        let num_dropped = self.window_drops.entry(window).or_default();
+        if *num_dropped < max_drops {
+            *num_dropped += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// True with a probability of `permille` per mille. These draws are not recorded
+    pub(crate) fn chance(&mut self, permille: u16) -> bool {
+        self.rng.gen_bool(f64::from(permille.min(1000)) / 1000.0)
+    }
+
+    /// Drops the payment with the probability of the ratio. None if there is no ratio
+    pub(crate) fn prob_drop(&mut self, payment: &Payment, ratio: Option<f32>) -> Option<bool> {
+        let key = (self.asn, payment.payment_id, payment.amount);
+        if let Some(replayed) = &self.decisions.replayed {
+            if let Some(decision) = replayed.get(&key) {
+                return Some(decision.dropped);
+            }
+            warn!(
+                "No recorded decision for payment {} of AS {}.",
+                payment.payment_id, self.asn
+            );
+        }
+        let ratio = ratio?;
+        let dropped = self.rng.gen_bool(ratio as f64);
+        if let Some(recorded) = &self.decisions.recorded {
+            if let Ok(mut recorded) = recorded.lock() {
+                recorded.push(ProbDrop {
+                    asn: key.0,
+                    payment_id: key.1,
+                    amount_msat: key.2,
+                    ratio,
+                    dropped,
+                });
+            }
+        }
+        Some(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn record_and_replay() {
+        let payments: Vec<Payment> = (0..20)
+            .map(|id| Payment::new(id, String::from("dina"), String::from("bob"), 1, None))
+            .collect();
+        let ratio = |p: &Payment| Some([0.2, 0.5, 0.8][p.payment_id % 3]);
+        let decisions = Decisions::recording(19);
+        let mut rng = decisions.rng_for(797);
+        let recorded: Vec<Option<bool>> = payments
+            .iter()
+            .map(|p| rng.prob_drop(p, ratio(p)))
+            .collect();
+        assert_eq!(rng.prob_drop(&payments[0], None), None);
+        let pairs = vec![(String::from("dina"), String::from("bob"))];
+        let replay = decisions.into_replay(pairs.clone());
+        assert_eq!(replay.run, 19);
+        assert_eq!(replay.pairs, pairs);
+        assert_eq!(replay.prob_drops.len(), 20);
+
+        let dir = TempDir::new().expect("Error opening tempdir");
+        let path = replay.write_to_file(PathBuf::from(dir.path())).unwrap();
+        let mut replay = Replay::from_json_file(&path).unwrap();
+        // a different seed must not change the outcome
+        replay.run = 0;
+        let decisions = Decisions::replaying(&replay);
+        let mut rng = decisions.rng_for(797);
+        // in reverse order
+        let mut replayed: Vec<Option<bool>> = payments
+            .iter()
+            .rev()
+            .map(|p| rng.prob_drop(p, ratio(p)))
+            .collect();
+        replayed.reverse();
+        assert_eq!(replayed, recorded);
+    }
+}
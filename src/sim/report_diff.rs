@@ -0,0 +1,336 @@
+use super::output::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// How much a metric may change before the reports are considered different. A delta is within
+/// the tolerance if it is within the absolute or the relative tolerance
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Tolerance {
+    pub absolute: f64,
+    /// Share of the larger magnitude of the two values
+    pub relative: f64,
+}
+
+/// The metrics of two reports side by side, e.g. before and after a refactoring
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportDiff {
+    pub tolerance: Tolerance,
+    /// Metrics present in both reports, sorted by key
+    pub deltas: Vec<MetricDelta>,
+    /// Keys of the metrics only present in this report
+    pub only_this: Vec<String>,
+    /// Keys of the metrics only present in the other report
+    pub only_other: Vec<String>,
+}
+
+/// The censorship of an AS under a strategy at an amount in both reports, e.g. of two snapshots
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AsDelta {
+    pub amt_sat: usize,
+    pub strategy: String,
+    pub asn: String,
+    /// None if the AS was not simulated in this report
+    pub this: Option<AsCensorship>,
+    /// None if the AS was not simulated in the other report
+    pub other: Option<AsCensorship>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AsCensorship {
+    /// Payments that succeeded in the baseline and failed under attack
+    pub num_censored: usize,
+    /// Share of the baseline's successful payments the AS censored
+    pub censorship_rate: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accuracy: Option<PerSimAccuracy>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricDelta {
+    /// The amount, strategy, AS and metric, e.g. `1000sat/All/797/numSuccessful`
+    pub key: String,
+    pub this: f64,
+    pub other: f64,
+    /// `other - this`
+    pub delta: f64,
+    pub within_tolerance: bool,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            absolute: 1e-6,
+            relative: 0.0,
+        }
+    }
+}
+
+impl Tolerance {
+    pub fn contains(&self, this: f64, other: f64) -> bool {
+        let delta = (other - this).abs();
+        delta <= self.absolute || delta <= self.relative * this.abs().max(other.abs())
+    }
+}
+
+impl ReportDiff {
+    /// True if both reports have the same metrics and all deltas are within the tolerance
+    pub fn is_within_tolerance(&self) -> bool {
+        self.only_this.is_empty()
+            && self.only_other.is_empty()
+            && self.deltas.iter().all(|d| d.within_tolerance)
+    }
+
+    /// The deltas that exceed the tolerance
+    pub fn exceeding(&self) -> impl Iterator<Item = &MetricDelta> {
+        self.deltas.iter().filter(|d| !d.within_tolerance)
+    }
+}
+
+impl AsCensorship {
+    fn new(attack: &AttackSim) -> Self {
+        let num_censored: usize = attack.sim_results.iter().map(|r| r.num_censored).sum();
+        let num_successful: usize = attack.sim_results.iter().map(|r| r.num_successful).sum();
+        let num_baseline_successful = num_censored + num_successful;
+        Self {
+            num_censored,
+            censorship_rate: if num_baseline_successful > 0 {
+                num_censored as f32 / num_baseline_successful as f32
+            } else {
+                0.0
+            },
+            accuracy: attack.per_sim_accuracy.clone(),
+        }
+    }
+}
+
+impl AsDelta {
+    /// `other - this`, None if the AS is missing from either report
+    pub fn censorship_rate_delta(&self) -> Option<f32> {
+        Some(self.other.as_ref()?.censorship_rate - self.this.as_ref()?.censorship_rate)
+    }
+
+    pub fn num_censored_delta(&self) -> Option<i64> {
+        Some(self.other.as_ref()?.num_censored as i64 - self.this.as_ref()?.num_censored as i64)
+    }
+
+    /// `other - this` of the F1 score, None if either has no accuracy
+    pub fn f1_delta(&self) -> Option<f32> {
+        let f1 = |c: &Option<AsCensorship>| c.as_ref()?.accuracy.as_ref().map(|a| a.f1);
+        Some(f1(&self.other)? - f1(&self.this)?)
+    }
+}
+
+impl Report {
+    /// The censorship of each AS per amount and strategy side by side, sorted by amount, strategy
+    /// and ASN
+    pub fn as_deltas(&self, other: &Self) -> Vec<AsDelta> {
+        let mut deltas: BTreeMap<(usize, String, String), AsDelta> = BTreeMap::new();
+        for (report, is_this) in [(self, true), (other, false)] {
+            for output in report.1.iter() {
+                for results in output.per_strategy_results.iter() {
+                    let strategy = format!("{:?}", results.strategy);
+                    for attack in results.attack_results.iter() {
+                        let key = (output.amt_sat, strategy.clone(), attack.asn.clone());
+                        let delta = deltas.entry(key).or_insert_with(|| AsDelta {
+                            amt_sat: output.amt_sat,
+                            strategy: strategy.clone(),
+                            asn: attack.asn.clone(),
+                            ..Default::default()
+                        });
+                        let censorship = Some(AsCensorship::new(attack));
+                        if is_this {
+                            delta.this = censorship;
+                        } else {
+                            delta.other = censorship;
+                        }
+                    }
+                }
+            }
+        }
+        deltas.into_values().collect()
+    }
+
+    /// Compares the metrics of both reports with the default tolerance
+    pub fn diff(&self, other: &Self) -> ReportDiff {
+        self.diff_with_tolerance(other, Tolerance::default())
+    }
+
+    pub fn diff_with_tolerance(&self, other: &Self, tolerance: Tolerance) -> ReportDiff {
+        let mut this = self.metrics();
+        let other = other.metrics();
+        let mut diff = ReportDiff {
+            tolerance,
+            ..Default::default()
+        };
+        for (key, other) in other {
+            match this.remove(&key) {
+                Some(this) => diff.deltas.push(MetricDelta {
+                    key,
+                    this,
+                    other,
+                    delta: other - this,
+                    within_tolerance: tolerance.contains(this, other),
+                }),
+                None => diff.only_other.push(key),
+            }
+        }
+        diff.only_this = this.into_keys().collect();
+        diff
+    }
+
+    /// The summary metrics of each amount, strategy and AS by key. Individual payments are not
+    /// compared
+    pub fn metrics(&self) -> BTreeMap<String, f64> {
+        let mut metrics = BTreeMap::new();
+        for output in self.1.iter() {
+            let amount = format!("{}sat", output.amt_sat);
+            metrics.insert(
+                format!("{}/totalNumPayments", amount),
+                output.total_num_payments as f64,
+            );
+            if let Some(baseline) = &output.baseline {
+                Self::insert_result(
+                    &mut metrics,
+                    &format!("{}/baseline", amount),
+                    &baseline.result,
+                );
+            }
+            for results in output.per_strategy_results.iter() {
+                for attack in results.attack_results.iter() {
+                    let prefix = format!("{}/{:?}/{}", amount, results.strategy, attack.asn);
+                    for (i, result) in attack.sim_results.iter().enumerate() {
+                        let prefix = if i > 0 {
+                            format!("{}/{}", prefix, i)
+                        } else {
+                            prefix.clone()
+                        };
+                        Self::insert_result(&mut metrics, &prefix, result);
+                    }
+                    if let Some(accuracy) = &attack.per_sim_accuracy {
+                        for (name, value) in [
+                            ("precision", accuracy.precision),
+                            ("recall", accuracy.recall),
+                            ("f1", accuracy.f1),
+                        ] {
+                            metrics.insert(format!("{}/{}", prefix, name), value as f64);
+                        }
+                    }
+                    if let Some(observation) = &attack.observation {
+                        metrics.insert(
+                            format!("{}/observedDropped", prefix),
+                            observation.observed_dropped as f64,
+                        );
+                        metrics.insert(
+                            format!("{}/unobservedDropped", prefix),
+                            observation.unobserved_dropped as f64,
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(concentration) = &self.2 {
+            for (name, m) in [
+                ("nodes", &concentration.nodes),
+                ("channels", &concentration.channels),
+                ("capacity", &concentration.capacity),
+            ] {
+                metrics.insert(format!("concentration/{}/gini", name), m.gini);
+                metrics.insert(format!("concentration/{}/hhi", name), m.hhi);
+            }
+        }
+        metrics
+    }
+
+    fn insert_result(metrics: &mut BTreeMap<String, f64>, prefix: &str, result: &SimResult) {
+        for (name, value) in [
+            ("numNodesUnderAttack", result.num_nodes_under_attack),
+            ("numSuccessful", result.num_successful),
+            ("numFailed", result.num_failed),
+        ] {
+            metrics.insert(format!("{}/{}", prefix, name), value as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PacketDropStrategy;
+
+    #[test]
+    fn diff_reports() {
+        let report = |num_successful, precision| {
+            Report(
+                19,
+                vec![SimOutput {
+                    amt_sat: 1000,
+                    total_num_payments: 10,
+                    per_strategy_results: vec![PerStrategyResults {
+                        strategy: PacketDropStrategy::All,
+                        attack_results: vec![AttackSim {
+                            asn: String::from("797"),
+                            sim_results: vec![SimResult {
+                                num_successful,
+                                ..Default::default()
+                            }],
+                            per_sim_accuracy: Some(PerSimAccuracy {
+                                precision,
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }],
+                        other_asns: None,
+                    }],
+                    ..Default::default()
+                }],
+                None,
+            )
+        };
+        let this = report(5, 0.5);
+        assert!(this.diff(&this).is_within_tolerance());
+        let diff = this.diff(&report(6, 0.5));
+        assert!(!diff.is_within_tolerance());
+        let exceeding: Vec<&MetricDelta> = diff.exceeding().collect();
+        assert_eq!(exceeding.len(), 1);
+        assert_eq!(exceeding[0].key, "1000sat/All/797/numSuccessful");
+        assert_eq!(exceeding[0].delta, 1.0);
+        // a change of the precision in the order of float rounding
+        let other = report(5, 0.5 + 1e-7);
+        assert!(this.diff(&other).is_within_tolerance());
+        let strict = Tolerance {
+            absolute: 0.0,
+            relative: 0.0,
+        };
+        assert!(!this
+            .diff_with_tolerance(&other, strict)
+            .is_within_tolerance());
+        let relative = Tolerance {
+            absolute: 0.0,
+            relative: 0.2,
+        };
+        assert!(this
+            .diff_with_tolerance(&report(6, 0.5), relative)
+            .is_within_tolerance());
+        let diff = this.diff(&Report(19, vec![], None));
+        assert!(diff.deltas.is_empty());
+        assert_eq!(diff.only_this.len(), this.metrics().len());
+        assert!(!diff.is_within_tolerance());
+
+        let mut other = report(4, 0.5);
+        other.1[0].per_strategy_results[0].attack_results[0].sim_results[0].num_censored = 1;
+        let deltas = this.as_deltas(&other);
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].asn, "797");
+        assert_eq!(deltas[0].num_censored_delta(), Some(1));
+        assert_eq!(deltas[0].censorship_rate_delta(), Some(0.2));
+        assert_eq!(deltas[0].f1_delta(), Some(0.0));
+        let deltas = this.as_deltas(&Report(19, vec![], None));
+        assert!(deltas[0].other.is_none());
+        assert_eq!(deltas[0].censorship_rate_delta(), None);
+    }
+}
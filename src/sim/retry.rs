@@ -0,0 +1,242 @@
+use super::{AttackSim, Decisions, FailureCause, SimBuilder};
+use crate::{net::Asn, AsIpMap, AsNodeRatios, PacketDropStrategy, SimulatorError};
+use serde::{Deserialize, Serialize};
+use simlib::{graph::Graph, payment::Payment, ID};
+use std::collections::HashSet;
+
+/// Senders whose payment was censored retry it on alternative paths that avoid the hops that
+/// failed before
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Attempts after the first one
+    pub max_retries: usize,
+}
+
+/// The fate of the baseline's successful payments when the senders retry censored payments
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryOutcome {
+    pub max_retries: usize,
+    pub num_first_attempt_success: usize,
+    /// Censored payments that succeeded on a retry
+    pub num_eventual_success: usize,
+    /// Censored payments that failed on every attempt or ran out of paths
+    pub num_hard_failure: usize,
+    /// Number of censored payments that succeeded on the first, second, ... retry
+    pub successes_per_retry: Vec<usize>,
+    /// IDs of the hard failures
+    #[serde(skip)]
+    pub hard_failed_payments: Vec<usize>,
+}
+
+impl RetryPolicy {
+    /// The graph without the channels from or to the excluded nodes
+    pub fn exclude_nodes(graph: &Graph, excluded: &HashSet<ID>) -> Graph {
+        let mut graph = graph.clone();
+        for node in excluded {
+            graph.edges.remove(node);
+        }
+        for edges in graph.edges.values_mut() {
+            edges.retain(|e| !excluded.contains(&e.destination));
+        }
+        graph
+    }
+}
+
+impl SimBuilder {
+    /// Retries every payment censored in the attack on the graph without the adversary's nodes on
+    /// its previous paths. The sender only learns of the first such node of each attempt. The
+    /// decisions are drawn from the seed and not recorded
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn retry_censored(
+        &self,
+        baseline: &simlib::SimResult,
+        attack_sim: &AttackSim,
+        asn: Asn,
+        nodes: &[ID],
+        strategy: &PacketDropStrategy,
+        ratios: Option<&AsNodeRatios>,
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+        policy: RetryPolicy,
+    ) -> Result<RetryOutcome, SimulatorError> {
+        let censored: HashSet<usize> = attack_sim
+            .sim_results
+            .first()
+            .map(|r| {
+                r.payments
+                    .iter()
+                    .filter(|p| p.drop_reason.is_some())
+                    .map(|p| p.fields.payment_id)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut rng = Decisions::new(decisions.seed()).rng_for(asn);
+        let mut outcome = RetryOutcome {
+            max_retries: policy.max_retries,
+            num_first_attempt_success: baseline.num_succesful.saturating_sub(censored.len()),
+            successes_per_retry: vec![0; policy.max_retries],
+            ..Default::default()
+        };
+        for payment in baseline
+            .successful_payments
+            .iter()
+            .filter(|p| censored.contains(&p.payment_id))
+        {
+            let mut excluded = HashSet::new();
+            let mut failed_attempt = payment.clone();
+            let mut succeeded_at = None;
+            for retry in 0..policy.max_retries {
+                let Some(failed_hop) = Self::first_adversary_hop(&failed_attempt, nodes, &excluded)
+                else {
+                    break;
+                };
+                excluded.insert(failed_hop);
+                let graph = RetryPolicy::exclude_nodes(&self.graph, &excluded);
+                let attempt = self.simulate_on_graph(
+                    graph,
+                    vec![(payment.source.clone(), payment.dest.clone())].into_iter(),
+                );
+                let Some(routed) = attempt.successful_payments.first().cloned() else {
+                    break;
+                };
+                let attacked = Self::apply_drop_strategy(
+                    attempt, asn, nodes, strategy, ratios, as_ip_map, &mut rng,
+                )?;
+                if attacked.result.num_succesful > 0 {
+                    succeeded_at = Some(retry);
+                    break;
+                }
+                failed_attempt = routed;
+            }
+            match succeeded_at {
+                Some(retry) => {
+                    outcome.num_eventual_success += 1;
+                    outcome.successes_per_retry[retry] += 1;
+                }
+                None => {
+                    outcome.num_hard_failure += 1;
+                    outcome.hard_failed_payments.push(payment.payment_id);
+                }
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Reclassifies the censored payments whose retries failed as well
+    pub(crate) fn record_reroute_failures(attack_sim: &mut AttackSim) {
+        let Some(retries) = &attack_sim.retries else {
+            return;
+        };
+        let hard_failed: HashSet<&usize> = retries.hard_failed_payments.iter().collect();
+        for result in attack_sim.sim_results.iter_mut() {
+            for p in result.payments.iter_mut() {
+                if p.failure_cause == Some(FailureCause::Censored)
+                    && hard_failed.contains(&p.fields.payment_id)
+                {
+                    p.failure_cause = Some(FailureCause::CensoredThenRerouteFailed);
+                    result.num_censored -= 1;
+                    result.num_censored_then_reroute_failed += 1;
+                }
+            }
+        }
+    }
+
+    /// The first node of the adversary on the payment's paths that the sender can route around
+    fn first_adversary_hop(payment: &Payment, nodes: &[ID], excluded: &HashSet<ID>) -> Option<ID> {
+        payment
+            .used_paths
+            .iter()
+            .flat_map(|p| p.path.get_involved_nodes())
+            .find(|hop| {
+                nodes.contains(hop)
+                    && *hop != payment.source
+                    && *hop != payment.dest
+                    && !excluded.contains(hop)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsSelectionStrategy;
+    use network_parser::GraphSource::*;
+    use std::path::Path;
+
+    #[test]
+    fn retry_censored_payments() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let excluded = HashSet::from([String::from("bob")]);
+        let without_bob = RetryPolicy::exclude_nodes(&graph, &excluded);
+        assert!(without_bob
+            .get_edges_for_node(&String::from("bob"))
+            .is_none());
+        assert!(without_bob
+            .edges
+            .values()
+            .flatten()
+            .all(|e| e.destination != "bob"));
+
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let nodes = as_ip_map.as_to_nodes()[&797].clone();
+        let mut builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        let pairs = vec![
+            (String::from("alice"), String::from("bob")),
+            (String::from("alice"), String::from("dina")),
+        ];
+        let baseline = builder.simulate(pairs.into_iter());
+        let decisions = Decisions::new(19);
+        let mut attack_sim = SimBuilder::per_asn_simulation(
+            (*baseline).clone(),
+            797,
+            &nodes,
+            &PacketDropStrategy::All,
+            None,
+            &as_ip_map,
+            &decisions,
+            None,
+        )
+        .unwrap();
+        let policy = RetryPolicy { max_retries: 2 };
+        let outcome = builder
+            .retry_censored(
+                &baseline,
+                &attack_sim,
+                797,
+                &nodes,
+                &PacketDropStrategy::All,
+                None,
+                &as_ip_map,
+                &decisions,
+                policy,
+            )
+            .unwrap();
+        assert_eq!(outcome.max_retries, 2);
+        assert_eq!(outcome.successes_per_retry, vec![0, 0]);
+        // dina is part of the AS so there is no way around it
+        assert_eq!(outcome.num_eventual_success, 0);
+        assert_eq!(
+            outcome.num_first_attempt_success + outcome.num_hard_failure,
+            baseline.num_succesful
+        );
+        let num_hard_failure = outcome.num_hard_failure;
+        attack_sim.retries = Some(outcome);
+        SimBuilder::record_reroute_failures(&mut attack_sim);
+        let result = &attack_sim.sim_results[0];
+        assert_eq!(result.num_censored_then_reroute_failed, num_hard_failure);
+        assert_eq!(result.num_censored, 0);
+        assert_eq!(
+            result.num_failed_baseline + result.num_censored_then_reroute_failed,
+            result.num_failed
+        );
+    }
+}
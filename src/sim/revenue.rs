@@ -0,0 +1,170 @@
+use super::SimBuilder;
+use crate::AsIpMap;
+use csv::Writer;
+use serde::Serialize;
+use simlib::ID;
+use std::{collections::HashMap, error::Error, fs, path::PathBuf};
+
+/// Routing fees earned by the nodes of an AS in the baseline
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AsFeeRevenue {
+    pub asn: u32,
+    /// Number of the AS's nodes that forwarded at least one payment
+    pub num_nodes: usize,
+    pub fees_msat: usize,
+}
+
+impl SimBuilder {
+    /// Sums the fee recorded for every intermediate hop of the successful payments
+    pub fn fees_per_node(sim_result: &simlib::SimResult) -> HashMap<ID, usize> {
+        let mut fees = HashMap::new();
+        for (id, fee) in Self::forwarding_fees(sim_result) {
+            *fees.entry(id.clone()).or_default() += fee;
+        }
+        fees
+    }
+
+    /// Sums the fees the given nodes earned from the successful payments
+    pub fn fees_earned(sim_result: &simlib::SimResult, nodes: &[ID]) -> usize {
+        Self::forwarding_fees(sim_result)
+            .filter(|(id, _)| nodes.contains(id))
+            .map(|(_, fee)| fee)
+            .sum()
+    }
+
+    /// The fee of every intermediate hop of the successful payments
+    fn forwarding_fees(sim_result: &simlib::SimResult) -> impl Iterator<Item = (&ID, usize)> {
+        sim_result
+            .successful_payments
+            .iter()
+            .flat_map(|payment| payment.used_paths.iter())
+            .flat_map(|path| {
+                let num_hops = path.path.hops.len();
+                // the sender and receiver do not earn anything
+                path.path
+                    .hops
+                    .iter()
+                    .skip(1)
+                    .take(num_hops.saturating_sub(2))
+                    .map(|(id, fee, _, _)| (id, *fee))
+            })
+    }
+
+    /// Aggregates the fees per AS in descending order of revenue. Nodes without an ASN are ignored
+    pub fn fees_per_asn(
+        fees_per_node: &HashMap<ID, usize>,
+        as_ip_map: &AsIpMap,
+    ) -> Vec<AsFeeRevenue> {
+        let mut per_asn: HashMap<u32, AsFeeRevenue> = HashMap::new();
+        for (node, fees) in fees_per_node.iter() {
+            if let Some(asn) = as_ip_map.asn_of(node) {
+                let revenue = per_asn.entry(asn).or_insert(AsFeeRevenue {
+                    asn,
+                    ..Default::default()
+                });
+                revenue.num_nodes += 1;
+                revenue.fees_msat += fees;
+            }
+        }
+        let mut per_asn: Vec<AsFeeRevenue> = per_asn.into_values().collect();
+        per_asn.sort_by(|a, b| b.fees_msat.cmp(&a.fees_msat).then(a.asn.cmp(&b.asn)));
+        per_asn
+    }
+}
+
+/// Writes the revenue to a CSV file, creating the parent directory if needed
+pub fn write_fee_revenue_csv(
+    revenue: &[AsFeeRevenue],
+    output_path: &PathBuf,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = Writer::from_path(output_path)?;
+    writer.serialize(("asn", "num_nodes", "fees_msat"))?;
+    for r in revenue.iter() {
+        writer.serialize((r.asn, r.num_nodes, r.fees_msat))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EconomicImpact;
+    use csv::{Reader, StringRecord};
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, payment::Payment, CandidatePath};
+    use std::{collections::VecDeque, path::Path};
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn fee_revenue() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                &Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let mut successful_payment =
+            Payment::new(0, String::from("dina"), String::from("bob"), 1, None);
+        let mut path = simlib::Path::new(String::from("dina"), String::from("bob"));
+        path.hops = VecDeque::from([
+            ("dina".to_string(), 0, 0, "".to_string()),
+            ("chan".to_string(), 10, 0, "c".to_string()),
+            ("alice".to_string(), 5, 0, "c".to_string()),
+            ("bob".to_string(), 0, 0, "".to_string()),
+        ]);
+        successful_payment.succeeded = true;
+        successful_payment.used_paths = vec![CandidatePath::new_with_path(path)];
+        let sim_result = simlib::SimResult {
+            num_succesful: 2,
+            total_num: 2,
+            successful_payments: vec![successful_payment.clone(), successful_payment],
+            ..Default::default()
+        };
+        let per_node = SimBuilder::fees_per_node(&sim_result);
+        let expected = HashMap::from([("chan".to_owned(), 20), ("alice".to_owned(), 10)]);
+        assert_eq!(per_node, expected);
+        let as_nodes = vec![String::from("alice"), String::from("bob")];
+        assert_eq!(SimBuilder::fees_earned(&sim_result, &as_nodes), 10);
+        let censored = simlib::SimResult {
+            successful_payments: vec![sim_result.successful_payments[0].clone()],
+            ..Default::default()
+        };
+        let impact = EconomicImpact::new(
+            SimBuilder::fees_earned(&sim_result, &as_nodes),
+            SimBuilder::fees_earned(&censored, &as_nodes),
+        );
+        assert_eq!(impact.lost_fees_msat, 5);
+        assert_eq!(impact.lost_fee_share, 0.5);
+        let actual = SimBuilder::fees_per_asn(&per_node, &as_ip_map);
+        let expected = vec![
+            AsFeeRevenue {
+                asn: 797,
+                num_nodes: 1,
+                fees_msat: 20,
+            },
+            AsFeeRevenue {
+                asn: 24940,
+                num_nodes: 1,
+                fees_msat: 10,
+            },
+        ];
+        assert_eq!(actual, expected);
+        let file = NamedTempFile::new().expect("Error opening tempfile");
+        assert!(write_fee_revenue_csv(&actual, &PathBuf::from(file.path())).is_ok());
+        let mut reader = Reader::from_path(file.path()).unwrap();
+        assert_eq!(
+            *reader.headers().unwrap(),
+            StringRecord::from(vec!["asn", "num_nodes", "fees_msat"])
+        );
+        let records: Vec<StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records[0], StringRecord::from(vec!["797", "1", "20"]));
+    }
+}
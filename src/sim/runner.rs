@@ -1,35 +1,145 @@
-use super::{output::*, SimBuilder};
-use crate::{net::Asn, AsIpMap, PacketDropStrategy};
+use super::{
+    output::*, BlacklistCensorship, DecisionRng, Decisions, LocationCensorship, LocationCounts,
+    Observation, PathTable, RateLimiting, SimBuilder,
+};
+use crate::{net::Asn, AsIpMap, AsNodeRatios, PacketDropStrategy, SimulatorError};
 #[cfg(not(test))]
 use log::info;
-use simlib::{PaymentParts, RoutingMetric, Simulation, ID};
+use simlib::{graph::Graph, Simulation, ID};
 #[cfg(test)]
 use std::println as info;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// The baseline after applying a drop strategy
+pub(crate) struct StrategyOutcome {
+    pub(crate) result: simlib::SimResult,
+    pub(crate) per_sim_accuracy: Option<PerSimAccuracy>,
+    pub(crate) split_censorship: Option<SplitCensorship>,
+    pub(crate) delay_impact: Option<DelayImpact>,
+    pub(crate) amount_buckets: Option<Vec<AmountBucket>>,
+    pub(crate) probe_filtering: Option<ProbeFiltering>,
+    pub(crate) blacklist_censorship: Option<BlacklistCensorship>,
+    pub(crate) rate_limiting: Option<RateLimiting>,
+    pub(crate) observation: Observation,
+    /// Number of nodes under attack, usize::MAX if the strategy does not target specific nodes
+    pub(crate) num_nodes: usize,
+}
 
 impl SimBuilder {
-    /// Simulate payments with different ASs attacking up to 5 nodes and return a SimOutput
-    /// aggregating the outcome
-    pub fn simulate(&mut self, pairs: impl Iterator<Item = (ID, ID)> + Clone) -> simlib::SimResult {
+    /// Simulates the pairs without an adversary. The baseline is cached in the builder and shared
+    /// with the caller, so every strategy and AS can be applied to it without copying it first
+    pub fn simulate(
+        &mut self,
+        pairs: impl Iterator<Item = (ID, ID)> + Clone,
+    ) -> Arc<simlib::SimResult> {
+        if let Some(progress) = &self.progress {
+            progress.add_steps(1);
+            progress.set_message("baseline");
+        }
+        let baseline = Arc::new(self.simulate_on_graph(self.graph.clone(), pairs));
+        if let Some(progress) = &self.progress {
+            progress.inc(1);
+        }
+        self.baseline = Some(baseline.clone());
+        baseline
+    }
+
+    /// The baseline of the last call to `simulate`, None before the first one
+    pub fn baseline(&self) -> Option<&simlib::SimResult> {
+        self.baseline.as_deref()
+    }
+
+    /// Applies the strategy of a single AS to the cached baseline, None if `simulate` has not been
+    /// called yet. Random decisions are drawn from the seed of the builder
+    pub fn attack(
+        &self,
+        strategy: &PacketDropStrategy,
+        asn: Asn,
+        nodes: &[ID],
+        as_ip_map: &AsIpMap,
+    ) -> Option<Result<AttackSim, SimulatorError>> {
+        let baseline = self.baseline()?;
+        let ratios = if *strategy == PacketDropStrategy::IntraProbability {
+            as_ip_map.get_intra_as_node_ratios(&self.graph)
+        } else {
+            HashMap::default()
+        };
+        Some(Self::per_asn_simulation(
+            baseline.clone(),
+            asn,
+            nodes,
+            strategy,
+            ratios.get(&asn),
+            as_ip_map,
+            &Decisions::new(self.run),
+            None,
+        ))
+    }
+
+    /// Simulates the pairs without an adversary on a modified graph, e.g. with additional channels
+    pub(crate) fn simulate_on_graph(
+        &self,
+        graph: Graph,
+        pairs: impl Iterator<Item = (ID, ID)> + Clone,
+    ) -> simlib::SimResult {
+        if let Some(model) = &self.amount_model {
+            let pairs: Vec<(ID, ID)> = pairs.collect();
+            if let Some(amounts_sat) = model.draw_sat(pairs.len(), self.run) {
+                return self.simulate_drawn_amounts(graph, pairs, amounts_sat);
+            }
+            return self.simulate_fixed_amount(graph, pairs.into_iter());
+        }
+        self.simulate_fixed_amount(graph, pairs)
+    }
+
+    fn simulate_fixed_amount(
+        &self,
+        graph: Graph,
+        pairs: impl Iterator<Item = (ID, ID)> + Clone,
+    ) -> simlib::SimResult {
         let mut baseline_sim = Simulation::new(
             self.run,
-            self.graph.clone(),
+            graph,
             self.amt_msat,
-            RoutingMetric::MinFee,
-            PaymentParts::Split,
+            self.routing_metric,
+            self.payment_parts,
             Some(vec![0]),
             &[],
         );
-        baseline_sim.run(pairs.clone(), None, false)
+        baseline_sim.run(pairs, None, false)
     }
 
+    /// The pairs of the baseline's payments in the order they were simulated
+    pub(crate) fn baseline_pairs(baseline: &simlib::SimResult) -> Vec<(ID, ID)> {
+        let mut payments: Vec<&simlib::payment::Payment> = baseline
+            .successful_payments
+            .iter()
+            .chain(baseline.failed_payments.iter())
+            .collect();
+        payments.sort_by_key(|p| p.payment_id);
+        payments
+            .iter()
+            .map(|p| (p.source.clone(), p.dest.clone()))
+            .collect()
+    }
+
+    /// Applies the drop strategy of a single AS to the baseline. Random decisions are derived from
+    /// the seed of `decisions` and the ASN so that identical runs yield identical results. The
+    /// payments' paths are interned in `paths` if a table is given
+    #[allow(clippy::too_many_arguments)]
     pub fn per_asn_simulation(
         baseline_result: simlib::SimResult,
         asn: Asn,
         nodes: &[ID],
-        strategy: PacketDropStrategy,
-        ratios: Option<&Vec<f32>>,
+        strategy: &PacketDropStrategy,
+        ratios: Option<&AsNodeRatios>,
         as_ip_map: &AsIpMap,
-    ) -> AttackSim {
+        decisions: &Decisions,
+        paths: Option<&PathTable>,
+    ) -> Result<AttackSim, SimulatorError> {
         let max_nodes_under_attack = nodes.len();
         info!(
             "Simulating {} nodes under attack by AS {}.",
@@ -39,8 +149,87 @@ impl SimBuilder {
             asn: asn.to_string(),
             ..Default::default()
         };
-        let mut sim_results = vec![];
-        let ((updated_results, per_sim_accuracy), num_nodes) = match strategy {
+        let mut rng = decisions.rng_for(asn);
+        let blamed = Self::blamed_asns(&baseline_result, nodes, as_ip_map);
+        let baseline_fees = Self::fees_earned(&baseline_result, nodes);
+        let locations = Self::payment_locations(&baseline_result, as_ip_map);
+        let censorable: HashSet<usize> = baseline_result
+            .successful_payments
+            .iter()
+            .map(|p| p.payment_id)
+            .collect();
+        let drop_reason = strategy.drop_rule().map(|rule| DropReason {
+            strategy: strategy.clone(),
+            asn,
+            rule,
+        });
+        let outcome = Self::apply_drop_strategy(
+            baseline_result,
+            asn,
+            nodes,
+            strategy,
+            ratios,
+            as_ip_map,
+            &mut rng,
+        )?;
+        if !matches!(strategy, PacketDropStrategy::Delay { .. }) {
+            summary.misattribution = Some(Self::misattribution(&blamed, &outcome.result, asn));
+        }
+        summary.economic_impact = Some(EconomicImpact::new(
+            baseline_fees,
+            Self::fees_earned(&outcome.result, nodes),
+        ));
+        let mut location_counts = LocationCounts::default();
+        LocationCensorship::count(&mut location_counts, &locations, &outcome.result);
+        summary.location_censorship = LocationCensorship::top_pairs(location_counts);
+        summary.sim_results = vec![SimResult::with_drop_reasons(
+            outcome.result,
+            outcome.num_nodes,
+            |p| {
+                drop_reason
+                    .clone()
+                    .filter(|_| censorable.contains(&p.payment_id))
+            },
+            paths,
+        )];
+        summary.per_sim_accuracy = outcome.per_sim_accuracy;
+        summary.split_censorship = outcome.split_censorship;
+        summary.delay_impact = outcome.delay_impact;
+        summary.amount_buckets = outcome.amount_buckets;
+        summary.probe_filtering = outcome.probe_filtering;
+        summary.blacklist_censorship = outcome.blacklist_censorship;
+        summary.rate_limiting = outcome.rate_limiting;
+        summary.observation = Some(outcome.observation);
+        info!(
+            "Completed simulation of {:?} attack by AS {}.",
+            strategy, asn
+        );
+        Ok(summary)
+    }
+
+    /// Fails if the strategy looks up an AS that is not in the AS map
+    pub(crate) fn apply_drop_strategy(
+        baseline_result: simlib::SimResult,
+        asn: Asn,
+        nodes: &[ID],
+        strategy: &PacketDropStrategy,
+        ratios: Option<&AsNodeRatios>,
+        as_ip_map: &AsIpMap,
+        rng: &mut DecisionRng,
+    ) -> Result<StrategyOutcome, SimulatorError> {
+        let mut split_censorship = None;
+        let mut delay_impact = None;
+        let mut amount_buckets = None;
+        let mut probe_filtering = None;
+        let mut blacklist_censorship = None;
+        let mut rate_limiting = None;
+        let observed = Self::observe(&baseline_result, nodes);
+        let censorable: HashSet<usize> = baseline_result
+            .successful_payments
+            .iter()
+            .map(|p| p.payment_id)
+            .collect();
+        let ((result, per_sim_accuracy), num_nodes) = match *strategy {
             PacketDropStrategy::IntraProbability => {
                 if let Some(ratios) = ratios {
                     (
@@ -50,6 +239,7 @@ impl SimBuilder {
                             nodes,
                             asn,
                             as_ip_map,
+                            rng,
                         ),
                         usize::MAX,
                     )
@@ -58,7 +248,7 @@ impl SimBuilder {
                 }
             }
             PacketDropStrategy::All => (
-                Self::apply_all_dropped_strategy(baseline_result, nodes),
+                Self::apply_all_dropped_strategy(baseline_result, nodes, asn, as_ip_map),
                 nodes.len(),
             ),
             PacketDropStrategy::IntraAs => (
@@ -66,18 +256,96 @@ impl SimBuilder {
                 usize::MAX,
             ),
             PacketDropStrategy::InterAs => (
-                Self::apply_inter_as_drop_strategy(baseline_result, asn, as_ip_map),
+                Self::apply_inter_as_drop_strategy(baseline_result, asn, as_ip_map)?,
                 usize::MAX,
             ),
+            PacketDropStrategy::SplitAware => {
+                let (result, accuracy, split) =
+                    Self::apply_split_aware_drop_strategy(baseline_result, nodes, asn, as_ip_map);
+                split_censorship = Some(split);
+                ((result, Some(accuracy)), nodes.len())
+            }
+            PacketDropStrategy::IntraAsChannel => (
+                Self::apply_intra_as_channel_strategy(baseline_result, nodes, asn, as_ip_map),
+                nodes.len(),
+            ),
+            PacketDropStrategy::Delay {
+                hold_time_secs,
+                timeout_secs,
+            } => {
+                let (result, delay) = Self::apply_delay_strategy(
+                    baseline_result,
+                    nodes,
+                    hold_time_secs,
+                    timeout_secs,
+                );
+                delay_impact = Some(delay);
+                ((result, None), nodes.len())
+            }
+            PacketDropStrategy::AmountThreshold {
+                threshold_msat,
+                above,
+            } => {
+                let (result, accuracy, buckets) = Self::apply_amount_threshold_strategy(
+                    baseline_result,
+                    nodes,
+                    threshold_msat,
+                    above,
+                );
+                amount_buckets = Some(buckets);
+                ((result, Some(accuracy)), nodes.len())
+            }
+            PacketDropStrategy::ProbeFilter {
+                probe_max_msat,
+                evasion_permille,
+                detection_permille,
+            } => {
+                let (result, filtering) = Self::apply_probe_filter_strategy(
+                    baseline_result,
+                    nodes,
+                    probe_max_msat,
+                    (evasion_permille, detection_permille),
+                    rng,
+                );
+                probe_filtering = Some(filtering);
+                ((result, None), nodes.len())
+            }
+            PacketDropStrategy::DestinationBlacklist { ref blacklist } => {
+                let (result, accuracy, censorship) =
+                    Self::apply_destination_blacklist_strategy(baseline_result, nodes, blacklist);
+                blacklist_censorship = Some(censorship);
+                ((result, Some(accuracy)), nodes.len())
+            }
+            PacketDropStrategy::RateLimit {
+                max_drops,
+                window_secs,
+                interval_secs,
+            } => {
+                let (result, accuracy, limiting) = Self::apply_rate_limit_strategy(
+                    baseline_result,
+                    nodes,
+                    asn,
+                    as_ip_map,
+                    (max_drops, window_secs, interval_secs),
+                    rng,
+                );
+                rate_limiting = Some(limiting);
+                ((result, Some(accuracy)), nodes.len())
+            }
         };
-        sim_results.push(SimResult::from_simlib_results(updated_results, num_nodes));
-        summary.sim_results = sim_results;
-        summary.per_sim_accuracy = per_sim_accuracy;
-        info!(
-            "Completed simulation of {:?} attack by AS {}.",
-            strategy, asn
-        );
-        summary
+        let observation = Observation::new(&observed, &censorable, &result);
+        Ok(StrategyOutcome {
+            result,
+            per_sim_accuracy,
+            split_censorship,
+            delay_impact,
+            amount_buckets,
+            probe_filtering,
+            blacklist_censorship,
+            rate_limiting,
+            observation,
+            num_nodes,
+        })
     }
 }
 
@@ -115,5 +383,48 @@ mod tests {
         assert_eq!(actual.run, run);
         assert_eq!(actual.total_num, num_pairs);
         assert_eq!(actual.num_failed + actual.num_succesful, num_pairs);
+        // the builder keeps the same baseline instead of a copy
+        assert!(std::ptr::eq(builder.baseline().unwrap(), &*actual));
+    }
+
+    #[test]
+    fn attack_cached_baseline() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let nodes = as_ip_map.as_to_nodes()[&797].clone();
+        let mut builder = SimBuilder::new(19, &graph, 1000, 1, AsSelectionStrategy::MaxNodes);
+        assert!(builder
+            .attack(&PacketDropStrategy::All, 797, &nodes, &as_ip_map)
+            .is_none());
+        let pairs = vec![(String::from("alice"), String::from("dina"))];
+        let baseline = builder.simulate(pairs.into_iter());
+        let expected = SimBuilder::per_asn_simulation(
+            (*baseline).clone(),
+            797,
+            &nodes,
+            &PacketDropStrategy::All,
+            None,
+            &as_ip_map,
+            &Decisions::new(19),
+            None,
+        )
+        .unwrap();
+        let actual = builder
+            .attack(&PacketDropStrategy::All, 797, &nodes, &as_ip_map)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&actual).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+        // the cached baseline is not consumed by the attack
+        assert_eq!(builder.baseline().unwrap().total_num, 1);
     }
 }
@@ -0,0 +1,120 @@
+use super::output::*;
+use serde::{Deserialize, Serialize};
+
+/// Rates computed from fewer payments are flagged unless another minimum is given
+pub static DEFAULT_MIN_SAMPLE_SIZE: usize = 30;
+
+/// Number of payments a reported rate is computed from
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SampleSize {
+    /// The rate in the attack's results, e.g. `perSimAccuracy/precision`
+    pub metric: String,
+    pub num_payments: usize,
+    /// Half-width of the 95% confidence interval of a rate of 0.5, the widest interval for the
+    /// number of payments. None if there are no payments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_margin_of_error: Option<f32>,
+    /// The sample is smaller than the minimum, the rate should not be interpreted on its own
+    pub low_sample: bool,
+}
+
+impl SampleSize {
+    pub fn new(metric: impl Into<String>, num_payments: usize, min_sample_size: usize) -> Self {
+        Self {
+            metric: metric.into(),
+            num_payments,
+            max_margin_of_error: (num_payments > 0)
+                .then(|| 1.96 * (0.25 / num_payments as f32).sqrt()),
+            low_sample: num_payments < min_sample_size,
+        }
+    }
+}
+
+impl AttackSim {
+    /// The number of payments each of the attack's rates is computed from, e.g. the payments
+    /// traversing the AS for the share of observed payments that were dropped
+    pub fn effective_sample_sizes(&self, min_sample_size: usize) -> Vec<SampleSize> {
+        let sample = |metric: &str, n: usize| SampleSize::new(metric, n, min_sample_size);
+        let mut sizes = vec![];
+        let num_payments: usize = self
+            .sim_results
+            .iter()
+            .map(|r| r.num_successful + r.num_failed)
+            .sum();
+        sizes.push(sample("successRate", num_payments));
+        if let Some(a) = &self.per_sim_accuracy {
+            sizes.push(sample("perSimAccuracy/precision", a.tpos + a.fpos));
+            sizes.push(sample("perSimAccuracy/recall", a.tpos + a.fneg));
+            sizes.push(sample("perSimAccuracy/f1", a.tpos + a.fpos + a.fneg));
+        }
+        if let Some(d) = &self.delay_impact {
+            sizes.push(sample("delayImpact/timeoutRate", num_payments));
+            sizes.push(sample(
+                "delayImpact/meanAddedLatencySecs",
+                d.num_delayed_payments,
+            ));
+        }
+        for b in self.amount_buckets.iter().flatten() {
+            sizes.push(sample(
+                &format!("amountBuckets/{}/effectiveness", b.lower_sat),
+                b.num_observed,
+            ));
+        }
+        if let Some(p) = &self.probe_filtering {
+            sizes.push(sample("probeFiltering/evasionRate", p.num_observed));
+        }
+        if let Some(r) = &self.rate_limiting {
+            sizes.push(sample("rateLimiting/censorshipRate", r.num_observed));
+        }
+        if let Some(m) = &self.misattribution {
+            sizes.push(sample(
+                "misattribution/misattributionRate",
+                m.num_censored_payments,
+            ));
+        }
+        sizes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_sizes() {
+        let attack_sim = AttackSim {
+            sim_results: vec![SimResult {
+                num_successful: 90,
+                num_failed: 10,
+                ..Default::default()
+            }],
+            per_sim_accuracy: Some(PerSimAccuracy {
+                tpos: 4,
+                fpos: 1,
+                fneg: 40,
+                ..Default::default()
+            }),
+            misattribution: Some(Misattribution::default()),
+            ..Default::default()
+        };
+        let sizes = attack_sim.effective_sample_sizes(DEFAULT_MIN_SAMPLE_SIZE);
+        let size = |metric: &str| sizes.iter().find(|s| s.metric == metric).unwrap();
+        assert_eq!(sizes.len(), 5);
+        assert_eq!(size("successRate").num_payments, 100);
+        assert!(!size("successRate").low_sample);
+        let margin = size("successRate").max_margin_of_error.unwrap();
+        assert!((margin - 0.098).abs() < 1e-6);
+        // precision is computed from the five dropped payments only
+        assert_eq!(size("perSimAccuracy/precision").num_payments, 5);
+        assert!(size("perSimAccuracy/precision").low_sample);
+        assert!(!size("perSimAccuracy/recall").low_sample);
+        let misattribution = size("misattribution/misattributionRate");
+        assert_eq!(misattribution.max_margin_of_error, None);
+        assert!(misattribution.low_sample);
+        assert!(attack_sim
+            .effective_sample_sizes(0)
+            .iter()
+            .all(|s| !s.low_sample));
+    }
+}
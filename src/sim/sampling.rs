@@ -0,0 +1,243 @@
+#[cfg(not(test))]
+use log::warn;
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use simlib::{graph::Graph, ID};
+#[cfg(test)]
+use std::println as warn;
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// How the destinations of the payments are drawn. Senders are always drawn uniformly
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum PairSampling {
+    #[default]
+    Uniform,
+    /// Proportional to the total capacity of the node's channels
+    Capacity,
+    /// Proportional to the weights in a CSV file with the columns `node` and `weight`
+    Merchants(PathBuf),
+}
+
+/// The weight of each destination a sampling mode draws from
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingWeights {
+    pub mode: String,
+    /// Sorted by node so the weights can be compared across runs
+    pub weights: BTreeMap<ID, f64>,
+}
+
+/// Whether every amount simulates the same pairs or draws its own, so that the results of the
+/// amounts are not correlated through the pairs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "camelCase")]
+pub enum PairDraw {
+    #[default]
+    Shared,
+    Independent {
+        seed: u64,
+    },
+}
+
+impl PairDraw {
+    /// Derives the seed of an amount's independent pairs from the run
+    pub fn seed_for(run: u64, amt_sat: usize) -> u64 {
+        run ^ (amt_sat as u64).rotate_left(32)
+    }
+}
+
+#[derive(Deserialize)]
+struct MerchantRecord {
+    node: ID,
+    weight: f64,
+}
+
+impl FromStr for PairSampling {
+    type Err = String;
+
+    /// "uniform", "capacity" or "merchants=<FILE>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some(("merchants", path)) => Ok(Self::Merchants(PathBuf::from(path))),
+            None if s.eq_ignore_ascii_case("uniform") => Ok(Self::Uniform),
+            None if s.eq_ignore_ascii_case("capacity") => Ok(Self::Capacity),
+            _ => Err(format!("Unknown sampling mode {}", s)),
+        }
+    }
+}
+
+impl PairSampling {
+    /// The weights of the destinations in the graph, None if destinations are drawn uniformly
+    pub fn weights(&self, graph: &Graph) -> Result<Option<SamplingWeights>, Box<dyn Error>> {
+        let (mode, weights) = match self {
+            Self::Uniform => return Ok(None),
+            Self::Capacity => (
+                String::from("capacity"),
+                graph
+                    .get_nodes()
+                    .into_iter()
+                    .map(|n| {
+                        let capacity: usize = graph
+                            .get_edges_for_node(&n.id)
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|e| e.capacity)
+                            .sum();
+                        (n.id, capacity as f64)
+                    })
+                    .collect(),
+            ),
+            Self::Merchants(path) => (
+                format!("merchants={}", path.display()),
+                Self::read_merchants(path, graph)?,
+            ),
+        };
+        let weights: BTreeMap<ID, f64> = weights.into_iter().filter(|(_, w)| *w > 0.0).collect();
+        if weights.is_empty() {
+            return Err(format!("No destination has a positive weight for {}", mode).into());
+        }
+        Ok(Some(SamplingWeights { mode, weights }))
+    }
+
+    fn read_merchants(path: &Path, graph: &Graph) -> Result<BTreeMap<ID, f64>, Box<dyn Error>> {
+        let nodes: HashSet<ID> = graph.get_nodes().into_iter().map(|n| n.id).collect();
+        let mut weights = BTreeMap::new();
+        for record in csv::Reader::from_path(path)?.deserialize() {
+            let record: MerchantRecord = record?;
+            if nodes.contains(&record.node) {
+                *weights.entry(record.node).or_default() += record.weight;
+            } else {
+                warn!(
+                    "Skipping merchant {} that is not in the graph.",
+                    record.node
+                );
+            }
+        }
+        Ok(weights)
+    }
+}
+
+impl SamplingWeights {
+    /// Draws `num_pairs` pairs with a uniformly drawn sender and a weighted receiver that differ.
+    /// The same seed yields the same pairs
+    pub fn draw_pairs(&self, graph: &Graph, num_pairs: usize, seed: u64) -> Vec<(ID, ID)> {
+        let mut senders: Vec<ID> = graph.get_nodes().into_iter().map(|n| n.id).collect();
+        senders.sort();
+        let (receivers, weights): (Vec<&ID>, Vec<f64>) = self.weights.iter().unzip();
+        if senders.len() < 2 {
+            return vec![];
+        }
+        let Ok(dist) = WeightedIndex::new(weights) else {
+            return vec![];
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut pairs = Vec::with_capacity(num_pairs);
+        while pairs.len() < num_pairs {
+            let dest = receivers[dist.sample(&mut rng)];
+            let src = &senders[rng.gen_range(0..senders.len())];
+            if src != dest {
+                pairs.push((src.clone(), dest.clone()));
+            }
+        }
+        pairs
+    }
+}
+
+/// Draws `num_pairs` pairs of distinct nodes uniformly. The same seed yields the same pairs
+pub fn draw_uniform_pairs(graph: &Graph, num_pairs: usize, seed: u64) -> Vec<(ID, ID)> {
+    let mut nodes: Vec<ID> = graph.get_nodes().into_iter().map(|n| n.id).collect();
+    nodes.sort();
+    if nodes.len() < 2 {
+        return vec![];
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut pairs = Vec::with_capacity(num_pairs);
+    while pairs.len() < num_pairs {
+        let src = &nodes[rng.gen_range(0..nodes.len())];
+        let dest = &nodes[rng.gen_range(0..nodes.len())];
+        if src != dest {
+            pairs.push((src.clone(), dest.clone()));
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn weighted_pairs() {
+        let mut graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        assert!(PairSampling::Uniform.weights(&graph).unwrap().is_none());
+        for edges in graph.edges.values_mut() {
+            for edge in edges.iter_mut() {
+                edge.capacity = 0;
+            }
+        }
+        assert!(PairSampling::Capacity.weights(&graph).is_err());
+        graph.edges.get_mut("alice").unwrap()[0].capacity = 500;
+        let capacity = PairSampling::Capacity.weights(&graph).unwrap().unwrap();
+        assert_eq!(capacity.mode, "capacity");
+        assert_eq!(
+            capacity.weights,
+            BTreeMap::from([(String::from("alice"), 500.0)])
+        );
+        let mut file = NamedTempFile::new().expect("Error opening tempfile");
+        writeln!(file, "node,weight\nbob,3\nunknown,1\nalice,0").unwrap();
+        let sampling =
+            PairSampling::from_str(&format!("merchants={}", file.path().display())).unwrap();
+        let merchants = sampling.weights(&graph).unwrap().unwrap();
+        assert_eq!(
+            merchants.weights,
+            BTreeMap::from([(String::from("bob"), 3.0)])
+        );
+        let pairs = merchants.draw_pairs(&graph, 10, 19);
+        assert_eq!(pairs.len(), 10);
+        assert!(pairs
+            .iter()
+            .all(|(src, dest)| dest == "bob" && src != "bob"));
+        assert_eq!(pairs, merchants.draw_pairs(&graph, 10, 19));
+        assert!(PairSampling::from_str("popularity").is_err());
+    }
+
+    #[test]
+    fn independent_pairs() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let small = PairDraw::seed_for(19, 1000);
+        let large = PairDraw::seed_for(19, 100_000);
+        assert_ne!(small, large);
+        assert_ne!(small, PairDraw::seed_for(20, 1000));
+        let pairs = draw_uniform_pairs(&graph, 20, small);
+        assert_eq!(pairs.len(), 20);
+        assert!(pairs.iter().all(|(src, dest)| src != dest));
+        assert_eq!(pairs, draw_uniform_pairs(&graph, 20, small));
+        assert_ne!(pairs, draw_uniform_pairs(&graph, 20, large));
+        assert_eq!(
+            serde_json::to_value(PairDraw::Shared).unwrap(),
+            serde_json::json!({"mode": "shared"})
+        );
+    }
+}
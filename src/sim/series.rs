@@ -0,0 +1,223 @@
+use super::SimBuilder;
+use crate::{net::Asn, AsIpMap, PacketDropStrategy};
+#[cfg(not(test))]
+use log::info;
+use serde::Serialize;
+#[cfg(test)]
+use std::println as info;
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs::{self, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+/// Outcome of one strategy of an AS in a single graph snapshot
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotResult {
+    /// File name of the snapshot without the extension, e.g. its date
+    pub snapshot: String,
+    pub amt_sat: usize,
+    pub asn: Asn,
+    pub strategy: String,
+    pub num_nodes: usize,
+    pub num_successful: usize,
+    pub num_failed: usize,
+    /// Share of the payments that still succeed under attack
+    pub success_rate: f32,
+}
+
+/// Returns the JSON files in the directory sorted by name, i.e., by timestamp if the snapshots are
+/// named after their date
+pub fn snapshot_files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+impl SimBuilder {
+    /// Simulates the baseline of the builder's graph and applies every strategy of each adversary.
+    /// The adversaries are selected per snapshot so explicitly selected ASs without nodes in the
+    /// snapshot are skipped
+    pub fn simulate_snapshot(
+        &mut self,
+        snapshot: &str,
+        num_pairs: usize,
+        as_ip_map: &AsIpMap,
+        drop_strategies: &[PacketDropStrategy],
+    ) -> Vec<SnapshotResult> {
+        let adversaries = if let Some(target_asns) = &self.target_asns {
+            target_asns
+                .iter()
+                .filter_map(|asn| {
+                    as_ip_map
+                        .selected_asns(&[*asn], &self.graph)
+                        .map_err(|e| info!("Skipping AS in snapshot {}: {}", snapshot, e))
+                        .ok()
+                })
+                .flatten()
+                .collect()
+        } else {
+            self.get_adverserial_asns(as_ip_map).unwrap_or_default()
+        };
+        let pairs = simlib::Simulation::draw_n_pairs_for_simulation(&self.graph, num_pairs);
+        self.simulate(pairs);
+        let amt_sat = self.amt_msat / 1000;
+        let mut results = vec![];
+        for (asn, nodes) in adversaries.iter() {
+            for strategy in drop_strategies.iter() {
+                let Some(attack) = self.attack(strategy, *asn, nodes, as_ip_map) else {
+                    continue;
+                };
+                let Some(result) = attack.sim_results.first() else {
+                    continue;
+                };
+                let total = result.num_successful + result.num_failed;
+                results.push(SnapshotResult {
+                    snapshot: snapshot.to_owned(),
+                    amt_sat,
+                    asn: *asn,
+                    strategy: format!("{:?}", strategy),
+                    num_nodes: nodes.len(),
+                    num_successful: result.num_successful,
+                    num_failed: result.num_failed,
+                    success_rate: if total > 0 {
+                        result.num_successful as f32 / total as f32
+                    } else {
+                        0.0
+                    },
+                });
+            }
+        }
+        results
+    }
+}
+
+static SERIES_HEADER: [&str; 8] = [
+    "snapshot",
+    "amt_sat",
+    "asn",
+    "strategy",
+    "num_nodes",
+    "num_successful",
+    "num_failed",
+    "success_rate",
+];
+
+pub fn write_series_csv(results: &[SnapshotResult], path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(SERIES_HEADER)?;
+    write_series_records(&mut writer, results)
+}
+
+/// Appends the results to the CSV file, which is created with a header if it does not exist yet
+pub fn append_series_csv(results: &[SnapshotResult], path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let is_new = fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true);
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = csv::Writer::from_writer(file);
+    if is_new {
+        writer.write_record(SERIES_HEADER)?;
+    }
+    write_series_records(&mut writer, results)
+}
+
+/// The snapshots that already have results in the CSV file, empty if the file does not exist
+pub fn processed_snapshots(path: &PathBuf) -> Result<HashSet<String>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(HashSet::new());
+    }
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut snapshots = HashSet::new();
+    for record in reader.records() {
+        if let Some(snapshot) = record?.get(0) {
+            snapshots.insert(snapshot.to_owned());
+        }
+    }
+    Ok(snapshots)
+}
+
+fn write_series_records<W: std::io::Write>(
+    writer: &mut csv::Writer<W>,
+    results: &[SnapshotResult],
+) -> Result<(), Box<dyn Error>> {
+    for r in results.iter() {
+        writer.write_record(&[
+            r.snapshot.clone(),
+            r.amt_sat.to_string(),
+            r.asn.to_string(),
+            r.strategy.clone(),
+            r.num_nodes.to_string(),
+            r.num_successful.to_string(),
+            r.num_failed.to_string(),
+            r.success_rate.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsSelectionStrategy;
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use tempfile::TempDir;
+
+    #[test]
+    fn snapshot_series() {
+        let dir = TempDir::new().expect("Error opening tempdir");
+        for name in ["2024-02.json", "2024-01.json", "notes.txt"] {
+            fs::copy("test_data/lnbook_example_lnr.json", dir.path().join(name)).unwrap();
+        }
+        let files = snapshot_files(dir.path()).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with("2024-01.json"));
+        let mut results = vec![];
+        for file in files.iter() {
+            let graph = Graph::to_sim_graph(
+                &network_parser::Graph::from_json_file(file, Lnresearch).unwrap(),
+                Lnresearch,
+            );
+            let as_ip_map = AsIpMap::new(&graph, false);
+            let mut builder = SimBuilder::new(0, &graph, 1000, 1, AsSelectionStrategy::MaxNodes)
+                .with_target_asns(vec![797, 16509]);
+            let snapshot = file.file_stem().unwrap().to_string_lossy();
+            results.extend(builder.simulate_snapshot(
+                &snapshot,
+                2,
+                &as_ip_map,
+                &[PacketDropStrategy::All],
+            ));
+        }
+        // AS 16509 has no nodes and is skipped
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].snapshot, "2024-01");
+        assert_eq!(results[1].asn, 797);
+        assert_eq!(results[0].num_successful + results[0].num_failed, 2);
+        let path = dir.path().join("series.csv");
+        assert!(write_series_csv(&results, &path).is_ok());
+        let store = dir.path().join("store.csv");
+        assert!(processed_snapshots(&store).unwrap().is_empty());
+        assert!(append_series_csv(&results[..1], &store).is_ok());
+        assert!(append_series_csv(&results[1..], &store).is_ok());
+        assert_eq!(
+            processed_snapshots(&store).unwrap(),
+            HashSet::from([String::from("2024-01"), String::from("2024-02")])
+        );
+        assert_eq!(
+            fs::read_to_string(&store).unwrap(),
+            fs::read_to_string(&path).unwrap()
+        );
+    }
+}
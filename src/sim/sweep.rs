@@ -0,0 +1,181 @@
+use super::SimBuilder;
+use crate::net::Asn;
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+#[cfg(test)]
+use std::println as info;
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// The sizes of the coalitions of the top-ranked ASs that are simulated, both bounds included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumAsSweep {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl FromStr for NumAsSweep {
+    type Err = String;
+
+    /// "<MIN>..<MAX>" or "<MIN>..=<MAX>", both include the maximum
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) = s
+            .split_once("..")
+            .ok_or(format!("Expected <MIN>..<MAX>, got {}", s))?;
+        let max = max.strip_prefix('=').unwrap_or(max);
+        let parse = |n: &str| {
+            n.parse::<usize>()
+                .map_err(|e| format!("Invalid number of ASs {}: {}", n, e))
+        };
+        let (min, max) = (parse(min)?, parse(max)?);
+        if min == 0 || min > max {
+            return Err(format!("Invalid range of ASs {}", s));
+        }
+        Ok(Self { min, max })
+    }
+}
+
+impl fmt::Display for NumAsSweep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.min, self.max)
+    }
+}
+
+/// Censorship rates of the cumulative coalitions of the top-ranked ASs, each dropping every payment
+/// that traverses one of its nodes
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CoalitionSweep {
+    pub num_baseline_successful: usize,
+    pub points: Vec<SweepPoint>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepPoint {
+    /// The coalition is formed by the top `numAsns` ASs
+    pub num_asns: usize,
+    /// The lowest-ranked AS of the coalition
+    pub asn: String,
+    pub num_nodes: usize,
+    pub num_censored: usize,
+    pub censorship_rate: f32,
+}
+
+impl SimBuilder {
+    /// Censors the baseline with every coalition of the first `n` ASs of the ranking, `n` in the
+    /// sweep. A payment is censored by every coalition that includes the highest-ranked AS it
+    /// traverses, so the baseline is scanned once
+    pub fn coalition_sweep(
+        baseline: &simlib::SimResult,
+        ranking: &[(Asn, Vec<ID>)],
+        sweep: NumAsSweep,
+    ) -> CoalitionSweep {
+        let mut node_ranks: HashMap<&ID, usize> = HashMap::new();
+        for (rank, (_, nodes)) in ranking.iter().enumerate() {
+            for node in nodes {
+                node_ranks.entry(node).or_insert(rank);
+            }
+        }
+        let mut first_censored_by = vec![0; ranking.len()];
+        for p in baseline.successful_payments.iter() {
+            let rank = p
+                .used_paths
+                .iter()
+                .flat_map(|path| path.path.get_involved_nodes())
+                .filter_map(|n| node_ranks.get(&n).copied())
+                .min();
+            if let Some(rank) = rank {
+                first_censored_by[rank] += 1;
+            }
+        }
+        let mut points = vec![];
+        let (mut num_censored, mut num_nodes) = (0, 0);
+        for (rank, (asn, nodes)) in ranking.iter().enumerate().take(sweep.max) {
+            num_censored += first_censored_by[rank];
+            num_nodes += nodes.len();
+            if rank + 1 >= sweep.min {
+                points.push(SweepPoint {
+                    num_asns: rank + 1,
+                    asn: asn.to_string(),
+                    num_nodes,
+                    num_censored,
+                    censorship_rate: if baseline.num_succesful > 0 {
+                        num_censored as f32 / baseline.num_succesful as f32
+                    } else {
+                        0.0
+                    },
+                });
+            }
+        }
+        if let Some(last) = points.last() {
+            info!(
+                "The top {} ASs censor {:.1}% of the payments.",
+                last.num_asns,
+                last.censorship_rate * 100.0
+            );
+        }
+        CoalitionSweep {
+            num_baseline_successful: baseline.num_succesful,
+            points,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simlib::{payment::Payment, CandidatePath};
+    use std::collections::VecDeque;
+
+    #[test]
+    fn sweep_coalitions() {
+        assert_eq!(
+            NumAsSweep::from_str("1..50"),
+            Ok(NumAsSweep { min: 1, max: 50 })
+        );
+        assert_eq!(NumAsSweep::from_str("2..=3").unwrap().to_string(), "2..3");
+        assert!(NumAsSweep::from_str("0..5").is_err());
+        assert!(NumAsSweep::from_str("5").is_err());
+
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let baseline = simlib::SimResult {
+            num_succesful: 4,
+            total_num: 4,
+            successful_payments: vec![
+                payment(0, vec!["alice", "bob"]),
+                payment(1, vec!["chan", "dina"]),
+                payment(2, vec!["alice", "bob", "chan"]),
+                payment(3, vec!["eve", "fred"]),
+            ],
+            ..Default::default()
+        };
+        let ranking = vec![
+            (797, vec![String::from("chan"), String::from("dina")]),
+            (24940, vec![String::from("alice"), String::from("bob")]),
+            (13335, vec![String::from("grace")]),
+        ];
+        let sweep =
+            SimBuilder::coalition_sweep(&baseline, &ranking, NumAsSweep { min: 1, max: 10 });
+        let censored: Vec<usize> = sweep.points.iter().map(|p| p.num_censored).collect();
+        assert_eq!(censored, vec![2, 3, 3]);
+        assert_eq!(sweep.points[1].censorship_rate, 0.75);
+        assert_eq!(sweep.points[2].num_nodes, 5);
+        let sweep = SimBuilder::coalition_sweep(&baseline, &ranking, NumAsSweep { min: 2, max: 2 });
+        assert_eq!(sweep.points.len(), 1);
+        assert_eq!(sweep.points[0].asn, "24940");
+    }
+}
@@ -0,0 +1,96 @@
+use crate::PacketDropStrategy;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Time and memory the stages of the simulation of an amount took, if requested
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Telemetry {
+    /// The baseline followed by each strategy per adversary in the order they were simulated
+    pub stages: Vec<StageTelemetry>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StageTelemetry {
+    /// None for the baseline
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<PacketDropStrategy>,
+    /// None for the baseline and strategies simulated in chunks, which cover all adversaries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asn: Option<String>,
+    pub wall_clock_ms: f64,
+    /// Peak resident set size of the process at the end of the stage. Adversaries are simulated in
+    /// parallel, so this is an upper bound of the stage's own memory. None if unavailable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_rss_kib: Option<u64>,
+    pub num_payments: usize,
+}
+
+impl StageTelemetry {
+    /// Runs the stage and measures it. `num_payments` counts the payments of its outcome
+    pub fn measure<T>(
+        strategy: Option<PacketDropStrategy>,
+        asn: Option<String>,
+        stage: impl FnOnce() -> T,
+        num_payments: impl FnOnce(&T) -> usize,
+    ) -> (T, Self) {
+        let start = Instant::now();
+        let outcome = stage();
+        let num_payments = num_payments(&outcome);
+        (outcome, Self::since(start, strategy, asn, num_payments))
+    }
+
+    pub(crate) fn since(
+        start: Instant,
+        strategy: Option<PacketDropStrategy>,
+        asn: Option<String>,
+        num_payments: usize,
+    ) -> Self {
+        Self {
+            strategy,
+            asn,
+            wall_clock_ms: start.elapsed().as_secs_f64() * 1000.0,
+            peak_rss_kib: peak_rss_kib(),
+            num_payments,
+        }
+    }
+}
+
+/// The high-water mark of the process's resident set size, read from `/proc/self/status`
+#[cfg(target_os = "linux")]
+pub fn peak_rss_kib() -> Option<u64> {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|l| l.strip_prefix("VmHWM:"))
+        .and_then(|v| v.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn peak_rss_kib() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measure_stage() {
+        let (outcome, stage) = StageTelemetry::measure(
+            Some(PacketDropStrategy::All),
+            Some(String::from("797")),
+            || vec![0; 1000],
+            |v| v.len(),
+        );
+        assert_eq!(outcome.len(), 1000);
+        assert_eq!(stage.num_payments, 1000);
+        assert!(stage.wall_clock_ms >= 0.0);
+        #[cfg(target_os = "linux")]
+        assert!(stage.peak_rss_kib.is_some_and(|kib| kib > 0));
+        let json = serde_json::to_value(&stage).unwrap();
+        assert_eq!(json["strategy"], "All");
+        assert_eq!(json["asn"], "797");
+    }
+}
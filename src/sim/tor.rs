@@ -0,0 +1,314 @@
+use super::{output::*, Decisions, SimBuilder};
+use crate::{net::Asn, AsIpMap, AsNodeRatios, PacketDropStrategy, SimulatorError, TorModel};
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+use std::collections::{BTreeMap, HashSet};
+
+/// Number of ASs and of the nodes mapped to them
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MappingSummary {
+    pub num_asns: usize,
+    pub num_mapped_nodes: usize,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdversaryOutcome {
+    pub num_nodes_under_attack: usize,
+    pub num_successful: usize,
+}
+
+/// The outcome of each strategy and adversary with onion-only nodes excluded from the AS map and
+/// mapped to the Tor ASN, side by side
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TorComparison {
+    pub without_tor: MappingSummary,
+    pub with_tor: MappingSummary,
+    pub rows: Vec<TorComparisonRow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TorComparisonRow {
+    pub strategy: PacketDropStrategy,
+    pub asn: String,
+    /// None if the AS was not an adversary without the Tor nodes
+    pub without_tor: Option<AdversaryOutcome>,
+    /// None if the AS was not an adversary with the Tor nodes
+    pub with_tor: Option<AdversaryOutcome>,
+}
+
+/// The outcome of each strategy and adversary in the order they were simulated
+pub(crate) type Outcomes = Vec<(PacketDropStrategy, String, AdversaryOutcome)>;
+
+impl MappingSummary {
+    pub fn new(as_ip_map: &AsIpMap) -> Self {
+        Self {
+            num_asns: as_ip_map.as_to_nodes().len(),
+            num_mapped_nodes: as_ip_map
+                .as_to_nodes()
+                .values()
+                .flatten()
+                .collect::<HashSet<_>>()
+                .len(),
+        }
+    }
+}
+
+impl AdversaryOutcome {
+    pub fn new(attack_sim: &AttackSim) -> Self {
+        Self {
+            num_nodes_under_attack: attack_sim
+                .sim_results
+                .iter()
+                .map(|r| r.num_nodes_under_attack)
+                .max()
+                .unwrap_or_default(),
+            num_successful: attack_sim
+                .sim_results
+                .iter()
+                .map(|r| r.num_successful)
+                .sum(),
+        }
+    }
+}
+
+impl TorComparison {
+    /// Pairs the outcomes of the same strategy and AS
+    pub(crate) fn new(
+        (without_map, without): (&AsIpMap, Outcomes),
+        (with_map, with): (&AsIpMap, Outcomes),
+    ) -> Self {
+        let mut rows: Vec<TorComparisonRow> = without
+            .into_iter()
+            .map(|(strategy, asn, outcome)| TorComparisonRow {
+                strategy,
+                asn,
+                without_tor: Some(outcome),
+                with_tor: None,
+            })
+            .collect();
+        for (strategy, asn, outcome) in with {
+            match rows
+                .iter_mut()
+                .find(|r| r.strategy == strategy && r.asn == asn)
+            {
+                Some(row) => row.with_tor = Some(outcome),
+                None => rows.push(TorComparisonRow {
+                    strategy,
+                    asn,
+                    without_tor: None,
+                    with_tor: Some(outcome),
+                }),
+            }
+        }
+        Self {
+            without_tor: MappingSummary::new(without_map),
+            with_tor: MappingSummary::new(with_map),
+            rows,
+        }
+    }
+}
+
+impl SimBuilder {
+    /// Adds the onion-only nodes whose guard relay is in an adversarial AS to the AS's nodes
+    pub(crate) fn add_guarded_nodes(
+        adversaries: Vec<(Asn, Vec<ID>)>,
+        guard_asns: &BTreeMap<ID, Asn>,
+    ) -> Vec<(Asn, Vec<ID>)> {
+        adversaries
+            .into_iter()
+            .map(|(asn, mut nodes)| {
+                nodes.extend(
+                    guard_asns
+                        .iter()
+                        .filter(|(node, guard_asn)| **guard_asn == asn && !nodes.contains(node))
+                        .map(|(node, _)| node.clone())
+                        .collect::<Vec<_>>(),
+                );
+                (asn, nodes)
+            })
+            .collect()
+    }
+
+    /// Compares the payments that succeeded despite the attack to the payments that would succeed
+    /// if the AS could censor every onion-only node, i.e., if the nodes were not hidden by Tor. The
+    /// counterfactual decisions are drawn from the seed and not recorded
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn tor_exposure(
+        baseline_result: &simlib::SimResult,
+        asn: Asn,
+        nodes: &[ID],
+        strategy: &PacketDropStrategy,
+        ratios: Option<&AsNodeRatios>,
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+        tor: &TorModel,
+        num_successful: usize,
+    ) -> Result<TorExposure, SimulatorError> {
+        let onion_only: Vec<ID> = tor.onion_only.iter().cloned().collect();
+        let num_censorable_nodes = onion_only.iter().filter(|n| nodes.contains(n)).count();
+        let num_tor_payments = baseline_result
+            .successful_payments
+            .iter()
+            .filter(|p| Self::payment_involves_asn(p, &onion_only))
+            .count();
+        let mut visible_nodes = nodes.to_vec();
+        visible_nodes.extend(onion_only.iter().filter(|n| !nodes.contains(n)).cloned());
+        let counterfactual = Self::apply_drop_strategy(
+            baseline_result.clone(),
+            asn,
+            &visible_nodes,
+            strategy,
+            ratios,
+            as_ip_map,
+            &mut Decisions::new(decisions.seed()).rng_for(asn),
+        )?;
+        Ok(TorExposure {
+            num_onion_only_nodes: onion_only.len(),
+            num_censorable_nodes,
+            num_tor_payments,
+            num_escaped: num_successful.saturating_sub(counterfactual.result.num_succesful),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, payment::Payment, CandidatePath};
+    use std::{
+        collections::{HashMap, VecDeque},
+        path::Path,
+    };
+
+    #[test]
+    fn tor_escapes() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let baseline_result = simlib::SimResult {
+            num_succesful: 3,
+            total_num: 3,
+            successful_payments: vec![
+                payment(0, vec!["onion1", "alice", "bob"]),
+                payment(1, vec!["onion1", "onion2"]),
+                payment(2, vec!["chan", "dina"]),
+            ],
+            ..Default::default()
+        };
+        let mut tor = TorModel::default();
+        tor.onion_only.insert(String::from("onion1"));
+        tor.onion_only.insert(String::from("onion2"));
+        let guard_asns = BTreeMap::from([
+            (String::from("onion1"), 797),
+            (String::from("onion2"), 24940),
+        ]);
+        let adversaries = SimBuilder::add_guarded_nodes(
+            vec![(797, vec![String::from("chan"), String::from("dina")])],
+            &guard_asns,
+        );
+        assert_eq!(adversaries[0].1.len(), 3);
+        // AS 24940 does not host the guard of onion1, so only the payment between the onion-only
+        // nodes escapes
+        let exposure = SimBuilder::tor_exposure(
+            &baseline_result,
+            24940,
+            &[String::from("alice"), String::from("bob")],
+            &PacketDropStrategy::All,
+            None,
+            &as_ip_map,
+            &Decisions::new(19),
+            &tor,
+            2,
+        )
+        .unwrap();
+        assert_eq!(
+            exposure,
+            TorExposure {
+                num_onion_only_nodes: 2,
+                num_censorable_nodes: 0,
+                num_tor_payments: 2,
+                num_escaped: 1,
+            }
+        );
+        // no onion-only node is hidden from AS 797
+        let exposure = SimBuilder::tor_exposure(
+            &baseline_result,
+            797,
+            &adversaries[0].1,
+            &PacketDropStrategy::All,
+            None,
+            &as_ip_map,
+            &Decisions::new(19),
+            &tor,
+            0,
+        )
+        .unwrap();
+        assert_eq!(exposure.num_censorable_nodes, 1);
+        assert_eq!(exposure.num_escaped, 0);
+    }
+
+    #[test]
+    fn compare_tor_treatment() {
+        let outcome = |num_successful| AdversaryOutcome {
+            num_nodes_under_attack: 2,
+            num_successful,
+        };
+        let without_map = AsIpMap::from(HashMap::from([(
+            797,
+            vec![String::from("chan"), String::from("dina")],
+        )]));
+        let mut as_to_nodes = without_map.as_to_nodes().clone();
+        as_to_nodes.insert(0, vec![String::from("onion1"), String::from("onion2")]);
+        let with_map = AsIpMap::from(as_to_nodes);
+        let comparison = TorComparison::new(
+            (
+                &without_map,
+                vec![(PacketDropStrategy::All, String::from("797"), outcome(3))],
+            ),
+            (
+                &with_map,
+                vec![
+                    (PacketDropStrategy::All, String::from("0"), outcome(4)),
+                    (PacketDropStrategy::All, String::from("797"), outcome(2)),
+                ],
+            ),
+        );
+        assert_eq!(comparison.without_tor.num_mapped_nodes, 2);
+        assert_eq!(
+            comparison.with_tor,
+            MappingSummary {
+                num_asns: 2,
+                num_mapped_nodes: 4,
+            }
+        );
+        assert_eq!(comparison.rows.len(), 2);
+        assert_eq!(comparison.rows[0].without_tor, Some(outcome(3)));
+        assert_eq!(comparison.rows[0].with_tor, Some(outcome(2)));
+        assert_eq!(comparison.rows[1].asn, "0");
+        assert_eq!(comparison.rows[1].without_tor, None);
+    }
+}
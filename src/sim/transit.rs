@@ -0,0 +1,138 @@
+use super::SimBuilder;
+use crate::{net::Asn, AsIpMap, TransitMap};
+#[cfg(not(test))]
+use log::info;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use std::println as info;
+
+/// The payments a transit AS could censor by dropping the IP traffic of the channels it carries,
+/// in addition to those it censors by hosting nodes
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitCensorship {
+    pub asn: String,
+    /// Channels whose IP traffic crosses the AS although it hosts neither peer
+    pub num_transit_channels: usize,
+    /// Nodes the AS hosts
+    pub num_nodes: usize,
+    pub num_baseline_successful: usize,
+    /// Payments that survive the AS dropping the traffic of its nodes and transit channels
+    pub num_successful: usize,
+    /// Payments that avoid the AS's nodes and are only censored because of its transit position
+    pub num_censored_by_transit: usize,
+}
+
+impl SimBuilder {
+    /// Fails every payment with a part that traverses one of the AS's nodes or a channel whose IP
+    /// traffic the AS carries. The AS cannot tell payments from other traffic
+    pub fn transit_censorship(
+        baseline: &simlib::SimResult,
+        asn: Asn,
+        as_ip_map: &AsIpMap,
+        transit: &TransitMap,
+    ) -> TransitCensorship {
+        let nodes = as_ip_map
+            .as_to_nodes()
+            .get(&asn)
+            .cloned()
+            .unwrap_or_default();
+        let num_transit_channels = transit
+            .channels
+            .values()
+            .filter(|asns| asns.contains(&asn))
+            .count();
+        let (mut num_successful, mut num_censored_by_transit) = (0, 0);
+        for p in baseline.successful_payments.iter() {
+            if Self::payment_involves_asn(p, &nodes) {
+                continue;
+            }
+            let crosses_as = p.used_paths.iter().any(|path| {
+                path.path
+                    .get_involved_nodes()
+                    .windows(2)
+                    .any(|hops| transit.transit_asns(&hops[0], &hops[1]).contains(&asn))
+            });
+            if crosses_as {
+                num_censored_by_transit += 1;
+            } else {
+                num_successful += 1;
+            }
+        }
+        info!(
+            "AS {} carries {} channels and censors {} payments as transit.",
+            asn, num_transit_channels, num_censored_by_transit
+        );
+        TransitCensorship {
+            asn: asn.to_string(),
+            num_transit_channels,
+            num_nodes: nodes.len(),
+            num_baseline_successful: baseline.num_succesful,
+            num_successful,
+            num_censored_by_transit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsRelationships;
+    use network_parser::GraphSource::*;
+    use simlib::{graph::Graph, payment::Payment, CandidatePath};
+    use std::{collections::VecDeque, path::Path};
+
+    #[test]
+    fn transit_censors_channels() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let mut relationships = AsRelationships::default();
+        relationships.add_customer(3356, 24940);
+        relationships.add_customer(3356, 797);
+        let transit = TransitMap::new(&graph, &as_ip_map, &relationships);
+        let payment = |id, hops: Vec<&str>| {
+            let src = hops.first().unwrap().to_string();
+            let dest = hops.last().unwrap().to_string();
+            let mut p = Payment::new(id, src.clone(), dest.clone(), 1, None);
+            let mut path = simlib::Path::new(src, dest);
+            path.hops = VecDeque::from_iter(
+                hops.into_iter()
+                    .map(|h| (h.to_string(), 0, 0, "".to_string())),
+            );
+            p.succeeded = true;
+            p.used_paths = vec![CandidatePath::new_with_path(path)];
+            p
+        };
+        let baseline = simlib::SimResult {
+            num_succesful: 3,
+            total_num: 3,
+            successful_payments: vec![
+                payment(0, vec!["alice", "bob", "chan", "dina"]),
+                payment(1, vec!["alice", "bob"]),
+                payment(2, vec!["chan", "dina"]),
+            ],
+            ..Default::default()
+        };
+        let actual = SimBuilder::transit_censorship(&baseline, 3356, &as_ip_map, &transit);
+        let expected = TransitCensorship {
+            asn: String::from("3356"),
+            num_transit_channels: 1,
+            num_nodes: 0,
+            num_baseline_successful: 3,
+            num_successful: 2,
+            num_censored_by_transit: 1,
+        };
+        assert_eq!(actual, expected);
+        // the payments traverse the AS's nodes, which it censors anyway
+        let actual = SimBuilder::transit_censorship(&baseline, 797, &as_ip_map, &transit);
+        assert_eq!(actual.num_successful, 1);
+        assert_eq!(actual.num_censored_by_transit, 0);
+    }
+}
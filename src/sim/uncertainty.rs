@@ -0,0 +1,116 @@
+use super::{Decisions, SimBuilder};
+use crate::{
+    net::{AsIpMap, AsNodeRatios, Asn, AsnCandidates},
+    PacketDropStrategy, SimulatorError,
+};
+use serde::{Deserialize, Serialize};
+use simlib::ID;
+
+/// The successful payments if the adversary controlled only its unambiguously attributed nodes
+/// (best case) or every node with an address in its AS (worst case)
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributionBounds {
+    /// Nodes of the adversary whose addresses also map to other ASs
+    pub num_ambiguous_nodes: usize,
+    /// Nodes attributed to other ASs that have an address in the adversary's
+    pub num_candidate_nodes: usize,
+    /// Sum of the adversary's weights over its and its candidate nodes
+    pub expected_num_nodes: f32,
+    pub best_case_successful: usize,
+    pub worst_case_successful: usize,
+}
+
+impl SimBuilder {
+    /// Applies the strategy with the best- and worst-case nodes of the adversary. Strategies that
+    /// select the payments by the AS map rather than the nodes yield equal bounds. The decisions
+    /// are drawn from the seed and not recorded
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn attribution_bounds(
+        baseline_result: &simlib::SimResult,
+        asn: Asn,
+        nodes: &[ID],
+        strategy: &PacketDropStrategy,
+        ratios: Option<&AsNodeRatios>,
+        as_ip_map: &AsIpMap,
+        decisions: &Decisions,
+        candidates: &AsnCandidates,
+    ) -> Result<AttributionBounds, SimulatorError> {
+        let best_case = candidates.best_case_nodes(nodes);
+        let worst_case = candidates.worst_case_nodes(asn, nodes);
+        let num_successful = |nodes: &[ID]| {
+            Self::apply_drop_strategy(
+                baseline_result.clone(),
+                asn,
+                nodes,
+                strategy,
+                ratios,
+                as_ip_map,
+                &mut Decisions::new(decisions.seed()).rng_for(asn),
+            )
+            .map(|outcome| outcome.result.num_succesful)
+        };
+        Ok(AttributionBounds {
+            num_ambiguous_nodes: nodes.len() - best_case.len(),
+            num_candidate_nodes: worst_case.len() - nodes.len(),
+            expected_num_nodes: worst_case.iter().map(|n| candidates.weight(n, asn)).sum(),
+            best_case_successful: num_successful(&best_case)?,
+            worst_case_successful: num_successful(&worst_case)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AsSelectionStrategy;
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use std::{collections::HashMap, path::Path};
+
+    #[test]
+    fn attribution_bounds() {
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let mut builder = SimBuilder::new(19, &graph, 1000, 2, AsSelectionStrategy::MaxNodes);
+        let baseline = builder.simulate(
+            vec![
+                (String::from("alice"), String::from("bob")),
+                (String::from("chan"), String::from("dina")),
+                (String::from("alice"), String::from("dina")),
+            ]
+            .into_iter(),
+        );
+        // bob also has an address in AS 797 and dina one in AS 24940
+        let candidates = AsnCandidates {
+            candidates: HashMap::from([
+                (String::from("bob"), vec![(797, 0.5), (24940, 0.5)]),
+                (String::from("dina"), vec![(797, 0.5), (24940, 0.5)]),
+            ]),
+        };
+        let nodes = as_ip_map.as_to_nodes()[&24940].clone();
+        let bounds = SimBuilder::attribution_bounds(
+            &baseline,
+            24940,
+            &nodes,
+            &PacketDropStrategy::All,
+            None,
+            &as_ip_map,
+            &Decisions::new(19),
+            &candidates,
+        )
+        .unwrap();
+        assert_eq!(bounds.num_ambiguous_nodes, 1);
+        assert_eq!(bounds.num_candidate_nodes, 1);
+        assert_eq!(bounds.expected_num_nodes, 2.0);
+        assert!(bounds.best_case_successful >= bounds.worst_case_successful);
+        assert_eq!(bounds.worst_case_successful, 0);
+    }
+}
@@ -0,0 +1,112 @@
+use super::output::*;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// What each outcome of a targeted drop is worth to the adversary, e.g. in sat or reputation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CostModel {
+    /// Gained per censored payment the strategy targets
+    pub benefit_per_tpos: f32,
+    /// Lost per dropped payment the strategy does not target, e.g. the risk of being noticed
+    pub cost_per_fpos: f32,
+}
+
+impl FromStr for CostModel {
+    type Err = String;
+
+    /// "<BENEFIT>:<COST>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (benefit, cost) = s
+            .split_once(':')
+            .ok_or(format!("Expected <BENEFIT>:<COST>, got {}", s))?;
+        let parse = |v: &str| {
+            v.parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .ok_or(format!("Invalid benefit or cost {}", v))
+        };
+        Ok(Self {
+            benefit_per_tpos: parse(benefit)?,
+            cost_per_fpos: parse(cost)?,
+        })
+    }
+}
+
+impl fmt::Display for CostModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.benefit_per_tpos, self.cost_per_fpos)
+    }
+}
+
+/// The adversary's net utility of its strategy under the cost model
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AdversaryUtility {
+    pub benefit: f32,
+    pub cost: f32,
+    pub net_utility: f32,
+    /// Net utility per dropped payment, 0 if nothing was dropped
+    pub utility_per_drop: f32,
+    /// The precision above which dropping pays off, independent of the AS
+    pub break_even_precision: f32,
+}
+
+impl AttackSim {
+    /// Weighs the correctly against the falsely dropped payments, None without accuracy
+    pub fn utility(&self, model: &CostModel) -> Option<AdversaryUtility> {
+        let a = self.per_sim_accuracy.as_ref()?;
+        let benefit = a.tpos as f32 * model.benefit_per_tpos;
+        let cost = a.fpos as f32 * model.cost_per_fpos;
+        let num_dropped = a.tpos + a.fpos;
+        let weights = model.benefit_per_tpos + model.cost_per_fpos;
+        Some(AdversaryUtility {
+            benefit,
+            cost,
+            net_utility: benefit - cost,
+            utility_per_drop: if num_dropped > 0 {
+                (benefit - cost) / num_dropped as f32
+            } else {
+                0.0
+            },
+            break_even_precision: if weights > 0.0 {
+                model.cost_per_fpos / weights
+            } else {
+                0.0
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn net_utility() {
+        let model = CostModel::from_str("1:4").unwrap();
+        assert_eq!(model.to_string(), "1:4");
+        assert!(CostModel::from_str("1").is_err());
+        assert!(CostModel::from_str("1:-2").is_err());
+
+        let attack_sim = AttackSim {
+            per_sim_accuracy: Some(
+                PerSimAccuracy {
+                    tpos: 30,
+                    fpos: 5,
+                    fneg: 10,
+                    tneg: 55,
+                    ..Default::default()
+                }
+                .finalise(),
+            ),
+            ..Default::default()
+        };
+        let utility = attack_sim.utility(&model).unwrap();
+        assert_eq!(utility.net_utility, 10.0);
+        assert_eq!(utility.utility_per_drop, 10.0 / 35.0);
+        assert_eq!(utility.break_even_precision, 0.8);
+        // the precision of 30/35 beats the break-even precision
+        assert!(attack_sim.per_sim_accuracy.unwrap().precision > utility.break_even_precision);
+        assert_eq!(AttackSim::default().utility(&model), None);
+    }
+}
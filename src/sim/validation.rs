@@ -0,0 +1,188 @@
+use super::SimBuilder;
+use crate::{
+    net::{AsIpMap, Asn},
+    AsSelectionStrategy, PacketDropStrategy,
+};
+use std::fmt;
+
+/// A combination of parameters the simulation cannot run with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The AS selection strategy is not 0, 1 or 2
+    InvalidAsSelection(usize),
+    NoPayments,
+    NoAdversaries,
+    /// More top-n adversaries than ASs in the graph
+    TooManyAdversaries {
+        num_adv_as: usize,
+        num_asns: usize,
+    },
+    /// An explicitly selected AS without nodes in the graph
+    UnknownAsn(Asn),
+    /// The strategy needs the intra-AS channel ratios, which require channels of mapped nodes
+    MissingRatios(PacketDropStrategy),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAsSelection(strategy) => write!(
+                f,
+                "Invalid AS selection strategy {}, expected 0, 1 or 2.",
+                strategy
+            ),
+            Self::NoPayments => write!(f, "At least one payment must be simulated."),
+            Self::NoAdversaries => write!(f, "At least one adversarial AS must be simulated."),
+            Self::TooManyAdversaries {
+                num_adv_as,
+                num_asns,
+            } => write!(
+                f,
+                "Cannot simulate the top {} ASs as the graph only has {}.",
+                num_adv_as, num_asns
+            ),
+            Self::UnknownAsn(asn) => write!(f, "AS {} has no nodes in the graph.", asn),
+            Self::MissingRatios(strategy) => write!(
+                f,
+                "{:?} requires intra-AS channel ratios but no mapped node has a channel.",
+                strategy
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl TryFrom<usize> for AsSelectionStrategy {
+    type Error = ConfigError;
+
+    fn try_from(strategy: usize) -> Result<Self, Self::Error> {
+        match strategy {
+            0 => Ok(Self::MaxNodes),
+            1 => Ok(Self::MaxChannels),
+            2 => Ok(Self::MaxBetweenness),
+            _ => Err(ConfigError::InvalidAsSelection(strategy)),
+        }
+    }
+}
+
+impl SimBuilder {
+    /// Checks the parameters against the graph's AS map before any payment is simulated
+    pub fn validate(
+        &self,
+        num_pairs: usize,
+        as_ip_map: &AsIpMap,
+        strategies: &[PacketDropStrategy],
+    ) -> Result<(), ConfigError> {
+        if num_pairs == 0 {
+            return Err(ConfigError::NoPayments);
+        }
+        match &self.target_asns {
+            Some(target_asns) => {
+                if let Some(asn) = target_asns.iter().find(|asn| {
+                    as_ip_map
+                        .as_to_nodes()
+                        .get(asn)
+                        .map_or(true, |nodes| nodes.is_empty())
+                }) {
+                    return Err(ConfigError::UnknownAsn(*asn));
+                }
+            }
+            None if self.num_adv_as == 0 => return Err(ConfigError::NoAdversaries),
+            None if self.num_adv_as > as_ip_map.as_to_nodes().len() => {
+                return Err(ConfigError::TooManyAdversaries {
+                    num_adv_as: self.num_adv_as,
+                    num_asns: as_ip_map.as_to_nodes().len(),
+                })
+            }
+            None => {}
+        }
+        let strategy = PacketDropStrategy::IntraProbability;
+        if strategies.contains(&strategy)
+            && !as_ip_map.as_to_nodes().values().flatten().any(|n| {
+                !self
+                    .graph
+                    .get_edges_for_node(n)
+                    .unwrap_or_default()
+                    .is_empty()
+            })
+        {
+            return Err(ConfigError::MissingRatios(strategy));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use simlib::graph::Graph;
+    use std::path::Path;
+
+    #[test]
+    fn validate_config() {
+        assert_eq!(
+            AsSelectionStrategy::try_from(2),
+            Ok(AsSelectionStrategy::MaxBetweenness)
+        );
+        assert_eq!(
+            AsSelectionStrategy::try_from(3),
+            Err(ConfigError::InvalidAsSelection(3))
+        );
+        let graph = Graph::to_sim_graph(
+            &network_parser::Graph::from_json_file(
+                Path::new("test_data/lnbook_example_lnr.json"),
+                Lnresearch,
+            )
+            .unwrap(),
+            Lnresearch,
+        );
+        let as_ip_map = AsIpMap::new(&graph, false);
+        let strategies = [PacketDropStrategy::IntraProbability];
+        let builder = |num_adv_as| {
+            SimBuilder::new(19, &graph, 1000, num_adv_as, AsSelectionStrategy::MaxNodes)
+        };
+        assert_eq!(builder(2).validate(10, &as_ip_map, &strategies), Ok(()));
+        assert_eq!(
+            builder(2).validate(0, &as_ip_map, &strategies),
+            Err(ConfigError::NoPayments)
+        );
+        assert_eq!(
+            builder(0).validate(10, &as_ip_map, &strategies),
+            Err(ConfigError::NoAdversaries)
+        );
+        assert_eq!(
+            builder(3).validate(10, &as_ip_map, &strategies),
+            Err(ConfigError::TooManyAdversaries {
+                num_adv_as: 3,
+                num_asns: 2
+            })
+        );
+        assert_eq!(
+            builder(3)
+                .with_target_asns(vec![797, 16509])
+                .validate(10, &as_ip_map, &strategies),
+            Err(ConfigError::UnknownAsn(16509))
+        );
+        let mut without_channels = graph.clone();
+        without_channels.edges.clear();
+        let isolated = SimBuilder::new(
+            19,
+            &without_channels,
+            1000,
+            2,
+            AsSelectionStrategy::MaxNodes,
+        );
+        assert_eq!(
+            isolated.validate(10, &as_ip_map, &strategies),
+            Err(ConfigError::MissingRatios(
+                PacketDropStrategy::IntraProbability
+            ))
+        );
+        assert_eq!(
+            isolated.validate(10, &as_ip_map, &[PacketDropStrategy::All]),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,264 @@
+#[cfg(feature = "mmdb")]
+use crate::{AsIpMap, DbReader};
+use serde::Serialize;
+use simlib::graph::Graph;
+#[cfg(feature = "mmdb")]
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, path::PathBuf};
+
+#[cfg(feature = "mmdb")]
+static SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Parameters of an experiment that are checked before any payments are simulated
+#[derive(Clone)]
+pub struct ExperimentConfig {
+    pub graph_file: PathBuf,
+    pub graph_source: network_parser::GraphSource,
+    pub output_dir: PathBuf,
+    /// Payment volumes in sat
+    pub amounts: Vec<usize>,
+    pub num_pairs: usize,
+    pub num_adv_as: usize,
+    pub as_sel_strategy: usize,
+    /// Minimum share of nodes (0 to 1) that have to be mapped to an ASN
+    pub min_asn_coverage: f32,
+    /// Maximum age of the AS database in days before a warning is issued
+    pub max_db_age_days: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Passed,
+    /// The run can go ahead but the results may be affected
+    Warning,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    /// False if at least one check failed
+    pub valid: bool,
+    pub checks: Vec<ValidationCheck>,
+}
+
+impl ValidationReport {
+    fn push(&mut self, name: &str, status: CheckStatus, message: String) {
+        self.checks.push(ValidationCheck {
+            name: name.to_owned(),
+            status,
+            message,
+        });
+    }
+}
+
+impl ExperimentConfig {
+    /// Runs all checks and returns a report describing the outcome of each
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let graph = self.check_graph(&mut report);
+        let db_available = self.check_database(&mut report);
+        match (graph, db_available) {
+            (Some(graph), true) => self.check_asn_coverage(&graph, &mut report),
+            _ => report.push(
+                "asnCoverage",
+                CheckStatus::Failed,
+                "Skipped as the graph or AS database could not be read.".to_owned(),
+            ),
+        }
+        self.check_output_dir(&mut report);
+        self.check_parameters(&mut report);
+        report.valid = report
+            .checks
+            .iter()
+            .all(|c| c.status != CheckStatus::Failed);
+        report
+    }
+
+    fn check_graph(&self, report: &mut ValidationReport) -> Option<Graph> {
+        match network_parser::Graph::from_json_file(&self.graph_file, self.graph_source.clone()) {
+            Ok(graph) => {
+                let graph = Graph::to_sim_graph(&graph, self.graph_source.clone());
+                let status = if graph.node_count() > 1 {
+                    CheckStatus::Passed
+                } else {
+                    CheckStatus::Failed
+                };
+                report.push(
+                    "graph",
+                    status,
+                    format!("Graph contains {} nodes.", graph.node_count()),
+                );
+                Some(graph)
+            }
+            Err(e) => {
+                report.push("graph", CheckStatus::Failed, format!("{}", e));
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "mmdb"))]
+    fn check_database(&self, report: &mut ValidationReport) -> bool {
+        report.push(
+            "asDatabase",
+            CheckStatus::Failed,
+            String::from("Built without the mmdb feature, nodes cannot be mapped to ASNs."),
+        );
+        false
+    }
+
+    #[cfg(feature = "mmdb")]
+    fn check_database(&self, report: &mut ValidationReport) -> bool {
+        match DbReader::new() {
+            Ok(db_reader) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                let age_days = now.saturating_sub(db_reader.build_epoch()) / SECONDS_PER_DAY;
+                let status = if age_days > self.max_db_age_days {
+                    CheckStatus::Warning
+                } else {
+                    CheckStatus::Passed
+                };
+                report.push(
+                    "asDatabase",
+                    status,
+                    format!("AS database is {} days old.", age_days),
+                );
+                true
+            }
+            Err(e) => {
+                report.push("asDatabase", CheckStatus::Failed, format!("{}", e));
+                false
+            }
+        }
+    }
+
+    #[cfg(not(feature = "mmdb"))]
+    fn check_asn_coverage(&self, _graph: &Graph, _report: &mut ValidationReport) {}
+
+    #[cfg(feature = "mmdb")]
+    fn check_asn_coverage(&self, graph: &Graph, report: &mut ValidationReport) {
+        let as_ip_map = AsIpMap::new(graph, false);
+        let num_mapped: usize = as_ip_map.as_to_nodes().values().map(|n| n.len()).sum();
+        let coverage = num_mapped as f32 / graph.node_count() as f32;
+        let status = if coverage >= self.min_asn_coverage {
+            CheckStatus::Passed
+        } else {
+            CheckStatus::Failed
+        };
+        report.push(
+            "asnCoverage",
+            status,
+            format!(
+                "{}% of nodes mapped to {} ASNs.",
+                coverage * 100.0,
+                as_ip_map.as_to_nodes().len()
+            ),
+        );
+    }
+
+    fn check_output_dir(&self, report: &mut ValidationReport) {
+        let probe = self.output_dir.join(".write-probe");
+        let writable = fs::create_dir_all(&self.output_dir)
+            .and_then(|_| fs::write(&probe, []))
+            .and_then(|_| fs::remove_file(&probe));
+        match writable {
+            Ok(_) => report.push(
+                "outputDir",
+                CheckStatus::Passed,
+                format!("{} is writable.", self.output_dir.display()),
+            ),
+            Err(e) => report.push("outputDir", CheckStatus::Failed, format!("{}", e)),
+        }
+    }
+
+    fn check_parameters(&self, report: &mut ValidationReport) {
+        let mut problems = vec![];
+        if self.amounts.is_empty() || self.amounts.contains(&0) {
+            problems.push("payment amounts must be non-empty and positive");
+        }
+        if self.num_pairs == 0 {
+            problems.push("number of payments must be positive");
+        }
+        if self.num_adv_as == 0 {
+            problems.push("number of adversarial ASs must be positive");
+        }
+        if self.as_sel_strategy > 2 {
+            problems.push("AS selection strategy must be 0, 1 or 2");
+        }
+        if !(0.0..=1.0).contains(&self.min_asn_coverage) {
+            problems.push("ASN coverage threshold must be between 0 and 1");
+        }
+        if problems.is_empty() {
+            report.push(
+                "parameters",
+                CheckStatus::Passed,
+                "Parameters are consistent.".to_owned(),
+            );
+        } else {
+            report.push("parameters", CheckStatus::Failed, problems.join("; "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_parser::GraphSource::*;
+    use tempfile::TempDir;
+
+    fn config(dir: &TempDir) -> ExperimentConfig {
+        ExperimentConfig {
+            graph_file: PathBuf::from("test_data/lnbook_example_lnr.json"),
+            graph_source: Lnresearch,
+            output_dir: PathBuf::from(dir.path()),
+            amounts: vec![1000],
+            num_pairs: 10,
+            num_adv_as: 1,
+            as_sel_strategy: 1,
+            min_asn_coverage: 0.5,
+            max_db_age_days: u64::MAX,
+        }
+    }
+
+    #[test]
+    fn valid_config() {
+        let dir = TempDir::new().expect("Error opening tempdir");
+        let report = config(&dir).validate();
+        assert!(report.valid);
+        assert_eq!(report.checks.len(), 5);
+        assert!(report
+            .checks
+            .iter()
+            .all(|c| c.status == CheckStatus::Passed));
+    }
+
+    #[test]
+    fn invalid_config() {
+        let dir = TempDir::new().expect("Error opening tempdir");
+        let mut config = config(&dir);
+        config.graph_file = PathBuf::from("test_data/does_not_exist.json");
+        config.as_sel_strategy = 3;
+        let report = config.validate();
+        assert!(!report.valid);
+        let failed: Vec<&str> = report
+            .checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Failed)
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(failed, vec!["graph", "asnCoverage", "parameters"]);
+    }
+}